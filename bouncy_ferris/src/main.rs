@@ -4,14 +4,15 @@ mod utility;
 
 use std::{
   ffi::CStr,
+  path::Path,
   time::{Duration, Instant},
 };
 
 use ash::vk;
+use ash_by_example_core::{cstr, profiler::format_table};
 use ferris::Ferris;
 use rand::Rng;
-use render::RenderEngine;
-use utility::cstr;
+use render::{FrameError, RenderEngine};
 use winit::{
   dpi::PhysicalSize,
   event::{Event, WindowEvent},
@@ -19,10 +20,10 @@ use winit::{
   keyboard::{KeyCode, PhysicalKey},
 };
 
-pub const APPLICATION_NAME: &'static CStr = cstr!("Bouncy Ferris");
+pub const APPLICATION_NAME: &CStr = cstr!("Bouncy Ferris");
 pub const APPLICATION_VERSION: u32 = vk::make_api_version(0, 1, 0, 0);
 
-pub const WINDOW_TITLE: &'static str = "Bouncy Ferris";
+pub const WINDOW_TITLE: &str = "Bouncy Ferris";
 pub const INITIAL_WINDOW_WIDTH: u32 = 800;
 pub const INITIAL_WINDOW_HEIGHT: u32 = 800;
 
@@ -41,6 +42,10 @@ const WAIT_AFTER_WINDOW_RESIZE_ENABLED: bool = true;
 const WAIT_AFTER_WINDOW_RESIZE_THRESHOLD: u32 = 20;
 const WAIT_AFTER_WINDOW_RESIZE_DURATION: Duration = Duration::from_millis(60);
 
+// Ferris position when rendering a single frame with --headless
+// has to be deterministic so that the result can be compared with a reference image
+const HEADLESS_FERRIS_POSITION: [f32; 2] = [336.0, 358.0];
+
 // prints current frame 1 / <time since last frame> every x time
 const PRINT_FPS_EVERY: Duration = Duration::from_millis(1000);
 
@@ -104,6 +109,10 @@ pub fn main_loop(event_loop: EventLoop<()>, mut engine: RenderEngine) {
         if time_since_last_fps_print >= PRINT_FPS_EVERY {
           time_since_last_fps_print -= PRINT_FPS_EVERY;
           println!("FPS: {}", 1.0 / time_passed.as_secs_f32());
+          print!("{}", format_table(engine.last_gpu_timings()));
+          if let Some(statistics) = engine.last_draw_statistics() {
+            println!("Draw statistics: {}", statistics);
+          }
         }
 
         ferris.update(time_passed, cur_window_size);
@@ -116,11 +125,10 @@ pub fn main_loop(event_loop: EventLoop<()>, mut engine: RenderEngine) {
         }
 
         if engine_running {
-          if engine
-            .render_frame(&ferris.get_render_position(cur_window_size))
-            .is_err()
-          {
-            log::warn!("Frame failed to render");
+          match engine.render_frame(&ferris.get_render_position(cur_window_size)) {
+            Ok(()) => {}
+            Err(FrameError::Skipped) => log::warn!("Frame failed to render"),
+            Err(FrameError::DeviceLost) => log::warn!("Frame lost with the device"),
           }
         }
       }
@@ -145,15 +153,12 @@ pub fn main_loop(event_loop: EventLoop<()>, mut engine: RenderEngine) {
 
           cur_window_size = new_size;
         }
-        WindowEvent::KeyboardInput { event, .. } => match event.physical_key {
-          // close on escape
-          PhysicalKey::Code(code) => {
-            if code == KeyCode::Escape {
-              target.exit();
-            }
-          }
-          _ => {}
-        },
+        // close on escape
+        WindowEvent::KeyboardInput { event, .. }
+          if event.physical_key == PhysicalKey::Code(KeyCode::Escape) =>
+        {
+          target.exit();
+        }
         _ => {}
       },
       _ => (),
@@ -161,8 +166,43 @@ pub fn main_loop(event_loop: EventLoop<()>, mut engine: RenderEngine) {
     .expect("Failed to run event loop")
 }
 
+// renders a single frame without opening a window and saves it to `path`
+fn render_headless(path: &Path) {
+  let size = PhysicalSize {
+    width: INITIAL_WINDOW_WIDTH,
+    height: INITIAL_WINDOW_HEIGHT,
+  };
+  let ferris = Ferris::new(HEADLESS_FERRIS_POSITION, true, true);
+
+  println!("Rendering headless frame...");
+  render::render_to_file(
+    path,
+    vk::Extent2D {
+      width: size.width,
+      height: size.height,
+    },
+    &ferris.get_render_position(size),
+  );
+  println!("Saved frame to {}", path.display());
+}
+
 fn main() {
   env_logger::init();
+
+  // usage: bouncy-ferris [--headless <output path>]
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  match args.as_slice() {
+    [] => {}
+    [flag, path] if flag == "--headless" => {
+      render_headless(Path::new(path));
+      return;
+    }
+    _ => {
+      eprintln!("Usage: bouncy-ferris [--headless <output path>]");
+      std::process::exit(2);
+    }
+  }
+
   let event_loop = EventLoop::new().expect("Failed to initialize event loop");
 
   // make the event loop run continuously even if there is no new user input
@@ -5,75 +5,95 @@ use winit::{
   window::{Window, WindowBuilder},
 };
 
-use crate::{INITIAL_WINDOW_HEIGHT, INITIAL_WINDOW_WIDTH, WINDOW_TITLE};
+use crate::{
+  APPLICATION_NAME, APPLICATION_VERSION, INITIAL_WINDOW_HEIGHT, INITIAL_WINDOW_WIDTH, WINDOW_TITLE,
+};
+
+use std::{ffi::CStr, sync::Arc};
 
-#[cfg(feature = "vl")]
-use super::objects::DebugUtils;
 use super::{
-  objects::{create_instance, get_entry, Surface},
+  objects::Surface,
   renderer::Renderer,
-  sync_renderer::SyncRenderer,
-  RenderPosition,
+  sync_renderer::{FrameError, SyncRenderer},
+  RenderPosition, TARGET_API_VERSION,
+};
+use ash_by_example_core::{
+  device_lost::FakeDeviceLost, get_entry, profiler::ScopeTiming, queries::DrawStatistics,
+  ApplicationInfo, Instance,
 };
 
 pub struct RenderEngine {
-  entry: ash::Entry,
-  instance: ash::Instance,
-  #[cfg(feature = "vl")]
-  debug_utils: DebugUtils,
-
+  // dropped before the instance
   windowed: Option<WindowedRender>,
+  instance: Arc<Instance>,
 }
 
 impl RenderEngine {
   pub fn init(event_loop: &EventLoop<()>) -> Self {
     let entry: ash::Entry = unsafe { get_entry() };
 
-    #[cfg(feature = "vl")]
-    let (instance, debug_utils) = create_instance(&entry, event_loop.raw_display_handle());
-    #[cfg(not(feature = "vl"))]
-    let instance = create_instance(&entry, event_loop.raw_display_handle());
+    let app_info = ApplicationInfo {
+      name: APPLICATION_NAME,
+      version: APPLICATION_VERSION,
+      api_version: TARGET_API_VERSION,
+    };
+
+    // extensions required to create a window surface
+    let surface_extensions: Vec<&CStr> =
+      ash_window::enumerate_required_extensions(event_loop.raw_display_handle())
+        .expect("Failed to enumerate window extensions")
+        .iter()
+        .map(|&ptr| unsafe { CStr::from_ptr(ptr) })
+        .collect();
+
+    let instance = Instance::create(entry, &app_info, &surface_extensions)
+      .expect("Failed to create an instance");
 
     Self {
-      entry,
-      instance,
-      #[cfg(feature = "vl")]
-      debug_utils,
       windowed: None,
+      instance,
     }
   }
 
   pub fn start(&mut self, target: &EventLoopWindowTarget<()>) -> PhysicalSize<u32> {
     assert!(self.windowed.is_none());
 
-    let (windowed, initial_window_size) = WindowedRender::new(target, &self.entry, &self.instance);
+    let (windowed, initial_window_size) = WindowedRender::new(target, &self.instance);
     self.windowed = Some(windowed);
 
     initial_window_size
   }
 
-  pub fn render_frame(&mut self, position: &RenderPosition) -> Result<(), ()> {
-    self.windowed.as_mut().unwrap().render_next_frame(position)
+  // the renderer gets recreated if the frame fails because the device was lost
+  pub fn render_frame(&mut self, position: &RenderPosition) -> Result<(), FrameError> {
+    let windowed = self.windowed.as_mut().unwrap();
+    let result = windowed.render_next_frame(position);
+    if result == Err(FrameError::DeviceLost) {
+      windowed.sync().log_device_lost();
+      windowed.recreate_renderer(&self.instance);
+    }
+    result
   }
 
   pub fn window_resized(&mut self, new_size: PhysicalSize<u32>) {
     self.windowed.as_mut().unwrap().window_resized(new_size);
   }
-}
 
-impl Drop for RenderEngine {
-  fn drop(&mut self) {
-    unsafe {
-      if let Some(windowed) = self.windowed.as_mut() {
-        windowed.destroy_self();
-      }
-
-      #[cfg(feature = "vl")]
-      self.debug_utils.destroy_self();
-
-      self.instance.destroy_instance(None);
+  // GPU timings of the last completed frame, empty if no frame has completed yet
+  pub fn last_gpu_timings(&self) -> &[ScopeTiming] {
+    match &self.windowed {
+      Some(windowed) => windowed.sync().last_timings(),
+      None => &[],
     }
   }
+
+  // draw queries of the last completed frame
+  pub fn last_draw_statistics(&self) -> Option<DrawStatistics> {
+    self
+      .windowed
+      .as_ref()
+      .and_then(|windowed| windowed.sync().last_statistics())
+  }
 }
 
 fn create_window(target: &EventLoopWindowTarget<()>, initial_size: PhysicalSize<u32>) -> Window {
@@ -84,20 +104,23 @@ fn create_window(target: &EventLoopWindowTarget<()>, initial_size: PhysicalSize<
     .expect("Failed to create window.")
 }
 
+// the renderer is dropped before the surface, which is dropped before the window
 struct WindowedRender {
+  // only None while being recreated
+  sync: Option<SyncRenderer>,
+  surface: Surface,
   _window: Window,
   window_size: PhysicalSize<u32>,
-  surface: Surface,
-  pub sync: SyncRenderer,
 
   extent_may_have_changed: bool,
+  // outlives renderer recreations so that the loss is only faked once
+  fake_device_lost: FakeDeviceLost,
 }
 
 impl WindowedRender {
   pub fn new(
     target: &EventLoopWindowTarget<()>,
-    entry: &ash::Entry,
-    instance: &ash::Instance,
+    instance: &Arc<Instance>,
   ) -> (Self, PhysicalSize<u32>) {
     let initial_size = PhysicalSize {
       width: INITIAL_WINDOW_WIDTH,
@@ -107,7 +130,6 @@ impl WindowedRender {
     let window = create_window(target, initial_size);
 
     let surface = Surface::new(
-      entry,
       instance,
       target.raw_display_handle(),
       window.raw_window_handle(),
@@ -118,19 +140,25 @@ impl WindowedRender {
 
     (
       Self {
+        sync: Some(sync_renderer),
+        surface,
         _window: window,
         window_size: initial_size,
-        surface,
-        sync: sync_renderer,
 
         extent_may_have_changed: false,
+        fake_device_lost: FakeDeviceLost::from_env(),
       },
       initial_size,
     )
   }
 
-  pub fn render_next_frame(&mut self, position: &RenderPosition) -> Result<(), ()> {
+  fn sync(&self) -> &SyncRenderer {
+    self.sync.as_ref().unwrap()
+  }
+
+  pub fn render_next_frame(&mut self, position: &RenderPosition) -> Result<(), FrameError> {
     let mut extent_changed = false;
+    let sync = self.sync.as_mut().unwrap();
 
     if self.extent_may_have_changed {
       self.extent_may_have_changed = false;
@@ -138,17 +166,31 @@ impl WindowedRender {
       let capabilities = unsafe {
         self
           .surface
-          .get_capabilities(*self.sync.renderer.physical_device)
+          .get_capabilities(*sync.renderer.physical_device)
       };
       let new_extent = Surface::get_extent_from_capabilities(&capabilities);
-      if new_extent.is_some_and(|extent| self.sync.renderer.swapchains.get_extent() != extent) {
+      if new_extent.is_some_and(|extent| sync.renderer.swapchains.get_extent() != extent) {
         extent_changed = true
       }
     }
 
-    self
-      .sync
-      .render_next_frame(&self.surface, self.window_size, extent_changed, position)
+    sync.render_next_frame(
+      &self.surface,
+      self.window_size,
+      extent_changed,
+      position,
+      &mut self.fake_device_lost,
+    )
+  }
+
+  // Recreates every device level object after a device loss
+  // The physical device is selected again, as the lost one may no longer be available
+  pub fn recreate_renderer(&mut self, instance: &Arc<Instance>) {
+    log::warn!("Device lost, recreating the renderer");
+    // the old swapchain has to be destroyed before a new one can be created for the same surface
+    self.sync = None;
+    let renderer = Renderer::new(instance, &self.surface, self.window_size);
+    self.sync = Some(SyncRenderer::new(renderer));
   }
 
   pub fn window_resized(&mut self, new_size: PhysicalSize<u32>) {
@@ -157,9 +199,4 @@ impl WindowedRender {
       self.extent_may_have_changed = true;
     }
   }
-
-  pub unsafe fn destroy_self(&mut self) {
-    self.sync.destroy_self();
-    self.surface.destroy_self();
-  }
 }
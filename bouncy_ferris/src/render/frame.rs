@@ -1,16 +1,17 @@
-use std::ptr;
+use std::{ptr, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 // contains synchronization objects for one frame
 pub struct Frame {
-  pub image_available: vk::Semaphore,
-  pub presentable: vk::Semaphore,
-  pub finished: vk::Fence,
+  pub image_available: Owned<vk::Semaphore>,
+  pub presentable: Owned<vk::Semaphore>,
+  pub finished: Owned<vk::Fence>,
 }
 
 impl Frame {
-  pub fn new(device: &ash::Device) -> Self {
+  pub fn new(device: &Arc<Device>) -> Self {
     let semaphore_create_info = vk::SemaphoreCreateInfo {
       s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
       p_next: ptr::null(),
@@ -18,9 +19,10 @@ impl Frame {
     };
 
     let create_semaphore = || unsafe {
-      device
+      let semaphore = device
         .create_semaphore(&semaphore_create_info, None)
-        .expect("Failed to create Semaphore")
+        .expect("Failed to create Semaphore");
+      Owned::from_raw(device, semaphore)
     };
 
     let image_available = create_semaphore();
@@ -33,9 +35,10 @@ impl Frame {
     };
 
     let finished = unsafe {
-      device
+      let fence = device
         .create_fence(&fence_create_info, None)
-        .expect("Failed to create Fence Object!")
+        .expect("Failed to create Fence Object!");
+      Owned::from_raw(device, fence)
     };
     Self {
       image_available,
@@ -44,22 +47,15 @@ impl Frame {
     }
   }
 
-  pub fn wait_finished(&self, device: &ash::Device) {
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn wait_finished(&self, device: &ash::Device) -> Result<(), vk::Result> {
     unsafe {
-      device
-        .wait_for_fences(&[self.finished], true, u64::MAX)
-        .expect("Failed to wait for fences");
+      device.wait_for_fences(&[*self.finished], true, u64::MAX)?;
 
       device
-        .reset_fences(&[self.finished])
+        .reset_fences(&[*self.finished])
         .expect("Failed to reset fence");
     }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_semaphore(self.image_available, None);
-    device.destroy_semaphore(self.presentable, None);
-
-    device.destroy_fence(self.finished, None);
+    Ok(())
   }
 }
@@ -0,0 +1,222 @@
+use std::path::Path;
+
+use ash::vk;
+use std::sync::Arc;
+
+use ash_by_example_core::{
+  create_pipeline_cache,
+  device_lost::FakeDeviceLost,
+  diagnostics::{log_device_lost, Breadcrumbs},
+  get_entry,
+  profiler::format_table,
+  save_pipeline_cache, ApplicationInfo, Device, Instance, MemoryAllocator, Owned, PhysicalDevice,
+  PhysicalDeviceProperties, SharedAllocator,
+};
+
+use crate::{APPLICATION_NAME, APPLICATION_VERSION};
+
+use super::{
+  objects::{
+    command_buffer_submit_info,
+    command_pools::{GraphicsCommandBufferPool, ReadbackCommandBufferPool},
+    create_buffer, create_framebuffer, create_image, create_image_view, create_render_pass,
+    create_unsignaled_fence, device, submit_info, DescriptorSets, GraphicsPipeline,
+  },
+  render_object::INDICES,
+  renderer::{create_constant_objects, create_sampler, create_upload_manager},
+  RenderPosition, PIPELINE_CACHE_PATH, TARGET_API_VERSION,
+};
+
+// rendered image bytes are saved directly, so the format should have a matching image::ColorType
+const HEADLESS_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+const HEADLESS_SAVE_TYPE: image::ColorType = image::ColorType::Rgba8;
+const HEADLESS_FORMAT_SIZE: u64 = 4;
+
+// number of times rendering is attempted again after the device gets lost
+const DEVICE_LOST_RETRIES: u32 = 2;
+
+// Renders a single frame without creating a window or a swapchain and saves it to a file
+// The same pipeline and objects are used as when rendering to a window, so the output should be
+// identical to a frame with the same size and position
+// Every object gets destroyed when going out of scope (or when panicking) in reverse creation
+// order, the device and instance last
+// If the device gets lost, every object except the instance is created again and the frame is
+// rendered from scratch
+pub fn render_to_file(path: &Path, extent: vk::Extent2D, position: &RenderPosition) {
+  let entry: ash::Entry = unsafe { get_entry() };
+
+  let app_info = ApplicationInfo {
+    name: APPLICATION_NAME,
+    version: APPLICATION_VERSION,
+    api_version: TARGET_API_VERSION,
+  };
+
+  let instance = Instance::create(entry, &app_info, &[]).expect("Failed to create an instance");
+  let mut fake_device_lost = FakeDeviceLost::from_env();
+
+  let mut retries = 0;
+  loop {
+    match render_attempt(&instance, path, extent, position, &mut fake_device_lost) {
+      Ok(()) => break,
+      Err(vk::Result::ERROR_DEVICE_LOST) if retries < DEVICE_LOST_RETRIES => {
+        retries += 1;
+        log::warn!(
+          "Device lost while rendering, retrying ({}/{})",
+          retries,
+          DEVICE_LOST_RETRIES
+        );
+      }
+      Err(err) => panic!("Failed to render headless frame: {:?}", err),
+    }
+  }
+}
+
+// errors are only returned by operations that can report a device loss after the frame was
+// submitted, other failures panic
+fn render_attempt(
+  instance: &Arc<Instance>,
+  path: &Path,
+  extent: vk::Extent2D,
+  position: &RenderPosition,
+  fake_device_lost: &mut FakeDeviceLost,
+) -> Result<(), vk::Result> {
+  let is_suitable =
+    |instance: &ash::Instance, physical_device, properties: &PhysicalDeviceProperties| {
+      device::is_suitable(instance, physical_device, properties, None)
+    };
+  let requirements = device::device_requirements(None, &is_suitable);
+
+  let physical_device = unsafe { PhysicalDevice::select(instance, &requirements) }
+    .expect("Failed to select a physical device");
+  let (device, queues) = Device::create(instance, &physical_device, &requirements)
+    .expect("Failed to create a logical device");
+
+  let allocator = SharedAllocator::new(&device, MemoryAllocator::new(&physical_device));
+
+  // render target and the host buffer it gets copied to
+  let target = create_image(
+    &device,
+    extent.width,
+    extent.height,
+    HEADLESS_FORMAT,
+    vk::ImageTiling::OPTIMAL,
+    vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+  );
+  let _target_allocation = unsafe {
+    allocator.allocate_image(
+      *target,
+      vk::MemoryPropertyFlags::DEVICE_LOCAL,
+      vk::MemoryPropertyFlags::empty(),
+    )
+  }
+  .expect("Failed to allocate memory for the render target");
+  let target_view = create_image_view(&device, *target, HEADLESS_FORMAT);
+
+  let buffer_size = extent.width as u64 * extent.height as u64 * HEADLESS_FORMAT_SIZE;
+  let host_buffer = create_buffer(&device, buffer_size, vk::BufferUsageFlags::TRANSFER_DST);
+  let host_allocation = unsafe {
+    allocator.allocate_buffer(
+      *host_buffer,
+      vk::MemoryPropertyFlags::HOST_VISIBLE,
+      vk::MemoryPropertyFlags::HOST_CACHED,
+    )
+  }
+  .expect("Failed to allocate host memory for the rendered image");
+
+  // the readback pool transitions the image out of this layout
+  let render_pass = create_render_pass(
+    &device,
+    HEADLESS_FORMAT,
+    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+  );
+  let framebuffer = create_framebuffer(&device, *render_pass, *target_view, extent);
+
+  let mut descriptor_sets = DescriptorSets::new(&device);
+  let (pipeline_cache, _) =
+    create_pipeline_cache(&device, &physical_device, Path::new(PIPELINE_CACHE_PATH))
+      .expect("Failed to create pipeline cache");
+  let pipeline_cache = unsafe { Owned::from_raw(&device, pipeline_cache) };
+  let pipeline = GraphicsPipeline::create(
+    &device,
+    *pipeline_cache,
+    *render_pass,
+    &descriptor_sets,
+    extent,
+  );
+
+  let mut uploads = create_upload_manager(&device, &physical_device, &queues, &allocator);
+  let constant_objects = create_constant_objects(&device, &allocator, &mut uploads);
+  let sampler = create_sampler(&device);
+  descriptor_sets
+    .pool
+    .write_texture(&device, *constant_objects.texture_view, *sampler);
+
+  let enabled_features = requirements.enabled_features(&physical_device.features);
+  let mut graphics_pool =
+    GraphicsCommandBufferPool::create(&device, &physical_device, &enabled_features);
+  let mut readback_pool =
+    ReadbackCommandBufferPool::create(&device, &physical_device.queue_families);
+  unsafe {
+    graphics_pool.record(
+      &device,
+      *render_pass,
+      &descriptor_sets,
+      extent,
+      *framebuffer,
+      &pipeline,
+      &constant_objects,
+      position,
+    );
+    readback_pool.record_copy_to_host(&device, *target, *host_buffer, extent);
+  }
+
+  // command buffers in the same batch start in submission order, so the barrier in the readback
+  // command buffer waits for the render pass
+  let command_buffers = [
+    command_buffer_submit_info(graphics_pool.triangle),
+    command_buffer_submit_info(readback_pool.copy_to_host),
+  ];
+  let submit_info = submit_info(&[], &command_buffers, &[]);
+  let finished = create_unsignaled_fence(&device);
+  let result = fake_device_lost.check().and_then(|_| unsafe {
+    device.queue_submit2(queues.graphics, &[submit_info], *finished)?;
+    device.wait_for_fences(&[*finished], true, u64::MAX)
+  });
+  if result == Err(vk::Result::ERROR_DEVICE_LOST) {
+    let breadcrumbs: Vec<&Breadcrumbs> = graphics_pool.breadcrumbs().into_iter().collect();
+    log_device_lost(&device, &breadcrumbs);
+  }
+  result?;
+  let timings = graphics_pool.timings()?;
+  print!("{}", format_table(&timings));
+  // catches mistakes in the vertex and index buffers or the pipeline vertex input state
+  let statistics = graphics_pool.draw_statistics()?;
+  println!("Draw statistics: {}", statistics);
+  statistics
+    .check_indexed_triangles(INDICES.len() as u64)
+    .expect("Draw statistics don't match the vertex and index buffers");
+
+  unsafe {
+    let mapped = host_allocation
+      .mapped()
+      .expect("Rendered image memory is not mapped");
+    mapped
+      .read(&device, 0, buffer_size, |bytes| {
+        image::save_buffer(path, bytes, extent.width, extent.height, HEADLESS_SAVE_TYPE)
+      })
+      .expect("Failed to invalidate rendered image memory")
+      .expect("Failed to save rendered image");
+  }
+
+  if let Err(err) = save_pipeline_cache(
+    &device,
+    &physical_device,
+    *pipeline_cache,
+    Path::new(PIPELINE_CACHE_PATH),
+  ) {
+    log::error!("Failed to save pipeline cache: {:?}", err);
+  }
+
+  log::debug!("Destroying headless objects");
+  Ok(())
+}
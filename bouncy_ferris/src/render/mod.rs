@@ -1,5 +1,6 @@
 mod engine;
 mod frame;
+mod headless;
 mod objects;
 mod render_object;
 mod renderer;
@@ -11,23 +12,16 @@ use std::ffi::CStr;
 
 use ash::vk;
 
-use crate::utility::cstr;
+use ash_by_example_core::cstr;
 
 pub use engine::RenderEngine;
+pub use headless::render_to_file;
 pub use render_object::RenderPosition;
+pub use sync_renderer::FrameError;
 
 const TARGET_API_VERSION: u32 = vk::API_VERSION_1_3;
 
-const REQUIRED_DEVICE_EXTENSIONS: [&'static CStr; 1] = [cstr!("VK_KHR_swapchain")];
-
-#[cfg(feature = "vl")]
-pub const VALIDATION_LAYERS: [&'static CStr; 1] =
-  [crate::utility::cstr!("VK_LAYER_KHRONOS_validation")];
-#[cfg(feature = "vl")]
-pub const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] = [
-  vk::ValidationFeatureEnableEXT::BEST_PRACTICES,
-  vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION,
-];
+const REQUIRED_DEVICE_EXTENSIONS: [&CStr; 1] = [cstr!("VK_KHR_swapchain")];
 
 pub const FRAMES_IN_FLIGHT: usize = 2;
 
@@ -35,4 +29,5 @@ pub const BACKGROUND_COLOR: vk::ClearColorValue = vk::ClearColorValue {
   float32: [0.01, 0.01, 0.01, 1.0],
 };
 
-const TEXTURE_PATH: &'static str = "./ferris.png";
+const TEXTURE_PATH: &str = "./ferris.png";
+const PIPELINE_CACHE_PATH: &str = "pipeline_cache";
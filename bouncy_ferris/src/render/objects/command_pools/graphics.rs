@@ -1,10 +1,19 @@
-use std::ptr::{self, addr_of};
+use std::{
+  ptr::{self, addr_of},
+  sync::Arc,
+};
 
 use ash::vk;
+use ash_by_example_core::{
+  diagnostics::Breadcrumbs,
+  profiler::{ScopeTiming, TimestampProfiler},
+  queries::{DrawQueries, DrawStatistics},
+  Device, Owned, PhysicalDevice, PhysicalDeviceFeatures,
+};
 
 use crate::{
   render::{
-    objects::{device::QueueFamilies, ConstantAllocatedObjects, DescriptorSets, GraphicsPipeline},
+    objects::{ConstantAllocatedObjects, DescriptorSets, GraphicsPipeline},
     render_object::INDICES,
     RenderPosition, BACKGROUND_COLOR,
   },
@@ -12,29 +21,80 @@ use crate::{
 };
 
 pub struct GraphicsCommandBufferPool {
-  pool: vk::CommandPool,
+  // command buffers are freed with the pool
+  pool: Owned<vk::CommandPool>,
   pub triangle: vk::CommandBuffer,
+  // times the render pass
+  profiler: TimestampProfiler,
+  queue_family: u32,
+  // surround the draw call
+  draw_queries: DrawQueries,
+  // mark the render pass if VK_EXT_device_fault is not enabled, see diagnostics.rs in core
+  breadcrumbs: Option<Breadcrumbs>,
+  // queries only have results after the command buffer has been recorded (and submitted)
+  recorded: bool,
 }
 
 impl GraphicsCommandBufferPool {
-  pub fn create(device: &ash::Device, queue_families: &QueueFamilies) -> Self {
+  // enabled_features are the features the device was created with
+  pub fn create(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+    enabled_features: &PhysicalDeviceFeatures,
+  ) -> Self {
     let flags = vk::CommandPoolCreateFlags::TRANSIENT;
-    let pool = super::create_command_pool(device, flags, queue_families.graphics.index);
+    let queue_family = physical_device.queue_families.get_graphics_index();
+    let pool = super::create_command_pool(device, flags, queue_family);
 
-    let buffers = super::allocate_primary_command_buffers(device, pool, 1);
+    let buffers = super::allocate_primary_command_buffers(device, *pool, 1);
+
+    let profiler =
+      TimestampProfiler::new(device, physical_device, 1).expect("Failed to create query pool");
+    let draw_queries =
+      DrawQueries::new(device, enabled_features).expect("Failed to create query pools");
+    let breadcrumbs = Breadcrumbs::new_if_needed(device, physical_device, 1)
+      .expect("Failed to create breadcrumb buffer");
 
     Self {
       pool,
       triangle: buffers[0],
+      profiler,
+      queue_family,
+      draw_queries,
+      breadcrumbs,
+      recorded: false,
     }
   }
 
+  pub fn recorded(&self) -> bool {
+    self.recorded
+  }
+
+  // GPU time of the last recorded render pass, its command buffer should have completed
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn timings(&self) -> Result<Vec<ScopeTiming>, vk::Result> {
+    self.profiler.results()
+  }
+
+  // occlusion and pipeline statistics of the last recorded draw, its command buffer should have
+  // completed
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn draw_statistics(&self) -> Result<DrawStatistics, vk::Result> {
+    self.draw_queries.results()
+  }
+
+  // where the device was in the last submission of the command buffer, after a device loss
+  pub fn breadcrumbs(&self) -> Option<&Breadcrumbs> {
+    self.breadcrumbs.as_ref()
+  }
+
   pub unsafe fn reset(&mut self, device: &ash::Device) {
     device
-      .reset_command_pool(self.pool, vk::CommandPoolResetFlags::empty())
+      .reset_command_pool(*self.pool, vk::CommandPoolResetFlags::empty())
       .expect("Failed to reset command pool");
   }
 
+  #[allow(clippy::too_many_arguments)]
   pub unsafe fn record(
     &mut self,
     device: &ash::Device,
@@ -75,41 +135,59 @@ impl GraphicsCommandBufferPool {
       p_clear_values: addr_of!(clear_value),
     };
 
+    // breadcrumbs and queries can't be written or reset inside a render pass instance
+    let marker = self.breadcrumbs.as_mut().map(|breadcrumbs| {
+      breadcrumbs.clear();
+      breadcrumbs.reset(cb);
+      breadcrumbs.begin(cb, "render pass")
+    });
+    // the last submission of this command buffer has completed
+    self.profiler.clear();
+    self.profiler.reset();
+    let scope = self
+      .profiler
+      .begin_scope(cb, "render pass", self.queue_family);
+    self.draw_queries.reset(cb);
+
     device.cmd_begin_render_pass(cb, &render_pass_begin_info, vk::SubpassContents::INLINE);
     {
       device.cmd_bind_descriptor_sets(
         cb,
         vk::PipelineBindPoint::GRAPHICS,
-        pipeline.layout,
+        *pipeline.layout,
         0,
         &[descriptor_sets.pool.texture],
         &[],
       );
       device.cmd_push_constants(
         cb,
-        pipeline.layout,
+        *pipeline.layout,
         vk::ShaderStageFlags::VERTEX,
         0,
         utility::any_as_u8_slice(position),
       );
       device.cmd_bind_pipeline(cb, vk::PipelineBindPoint::GRAPHICS, **pipeline);
-      device.cmd_bind_vertex_buffers(cb, 0, &[constant_allocated_objects.vertex], &[0]);
+      device.cmd_bind_vertex_buffers(cb, 0, &[*constant_allocated_objects.vertex], &[0]);
       device.cmd_bind_index_buffer(
         cb,
-        constant_allocated_objects.index,
+        *constant_allocated_objects.index,
         0,
         vk::IndexType::UINT16,
       );
+      self.draw_queries.begin(cb);
       device.cmd_draw_indexed(cb, INDICES.len() as u32, 1, 0, 0, 0);
+      self.draw_queries.end(cb);
     }
     device.cmd_end_render_pass(cb);
 
+    self.profiler.end_scope(cb, scope);
+    if let (Some(breadcrumbs), Some(marker)) = (self.breadcrumbs.as_ref(), marker) {
+      breadcrumbs.end(cb, marker);
+    }
+
     device
       .end_command_buffer(cb)
-      .expect("Failed to finish recording command buffer")
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_command_pool(self.pool, None);
+      .expect("Failed to finish recording command buffer");
+    self.recorded = true;
   }
 }
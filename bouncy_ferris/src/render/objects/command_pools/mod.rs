@@ -1,20 +1,19 @@
-use std::ptr;
+use std::{ptr, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 mod graphics;
-mod temporary_graphics;
-mod transfer;
+mod readback;
 
 pub use graphics::GraphicsCommandBufferPool;
-pub use temporary_graphics::TemporaryGraphicsCommandBufferPool;
-pub use transfer::TransferCommandBufferPool;
+pub use readback::ReadbackCommandBufferPool;
 
 pub fn create_command_pool(
-  device: &ash::Device,
+  device: &Arc<Device>,
   flags: vk::CommandPoolCreateFlags,
   queue_family_index: u32,
-) -> vk::CommandPool {
+) -> Owned<vk::CommandPool> {
   let command_pool_create_info = vk::CommandPoolCreateInfo {
     s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
     p_next: ptr::null(),
@@ -24,9 +23,10 @@ pub fn create_command_pool(
 
   log::debug!("Creating command pool");
   unsafe {
-    device
+    let pool = device
       .create_command_pool(&command_pool_create_info, None)
-      .expect("Failed to create Command Pool!")
+      .expect("Failed to create Command Pool!");
+    Owned::from_raw(device, pool)
   }
 }
 
@@ -50,3 +50,21 @@ fn allocate_primary_command_buffers(
       .expect("Failed to allocate command buffers")
   }
 }
+
+fn dependency_info(
+  memory: &[vk::MemoryBarrier2],
+  buffer: &[vk::BufferMemoryBarrier2],
+  image: &[vk::ImageMemoryBarrier2],
+) -> vk::DependencyInfo {
+  vk::DependencyInfo {
+    s_type: vk::StructureType::DEPENDENCY_INFO,
+    p_next: ptr::null(),
+    dependency_flags: vk::DependencyFlags::empty(),
+    memory_barrier_count: memory.len() as u32,
+    p_memory_barriers: memory.as_ptr(),
+    buffer_memory_barrier_count: buffer.len() as u32,
+    p_buffer_memory_barriers: buffer.as_ptr(),
+    image_memory_barrier_count: image.len() as u32,
+    p_image_memory_barriers: image.as_ptr(),
+  }
+}
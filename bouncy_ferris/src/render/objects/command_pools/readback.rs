@@ -0,0 +1,123 @@
+use std::{ptr, sync::Arc};
+
+use ash::vk;
+use ash_by_example_core::{Device, Owned, QueueFamilies};
+
+use super::dependency_info;
+
+// used when rendering headless in order to copy the rendered image to host accessible memory
+pub struct ReadbackCommandBufferPool {
+  // command buffers are freed with the pool
+  _pool: Owned<vk::CommandPool>,
+  pub copy_to_host: vk::CommandBuffer,
+}
+
+impl ReadbackCommandBufferPool {
+  pub fn create(device: &Arc<Device>, queue_families: &QueueFamilies) -> Self {
+    let flags = vk::CommandPoolCreateFlags::TRANSIENT;
+    let pool = super::create_command_pool(device, flags, queue_families.get_graphics_index());
+
+    let buffers = super::allocate_primary_command_buffers(device, *pool, 1);
+
+    Self {
+      _pool: pool,
+      copy_to_host: buffers[0],
+    }
+  }
+
+  // expects to be submitted after the render pass that writes to the image (in the same queue)
+  // the image should be in the COLOR_ATTACHMENT_OPTIMAL layout
+  pub unsafe fn record_copy_to_host(
+    &mut self,
+    device: &ash::Device,
+    image: vk::Image,
+    buffer: vk::Buffer,
+    extent: vk::Extent2D,
+  ) {
+    let cb = self.copy_to_host;
+
+    let command_buffer_begin_info = vk::CommandBufferBeginInfo {
+      s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+      p_next: ptr::null(),
+      p_inheritance_info: ptr::null(),
+      flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+    };
+    device
+      .begin_command_buffer(cb, &command_buffer_begin_info)
+      .expect("Failed to start recording command buffer");
+
+    let subresource_range = vk::ImageSubresourceRange {
+      aspect_mask: vk::ImageAspectFlags::COLOR,
+      base_mip_level: 0,
+      level_count: 1,
+      base_array_layer: 0,
+      layer_count: 1,
+    };
+
+    // wait for the render pass to finish writing
+    let attachment_to_transfer_src = vk::ImageMemoryBarrier2 {
+      s_type: vk::StructureType::IMAGE_MEMORY_BARRIER_2,
+      p_next: ptr::null(),
+      src_stage_mask: vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+      dst_stage_mask: vk::PipelineStageFlags2::COPY,
+      src_access_mask: vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+      dst_access_mask: vk::AccessFlags2::TRANSFER_READ,
+      old_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+      new_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+      src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+      dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+      image,
+      subresource_range,
+    };
+    device.cmd_pipeline_barrier2(
+      cb,
+      &dependency_info(&[], &[], &[attachment_to_transfer_src]),
+    );
+
+    // tightly packed
+    let copy_region = vk::BufferImageCopy {
+      buffer_offset: 0,
+      buffer_row_length: 0,
+      buffer_image_height: 0,
+      image_subresource: vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::COLOR,
+        mip_level: 0,
+        base_array_layer: 0,
+        layer_count: 1,
+      },
+      image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+      image_extent: vk::Extent3D {
+        width: extent.width,
+        height: extent.height,
+        depth: 1,
+      },
+    };
+    device.cmd_copy_image_to_buffer(
+      cb,
+      image,
+      vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+      buffer,
+      &[copy_region],
+    );
+
+    // make the copy visible to the host
+    let transfer_to_host = vk::BufferMemoryBarrier2 {
+      s_type: vk::StructureType::BUFFER_MEMORY_BARRIER_2,
+      p_next: ptr::null(),
+      src_stage_mask: vk::PipelineStageFlags2::COPY,
+      dst_stage_mask: vk::PipelineStageFlags2::HOST,
+      src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+      dst_access_mask: vk::AccessFlags2::HOST_READ,
+      src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+      dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+      buffer,
+      offset: 0,
+      size: vk::WHOLE_SIZE,
+    };
+    device.cmd_pipeline_barrier2(cb, &dependency_info(&[], &[transfer_to_host], &[]));
+
+    device
+      .end_command_buffer(cb)
+      .expect("Failed to finish recording command buffer")
+  }
+}
@@ -1,27 +1,26 @@
-use std::ptr::{self, addr_of};
+use std::{
+  ptr::{self, addr_of},
+  sync::Arc,
+};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 pub struct DescriptorSets {
-  pub layout: vk::DescriptorSetLayout,
   pub pool: DescriptorSetPool,
+  pub layout: Owned<vk::DescriptorSetLayout>,
 }
 
 impl DescriptorSets {
-  pub fn new(device: &ash::Device) -> Self {
+  pub fn new(device: &Arc<Device>) -> Self {
     let layout = create_layout(device);
 
-    let pool = DescriptorSetPool::new(device, layout);
-    Self { layout, pool }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    self.pool.destroy_self(device);
-    device.destroy_descriptor_set_layout(self.layout, None);
+    let pool = DescriptorSetPool::new(device, *layout);
+    Self { pool, layout }
   }
 }
 
-fn create_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+fn create_layout(device: &Arc<Device>) -> Owned<vk::DescriptorSetLayout> {
   let bindings = [vk::DescriptorSetLayoutBinding {
     binding: 0,
     descriptor_type: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
@@ -39,19 +38,21 @@ fn create_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
   };
 
   unsafe {
-    device
+    let layout = device
       .create_descriptor_set_layout(&create_info, None)
-      .expect("Failed to create a descriptor set layout")
+      .expect("Failed to create a descriptor set layout");
+    Owned::from_raw(device, layout)
   }
 }
 
 pub struct DescriptorSetPool {
-  pool: vk::DescriptorPool,
+  // sets are freed with the pool
+  _pool: Owned<vk::DescriptorPool>,
   pub texture: vk::DescriptorSet,
 }
 
 impl DescriptorSetPool {
-  pub fn new(device: &ash::Device, layout: vk::DescriptorSetLayout) -> Self {
+  pub fn new(device: &Arc<Device>, layout: vk::DescriptorSetLayout) -> Self {
     let sizes = [vk::DescriptorPoolSize {
       ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
       descriptor_count: 1,
@@ -65,15 +66,16 @@ impl DescriptorSetPool {
       flags: vk::DescriptorPoolCreateFlags::empty(),
     };
     let pool = unsafe {
-      device
+      let pool = device
         .create_descriptor_pool(&pool_create_info, None)
-        .expect("Failed to create descriptor pool")
+        .expect("Failed to create descriptor pool");
+      Owned::from_raw(device, pool)
     };
 
     let allocate_info = vk::DescriptorSetAllocateInfo {
       s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
       p_next: ptr::null(),
-      descriptor_pool: pool,
+      descriptor_pool: *pool,
       descriptor_set_count: 1,
       p_set_layouts: addr_of!(layout),
     };
@@ -84,7 +86,7 @@ impl DescriptorSetPool {
     };
 
     Self {
-      pool,
+      _pool: pool,
       texture: descriptor_set,
     }
   }
@@ -118,8 +120,4 @@ impl DescriptorSetPool {
       device.update_descriptor_sets(&[write], &[]);
     }
   }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_descriptor_pool(self.pool, None);
-  }
 }
@@ -0,0 +1,90 @@
+use std::mem::size_of;
+
+use ash::vk;
+use ash_by_example_core::{
+  const_flag_bitor, DeviceRequirements, PhysicalDeviceProperties, QueueFamilyRequirements,
+};
+
+use crate::render::{RenderPosition, REQUIRED_DEVICE_EXTENSIONS, TARGET_API_VERSION};
+
+use super::{ConstantAllocatedObjects, Surface};
+
+const REQUIRED_FORMAT_IMAGE_FLAGS_OPTIMAL: vk::FormatFeatureFlags = const_flag_bitor!(
+  vk::FormatFeatureFlags,
+  vk::FormatFeatureFlags::TRANSFER_DST,
+  vk::FormatFeatureFlags::SAMPLED_IMAGE,
+  vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR // can be used with linear sampler
+);
+
+fn check_formats_support(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> bool {
+  let properties = unsafe {
+    instance.get_physical_device_format_properties(
+      physical_device,
+      ConstantAllocatedObjects::TEXTURE_FORMAT,
+    )
+  };
+
+  properties
+    .optimal_tiling_features
+    .contains(REQUIRED_FORMAT_IMAGE_FLAGS_OPTIMAL)
+}
+
+fn check_swapchain_support(device: vk::PhysicalDevice, surface: &Surface) -> bool {
+  let formats = unsafe { surface.get_formats(device) };
+  let present_modes = unsafe { surface.get_present_modes(device) };
+
+  !formats.is_empty() && !present_modes.is_empty()
+}
+
+// checks specific to this application
+pub fn is_suitable(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  properties: &PhysicalDeviceProperties,
+  surface: Option<&Surface>, // None if rendering headless
+) -> bool {
+  // check if all required formats are supported
+  if !check_formats_support(instance, physical_device) {
+    log::warn!("Skipped physical device: Device does not support required formats");
+    return false;
+  }
+
+  if surface.is_some_and(|surface| !check_swapchain_support(physical_device, surface)) {
+    log::warn!("Skipped physical device: Device does not support swapchain");
+    return false;
+  }
+
+  if (properties.p10.limits.max_push_constants_size as usize) < size_of::<RenderPosition>() {
+    log::warn!("Skipped physical device: Device does not support required push constant size");
+    return false;
+  }
+
+  true
+}
+
+// both callbacks usually capture the window surface, so they have to be created by the caller
+// presentation is not required if supports_presentation is None (headless rendering)
+pub fn device_requirements<'a>(
+  supports_presentation: Option<&'a dyn Fn(vk::PhysicalDevice, u32) -> bool>,
+  is_suitable: &'a dyn Fn(&ash::Instance, vk::PhysicalDevice, &PhysicalDeviceProperties) -> bool,
+) -> DeviceRequirements<'a> {
+  let mut requirements = DeviceRequirements::new(TARGET_API_VERSION);
+  if supports_presentation.is_some() {
+    requirements.extensions = &REQUIRED_DEVICE_EXTENSIONS;
+  }
+  // used by the upload manager
+  requirements.features.f12.timeline_semaphore = vk::TRUE;
+  requirements.features.f13.synchronization2 = vk::TRUE;
+  // timestamp queries are reset from the host
+  requirements.features.f12.host_query_reset = vk::TRUE;
+  // draw statistics, only collected if supported
+  requirements.optional_features.f10.pipeline_statistics_query = vk::TRUE;
+  requirements.optional_features.f10.occlusion_query_precise = vk::TRUE;
+  requirements.queue_families = QueueFamilyRequirements {
+    graphics: true,
+    compute: false,
+    presentation: supports_presentation,
+  };
+  requirements.is_suitable = is_suitable;
+  requirements
+}
@@ -2,40 +2,29 @@ pub mod command_pools;
 mod constant_allocations;
 mod descriptor_sets;
 pub mod device;
-mod entry;
-mod instance;
 mod pipeline;
-mod pipeline_cache;
 mod render_pass;
 mod surface;
 mod swapchain;
 
-#[cfg(feature = "vl")]
-mod validation_layers;
-
-use std::ptr;
+use std::{ptr, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
-pub use constant_allocations::ConstantAllocatedObjects;
+pub use constant_allocations::{create_buffer, create_image, ConstantAllocatedObjects};
 pub use descriptor_sets::DescriptorSets;
-pub use entry::get_entry;
-pub use instance::create_instance;
 pub use pipeline::GraphicsPipeline;
-pub use pipeline_cache::{create_pipeline_cache, save_pipeline_cache};
 pub use render_pass::{create_framebuffer, create_render_pass};
 pub use surface::Surface;
 pub use swapchain::Swapchains;
 
-#[cfg(feature = "vl")]
-pub use validation_layers::{get_supported_validation_layers, DebugUtils};
-
 // 2d image all color channels
 pub fn create_image_view(
-  device: &ash::Device,
+  device: &Arc<Device>,
   image: vk::Image,
   format: vk::Format,
-) -> vk::ImageView {
+) -> Owned<vk::ImageView> {
   let create_info = vk::ImageViewCreateInfo {
     s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
     p_next: ptr::null(),
@@ -59,34 +48,67 @@ pub fn create_image_view(
   };
 
   unsafe {
-    device
+    let view = device
       .create_image_view(&create_info, None)
-      .expect("Failed to create image view")
-  }
-}
-
-pub fn create_semaphore(device: &ash::Device) -> vk::Semaphore {
-  let semaphore_create_info = vk::SemaphoreCreateInfo {
-    s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
-    p_next: ptr::null(),
-    flags: vk::SemaphoreCreateFlags::empty(),
-  };
-  unsafe {
-    device
-      .create_semaphore(&semaphore_create_info, None)
-      .expect("Failed to create semaphore")
+      .expect("Failed to create image view");
+    Owned::from_raw(device, view)
   }
 }
 
-pub fn create_unsignaled_fence(device: &ash::Device) -> vk::Fence {
+pub fn create_unsignaled_fence(device: &Arc<Device>) -> Owned<vk::Fence> {
   let create_info = vk::FenceCreateInfo {
     s_type: vk::StructureType::FENCE_CREATE_INFO,
     p_next: ptr::null(),
     flags: vk::FenceCreateFlags::empty(),
   };
   unsafe {
-    device
+    let fence = device
       .create_fence(&create_info, None)
-      .expect("Failed to create fence")
+      .expect("Failed to create fence");
+    Owned::from_raw(device, fence)
+  }
+}
+
+pub fn semaphore_submit_info(
+  semaphore: vk::Semaphore,
+  stage_mask: vk::PipelineStageFlags2,
+) -> vk::SemaphoreSubmitInfo {
+  vk::SemaphoreSubmitInfo {
+    s_type: vk::StructureType::SEMAPHORE_SUBMIT_INFO,
+    p_next: ptr::null(),
+    semaphore,
+    value: 0, // ignored for binary semaphores
+    stage_mask,
+    device_index: 0,
+  }
+}
+
+pub fn command_buffer_submit_info(
+  command_buffer: vk::CommandBuffer,
+) -> vk::CommandBufferSubmitInfo {
+  vk::CommandBufferSubmitInfo {
+    s_type: vk::StructureType::COMMAND_BUFFER_SUBMIT_INFO,
+    p_next: ptr::null(),
+    command_buffer,
+    device_mask: 0,
+  }
+}
+
+// referenced structs should live until the submission
+pub fn submit_info(
+  wait: &[vk::SemaphoreSubmitInfo],
+  command_buffers: &[vk::CommandBufferSubmitInfo],
+  signal: &[vk::SemaphoreSubmitInfo],
+) -> vk::SubmitInfo2 {
+  vk::SubmitInfo2 {
+    s_type: vk::StructureType::SUBMIT_INFO_2,
+    p_next: ptr::null(),
+    flags: vk::SubmitFlags::empty(),
+    wait_semaphore_info_count: wait.len() as u32,
+    p_wait_semaphore_infos: wait.as_ptr(),
+    command_buffer_info_count: command_buffers.len() as u32,
+    p_command_buffer_infos: command_buffers.as_ptr(),
+    signal_semaphore_info_count: signal.len() as u32,
+    p_signal_semaphore_infos: signal.as_ptr(),
   }
 }
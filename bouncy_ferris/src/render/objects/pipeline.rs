@@ -3,9 +3,11 @@ use std::{
   ops::Deref,
   pin::pin,
   ptr::{self, addr_of},
+  sync::Arc,
 };
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 use crate::render::{
   shaders::Shader,
@@ -15,10 +17,11 @@ use crate::render::{
 
 use super::DescriptorSets;
 
+// pipelines are dropped before their layout
 pub struct GraphicsPipeline {
-  pub layout: vk::PipelineLayout,
-  vk_obj: vk::Pipeline,
-  old: Option<vk::Pipeline>,
+  vk_obj: Owned<vk::Pipeline>,
+  old: Option<Owned<vk::Pipeline>>,
+  pub layout: Owned<vk::PipelineLayout>,
 }
 
 impl Deref for GraphicsPipeline {
@@ -31,7 +34,7 @@ impl Deref for GraphicsPipeline {
 
 impl GraphicsPipeline {
   pub fn create(
-    device: &ash::Device,
+    device: &Arc<Device>,
     cache: vk::PipelineCache,
     render_pass: vk::RenderPass,
     descriptor_sets: &DescriptorSets,
@@ -47,19 +50,20 @@ impl GraphicsPipeline {
       p_next: ptr::null(),
       flags: vk::PipelineLayoutCreateFlags::empty(),
       set_layout_count: 1,
-      p_set_layouts: &descriptor_sets.layout,
+      p_set_layouts: &*descriptor_sets.layout,
       push_constant_range_count: 1,
       p_push_constant_ranges: &push_constant_range,
     };
     let layout = unsafe {
-      device
+      let layout = device
         .create_pipeline_layout(&layout_create_info, None)
-        .expect("Failed to create pipeline layout")
+        .expect("Failed to create pipeline layout");
+      Owned::from_raw(device, layout)
     };
 
     let pipeline = Self::create_with_base(
       device,
-      layout,
+      *layout,
       cache,
       vk::Pipeline::null(),
       render_pass,
@@ -67,49 +71,50 @@ impl GraphicsPipeline {
     );
 
     Self {
-      layout,
       vk_obj: pipeline,
       old: None,
+      layout,
     }
   }
 
   pub fn recreate(
     &mut self,
-    device: &ash::Device,
+    device: &Arc<Device>,
     cache: vk::PipelineCache,
     render_pass: vk::RenderPass,
     extent: vk::Extent2D,
   ) {
     assert!(self.old.is_none());
 
-    let mut new =
-      Self::create_with_base(device, self.layout, cache, self.vk_obj, render_pass, extent);
-
-    let old = {
-      std::mem::swap(&mut self.vk_obj, &mut new);
-      new
-    };
+    let new = Self::create_with_base(
+      device,
+      *self.layout,
+      cache,
+      *self.vk_obj,
+      render_pass,
+      extent,
+    );
 
+    let old = std::mem::replace(&mut self.vk_obj, new);
     self.old = Some(old);
   }
 
   // destroy old pipeline once it stops being used
-  pub unsafe fn destroy_old(&mut self, device: &ash::Device) {
-    if let Some(old) = self.old {
-      device.destroy_pipeline(old, None);
-      self.old = None;
-    }
+  pub unsafe fn destroy_old(&mut self) {
+    self.old = None;
   }
 
   fn create_with_base(
-    device: &ash::Device,
+    device: &Arc<Device>,
     layout: vk::PipelineLayout,
     cache: vk::PipelineCache,
     base: vk::Pipeline,
     render_pass: vk::RenderPass,
     extent: vk::Extent2D,
-  ) -> vk::Pipeline {
-    let mut shader = Shader::load(device);
+  ) -> Owned<vk::Pipeline> {
+    // shader modules are not needed after pipeline creation and get dropped at the end of this
+    // function
+    let shader = Shader::load(device);
     let shader_stages = shader.get_pipeline_shader_creation_info();
 
     let vertex_input_state_gen = pin!(Vertex::get_input_state_create_info_gen(0, 0));
@@ -194,23 +199,12 @@ impl GraphicsPipeline {
       base_pipeline_handle: base,
       base_pipeline_index: -1, // -1 for null
     };
-    let pipeline = unsafe {
-      device
-        .create_graphics_pipelines(cache, &[create_info], None)
-        .expect("Failed to create graphics pipelines")[0]
-    };
-
     unsafe {
-      shader.destroy_self(device);
+      let pipeline = device
+        .create_graphics_pipelines(cache, &[create_info], None)
+        .expect("Failed to create graphics pipelines")[0];
+      Owned::from_raw(device, pipeline)
     }
-
-    pipeline
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    self.destroy_old(device);
-    device.destroy_pipeline(self.vk_obj, None);
-    device.destroy_pipeline_layout(self.layout, None);
   }
 }
 
@@ -1,8 +1,14 @@
-use std::ptr;
+use std::{ptr, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
-pub fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> vk::RenderPass {
+// final_layout is the layout the image is left in after the render pass finishes
+pub fn create_render_pass(
+  device: &Arc<Device>,
+  surface_format: vk::Format,
+  final_layout: vk::ImageLayout,
+) -> Owned<vk::RenderPass> {
   let image_attachment = vk::AttachmentDescription {
     flags: vk::AttachmentDescriptionFlags::empty(),
     format: surface_format,
@@ -12,7 +18,7 @@ pub fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> v
     stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
     stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
     initial_layout: vk::ImageLayout::UNDEFINED,
-    final_layout: vk::ImageLayout::PRESENT_SRC_KHR, // layout after render pass finishes
+    final_layout,
   };
 
   let attachment_ref = vk::AttachmentReference {
@@ -59,18 +65,19 @@ pub fn create_render_pass(device: &ash::Device, surface_format: vk::Format) -> v
     p_dependencies: dependencies.as_ptr(),
   };
   unsafe {
-    device
+    let render_pass = device
       .create_render_pass(&create_info, None)
-      .expect("Failed to create render pass!")
+      .expect("Failed to create render pass!");
+    Owned::from_raw(device, render_pass)
   }
 }
 
 pub fn create_framebuffer(
-  device: &ash::Device,
+  device: &Arc<Device>,
   render_pass: vk::RenderPass,
   image_view: vk::ImageView,
   extent: vk::Extent2D,
-) -> vk::Framebuffer {
+) -> Owned<vk::Framebuffer> {
   let create_info = vk::FramebufferCreateInfo {
     s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
     p_next: ptr::null(),
@@ -83,8 +90,9 @@ pub fn create_framebuffer(
     layers: 1,
   };
   unsafe {
-    device
+    let framebuffer = device
       .create_framebuffer(&create_info, None)
-      .expect("Failed to create framebuffer")
+      .expect("Failed to create framebuffer");
+    Owned::from_raw(device, framebuffer)
   }
 }
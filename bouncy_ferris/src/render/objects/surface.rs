@@ -1,11 +1,15 @@
-use std::ops::Deref;
+use std::{ops::Deref, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::Instance;
 use raw_window_handle::{RawDisplayHandle, RawWindowHandle};
 
+// Window surface that gets destroyed when dropped
+// Should be dropped before the window it was created from and after all swapchains that use it
 pub struct Surface {
   vk_obj: vk::SurfaceKHR,
   loader: ash::extensions::khr::Surface,
+  _instance: Arc<Instance>,
 }
 
 impl Deref for Surface {
@@ -18,18 +22,22 @@ impl Deref for Surface {
 
 impl Surface {
   pub fn new(
-    entry: &ash::Entry,
-    instance: &ash::Instance,
+    instance: &Arc<Instance>,
     display_handle: RawDisplayHandle,
     window_handle: RawWindowHandle,
   ) -> Self {
-    let loader = ash::extensions::khr::Surface::new(&entry, &instance);
+    let entry = instance.entry();
+    let loader = ash::extensions::khr::Surface::new(entry, instance);
     let vk_obj = unsafe {
       ash_window::create_surface(entry, instance, display_handle, window_handle, None)
         .expect("Failed to create window surface")
     };
 
-    Self { vk_obj, loader }
+    Self {
+      vk_obj,
+      loader,
+      _instance: instance.clone(),
+    }
   }
 
   pub unsafe fn supports_queue_family(
@@ -76,14 +84,17 @@ impl Surface {
   pub fn get_extent_from_capabilities(
     capabilities: &vk::SurfaceCapabilitiesKHR,
   ) -> Option<vk::Extent2D> {
-    if capabilities.current_extent.width != u32::max_value() {
+    if capabilities.current_extent.width != u32::MAX {
       Some(capabilities.current_extent)
     } else {
       None
     }
   }
+}
 
-  pub unsafe fn destroy_self(&mut self) {
-    self.loader.destroy_surface(self.vk_obj, None);
+impl Drop for Surface {
+  fn drop(&mut self) {
+    log::debug!("Destroying surface");
+    unsafe { self.loader.destroy_surface(self.vk_obj, None) };
   }
 }
@@ -1,15 +1,12 @@
-use std::{ops::Deref, ptr};
+use std::{ops::Deref, ptr, sync::Arc};
 
 pub use ash::vk;
+use ash_by_example_core::{Device, Owned, PhysicalDevice, QueueFamilies};
 use winit::dpi::PhysicalSize;
 
 use crate::PREFERRED_PRESENTATION_METHOD;
 
-use super::{
-  create_image_view,
-  device::{PhysicalDevice, QueueFamilies},
-  Surface,
-};
+use super::{create_image_view, Surface};
 
 pub struct Swapchains {
   loader: ash::extensions::khr::Swapchain,
@@ -21,7 +18,7 @@ impl Swapchains {
   pub fn new(
     instance: &ash::Instance,
     physical_device: &PhysicalDevice,
-    device: &ash::Device,
+    device: &Arc<Device>,
     surface: &Surface,
     window_size: PhysicalSize<u32>,
   ) -> Self {
@@ -46,7 +43,7 @@ impl Swapchains {
   pub unsafe fn recreate_swapchain(
     &mut self,
     physical_device: &PhysicalDevice,
-    device: &ash::Device,
+    device: &Arc<Device>,
     surface: &Surface,
     window_size: PhysicalSize<u32>,
   ) -> RecreationChanges {
@@ -79,18 +76,9 @@ impl Swapchains {
     unsafe { self.loader.queue_present(present_queue, &present_info) }
   }
 
-  pub fn destroy_old(&mut self, device: &ash::Device) {
-    if let Some(old) = &mut self.old {
-      unsafe {
-        old.destroy_self(device, &self.loader);
-      }
-      self.old = None;
-    }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    self.destroy_old(device);
-    self.current.destroy_self(device, &self.loader);
+  // old swapchain should not be in use
+  pub unsafe fn destroy_old(&mut self) {
+    self.old = None;
   }
 
   pub fn get_format(&self) -> vk::Format {
@@ -101,18 +89,20 @@ impl Swapchains {
     self.current.extent
   }
 
-  pub fn get_image_views(&self) -> &[vk::ImageView] {
+  pub fn get_image_views(&self) -> &[Owned<vk::ImageView>] {
     &self.current.image_views
   }
 }
 
-#[derive(Debug)]
+// gets destroyed (together with its image views) when dropped
 struct Swapchain {
   vk_obj: vk::SwapchainKHR,
   _images: Box<[vk::Image]>, // are owned by the swapchain
   pub format: vk::Format,
   pub extent: vk::Extent2D,
-  pub image_views: Box<[vk::ImageView]>,
+  pub image_views: Box<[Owned<vk::ImageView>]>,
+  loader: ash::extensions::khr::Swapchain,
+  _device: Arc<Device>,
 }
 
 impl Deref for Swapchain {
@@ -131,7 +121,7 @@ pub struct RecreationChanges {
 impl Swapchain {
   pub fn create(
     physical_device: &PhysicalDevice,
-    device: &ash::Device,
+    device: &Arc<Device>,
     surface: &Surface,
     swapchain_loader: &ash::extensions::khr::Swapchain,
     window_size: PhysicalSize<u32>,
@@ -165,7 +155,7 @@ impl Swapchain {
   pub fn recreate(
     &mut self,
     physical_device: &PhysicalDevice,
-    device: &ash::Device,
+    device: &Arc<Device>,
     surface: &Surface,
     swapchain_loader: &ash::extensions::khr::Swapchain,
     window_size: PhysicalSize<u32>,
@@ -188,7 +178,7 @@ impl Swapchain {
       extent: extent != self.extent,
     };
 
-    let mut new = Self::create_with(
+    let new = Self::create_with(
       device,
       &physical_device.queue_families,
       surface,
@@ -200,16 +190,13 @@ impl Swapchain {
       self.vk_obj,
     );
 
-    let old = {
-      std::mem::swap(&mut new, self);
-      new
-    };
-
+    let old = std::mem::replace(self, new);
     (old, changes)
   }
 
+  #[allow(clippy::too_many_arguments)]
   fn create_with(
-    device: &ash::Device,
+    device: &Arc<Device>,
     queue_families: &QueueFamilies,
     surface: &Surface,
     swapchain_loader: &ash::extensions::khr::Swapchain,
@@ -291,6 +278,8 @@ impl Swapchain {
       format: image_format.format,
       extent,
       image_views,
+      loader: swapchain_loader.clone(),
+      _device: device.clone(),
     }
   }
 
@@ -299,18 +288,15 @@ impl Swapchain {
     semaphore: vk::Semaphore,
     loader: &ash::extensions::khr::Swapchain,
   ) -> Result<(u32, bool), vk::Result> {
-    loader.acquire_next_image(self.vk_obj, std::u64::MAX, semaphore, vk::Fence::null())
+    loader.acquire_next_image(self.vk_obj, u64::MAX, semaphore, vk::Fence::null())
   }
+}
 
-  pub unsafe fn destroy_self(
-    &mut self,
-    device: &ash::Device,
-    loader: &ash::extensions::khr::Swapchain,
-  ) {
-    for &view in self.image_views.iter() {
-      device.destroy_image_view(view, None);
-    }
-    loader.destroy_swapchain(self.vk_obj, None);
+impl Drop for Swapchain {
+  fn drop(&mut self) {
+    // image views reference swapchain images, so they are destroyed first
+    self.image_views = Box::new([]);
+    unsafe { self.loader.destroy_swapchain(self.vk_obj, None) };
   }
 }
 
@@ -1,33 +1,36 @@
-use std::ptr;
+use std::{path::Path, ptr, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{
+  create_pipeline_cache, save_pipeline_cache,
+  upload::{UploadManager, UploadQueue},
+  Device, Instance, MemoryAllocator, Owned, PhysicalDevice, PhysicalDeviceProperties, Queues,
+  SharedAllocator,
+};
 use image::ImageError;
 use winit::dpi::PhysicalSize;
 
 use crate::{
   render::{
-    objects::{
-      command_pools::{
-        GraphicsCommandBufferPool, TemporaryGraphicsCommandBufferPool, TransferCommandBufferPool,
-      },
-      create_pipeline_cache, DescriptorSets,
-    },
+    objects::{command_pools::GraphicsCommandBufferPool, device, DescriptorSets},
     render_object::{INDICES, VERTICES},
-    TEXTURE_PATH,
+    PIPELINE_CACHE_PATH, TEXTURE_PATH,
   },
   utility::populate_array_with_expression,
 };
 
 use super::{
   objects::{
-    create_framebuffer, create_render_pass,
-    device::{create_logical_device, PhysicalDevice, Queues},
-    save_pipeline_cache, ConstantAllocatedObjects, GraphicsPipeline, Surface, Swapchains,
+    create_framebuffer, create_render_pass, ConstantAllocatedObjects, GraphicsPipeline, Surface,
+    Swapchains,
   },
   RenderPosition, FRAMES_IN_FLIGHT,
 };
 
-fn create_sampler(device: &ash::Device) -> vk::Sampler {
+// size of the staging ring buffer used for uploads, fits the whole texture
+const STAGING_SIZE: u64 = 4 * 1024 * 1024;
+
+pub fn create_sampler(device: &Arc<Device>) -> Owned<vk::Sampler> {
   let sampler_create_info = vk::SamplerCreateInfo {
     s_type: vk::StructureType::SAMPLER_CREATE_INFO,
     p_next: ptr::null(),
@@ -49,9 +52,10 @@ fn create_sampler(device: &ash::Device) -> vk::Sampler {
     min_lod: 0.0,
   };
   unsafe {
-    device
+    let sampler = device
       .create_sampler(&sampler_create_info, None)
-      .expect("Failed to create a sampler")
+      .expect("Failed to create a sampler");
+    Owned::from_raw(device, sampler)
   }
 }
 
@@ -67,33 +71,96 @@ fn read_texture_bytes_as_rgba8() -> Result<(u32, u32, Vec<u8>), ImageError> {
   Ok((width, height, bytes))
 }
 
-pub struct Renderer {
-  pub physical_device: PhysicalDevice,
-  pub device: ash::Device,
-  pub queues: Queues,
+// copies data with the transfer queue and hands it over to the graphics queue
+pub fn create_upload_manager(
+  device: &Arc<Device>,
+  physical_device: &PhysicalDevice,
+  queues: &Queues,
+  allocator: &Arc<SharedAllocator>,
+) -> UploadManager {
+  UploadManager::new(
+    device,
+    physical_device,
+    allocator,
+    UploadQueue {
+      queue: queues.transfer,
+      family: physical_device.queue_families.get_transfer_index(),
+    },
+    UploadQueue {
+      queue: queues.graphics,
+      family: physical_device.queue_families.get_graphics_index(),
+    },
+    STAGING_SIZE,
+  )
+  .expect("Failed to create upload manager")
+}
 
-  pub swapchains: Swapchains,
-  render_pass: vk::RenderPass,
-  framebuffers: Box<[vk::Framebuffer]>,
-  old_framebuffers: Option<Box<[vk::Framebuffer]>>,
+// uploads vertices, indices and the texture to device local memory
+pub fn create_constant_objects(
+  device: &Arc<Device>,
+  allocator: &Arc<SharedAllocator>,
+  uploads: &mut UploadManager,
+) -> ConstantAllocatedObjects {
+  let (texture_width, texture_height, texture_bytes) =
+    read_texture_bytes_as_rgba8().expect("Failed to read texture file");
+
+  ConstantAllocatedObjects::new(
+    device,
+    allocator,
+    uploads,
+    &VERTICES,
+    &INDICES,
+    &texture_bytes,
+    texture_width,
+    texture_height,
+  )
+}
+
+// Fields are dropped in declaration order, however all objects keep the device alive, so they are
+// always destroyed before it
+// The renderer should not be in use by the device when dropped
+pub struct Renderer {
+  pub graphics_pools: [GraphicsCommandBufferPool; FRAMES_IN_FLIGHT],
 
-  descriptor_sets: DescriptorSets,
-  pipeline_cache: vk::PipelineCache,
   pipeline: GraphicsPipeline,
+  pipeline_cache: Owned<vk::PipelineCache>,
+  descriptor_sets: DescriptorSets,
 
-  pub graphics_pools: [GraphicsCommandBufferPool; FRAMES_IN_FLIGHT],
+  framebuffers: Box<[Owned<vk::Framebuffer>]>,
+  old_framebuffers: Option<Box<[Owned<vk::Framebuffer>]>>,
+  render_pass: Owned<vk::RenderPass>,
+  pub swapchains: Swapchains,
+
+  _sampler: Owned<vk::Sampler>,
   constant_objects: ConstantAllocatedObjects,
-  sampler: vk::Sampler,
+  // can be used to stream new data while rendering
+  _uploads: UploadManager,
+
+  pub queues: Queues,
+  pub physical_device: PhysicalDevice,
+  pub device: Arc<Device>,
 }
 
 impl Renderer {
   pub fn new(
-    instance: &ash::Instance,
+    instance: &Arc<Instance>,
     surface: &Surface,
     initial_window_size: PhysicalSize<u32>,
   ) -> Self {
-    let physical_device = unsafe { PhysicalDevice::select(&instance, surface) };
-    let (device, queues) = create_logical_device(&instance, &physical_device);
+    let supports_presentation = |physical_device, family_i| unsafe {
+      surface.supports_queue_family(physical_device, family_i as usize)
+    };
+    let is_suitable =
+      |instance: &ash::Instance, physical_device, properties: &PhysicalDeviceProperties| {
+        device::is_suitable(instance, physical_device, properties, Some(surface))
+      };
+    let requirements = device::device_requirements(Some(&supports_presentation), &is_suitable);
+
+    let physical_device = unsafe { PhysicalDevice::select(instance, &requirements) }
+      .expect("Failed to select a physical device");
+    let (device, queues) = Device::create(instance, &physical_device, &requirements)
+      .expect("Failed to create a logical device");
+    let enabled_features = requirements.enabled_features(&physical_device.features);
 
     let swapchains = Swapchains::new(
       instance,
@@ -103,17 +170,24 @@ impl Renderer {
       initial_window_size,
     );
 
-    let render_pass = create_render_pass(&device, swapchains.get_format());
+    let render_pass = create_render_pass(
+      &device,
+      swapchains.get_format(),
+      vk::ImageLayout::PRESENT_SRC_KHR,
+    );
     let framebuffers = swapchains
       .get_image_views()
       .iter()
-      .map(|&view| create_framebuffer(&device, render_pass, view, swapchains.get_extent()))
+      .map(|view| create_framebuffer(&device, *render_pass, **view, swapchains.get_extent()))
       .collect();
 
     let mut descriptor_sets = DescriptorSets::new(&device);
 
     log::info!("Creating pipeline cache");
-    let (pipeline_cache, created_from_file) = create_pipeline_cache(&device, &physical_device);
+    let (pipeline_cache, created_from_file) =
+      create_pipeline_cache(&device, &physical_device, Path::new(PIPELINE_CACHE_PATH))
+        .expect("Failed to create pipeline cache");
+    let pipeline_cache = unsafe { Owned::from_raw(&device, pipeline_cache) };
     if created_from_file {
       log::info!("Cache successfully created from an existing cache file");
     } else {
@@ -121,69 +195,46 @@ impl Renderer {
     }
     let pipeline = GraphicsPipeline::create(
       &device,
-      pipeline_cache,
-      render_pass,
+      *pipeline_cache,
+      *render_pass,
       &descriptor_sets,
       swapchains.get_extent(),
     );
 
-    let constant_objects = {
-      let mut transfer_pool =
-        TransferCommandBufferPool::create(&device, &physical_device.queue_families);
-      let mut graphics_pool =
-        TemporaryGraphicsCommandBufferPool::create(&device, &physical_device.queue_families);
-
-      let (texture_width, texture_height, texture_bytes) =
-        read_texture_bytes_as_rgba8().expect("Failed to read texture file");
-
-      let objects = ConstantAllocatedObjects::new(
-        &device,
-        &physical_device,
-        &queues,
-        &mut transfer_pool,
-        &mut graphics_pool,
-        &VERTICES,
-        &INDICES,
-        &texture_bytes,
-        texture_width,
-        texture_height,
-      );
-
-      unsafe {
-        transfer_pool.destroy_self(&device);
-        graphics_pool.destroy_self(&device);
-      }
-
-      objects
-    };
+    // the allocator is kept alive by its allocations and the upload manager
+    let allocator = SharedAllocator::new(&device, MemoryAllocator::new(&physical_device));
+    let mut uploads = create_upload_manager(&device, &physical_device, &queues, &allocator);
+    let constant_objects = create_constant_objects(&device, &allocator, &mut uploads);
 
     let sampler = create_sampler(&device);
     descriptor_sets
       .pool
-      .write_texture(&device, constant_objects.texture_view, sampler);
+      .write_texture(&device, *constant_objects.texture_view, *sampler);
 
     let graphics_pools = populate_array_with_expression!(
-      GraphicsCommandBufferPool::create(&device, &physical_device.queue_families),
+      GraphicsCommandBufferPool::create(&device, &physical_device, &enabled_features),
       FRAMES_IN_FLIGHT
     );
 
     Self {
-      physical_device,
-      device,
-      queues,
+      graphics_pools,
+
+      pipeline,
+      pipeline_cache,
+      descriptor_sets,
 
-      swapchains,
-      render_pass,
       framebuffers,
       old_framebuffers: None,
+      render_pass,
+      swapchains,
 
-      descriptor_sets,
-      pipeline_cache,
-      pipeline,
-
-      graphics_pools,
+      _sampler: sampler,
       constant_objects,
-      sampler,
+      _uploads: uploads,
+
+      queues,
+      physical_device,
+      device,
     }
   }
 
@@ -195,10 +246,10 @@ impl Renderer {
   ) {
     self.graphics_pools[frame_i].record(
       &self.device,
-      self.render_pass,
+      *self.render_pass,
       &self.descriptor_sets,
       self.swapchains.get_extent(),
-      self.framebuffers[image_i],
+      *self.framebuffers[image_i],
       &self.pipeline,
       &self.constant_objects,
       position,
@@ -207,7 +258,7 @@ impl Renderer {
 
   pub unsafe fn recreate_swapchain(&mut self, surface: &Surface, window_size: PhysicalSize<u32>) {
     // it is possible to use more than two frames in flight, but it would require having more than one old swapchain and pipeline
-    assert!(FRAMES_IN_FLIGHT == 2);
+    const { assert!(FRAMES_IN_FLIGHT == 2) };
 
     // this function shouldn't be called if old objects haven't been destroyed
     assert!(self.old_framebuffers.is_none());
@@ -227,8 +278,12 @@ impl Renderer {
         .device_wait_idle()
         .expect("Failed to wait for device idleness while recreating swapchain and format");
 
-      self.device.destroy_render_pass(self.render_pass, None);
-      self.render_pass = create_render_pass(&self.device, self.swapchains.get_format());
+      // the old render pass gets dropped after the new one is created
+      self.render_pass = create_render_pass(
+        &self.device,
+        self.swapchains.get_format(),
+        vk::ImageLayout::PRESENT_SRC_KHR,
+      );
     } else {
       if !changes.extent {
         log::warn!("Recreating swapchain without any extent or format change");
@@ -239,11 +294,11 @@ impl Renderer {
       .swapchains
       .get_image_views()
       .iter()
-      .map(|&view| {
+      .map(|view| {
         create_framebuffer(
           &self.device,
-          self.render_pass,
-          view,
+          *self.render_pass,
+          **view,
           self.swapchains.get_extent(),
         )
       })
@@ -258,8 +313,8 @@ impl Renderer {
     if changes.extent || changes.format {
       self.pipeline.recreate(
         &self.device,
-        self.pipeline_cache,
-        self.render_pass,
+        *self.pipeline_cache,
+        *self.render_pass,
         self.swapchains.get_extent(),
       );
     }
@@ -268,49 +323,23 @@ impl Renderer {
   // destroy old objects that resulted of a swapchain recreation
   // this should only be called when they stop being in use
   pub unsafe fn destroy_old(&mut self) {
-    self.pipeline.destroy_old(&self.device);
-
-    for &framebuffer in self.old_framebuffers.as_mut().unwrap().iter() {
-      self.device.destroy_framebuffer(framebuffer, None);
-    }
+    self.pipeline.destroy_old();
     self.old_framebuffers = None;
-
-    self.swapchains.destroy_old(&self.device);
+    self.swapchains.destroy_old();
   }
+}
 
-  pub unsafe fn destroy_self(&mut self) {
-    self.device.destroy_sampler(self.sampler, None);
-    self.constant_objects.destroy_self(&self.device);
-    for pool in self.graphics_pools.iter_mut() {
-      pool.destroy_self(&self.device);
-    }
-
+impl Drop for Renderer {
+  fn drop(&mut self) {
     log::info!("Saving pipeline cache");
-    if let Err(err) = save_pipeline_cache(&self.device, &self.physical_device, self.pipeline_cache)
-    {
+    if let Err(err) = save_pipeline_cache(
+      &self.device,
+      &self.physical_device,
+      *self.pipeline_cache,
+      Path::new(PIPELINE_CACHE_PATH),
+    ) {
       log::error!("Failed to save pipeline cache: {:?}", err);
     }
-    self
-      .device
-      .destroy_pipeline_cache(self.pipeline_cache, None);
-
-    self.pipeline.destroy_self(&self.device);
-
-    self.descriptor_sets.destroy_self(&self.device);
-
-    for &framebuffer in self.framebuffers.iter() {
-      self.device.destroy_framebuffer(framebuffer, None);
-    }
-    if let Some(old) = self.old_framebuffers.as_mut() {
-      for &framebuffer in old.iter() {
-        self.device.destroy_framebuffer(framebuffer, None);
-      }
-    }
-
-    self.device.destroy_render_pass(self.render_pass, None);
-
-    self.swapchains.destroy_self(&self.device);
-
-    self.device.destroy_device(None);
+    // fields get dropped after this
   }
 }
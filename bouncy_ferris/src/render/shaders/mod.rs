@@ -1,19 +1,20 @@
-use std::{fs::File, io::Read, path::Path, ptr};
+use std::{fs::File, io::Read, path::Path, ptr, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 pub mod shader;
 
 pub use shader::Shader;
 
-pub fn load_shader(device: &ash::Device, shader_path: &Path) -> vk::ShaderModule {
+pub fn load_shader(device: &Arc<Device>, shader_path: &Path) -> Owned<vk::ShaderModule> {
   let code = read_shader_code(shader_path);
   create_shader_module(device, code)
 }
 
 fn read_shader_code(shader_path: &Path) -> Vec<u8> {
-  let mut file =
-    File::open(shader_path).expect(&format!("Failed to find spv file at {:?}", shader_path));
+  let mut file = File::open(shader_path)
+    .unwrap_or_else(|_| panic!("Failed to find spv file at {:?}", shader_path));
 
   let mut bytes = Vec::new();
   file
@@ -22,7 +23,7 @@ fn read_shader_code(shader_path: &Path) -> Vec<u8> {
   bytes
 }
 
-fn create_shader_module(device: &ash::Device, code: Vec<u8>) -> vk::ShaderModule {
+fn create_shader_module(device: &Arc<Device>, code: Vec<u8>) -> Owned<vk::ShaderModule> {
   let shader_module_create_info = vk::ShaderModuleCreateInfo {
     s_type: vk::StructureType::SHADER_MODULE_CREATE_INFO,
     p_next: ptr::null(),
@@ -32,8 +33,9 @@ fn create_shader_module(device: &ash::Device, code: Vec<u8>) -> vk::ShaderModule
   };
 
   unsafe {
-    device
+    let module = device
       .create_shader_module(&shader_module_create_info, None)
-      .expect("Failed to create shader module")
+      .expect("Failed to create shader module");
+    Owned::from_raw(device, module)
   }
 }
@@ -1,16 +1,49 @@
-use std::ptr;
-
 use ash::vk;
+use ash_by_example_core::{
+  device_lost::FakeDeviceLost,
+  diagnostics::{log_device_lost, Breadcrumbs},
+  profiler::ScopeTiming,
+  queries::DrawStatistics,
+};
 use winit::dpi::PhysicalSize;
 
 use crate::utility::populate_array_with_expression;
 
-use super::{frame::Frame, objects::Surface, renderer::Renderer, RenderPosition, FRAMES_IN_FLIGHT};
+use super::{
+  frame::Frame,
+  objects::{command_buffer_submit_info, semaphore_submit_info, submit_info, Surface},
+  render_object::INDICES,
+  renderer::Renderer,
+  RenderPosition, FRAMES_IN_FLIGHT,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameError {
+  // the swapchain image couldn't be acquired or presented (for example because the swapchain is
+  // out of date), the next frame should succeed
+  Skipped,
+  // all device level objects have to be recreated
+  DeviceLost,
+}
+
+// errors other than a device loss are not expected to happen
+fn device_lost_or_panic(result: vk::Result, operation: &str) -> FrameError {
+  match result {
+    vk::Result::ERROR_DEVICE_LOST => FrameError::DeviceLost,
+    other => panic!("Failed to {}: {:?}", operation, other),
+  }
+}
 
+// waits for the device to become idle when dropped, so that the renderer and frame objects are not
+// in use when they get destroyed
 pub struct SyncRenderer {
   pub renderer: Renderer,
   frames: [Frame; FRAMES_IN_FLIGHT],
   last_frame_i: usize,
+  // GPU time of the render pass of the last frame that completed
+  last_timings: Vec<ScopeTiming>,
+  // draw queries of the last frame that completed
+  last_statistics: Option<DrawStatistics>,
 
   // last frame swapchain was recreated and so current frame resources are marked as old
   // having more than two frames in flight could require having more than one old set of resources
@@ -27,6 +60,8 @@ impl SyncRenderer {
       renderer,
       frames,
       last_frame_i: 0,
+      last_timings: Vec::new(),
+      last_statistics: None,
 
       last_frame_recreated_swapchain: false,
       recreate_swapchain_next_frame: false,
@@ -39,7 +74,8 @@ impl SyncRenderer {
     window_size: PhysicalSize<u32>,
     extent_changed: bool,
     position: &RenderPosition,
-  ) -> Result<(), ()> {
+    fake_device_lost: &mut FakeDeviceLost,
+  ) -> Result<(), FrameError> {
     if extent_changed {
       self.recreate_swapchain_next_frame = true;
     }
@@ -48,10 +84,24 @@ impl SyncRenderer {
     let cur_frame: &Frame = &self.frames[cur_frame_i];
     self.last_frame_i = cur_frame_i;
 
-    cur_frame.wait_finished(&self.renderer.device);
+    cur_frame
+      .wait_finished(&self.renderer.device)
+      .map_err(|err| device_lost_or_panic(err, "wait for frame fence"))?;
 
     // current frame resources are now safe to use as they are not being used by the GPU
 
+    // queries of the last time this frame was recorded are available
+    let pool = &self.renderer.graphics_pools[cur_frame_i];
+    if pool.recorded() {
+      let query_error = |err| device_lost_or_panic(err, "get query results");
+      self.last_timings = pool.timings().map_err(query_error)?;
+      let statistics = pool.draw_statistics().map_err(query_error)?;
+      if let Err(err) = statistics.check_indexed_triangles(INDICES.len() as u64) {
+        log::error!("{}", err);
+      }
+      self.last_statistics = Some(statistics);
+    }
+
     if self.last_frame_recreated_swapchain {
       unsafe { self.renderer.destroy_old() }
       self.last_frame_recreated_swapchain = false;
@@ -69,7 +119,7 @@ impl SyncRenderer {
       self
         .renderer
         .swapchains
-        .acquire_next_image(cur_frame.image_available)
+        .acquire_next_image(*cur_frame.image_available)
     } {
       Ok((image_index, suboptimal)) => {
         if suboptimal {
@@ -77,11 +127,12 @@ impl SyncRenderer {
         }
         image_index
       }
+      Err(vk::Result::ERROR_DEVICE_LOST) => return Err(FrameError::DeviceLost),
       Err(_) => {
         log::warn!("Failed to acquire next swapchain image");
         self.recreate_swapchain_next_frame = true;
 
-        return Err(());
+        return Err(FrameError::Skipped);
       }
     };
 
@@ -95,35 +146,35 @@ impl SyncRenderer {
         .record_graphics(cur_frame_i, image_index as usize, position);
     }
 
-    let wait_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
-    let submit_info = vk::SubmitInfo {
-      s_type: vk::StructureType::SUBMIT_INFO,
-      p_next: ptr::null(),
-      wait_semaphore_count: 1,
-      p_wait_semaphores: &cur_frame.image_available,
-      p_wait_dst_stage_mask: &wait_stage,
-      command_buffer_count: 1,
-      p_command_buffers: &self.renderer.graphics_pools[cur_frame_i].triangle,
-      signal_semaphore_count: 1,
-      p_signal_semaphores: &cur_frame.presentable,
-    };
-    unsafe {
-      self
-        .renderer
-        .device
-        .queue_submit(
+    // the render pass only writes to the image after it is acquired
+    let wait = [semaphore_submit_info(
+      *cur_frame.image_available,
+      vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+    )];
+    let command_buffers = [command_buffer_submit_info(
+      self.renderer.graphics_pools[cur_frame_i].triangle,
+    )];
+    let signal = [semaphore_submit_info(
+      *cur_frame.presentable,
+      vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+    )];
+    let submit_info = submit_info(&wait, &command_buffers, &signal);
+    fake_device_lost
+      .check()
+      .and_then(|_| unsafe {
+        self.renderer.device.queue_submit2(
           self.renderer.queues.graphics,
           &[submit_info],
-          cur_frame.finished,
+          *cur_frame.finished,
         )
-        .expect("Failed to submit to queue");
-    }
+      })
+      .map_err(|err| device_lost_or_panic(err, "submit to queue"))?;
 
     unsafe {
       if let Err(vk_result) = self.renderer.swapchains.queue_present(
         image_index,
         self.renderer.queues.presentation,
-        &[cur_frame.presentable],
+        &[*cur_frame.presentable],
       ) {
         match vk_result {
           vk::Result::ERROR_OUT_OF_DATE_KHR => {
@@ -134,8 +185,9 @@ impl SyncRenderer {
             self.recreate_swapchain_next_frame = true;
 
             // errors of this type still signal sync objects accordingly
-            return Err(());
+            return Err(FrameError::Skipped);
           }
+          vk::Result::ERROR_DEVICE_LOST => return Err(FrameError::DeviceLost),
           other => panic!("Failed to present to swapchain: {:?}", other),
         }
       }
@@ -144,17 +196,34 @@ impl SyncRenderer {
     Ok(())
   }
 
-  pub unsafe fn destroy_self(&mut self) {
-    self
+  // logs what is known about a device loss, as reported by render_next_frame
+  pub fn log_device_lost(&self) {
+    let breadcrumbs: Vec<&Breadcrumbs> = self
       .renderer
-      .device
-      .device_wait_idle()
-      .expect("Failed to wait for device idleness while destroying resources");
+      .graphics_pools
+      .iter()
+      .filter_map(|pool| pool.breadcrumbs())
+      .collect();
+    log_device_lost(&self.renderer.device, &breadcrumbs);
+  }
 
-    for frame in self.frames.iter_mut() {
-      frame.destroy_self(&self.renderer.device);
-    }
+  pub fn last_timings(&self) -> &[ScopeTiming] {
+    &self.last_timings
+  }
 
-    self.renderer.destroy_self();
+  pub fn last_statistics(&self) -> Option<DrawStatistics> {
+    self.last_statistics
+  }
+}
+
+impl Drop for SyncRenderer {
+  fn drop(&mut self) {
+    // this only fails if the device is lost, in which case objects can be destroyed anyway
+    if let Err(err) = unsafe { self.renderer.device.device_wait_idle() } {
+      log::error!(
+        "Failed to wait for the device to become idle during drop: {:?}",
+        err
+      );
+    }
   }
 }
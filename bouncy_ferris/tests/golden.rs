@@ -0,0 +1,61 @@
+// Renders a single frame with --headless and compares it with tests/reference/headless.png
+// See golden/src/lib.rs on how to run this against a software Vulkan implementation
+
+use std::path::Path;
+
+use ash_by_example_core::device_lost::FAKE_DEVICE_LOST_VAR;
+use ash_by_example_golden::{
+  assert_matches_reference, run_example, run_example_with_env, skip_without_vulkan, Tolerance,
+};
+
+// linear texture filtering precision is implementation dependent
+const TOLERANCE: Tolerance = Tolerance {
+  channel: 3,
+  mismatched_ratio: 0.01,
+};
+
+#[test]
+fn headless_frame_matches_reference() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("headless.png");
+
+  run_example(
+    env!("CARGO_BIN_EXE_bouncy-ferris"),
+    dir,
+    &["--headless", output.to_str().unwrap()],
+  );
+
+  assert_matches_reference(
+    &output,
+    &dir.join("tests/reference/headless.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    TOLERANCE,
+  );
+}
+
+// the first submission reports a lost device, after which everything is created again
+#[test]
+fn headless_frame_recovers_from_device_loss() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("headless_device_lost.png");
+
+  run_example_with_env(
+    env!("CARGO_BIN_EXE_bouncy-ferris"),
+    dir,
+    &["--headless", output.to_str().unwrap()],
+    &[(FAKE_DEVICE_LOST_VAR, "1")],
+  );
+
+  assert_matches_reference(
+    &output,
+    &dir.join("tests/reference/headless.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    TOLERANCE,
+  );
+}
@@ -0,0 +1,293 @@
+use std::path::PathBuf;
+
+use ash::vk;
+use ash_by_example_core::image_file::{srgb_to_linear, TexelFormat};
+
+use crate::errors::ConfigError;
+
+pub const USAGE: &str = "Usage: compute-image-clear [OPTIONS]
+
+Options (each can also be set with the environment variable in brackets):
+  --width <PIXELS>       image width [ABE_CLEAR_WIDTH] (default: 1920)
+  --height <PIXELS>      image height [ABE_CLEAR_HEIGHT] (default: 1080)
+  --color <R,G,B[,A]>    clear color, each component from 0 to 255 [ABE_CLEAR_COLOR]
+                         (default: 134,206,203,255)
+  --format <FORMAT>      image format, one of: R8G8B8A8_UNORM, R8G8B8A8_SRGB, R16_UNORM,
+                         R16G16B16A16_UNORM, R16G16B16A16_SFLOAT, R32G32B32A32_SFLOAT
+                         [ABE_CLEAR_FORMAT] (default: R8G8B8A8_UNORM)
+  --output <PATH>        where to save the resulting image, .exr and .hdr files store floats
+                         [ABE_CLEAR_OUTPUT] (default: image.png)
+  --bench <N>            submit the recorded work N times and report timings [ABE_CLEAR_BENCH]
+  --help                 print this message";
+
+const DEFAULT_WIDTH: u32 = 1920;
+const DEFAULT_HEIGHT: u32 = 1080;
+const DEFAULT_COLOR: [u8; 4] = [134, 206, 203, 255];
+const DEFAULT_FORMAT: TexelFormat = TexelFormat::R8G8B8A8Unorm;
+const DEFAULT_OUTPUT: &str = "image.png";
+
+// Clear values are always in linear space, so for sRGB formats the color is decoded here so
+// that the stored values end up being the same as the ones provided
+// Single channel formats only keep the red component
+pub fn clear_value(format: TexelFormat, color: [u8; 4]) -> vk::ClearColorValue {
+  let unorm = color.map(|c| c as f32 / 255.0);
+  let float32 = match format {
+    TexelFormat::R8G8B8A8Srgb => [
+      srgb_to_linear(unorm[0]),
+      srgb_to_linear(unorm[1]),
+      srgb_to_linear(unorm[2]),
+      unorm[3], // alpha is always linear
+    ],
+    _ => unorm,
+  };
+  vk::ClearColorValue { float32 }
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+  pub width: u32,
+  pub height: u32,
+  pub color: [u8; 4],
+  pub format: TexelFormat,
+  pub output: PathBuf,
+  // number of benchmark iterations
+  pub bench: Option<u32>,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      width: DEFAULT_WIDTH,
+      height: DEFAULT_HEIGHT,
+      color: DEFAULT_COLOR,
+      format: DEFAULT_FORMAT,
+      output: PathBuf::from(DEFAULT_OUTPUT),
+      bench: None,
+    }
+  }
+}
+
+impl Config {
+  // Builds the configuration from environment variables and then from command line arguments,
+  // so that arguments take precedence
+  // Returns Ok(None) if --help was passed
+  pub fn from_env_and_args() -> Result<Option<Self>, ConfigError> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    Self::parse(|var| std::env::var(var).ok(), &args)
+  }
+
+  // environment variables are read with `var`, `args` don't include the program name
+  fn parse(
+    var: impl Fn(&str) -> Option<String>,
+    args: &[String],
+  ) -> Result<Option<Self>, ConfigError> {
+    // --help is recognized anywhere, even in place of a value
+    if args.iter().any(|arg| arg == "--help") {
+      return Ok(None);
+    }
+
+    let mut config = Self::default();
+
+    for (name, option) in [
+      ("ABE_CLEAR_WIDTH", "--width"),
+      ("ABE_CLEAR_HEIGHT", "--height"),
+      ("ABE_CLEAR_COLOR", "--color"),
+      ("ABE_CLEAR_FORMAT", "--format"),
+      ("ABE_CLEAR_OUTPUT", "--output"),
+      ("ABE_CLEAR_BENCH", "--bench"),
+    ] {
+      if let Some(value) = var(name) {
+        config.set(option, &value)?;
+      }
+    }
+
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+      let value = args
+        .next()
+        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+      config.set(option, value)?;
+    }
+
+    if config.width == 0 || config.height == 0 {
+      return Err(ConfigError::ZeroSizedImage);
+    }
+
+    Ok(Some(config))
+  }
+
+  fn set(&mut self, option: &str, value: &str) -> Result<(), ConfigError> {
+    let invalid = |reason: &str| ConfigError::InvalidValue {
+      option: option.to_owned(),
+      value: value.to_owned(),
+      reason: reason.to_owned(),
+    };
+
+    match option {
+      "--width" => self.width = value.parse().map_err(|_| invalid("expected a number"))?,
+      "--height" => self.height = value.parse().map_err(|_| invalid("expected a number"))?,
+      "--color" => {
+        let components = value
+          .split(',')
+          .map(|c| c.trim().parse::<u8>())
+          .collect::<Result<Vec<u8>, _>>()
+          .map_err(|_| invalid("components should be numbers from 0 to 255"))?;
+        self.color = match components[..] {
+          [r, g, b] => [r, g, b, 255],
+          [r, g, b, a] => [r, g, b, a],
+          _ => return Err(invalid("expected 3 or 4 components")),
+        };
+      }
+      "--format" => {
+        self.format = TexelFormat::from_name(value).ok_or_else(|| invalid("unsupported format"))?;
+      }
+      "--output" => self.output = PathBuf::from(value),
+      "--bench" => {
+        let iterations = value
+          .parse()
+          .ok()
+          .filter(|&n| n > 0)
+          .ok_or_else(|| invalid("expected a number greater than 0"))?;
+        self.bench = Some(iterations);
+      }
+      _ => return Err(ConfigError::UnknownOption(option.to_owned())),
+    }
+    Ok(())
+  }
+
+  // size of a buffer that can hold the whole image
+  pub fn image_size(&self) -> u64 {
+    self.width as u64 * self.height as u64 * self.format.texel_size()
+  }
+
+  pub fn extent(&self) -> vk::Extent3D {
+    vk::Extent3D {
+      width: self.width,
+      height: self.height,
+      depth: 1,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(env: &[(&str, &str)], args: &[&str]) -> Result<Option<Config>, ConfigError> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    Config::parse(
+      |var| {
+        env
+          .iter()
+          .find(|(name, _)| *name == var)
+          .map(|(_, value)| value.to_string())
+      },
+      &args,
+    )
+  }
+
+  #[test]
+  fn defaults_without_options() {
+    let config = parse(&[], &[]).unwrap().unwrap();
+    assert_eq!(
+      (config.width, config.height),
+      (DEFAULT_WIDTH, DEFAULT_HEIGHT)
+    );
+    assert_eq!(config.color, DEFAULT_COLOR);
+    assert_eq!(config.format, DEFAULT_FORMAT);
+    assert_eq!(config.output, PathBuf::from(DEFAULT_OUTPUT));
+    assert_eq!(config.bench, None);
+  }
+
+  #[test]
+  fn options_are_parsed() {
+    let config = parse(
+      &[],
+      &[
+        "--width",
+        "640",
+        "--height",
+        "480",
+        "--color",
+        "255,128,0",
+        "--format",
+        "r16g16b16a16_sfloat",
+        "--output",
+        "clear.exr",
+        "--bench",
+        "10",
+      ],
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!((config.width, config.height), (640, 480));
+    assert_eq!(config.color, [255, 128, 0, 255]);
+    assert_eq!(config.format, TexelFormat::R16G16B16A16Sfloat);
+    assert_eq!(config.output, PathBuf::from("clear.exr"));
+    assert_eq!(config.bench, Some(10));
+  }
+
+  #[test]
+  fn arguments_take_precedence_over_environment() {
+    let env = [("ABE_CLEAR_WIDTH", "100"), ("ABE_CLEAR_HEIGHT", "50")];
+    let config = parse(&env, &["--width", "200"]).unwrap().unwrap();
+    assert_eq!((config.width, config.height), (200, 50));
+  }
+
+  #[test]
+  fn help_is_recognized_in_any_position() {
+    assert!(parse(&[], &["--help"]).unwrap().is_none());
+    assert!(parse(&[], &["--width", "--help"]).unwrap().is_none());
+    assert!(parse(&[], &["--width", "10", "--help"]).unwrap().is_none());
+    assert!(parse(&[], &["--bogus", "1", "--help"]).unwrap().is_none());
+    // invalid environment variables don't matter either
+    assert!(parse(&[("ABE_CLEAR_WIDTH", "wide")], &["--help"])
+      .unwrap()
+      .is_none());
+  }
+
+  #[test]
+  fn missing_values_are_rejected() {
+    assert!(matches!(
+      parse(&[], &["--width", "10", "--height"]),
+      Err(ConfigError::MissingValue(option)) if option == "--height"
+    ));
+  }
+
+  #[test]
+  fn invalid_values_are_rejected() {
+    for args in [
+      ["--width", "wide"],
+      ["--color", "1,2"],
+      ["--color", "256,0,0"],
+      ["--format", "R8G8B8_UNORM"],
+      ["--bench", "0"],
+    ] {
+      assert!(
+        matches!(
+          parse(&[], &args),
+          Err(ConfigError::InvalidValue { ref option, ref value, .. })
+            if option == args[0] && value == args[1]
+        ),
+        "{:?} was accepted",
+        args
+      );
+    }
+    assert!(matches!(
+      parse(&[("ABE_CLEAR_BENCH", "-1")], &[]),
+      Err(ConfigError::InvalidValue { option, .. }) if option == "--bench"
+    ));
+    assert!(matches!(
+      parse(&[], &["--width", "0"]),
+      Err(ConfigError::ZeroSizedImage)
+    ));
+  }
+
+  #[test]
+  fn unknown_options_are_rejected() {
+    assert!(matches!(
+      parse(&[], &["--depth", "3"]),
+      Err(ConfigError::UnknownOption(option)) if option == "--depth"
+    ));
+  }
+}
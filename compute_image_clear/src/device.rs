@@ -0,0 +1,117 @@
+use ash::vk;
+use ash_by_example_core::{
+  const_flag_bitor, DeviceRequirements, PhysicalDeviceProperties, QueueFamilyRequirements,
+};
+
+use crate::{config::Config, REQUIRED_DEVICE_EXTENSIONS, TARGET_API_VERSION};
+
+const REQUIRED_IMAGE_FORMAT_FEATURES: vk::FormatFeatureFlags = const_flag_bitor!(
+  vk::FormatFeatureFlags,
+  vk::FormatFeatureFlags::TRANSFER_SRC,
+  vk::FormatFeatureFlags::TRANSFER_DST
+);
+
+const REQUIRED_IMAGE_USAGES: vk::ImageUsageFlags = const_flag_bitor!(
+  vk::ImageUsageFlags,
+  vk::ImageUsageFlags::TRANSFER_SRC,
+  vk::ImageUsageFlags::TRANSFER_DST
+);
+
+fn supports_required_image_formats(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  format: vk::Format,
+) -> bool {
+  let properties =
+    unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+
+  properties
+    .optimal_tiling_features
+    .contains(REQUIRED_IMAGE_FORMAT_FEATURES)
+}
+
+fn supports_image_dimensions(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  config: &Config,
+  tiling: vk::ImageTiling,
+  usage: vk::ImageUsageFlags,
+) -> Result<bool, vk::Result> {
+  let format = config.format.vk_format();
+  let properties = unsafe {
+    instance.get_physical_device_image_format_properties(
+      physical_device,
+      format,
+      vk::ImageType::TYPE_2D,
+      tiling,
+      usage,
+      vk::ImageCreateFlags::empty(),
+    )?
+  };
+  log::debug!("image {:?} properties: {:#?}", format, properties);
+
+  Ok(
+    config.width <= properties.max_extent.width
+      && config.height <= properties.max_extent.height
+      && config.image_size() <= properties.max_resource_size,
+  )
+}
+
+// checks specific to this example
+pub fn is_suitable(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  _properties: &PhysicalDeviceProperties,
+  config: &Config,
+) -> bool {
+  if !supports_required_image_formats(instance, physical_device, config.format.vk_format()) {
+    log::warn!(
+      "Skipped physical device: Device does not support the required image format ({})",
+      config.format.name()
+    );
+    return false;
+  }
+
+  match supports_image_dimensions(
+    instance,
+    physical_device,
+    config,
+    vk::ImageTiling::OPTIMAL,
+    REQUIRED_IMAGE_USAGES,
+  ) {
+    Ok(supports_dimensions) => {
+      if !supports_dimensions {
+        log::error!(
+          "Skipped physical device: Device does not support the required image dimensions ({}x{})",
+          config.width,
+          config.height
+        );
+        return false;
+      }
+    }
+    Err(err) => {
+      log::error!("Device selection error: {:?}", err);
+      return false;
+    }
+  }
+
+  true
+}
+
+pub fn device_requirements<'a>(
+  is_suitable: &'a dyn Fn(&ash::Instance, vk::PhysicalDevice, &PhysicalDeviceProperties) -> bool,
+) -> DeviceRequirements<'a> {
+  let mut requirements = DeviceRequirements::new(TARGET_API_VERSION);
+  requirements.extensions = &REQUIRED_DEVICE_EXTENSIONS;
+  requirements.features.f12.timeline_semaphore = vk::TRUE;
+  requirements.features.f13.synchronization2 = vk::TRUE;
+  // timestamp queries are reset from the host
+  requirements.features.f12.host_query_reset = vk::TRUE;
+  requirements.queue_families = QueueFamilyRequirements {
+    graphics: false,
+    compute: true,
+    presentation: None,
+  };
+  requirements.is_suitable = is_suitable;
+  requirements
+}
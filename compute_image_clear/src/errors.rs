@@ -1,6 +1,6 @@
 use ash::vk;
 
-use crate::utility::error_chain_fmt;
+use ash_by_example_core::{utility::error_chain_fmt, AllocatorError, DeviceSelectionError};
 
 #[derive(thiserror::Error)]
 pub enum OutOfMemoryError {
@@ -39,10 +39,10 @@ impl From<OutOfMemoryError> for vk::Result {
 #[derive(thiserror::Error)]
 pub enum InitializationError {
   #[error("No physical device supports the application")]
-  NoCompatibleDevices,
+  NoCompatibleDevices(#[source] DeviceSelectionError),
 
   #[error("Not enough memory")]
-  NotEnoughMemory(#[source] Option<AllocationError>),
+  NotEnoughMemory(#[source] Option<AllocatorError>),
 
   // undefined behavior / driver or application bug (see vl)
   #[error("Device is lost")]
@@ -74,10 +74,28 @@ impl From<vk::Result> for InitializationError {
   }
 }
 
-impl From<AllocationError> for InitializationError {
-  fn from(value: AllocationError) -> Self {
+impl From<DeviceSelectionError> for InitializationError {
+  fn from(value: DeviceSelectionError) -> Self {
     match value {
-      AllocationError::NotEnoughMemory(_) => {}
+      DeviceSelectionError::Vulkan(vk_err) => vk_err.into(),
+      _ => InitializationError::NoCompatibleDevices(value),
+    }
+  }
+}
+
+impl From<OutOfMemoryError> for InitializationError {
+  fn from(_value: OutOfMemoryError) -> Self {
+    InitializationError::NotEnoughMemory(None)
+  }
+}
+
+impl From<AllocatorError> for InitializationError {
+  fn from(value: AllocatorError) -> Self {
+    match value {
+      AllocatorError::Vulkan(
+        vk::Result::ERROR_OUT_OF_DEVICE_MEMORY | vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+      ) => {}
+      AllocatorError::Vulkan(vk_err) => return vk_err.into(),
       _ => {
         log::error!(
           "Allocation error failed because of an unhandled case: {:?}",
@@ -90,31 +108,76 @@ impl From<AllocationError> for InitializationError {
 }
 
 #[derive(thiserror::Error)]
-pub enum AllocationError {
-  #[error("No memory type supports all buffers and images")]
-  NoMemoryTypeSupportsAll,
-  #[error("Allocation size ({0}) exceeds value allowed by the device")]
-  TotalSizeExceedsAllowed(u64),
-  // allocation size is bigger than each supported heap size
-  #[error("Allocation size ({0}) is bigger than the capacity of each supported heap")]
-  TooBigForAllSupportedHeaps(u64),
-  #[error("Not enough memory")]
-  NotEnoughMemory(#[source] OutOfMemoryError),
+pub enum SaveError {
+  // map can fail with vk::Result::ERROR_MEMORY_MAP_FAILED
+  #[error("Failed to access buffer memory")]
+  Vulkan(#[source] vk::Result),
+  #[error("Failed to save image")]
+  Encoding(#[source] image::ImageError),
 }
-impl std::fmt::Debug for AllocationError {
+impl std::fmt::Debug for SaveError {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     error_chain_fmt(self, f)
   }
 }
 
-impl From<vk::Result> for AllocationError {
+impl From<vk::Result> for SaveError {
   fn from(value: vk::Result) -> Self {
-    AllocationError::NotEnoughMemory(OutOfMemoryError::from(value))
+    SaveError::Vulkan(value)
   }
 }
 
-impl From<OutOfMemoryError> for AllocationError {
-  fn from(value: OutOfMemoryError) -> Self {
-    AllocationError::NotEnoughMemory(value)
+impl From<image::ImageError> for SaveError {
+  fn from(value: image::ImageError) -> Self {
+    SaveError::Encoding(value)
+  }
+}
+
+// all errors that can stop the example, reported by main
+#[derive(thiserror::Error)]
+pub enum RunError {
+  #[error("Failed to initialize")]
+  Initialization(#[source] InitializationError),
+  // can be vk::Result::ERROR_DEVICE_LOST
+  #[error("Failed to submit work")]
+  Submission(#[source] vk::Result),
+  #[error("Failed to save the resulting image")]
+  Saving(#[source] SaveError),
+}
+impl std::fmt::Debug for RunError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl RunError {
+  // the work can be tried again from scratch with a new device
+  pub fn is_device_lost(&self) -> bool {
+    matches!(
+      self,
+      RunError::Initialization(InitializationError::DeviceLost)
+        | RunError::Submission(vk::Result::ERROR_DEVICE_LOST)
+    )
+  }
+}
+
+#[derive(thiserror::Error)]
+pub enum ConfigError {
+  #[error("Unknown option \"{0}\"")]
+  UnknownOption(String),
+  #[error("Missing value for option \"{0}\"")]
+  MissingValue(String),
+  #[error("Invalid value \"{value}\" for option \"{option}\": {reason}")]
+  InvalidValue {
+    option: String,
+    value: String,
+    reason: String,
+  },
+  #[error("Image width and height should be greater than 0")]
+  ZeroSizedImage,
+}
+impl std::fmt::Debug for ConfigError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
   }
 }
@@ -1,75 +1,108 @@
-mod allocator;
-mod command_pools;
+mod config;
 mod device;
-mod entry;
 mod errors;
-mod instance;
+mod passes;
 mod renderer;
-mod utility;
-
-// validation layers module will only exist if validation layers are enabled
-#[cfg(feature = "vl")]
-mod validation_layers;
 
 use ash::vk;
+use ash_by_example_core::{
+  bench, cstr,
+  device_lost::FakeDeviceLost,
+  profiler::{format_table, total_duration},
+};
 use std::ffi::CStr;
 
-use crate::renderer::Renderer;
-
-// validation layers names should be valid cstrings (not contain null bytes nor invalid characters)
-#[cfg(feature = "vl")]
-const VALIDATION_LAYERS: [&'static CStr; 1] = [cstr!("VK_LAYER_KHRONOS_validation")];
-#[cfg(feature = "vl")]
-const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] = [
-  vk::ValidationFeatureEnableEXT::BEST_PRACTICES,
-  vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION,
-];
+use crate::{config::Config, errors::RunError, renderer::Renderer};
 
 const TARGET_API_VERSION: u32 = vk::API_VERSION_1_3;
 
-const APPLICATION_NAME: &'static CStr = cstr!("Image clear");
+const APPLICATION_NAME: &CStr = cstr!("Image clear");
 const APPLICATION_VERSION: u32 = vk::make_api_version(0, 1, 0, 0);
 
-const REQUIRED_DEVICE_EXTENSIONS: [&'static CStr; 0] = [];
+const REQUIRED_DEVICE_EXTENSIONS: [&CStr; 0] = [];
 
-const IMAGE_WIDTH: u32 = 1920;
-const IMAGE_HEIGHT: u32 = 1080;
+// number of times the work is done again from scratch after the device gets lost
+const DEVICE_LOST_RETRIES: u32 = 2;
 
-const IMAGE_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
-const IMAGE_FORMAT_SIZE: u64 = 4;
-const IMAGE_MINIMAL_SIZE: u64 = IMAGE_WIDTH as u64 * IMAGE_HEIGHT as u64 * IMAGE_FORMAT_SIZE;
+// every device level object is recreated (and the physical device selected again) if the device
+// gets lost during initialization or submission
+fn run(config: &Config) -> Result<(), RunError> {
+  let mut fake_device_lost = FakeDeviceLost::from_env();
+  let mut retries = 0;
+  loop {
+    match run_once(config, &mut fake_device_lost) {
+      Err(err) if err.is_device_lost() && retries < DEVICE_LOST_RETRIES => {
+        retries += 1;
+        log::warn!(
+          "Device lost, recreating the renderer ({}/{})",
+          retries,
+          DEVICE_LOST_RETRIES
+        );
+      }
+      result => return result,
+    }
+  }
+}
 
-const IMAGE_SAVE_TYPE: image::ColorType = image::ColorType::Rgba8; // should be equivalent
-                                                                   // valid color values depend on IMAGE_FORMAT
-const IMAGE_COLOR: vk::ClearColorValue = vk::ClearColorValue {
-  float32: [134.0 / 255.0, 206.0 / 255.0, 203.0 / 255.0, 1.0], // rgba(134, 206, 203, 255)
-};
+fn run_once(config: &Config, fake_device_lost: &mut FakeDeviceLost) -> Result<(), RunError> {
+  let mut renderer = Renderer::initialize(config).map_err(RunError::Initialization)?;
 
-const IMAGE_SAVE_PATH: &str = "image.png";
+  match config.bench {
+    Some(iterations) => {
+      // the recorded command buffers are submitted again in each iteration
+      println!("Running {} benchmark iterations...", iterations);
+      let pixels = config.width as u64 * config.height as u64;
+      let report = bench::run(iterations, pixels, || {
+        fake_device_lost.check()?;
+        let finished = renderer.submit()?;
+        renderer.wait(finished)?;
+        Ok(total_duration(&renderer.timings()?))
+      })
+      .map_err(RunError::Submission)?;
+      print!("{}", report);
+    }
+    None => {
+      println!("Submitting work...");
+      fake_device_lost
+        .check()
+        .and_then(|_| renderer.submit())
+        .and_then(|finished| renderer.wait(finished))
+        .map_err(RunError::Submission)?;
+      println!("GPU finished!");
+      let timings = renderer.timings().map_err(RunError::Submission)?;
+      print!("{}", format_table(&timings));
+    }
+  }
+
+  println!("Saving file...");
+  renderer.save_image(config).map_err(RunError::Saving)?;
+  println!("Done!");
+
+  // renderer objects are destroyed when it is dropped
+  log::info!("Destroying and releasing resources");
+  Ok(())
+}
 
 fn main() {
   env_logger::init();
 
-  let mut renderer = Renderer::initialize(IMAGE_WIDTH, IMAGE_HEIGHT, IMAGE_MINIMAL_SIZE).expect("Failed to initialize");
-  unsafe { renderer.record_work() }.expect("Failed to record work");
+  // image size, color, format and output path are given at runtime, see config.rs
+  let config = match Config::from_env_and_args() {
+    Ok(Some(config)) => config,
+    Ok(None) => {
+      println!("{}", config::USAGE);
+      return;
+    }
+    Err(err) => {
+      eprintln!("{}\n\n{}", err, config::USAGE);
+      std::process::exit(2);
+    }
+  };
+  log::debug!("Running with {:?}", config);
 
-  println!("Submitting work...");
-  renderer.submit_and_wait().expect("Failed to submit work");
-  println!("GPU finished!");
-
-  println!("Saving file...");
-  unsafe {
-    renderer.get_resulting_data(|data| {
-      image::save_buffer(
-        IMAGE_SAVE_PATH,
-        data,
-        IMAGE_WIDTH,
-        IMAGE_HEIGHT,
-        IMAGE_SAVE_TYPE,
-      )
-      .expect("Failed to save image");
-    })
+  if let Err(err) = run(&config) {
+    // Debug prints the whole error chain
+    eprintln!("{:?}", err);
+    std::process::exit(1);
   }
-  .expect("Failed to get resulting data");
-  println!("Done!");
 }
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use ash::vk;
+use ash_by_example_core::{
+  graph::{CompiledGraph, QueueType, RenderGraph},
+  Device, PhysicalDevice, Queues,
+};
+
+// image has 1 mip_level / 1 array layer
+const SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  base_mip_level: 0,
+  level_count: 1,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// 1 color layer
+const SUBRESOURCE_LAYERS: vk::ImageSubresourceLayers = vk::ImageSubresourceLayers {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  mip_level: 0,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// Clears image in the compute queue, copies it to buffer in the transfer queue and makes the
+// buffer readable by the host
+// The graph inserts the layout transitions, the image ownership transfer from the compute to the
+// transfer family and the semaphores between both submissions
+pub fn build_graph(
+  device: &Arc<Device>,
+  physical_device: &PhysicalDevice,
+  queues: &Queues,
+  image: vk::Image,
+  image_extent: vk::Extent3D,
+  buffer: vk::Buffer,
+  clear_color: vk::ClearColorValue,
+) -> Result<CompiledGraph, vk::Result> {
+  let mut graph = RenderGraph::new(device, &physical_device.queue_families, queues);
+  // time each pass
+  graph.enable_profiling(physical_device);
+  // log what was executing if the device gets lost
+  graph.enable_loss_diagnostics(physical_device);
+  graph.import_image(image, SUBRESOURCE_RANGE);
+  graph.import_buffer(buffer);
+
+  // previous contents get overwritten
+  graph
+    .add_pass("clear", QueueType::Compute)
+    .discard_image(
+      image,
+      vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+      vk::PipelineStageFlags2::CLEAR,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    )
+    .record(move |device, cb| unsafe {
+      device.cmd_clear_color_image(
+        cb,
+        image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &clear_color,
+        &[SUBRESOURCE_RANGE],
+      );
+    });
+
+  graph
+    .add_pass("copy to buffer", QueueType::Transfer)
+    .image(
+      image,
+      vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_READ,
+    )
+    .discard_buffer(
+      buffer,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    )
+    .record(move |device, cb| unsafe {
+      // full image
+      let copy_region = vk::BufferImageCopy {
+        image_subresource: SUBRESOURCE_LAYERS,
+        image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        image_extent,
+        buffer_offset: 0,
+        buffer_image_height: 0, // densely packed
+        buffer_row_length: 0,
+      };
+      device.cmd_copy_image_to_buffer(
+        cb,
+        image,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        buffer,
+        &[copy_region],
+      );
+    });
+
+  // flush memory to host
+  graph
+    .add_pass("host read", QueueType::Transfer)
+    .buffer(
+      buffer,
+      vk::PipelineStageFlags2::HOST,
+      vk::AccessFlags2::HOST_READ,
+    )
+    .barriers_only();
+
+  graph.compile()
+}
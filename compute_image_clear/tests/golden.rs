@@ -0,0 +1,56 @@
+// Runs the example and compares the saved image with tests/reference/image.png
+// See golden/src/lib.rs on how to run this against a software Vulkan implementation
+
+use std::path::Path;
+
+use ash_by_example_core::device_lost::FAKE_DEVICE_LOST_VAR;
+use ash_by_example_golden::{
+  assert_matches_reference, run_example, run_example_with_env, skip_without_vulkan, Tolerance,
+};
+
+#[test]
+fn cleared_image_matches_reference() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("clear.png");
+
+  run_example(
+    env!("CARGO_BIN_EXE_compute-image-clear"),
+    dir,
+    &["--output", output.to_str().unwrap()],
+  );
+
+  // clearing to a UNORM color is exact
+  assert_matches_reference(
+    &output,
+    &dir.join("tests/reference/image.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    Tolerance::EXACT,
+  );
+}
+
+// the first submission reports a lost device, after which everything is created again
+#[test]
+fn cleared_image_recovers_from_device_loss() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("clear_device_lost.png");
+
+  run_example_with_env(
+    env!("CARGO_BIN_EXE_compute-image-clear"),
+    dir,
+    &["--output", output.to_str().unwrap()],
+    &[(FAKE_DEVICE_LOST_VAR, "1")],
+  );
+
+  assert_matches_reference(
+    &output,
+    &dir.join("tests/reference/image.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    Tolerance::EXACT,
+  );
+}
@@ -0,0 +1,143 @@
+// Repeated execution of the same recorded work, used by the --bench mode of the examples
+//
+// Each iteration measures the wall-clock time the host spends submitting and waiting for the
+// work, as well as the GPU time reported by timestamp queries (if the queue families support
+// them). Results are summarized into min, mean and 95th percentile and into pixel throughput.
+
+use std::{
+  fmt,
+  time::{Duration, Instant},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Summary {
+  pub min: Duration,
+  pub mean: Duration,
+  // nearest-rank 95th percentile
+  pub p95: Duration,
+}
+
+impl Summary {
+  // None if there are no samples
+  pub fn new(samples: &[Duration]) -> Option<Self> {
+    if samples.is_empty() {
+      return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let p95_rank = (sorted.len() * 95).div_ceil(100);
+    Some(Self {
+      min: sorted[0],
+      mean: sorted.iter().sum::<Duration>() / sorted.len() as u32,
+      p95: sorted[p95_rank - 1],
+    })
+  }
+}
+
+pub struct BenchReport {
+  pub wall: Vec<Duration>,
+  // empty if GPU times are not available
+  pub gpu: Vec<Duration>,
+  // pixels produced by each iteration
+  pub pixels: u64,
+}
+
+// megapixels per second at the mean duration
+fn mpix_per_second(pixels: u64, duration: Duration) -> f64 {
+  pixels as f64 / 1_000_000.0 / duration.as_secs_f64()
+}
+
+impl fmt::Display for BenchReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(
+      f,
+      "{:<6}{:>13}{:>13}{:>13}{:>13}",
+      "", "min", "mean", "p95", "MPix/s"
+    )?;
+    for (name, samples) in [("wall", &self.wall), ("gpu", &self.gpu)] {
+      match Summary::new(samples) {
+        Some(summary) => {
+          let ms = |d: Duration| format!("{:.3} ms", d.as_secs_f64() * 1000.0);
+          writeln!(
+            f,
+            "{:<6}{:>13}{:>13}{:>13}{:>13.1}",
+            name,
+            ms(summary.min),
+            ms(summary.mean),
+            ms(summary.p95),
+            mpix_per_second(self.pixels, summary.mean)
+          )?;
+        }
+        None => writeln!(f, "{:<6}{:>13}", name, "unsupported")?,
+      }
+    }
+    Ok(())
+  }
+}
+
+// Calls run_once for each iteration, after one warm-up call that is not measured
+// run_once should submit the work, wait for it and return its GPU time if available
+pub fn run<E>(
+  iterations: u32,
+  pixels: u64,
+  mut run_once: impl FnMut() -> Result<Option<Duration>, E>,
+) -> Result<BenchReport, E> {
+  // first submission can include lazy driver initialization
+  run_once()?;
+
+  let mut wall = Vec::with_capacity(iterations as usize);
+  let mut gpu = Vec::with_capacity(iterations as usize);
+  for _ in 0..iterations {
+    let start = Instant::now();
+    let gpu_time = run_once()?;
+    wall.push(start.elapsed());
+    gpu.extend(gpu_time);
+  }
+  // only report GPU times if all iterations have them
+  if gpu.len() != wall.len() {
+    gpu.clear();
+  }
+
+  Ok(BenchReport { wall, gpu, pixels })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ms(values: impl IntoIterator<Item = u64>) -> Vec<Duration> {
+    values.into_iter().map(Duration::from_millis).collect()
+  }
+
+  #[test]
+  fn summary_of_unsorted_samples() {
+    let summary = Summary::new(&ms((1..=20).rev())).unwrap();
+    assert_eq!(summary.min, Duration::from_millis(1));
+    assert_eq!(summary.mean, Duration::from_micros(10500));
+    assert_eq!(summary.p95, Duration::from_millis(19));
+
+    let single = Summary::new(&ms([7])).unwrap();
+    assert_eq!((single.min, single.p95), (single.mean, single.mean));
+    assert!(Summary::new(&[]).is_none());
+  }
+
+  #[test]
+  fn run_skips_warm_up_and_partial_gpu_times() {
+    let mut calls = 0;
+    let report = run::<()>(3, 1_000_000, || {
+      calls += 1;
+      // first measured iteration has no GPU time
+      Ok((calls > 2).then_some(Duration::from_millis(1)))
+    })
+    .unwrap();
+    assert_eq!(calls, 4);
+    assert_eq!(report.wall.len(), 3);
+    assert!(report.gpu.is_empty());
+    assert!(report.to_string().contains("unsupported"));
+  }
+
+  #[test]
+  fn throughput() {
+    assert_eq!(mpix_per_second(2_000_000, Duration::from_millis(500)), 4.0);
+  }
+}
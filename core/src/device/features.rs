@@ -0,0 +1,196 @@
+use std::{
+  ffi::c_void,
+  mem::{offset_of, size_of},
+  ptr::{self, addr_of, addr_of_mut},
+  slice,
+};
+
+use ash::vk;
+
+// Features of each Vulkan version
+// When used as a requirement, every feature set to vk::TRUE has to be supported
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PhysicalDeviceFeatures {
+  pub f10: vk::PhysicalDeviceFeatures,
+  pub f11: vk::PhysicalDeviceVulkan11Features,
+  pub f12: vk::PhysicalDeviceVulkan12Features,
+  pub f13: vk::PhysicalDeviceVulkan13Features,
+}
+
+// feature structs contain (after the s_type and p_next header) only vk::Bool32 members
+// returns a slice from the first to the last feature
+macro_rules! bool32_members {
+  ($features:expr, $t:ty, $first:ident, $last:ident) => {{
+    let start = offset_of!($t, $first);
+    let end = offset_of!($t, $last) + size_of::<vk::Bool32>();
+    unsafe {
+      slice::from_raw_parts(
+        (addr_of!($features) as *const u8).add(start) as *const vk::Bool32,
+        (end - start) / size_of::<vk::Bool32>(),
+      )
+    }
+  }};
+}
+
+// same as bool32_members, but mutable
+macro_rules! bool32_members_mut {
+  ($features:expr, $t:ty, $first:ident, $last:ident) => {{
+    let start = offset_of!($t, $first);
+    let end = offset_of!($t, $last) + size_of::<vk::Bool32>();
+    unsafe {
+      slice::from_raw_parts_mut(
+        (addr_of_mut!($features) as *mut u8).add(start) as *mut vk::Bool32,
+        (end - start) / size_of::<vk::Bool32>(),
+      )
+    }
+  }};
+}
+
+fn contains(available: &[vk::Bool32], required: &[vk::Bool32]) -> bool {
+  available
+    .iter()
+    .zip(required)
+    .all(|(&av, &req)| req == vk::FALSE || av == vk::TRUE)
+}
+
+fn any_enabled(features: &[vk::Bool32]) -> bool {
+  features.contains(&vk::TRUE)
+}
+
+impl PhysicalDeviceFeatures {
+  fn members(&self) -> [&[vk::Bool32]; 4] {
+    [
+      bool32_members!(
+        self.f10,
+        vk::PhysicalDeviceFeatures,
+        robust_buffer_access,
+        inherited_queries
+      ),
+      bool32_members!(
+        self.f11,
+        vk::PhysicalDeviceVulkan11Features,
+        storage_buffer16_bit_access,
+        shader_draw_parameters
+      ),
+      bool32_members!(
+        self.f12,
+        vk::PhysicalDeviceVulkan12Features,
+        sampler_mirror_clamp_to_edge,
+        subgroup_broadcast_dynamic_id
+      ),
+      bool32_members!(
+        self.f13,
+        vk::PhysicalDeviceVulkan13Features,
+        robust_image_access,
+        maintenance4
+      ),
+    ]
+  }
+
+  fn members_mut(&mut self) -> [&mut [vk::Bool32]; 4] {
+    [
+      bool32_members_mut!(
+        self.f10,
+        vk::PhysicalDeviceFeatures,
+        robust_buffer_access,
+        inherited_queries
+      ),
+      bool32_members_mut!(
+        self.f11,
+        vk::PhysicalDeviceVulkan11Features,
+        storage_buffer16_bit_access,
+        shader_draw_parameters
+      ),
+      bool32_members_mut!(
+        self.f12,
+        vk::PhysicalDeviceVulkan12Features,
+        sampler_mirror_clamp_to_edge,
+        subgroup_broadcast_dynamic_id
+      ),
+      bool32_members_mut!(
+        self.f13,
+        vk::PhysicalDeviceVulkan13Features,
+        robust_image_access,
+        maintenance4
+      ),
+    ]
+  }
+
+  // features enabled in self plus the ones enabled in "optional" that are also enabled in
+  // "supported"
+  pub fn with_supported(&self, optional: &Self, supported: &Self) -> Self {
+    let mut features = *self;
+    for ((enabled, opt), sup) in features
+      .members_mut()
+      .into_iter()
+      .zip(optional.members())
+      .zip(supported.members())
+    {
+      for ((en, &o), &s) in enabled.iter_mut().zip(opt).zip(sup) {
+        if o == vk::TRUE && s == vk::TRUE {
+          *en = vk::TRUE;
+        }
+      }
+    }
+    features
+  }
+
+  // returns true if all features enabled in "required" are also enabled in self
+  pub fn contains(&self, required: &Self) -> bool {
+    self
+      .members()
+      .into_iter()
+      .zip(required.members())
+      .all(|(av, req)| contains(av, req))
+  }
+
+  // Returns a copy of the features that can be passed to device creation
+  // Vulkan11, Vulkan12 and Vulkan13 structures are only chained if they contain any enabled
+  // feature, as they are not valid in devices that don't support their respective versions
+  // Also returns the root of the chain, to be used as DeviceCreateInfo's p_next
+  pub(crate) fn chained(&self) -> (Box<Self>, *const c_void) {
+    let mut features = Box::new(*self);
+    let [_, f11, f12, f13] = self.members().map(any_enabled);
+
+    features.f13.p_next = ptr::null_mut();
+    let mut next = ptr::null_mut();
+    if f13 {
+      next = addr_of!(features.f13) as *mut _;
+    }
+    features.f12.p_next = next;
+    if f12 {
+      next = addr_of!(features.f12) as *mut _;
+    }
+    features.f11.p_next = next;
+    if f11 {
+      next = addr_of!(features.f11) as *mut _;
+    }
+
+    (features, next as *const c_void)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn only_supported_optional_features_are_added() {
+    let mut required = PhysicalDeviceFeatures::default();
+    required.f13.synchronization2 = vk::TRUE;
+    let mut optional = PhysicalDeviceFeatures::default();
+    optional.f10.pipeline_statistics_query = vk::TRUE;
+    optional.f10.occlusion_query_precise = vk::TRUE;
+    optional.f13.maintenance4 = vk::TRUE;
+    let mut supported = required;
+    supported.f10.pipeline_statistics_query = vk::TRUE;
+    supported.f13.maintenance4 = vk::TRUE;
+
+    let enabled = required.with_supported(&optional, &supported);
+    assert_eq!(enabled.f13.synchronization2, vk::TRUE);
+    assert_eq!(enabled.f10.pipeline_statistics_query, vk::TRUE);
+    assert_eq!(enabled.f13.maintenance4, vk::TRUE);
+    assert_eq!(enabled.f10.occlusion_query_precise, vk::FALSE);
+    assert!(supported.contains(&enabled));
+  }
+}
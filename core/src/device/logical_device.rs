@@ -0,0 +1,61 @@
+use ash::vk;
+use std::{
+  ffi::{c_char, c_void},
+  ptr,
+};
+
+use crate::diagnostics::DEVICE_FAULT_EXTENSION;
+
+use super::{DeviceRequirements, PhysicalDevice, Queues};
+
+// Creates a logical device with all required extensions and features enabled, as well as the
+// optional extensions and features that are supported
+pub fn create_logical_device(
+  instance: &ash::Instance,
+  physical_device: &PhysicalDevice,
+  requirements: &DeviceRequirements,
+) -> Result<(ash::Device, Queues), vk::Result> {
+  let queue_create_infos = Queues::get_queue_create_infos(&physical_device.queue_families);
+
+  let enabled_extensions = requirements.enabled_extensions(physical_device);
+  let device_extensions_pointers: Vec<*const c_char> =
+    enabled_extensions.iter().map(|s| s.as_ptr()).collect();
+
+  // enabled features, have to be alive until device creation
+  let (features, mut features_p_next) = requirements
+    .enabled_features(&physical_device.features)
+    .chained();
+  // deviceFault has to be supported by devices that support VK_EXT_device_fault
+  let fault_features = vk::PhysicalDeviceFaultFeaturesEXT {
+    s_type: vk::StructureType::PHYSICAL_DEVICE_FAULT_FEATURES_EXT,
+    p_next: features_p_next as *mut c_void,
+    device_fault: vk::TRUE,
+    device_fault_vendor_binary: vk::FALSE,
+  };
+  if enabled_extensions.contains(&DEVICE_FAULT_EXTENSION) {
+    features_p_next = &fault_features as *const _ as *const c_void;
+  }
+
+  // pp_enabled_layer_names are deprecated however they are still required in struct initialization
+  #[allow(deprecated)]
+  let create_info = vk::DeviceCreateInfo {
+    s_type: vk::StructureType::DEVICE_CREATE_INFO,
+    p_queue_create_infos: queue_create_infos.as_ptr(),
+    queue_create_info_count: queue_create_infos.len() as u32,
+    p_enabled_features: &features.f10,
+    p_next: features_p_next,
+    pp_enabled_layer_names: ptr::null(), // deprecated
+    enabled_layer_count: 0,              // deprecated
+    pp_enabled_extension_names: device_extensions_pointers.as_ptr(),
+    enabled_extension_count: device_extensions_pointers.len() as u32,
+    flags: vk::DeviceCreateFlags::empty(),
+  };
+  log::debug!("Creating logical device");
+  let device: ash::Device =
+    unsafe { instance.create_device(**physical_device, &create_info, None)? };
+
+  log::debug!("Retrieving queues");
+  let queues = unsafe { Queues::retrieve(&device, &physical_device.queue_families) };
+
+  Ok((device, queues))
+}
@@ -0,0 +1,356 @@
+mod features;
+mod logical_device;
+mod physical_device;
+mod queues;
+mod selector;
+mod vendor;
+
+pub use features::PhysicalDeviceFeatures;
+pub use logical_device::create_logical_device;
+pub use physical_device::{MemoryTypesIterator, PhysicalDevice, UniqueHeapMemoryTypesIterator};
+pub use queues::{QueueCreateInfos, QueueFamilies, QueueFamily, QueueFamilyRequirements, Queues};
+pub use selector::{
+  format_uuid, DeviceSelectionError, DeviceSelector, RejectedDevice, RejectionReason,
+  DEVICE_SELECTOR_VAR,
+};
+pub use vendor::Vendor;
+
+use std::{
+  ffi::{c_void, CStr},
+  mem::MaybeUninit,
+  ptr::{self, addr_of_mut},
+};
+
+use ash::vk;
+
+use crate::{
+  diagnostics::DEVICE_FAULT_EXTENSION,
+  utility::{self, c_char_array_to_string},
+};
+
+// Everything an application requires from a physical device
+// Devices that don't fulfill all requirements are skipped during selection
+pub struct DeviceRequirements<'a> {
+  pub api_version: u32,
+  pub extensions: &'a [&'a CStr],
+  // extensions that get enabled if they are supported, without affecting selection
+  pub optional_extensions: &'a [&'a CStr],
+  // features that have to be supported and that get enabled during logical device creation
+  pub features: PhysicalDeviceFeatures,
+  // features that get enabled if they are supported, without affecting selection
+  pub optional_features: PhysicalDeviceFeatures,
+  pub queue_families: QueueFamilyRequirements<'a>,
+  // any additional application specific checks (formats, limits, etc.)
+  // should log why a device is not suitable
+  pub is_suitable:
+    &'a dyn Fn(&ash::Instance, vk::PhysicalDevice, &PhysicalDeviceProperties) -> bool,
+}
+
+impl<'a> DeviceRequirements<'a> {
+  pub fn new(api_version: u32) -> Self {
+    Self {
+      api_version,
+      extensions: &[],
+      // reports what caused a device loss, see diagnostics.rs
+      optional_extensions: &[DEVICE_FAULT_EXTENSION],
+      features: PhysicalDeviceFeatures::default(),
+      optional_features: PhysicalDeviceFeatures::default(),
+      queue_families: QueueFamilyRequirements::default(),
+      is_suitable: &|_, _, _| true,
+    }
+  }
+}
+
+impl DeviceRequirements<'_> {
+  // features that get enabled on a device that supports the requirements
+  pub fn enabled_features(&self, supported: &PhysicalDeviceFeatures) -> PhysicalDeviceFeatures {
+    self
+      .features
+      .with_supported(&self.optional_features, supported)
+  }
+
+  // required extensions plus the optional ones that the device supports
+  pub fn enabled_extensions(&self, physical_device: &PhysicalDevice) -> Vec<&CStr> {
+    self
+      .extensions
+      .iter()
+      .chain(
+        self
+          .optional_extensions
+          .iter()
+          .filter(|&&ext| physical_device.supports_extension(ext)),
+      )
+      .copied()
+      .collect()
+  }
+}
+
+fn log_device_properties(index: usize, properties: &PhysicalDeviceProperties) {
+  let p10 = &properties.p10;
+  let vendor = Vendor::from_id(p10.vendor_id);
+  let driver_version = vendor.parse_driver_version(p10.driver_version);
+
+  log::info!(
+    "\nFound physical device {} \"{}\":
+      API Version: {},
+      Vendor: {},
+      Driver Version: {},
+      ID: {},
+      UUID: {},
+      Type: {},",
+    index,
+    c_char_array_to_string(&p10.device_name),
+    utility::parse_vulkan_api_version(p10.api_version),
+    vendor,
+    driver_version,
+    p10.device_id,
+    format_uuid(&properties.p11.device_uuid),
+    match p10.device_type {
+      vk::PhysicalDeviceType::INTEGRATED_GPU => "Integrated GPU",
+      vk::PhysicalDeviceType::DISCRETE_GPU => "Discrete GPU",
+      vk::PhysicalDeviceType::VIRTUAL_GPU => "Virtual GPU",
+      vk::PhysicalDeviceType::CPU => "CPU",
+      _ => "Unknown",
+    },
+  );
+}
+
+// returns the names of all required extensions that the device doesn't support
+fn get_missing_extensions(
+  instance: &ash::Instance,
+  device: vk::PhysicalDevice,
+  extensions: &[&CStr],
+) -> Result<Vec<String>, vk::Result> {
+  let properties = unsafe { instance.enumerate_device_extension_properties(device)? };
+
+  Ok(
+    extensions
+      .iter()
+      .filter(|&&req| {
+        !properties
+          .iter()
+          .any(|props| utility::i8_array_as_cstr(&props.extension_name).unwrap() == req)
+      })
+      .map(|req| req.to_string_lossy().into_owned())
+      .collect(),
+  )
+}
+
+// Filter devices that are strictly not supported
+// Check for any features, limits or queue families required by the application
+unsafe fn check_physical_device(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  properties: &PhysicalDeviceProperties,
+  requirements: &DeviceRequirements,
+) -> Result<(PhysicalDeviceFeatures, QueueFamilies), RejectionReason> {
+  if properties.p10.api_version < requirements.api_version {
+    return Err(RejectionReason::ApiVersion {
+      supported: properties.p10.api_version,
+      required: requirements.api_version,
+    });
+  }
+
+  // check if device supports all required extensions
+  let missing_extensions =
+    get_missing_extensions(instance, physical_device, requirements.extensions)
+      .map_err(RejectionReason::QueryFailed)?;
+  if !missing_extensions.is_empty() {
+    return Err(RejectionReason::MissingExtensions(missing_extensions));
+  }
+
+  let features = get_extended_features(instance, physical_device);
+  if !features.contains(&requirements.features) {
+    return Err(RejectionReason::MissingFeatures);
+  }
+
+  if !(requirements.is_suitable)(instance, physical_device, properties) {
+    return Err(RejectionReason::NotSuitable);
+  }
+
+  // filter devices that do not have required queue families
+  let families = QueueFamilies::get_from_physical_device(
+    instance,
+    physical_device,
+    &requirements.queue_families,
+  )
+  .ok_or(RejectionReason::MissingQueueFamilies)?;
+
+  Ok((features, families))
+}
+
+// Assign a score to each device, lower is better
+// A full application may use multiple metrics like limits, queue families and even the device id
+// to rank each device that a user can have
+fn device_score(properties: &PhysicalDeviceProperties, families: &QueueFamilies) -> (usize, u32) {
+  // rank devices by number of specialized queue families
+  let queue_score = families.shared_family_count();
+
+  // rank devices by commonly most powerful device type
+  let device_type_score = match properties.p10.device_type {
+    vk::PhysicalDeviceType::DISCRETE_GPU => 0,
+    vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
+    vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+    vk::PhysicalDeviceType::CPU => 3,
+    vk::PhysicalDeviceType::OTHER => 4,
+    _ => 5,
+  };
+
+  // compared lexicographically, device type only breaks ties between equal queue scores
+  (queue_score, device_type_score)
+}
+
+// Selects the device with the best score out of all devices that fulfill the requirements
+// If ABE_DEVICE is set, only devices that match it are considered
+unsafe fn select_physical_device(
+  instance: &ash::Instance,
+  requirements: &DeviceRequirements,
+) -> Result<
+  (
+    vk::PhysicalDevice,
+    PhysicalDeviceProperties,
+    PhysicalDeviceFeatures,
+    QueueFamilies,
+  ),
+  DeviceSelectionError,
+> {
+  let selector = DeviceSelector::from_env()?;
+  if let Some(selector) = &selector {
+    log::info!(
+      "Only considering physical devices with {} ({} is set)",
+      selector,
+      DEVICE_SELECTOR_VAR
+    );
+  }
+
+  let mut rejected = Vec::new();
+  let mut best = None;
+  for (index, physical_device) in instance
+    .enumerate_physical_devices()?
+    .into_iter()
+    .enumerate()
+  {
+    let properties = get_extended_properties(instance, physical_device);
+    log_device_properties(index, &properties);
+
+    let result = match &selector {
+      Some(selector) if !selector.matches(index, &properties) => Err(RejectionReason::NotSelected),
+      _ => check_physical_device(instance, physical_device, &properties, requirements),
+    };
+
+    match result {
+      Ok((features, families)) => {
+        let score = device_score(&properties, &families);
+        // the first device is kept if scores are equal
+        if best
+          .as_ref()
+          .is_none_or(|(best_score, _)| score < *best_score)
+        {
+          best = Some((score, (physical_device, properties, features, families)));
+        }
+      }
+      Err(reason) => {
+        log::info!("Skipped physical device: Device {}", reason);
+        rejected.push(RejectedDevice::new(index, &properties, reason));
+      }
+    }
+  }
+
+  best
+    .map(|(_score, device)| device)
+    .ok_or(DeviceSelectionError::NoSuitableDevice { selector, rejected })
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalDeviceProperties {
+  pub p10: vk::PhysicalDeviceProperties,
+  pub p11: vk::PhysicalDeviceVulkan11Properties,
+  pub p12: vk::PhysicalDeviceVulkan12Properties,
+  pub p13: vk::PhysicalDeviceVulkan13Properties,
+}
+
+// queries Vulkan 1.0 to 1.3 properties
+pub fn get_extended_properties(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+) -> PhysicalDeviceProperties {
+  // see https://doc.rust-lang.org/std/mem/union.MaybeUninit.html
+  let mut props10: MaybeUninit<vk::PhysicalDeviceProperties2> = MaybeUninit::uninit();
+  let mut props11: MaybeUninit<vk::PhysicalDeviceVulkan11Properties> = MaybeUninit::uninit();
+  let mut props12: MaybeUninit<vk::PhysicalDeviceVulkan12Properties> = MaybeUninit::uninit();
+  let mut props13: MaybeUninit<vk::PhysicalDeviceVulkan13Properties> = MaybeUninit::uninit();
+
+  let props10_ptr = props10.as_mut_ptr();
+  let props11_ptr = props11.as_mut_ptr();
+  let props12_ptr = props12.as_mut_ptr();
+  let props13_ptr = props13.as_mut_ptr();
+
+  unsafe {
+    addr_of_mut!((*props10_ptr).s_type).write(vk::StructureType::PHYSICAL_DEVICE_PROPERTIES_2);
+    addr_of_mut!((*props11_ptr).s_type)
+      .write(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_1_PROPERTIES);
+    addr_of_mut!((*props12_ptr).s_type)
+      .write(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_PROPERTIES);
+    addr_of_mut!((*props13_ptr).s_type)
+      .write(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_PROPERTIES);
+
+    addr_of_mut!((*props10_ptr).p_next).write(props11_ptr as *mut c_void);
+    addr_of_mut!((*props11_ptr).p_next).write(props12_ptr as *mut c_void);
+    addr_of_mut!((*props12_ptr).p_next).write(props13_ptr as *mut c_void);
+    addr_of_mut!((*props13_ptr).p_next).write(ptr::null_mut::<c_void>());
+
+    instance.get_physical_device_properties2(physical_device, props10_ptr.as_mut().unwrap());
+    let mut properties = PhysicalDeviceProperties {
+      p10: props10.assume_init().properties,
+      p11: props11.assume_init(),
+      p12: props12.assume_init(),
+      p13: props13.assume_init(),
+    };
+    // chain pointers don't outlive this function
+    properties.p11.p_next = ptr::null_mut();
+    properties.p12.p_next = ptr::null_mut();
+    properties
+  }
+}
+
+// queries all supported Vulkan 1.0 to 1.3 features
+pub fn get_extended_features(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+) -> PhysicalDeviceFeatures {
+  let mut features10: MaybeUninit<vk::PhysicalDeviceFeatures2> = MaybeUninit::uninit();
+  let mut features11: MaybeUninit<vk::PhysicalDeviceVulkan11Features> = MaybeUninit::uninit();
+  let mut features12: MaybeUninit<vk::PhysicalDeviceVulkan12Features> = MaybeUninit::uninit();
+  let mut features13: MaybeUninit<vk::PhysicalDeviceVulkan13Features> = MaybeUninit::uninit();
+
+  let features10_ptr = features10.as_mut_ptr();
+  let features11_ptr = features11.as_mut_ptr();
+  let features12_ptr = features12.as_mut_ptr();
+  let features13_ptr = features13.as_mut_ptr();
+
+  unsafe {
+    addr_of_mut!((*features10_ptr).s_type).write(vk::StructureType::PHYSICAL_DEVICE_FEATURES_2);
+    addr_of_mut!((*features11_ptr).s_type)
+      .write(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_1_FEATURES);
+    addr_of_mut!((*features12_ptr).s_type)
+      .write(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES);
+    addr_of_mut!((*features13_ptr).s_type)
+      .write(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES);
+
+    addr_of_mut!((*features10_ptr).p_next).write(features11_ptr as *mut c_void);
+    addr_of_mut!((*features11_ptr).p_next).write(features12_ptr as *mut c_void);
+    addr_of_mut!((*features12_ptr).p_next).write(features13_ptr as *mut c_void);
+    addr_of_mut!((*features13_ptr).p_next).write(ptr::null_mut::<c_void>());
+
+    instance.get_physical_device_features2(physical_device, features10_ptr.as_mut().unwrap());
+    let mut features = PhysicalDeviceFeatures {
+      f10: features10.assume_init().features,
+      f11: features11.assume_init(),
+      f12: features12.assume_init(),
+      f13: features13.assume_init(),
+    };
+    features.f11.p_next = ptr::null_mut();
+    features.f12.p_next = ptr::null_mut();
+    features
+  }
+}
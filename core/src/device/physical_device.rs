@@ -0,0 +1,256 @@
+use std::{
+  ffi::{CStr, CString},
+  ops::{BitOr, Deref},
+};
+
+use ash::vk;
+
+use crate::{
+  memory::{MemoryBudget, MemoryBudgetQuery},
+  utility::{self, c_char_array_to_string},
+};
+
+use super::{
+  select_physical_device, DeviceRequirements, DeviceSelectionError, PhysicalDeviceFeatures,
+  PhysicalDeviceProperties, QueueFamilies,
+};
+
+// Saves physical device additional information in order to not query it multiple times
+pub struct PhysicalDevice {
+  inner: vk::PhysicalDevice,
+  pub queue_families: QueueFamilies,
+  pub mem_properties: vk::PhysicalDeviceMemoryProperties,
+  pub properties: PhysicalDeviceProperties,
+  // all supported features, not only the enabled ones
+  pub features: PhysicalDeviceFeatures,
+  supported_extensions: Vec<CString>,
+  budget_query: MemoryBudgetQuery,
+}
+
+impl Deref for PhysicalDevice {
+  type Target = vk::PhysicalDevice;
+
+  fn deref(&self) -> &Self::Target {
+    &self.inner
+  }
+}
+
+impl PhysicalDevice {
+  // Selects the best available physical device that fulfills all requirements
+  // The choice can be overridden with the ABE_DEVICE environment variable (see DeviceSelector)
+  // On failure, the error lists all devices and why each one was rejected
+  pub unsafe fn select(
+    instance: &ash::Instance,
+    requirements: &DeviceRequirements,
+  ) -> Result<PhysicalDevice, DeviceSelectionError> {
+    let (physical_device, properties, features, queue_families) =
+      select_physical_device(instance, requirements)?;
+
+    let mem_properties = instance.get_physical_device_memory_properties(physical_device);
+    let queue_family_properties =
+      instance.get_physical_device_queue_family_properties(physical_device);
+
+    log::info!(
+      "Using physical device \"{}\"",
+      c_char_array_to_string(&properties.p10.device_name)
+    );
+    print_queue_families_debug_info(&queue_family_properties);
+    print_device_memory_debug_info(&mem_properties);
+
+    let supported_extensions = instance
+      .enumerate_device_extension_properties(physical_device)?
+      .iter()
+      .filter_map(|props| utility::i8_array_as_cstr(&props.extension_name).ok())
+      .map(CStr::to_owned)
+      .collect();
+
+    let budget_query = MemoryBudgetQuery::new(instance, physical_device, mem_properties);
+    log::debug!("Memory budget: {}", budget_query.query());
+
+    Ok(PhysicalDevice {
+      inner: physical_device,
+      queue_families,
+      mem_properties,
+      properties,
+      features,
+      supported_extensions,
+      budget_query,
+    })
+  }
+
+  pub fn supports_extension(&self, name: &CStr) -> bool {
+    self
+      .supported_extensions
+      .iter()
+      .any(|ext| ext.as_c_str() == name)
+  }
+
+  // current per heap budget and usage (uses VK_EXT_memory_budget if available)
+  pub fn memory_budget(&self) -> MemoryBudget {
+    self.budget_query.query()
+  }
+
+  pub fn memory_budget_query(&self) -> &MemoryBudgetQuery {
+    &self.budget_query
+  }
+
+  pub fn memory_type(&self, type_i: usize) -> vk::MemoryType {
+    self.mem_properties.memory_types[type_i]
+  }
+
+  pub fn memory_type_heap(&self, type_i: usize) -> vk::MemoryHeap {
+    self.mem_properties.memory_heaps[self.memory_type(type_i).heap_index as usize]
+  }
+
+  // returns the first memory type that is valid and contains all required properties
+  pub fn find_memory_type(
+    &self,
+    valid_types_bitmask: u32,
+    required_properties: vk::MemoryPropertyFlags,
+  ) -> Option<usize> {
+    MemoryTypesIterator::new(self, valid_types_bitmask, required_properties)
+      .next()
+      .map(|(i, _)| i)
+  }
+
+  // Tries to find optimal memory type. If it fails, tries to find a memory type with only
+  // required flags
+  pub fn find_optimal_memory_type(
+    &self,
+    valid_types_bitmask: u32,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Option<usize> {
+    self
+      .find_memory_type(
+        valid_types_bitmask,
+        required_properties.bitor(optional_properties),
+      )
+      .or_else(|| self.find_memory_type(valid_types_bitmask, required_properties))
+  }
+
+  pub fn iterate_memory_types_with_unique_heaps(
+    &self,
+    valid_types_bitmask: u32,
+    memory_properties: vk::MemoryPropertyFlags,
+  ) -> UniqueHeapMemoryTypesIterator<'_> {
+    UniqueHeapMemoryTypesIterator::new(self, valid_types_bitmask, memory_properties)
+  }
+}
+
+pub struct MemoryTypesIterator<'a> {
+  valid_types_bitmask: u32,
+  i: usize,
+  required_properties: vk::MemoryPropertyFlags,
+  types: &'a [vk::MemoryType; vk::MAX_MEMORY_TYPES],
+  types_count: usize,
+}
+
+impl<'a> Iterator for MemoryTypesIterator<'a> {
+  type Item = (usize, vk::MemoryType);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while self.i < self.types_count {
+      let valid_bit = self.valid_types_bitmask & (1 << self.i) > 0;
+      let i = self.i;
+      self.i += 1;
+      if valid_bit
+        && self.types[i]
+          .property_flags
+          .contains(self.required_properties)
+      {
+        return Some((i, self.types[i]));
+      }
+    }
+    None
+  }
+}
+
+impl<'a> MemoryTypesIterator<'a> {
+  pub fn new(
+    physical_device: &'a PhysicalDevice,
+    valid_types_bitmask: u32,
+    memory_properties: vk::MemoryPropertyFlags,
+  ) -> Self {
+    Self {
+      valid_types_bitmask,
+      i: 0,
+      required_properties: memory_properties,
+      types: &physical_device.mem_properties.memory_types,
+      types_count: physical_device.mem_properties.memory_type_count as usize,
+    }
+  }
+}
+
+// filters memory types by unique heaps
+pub struct UniqueHeapMemoryTypesIterator<'a> {
+  iter: MemoryTypesIterator<'a>,
+  iterated_heaps: [bool; vk::MAX_MEMORY_HEAPS],
+}
+
+impl<'a> Iterator for UniqueHeapMemoryTypesIterator<'a> {
+  type Item = <MemoryTypesIterator<'a> as Iterator>::Item;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    for next in self.iter.by_ref() {
+      if !self.iterated_heaps[next.1.heap_index as usize] {
+        self.iterated_heaps[next.1.heap_index as usize] = true;
+        return Some(next);
+      }
+    }
+    None
+  }
+}
+
+impl<'a> UniqueHeapMemoryTypesIterator<'a> {
+  pub fn new(
+    physical_device: &'a PhysicalDevice,
+    valid_types_bitmask: u32,
+    memory_properties: vk::MemoryPropertyFlags,
+  ) -> Self {
+    Self {
+      iter: MemoryTypesIterator::new(physical_device, valid_types_bitmask, memory_properties),
+      iterated_heaps: [false; vk::MAX_MEMORY_HEAPS],
+    }
+  }
+}
+
+fn print_queue_families_debug_info(properties: &[vk::QueueFamilyProperties]) {
+  log::debug!("Queue family properties: {:#?}", properties);
+}
+
+fn print_device_memory_debug_info(mem_properties: &vk::PhysicalDeviceMemoryProperties) {
+  log::debug!("Available memory heaps:");
+  for heap_i in 0..mem_properties.memory_heap_count {
+    let heap = mem_properties.memory_heaps[heap_i as usize];
+    let heap_flags = if heap.flags.is_empty() {
+      String::from("no heap flags")
+    } else {
+      format!("heap flags [{:?}]", heap.flags)
+    };
+
+    log::debug!(
+      "    {} -> {}mb with {} and attributed memory types:",
+      heap_i,
+      heap.size / 1000000,
+      heap_flags
+    );
+    for type_i in 0..mem_properties.memory_type_count {
+      let mem_type = mem_properties.memory_types[type_i as usize];
+      if mem_type.heap_index != heap_i {
+        continue;
+      }
+
+      let flags = mem_type.property_flags;
+      log::debug!(
+        "        {} -> {}",
+        type_i,
+        if flags.is_empty() {
+          "<no flags>".to_owned()
+        } else {
+          format!("[{:?}]", flags)
+        }
+      );
+    }
+  }
+}
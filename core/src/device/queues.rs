@@ -0,0 +1,334 @@
+use std::{cmp::min, ops::Deref, pin::Pin, ptr};
+
+use ash::vk;
+
+#[derive(Debug, Clone, Copy)]
+pub struct QueueFamily {
+  pub index: u32,
+  pub queue_count: u32,
+  // 0 if the family doesn't support timestamp queries
+  pub timestamp_valid_bits: u32,
+}
+
+impl PartialEq for QueueFamily {
+  fn eq(&self, other: &Self) -> bool {
+    self.index == other.index
+  }
+}
+
+// Queue families required by the application
+// Dedicated compute and transfer families are always preferred and, if not available, get
+// substituted by the graphics (or main compute) family
+#[derive(Default)]
+pub struct QueueFamilyRequirements<'a> {
+  pub graphics: bool,
+  pub compute: bool,
+  // if presentation is required, returns if the family with the given index can present to
+  // the surface used by the application
+  #[allow(clippy::type_complexity)]
+  pub presentation: Option<&'a dyn Fn(vk::PhysicalDevice, u32) -> bool>,
+}
+
+#[derive(Debug)]
+pub struct QueueFamilies {
+  // only exists if graphics are required
+  pub graphics: Option<QueueFamily>,
+  // if graphics are required, contains a dedicated compute (non graphics) family if one exists
+  // otherwise, contains the first family that supports compute operations
+  pub compute: Option<QueueFamily>,
+  // dedicated transfer family (without graphics or compute)
+  pub transfer: Option<QueueFamily>,
+  // only exists if presentation is required, will try to be equal to graphics
+  pub presentation: Option<QueueFamily>,
+  pub unique_indices: Box<[u32]>,
+  // if a compute queue should be retrieved, even if it has to be substituted
+  compute_required: bool,
+}
+
+impl QueueFamilies {
+  // graphics, compute, transfer and presentation
+  pub const MAX_FAMILY_COUNT: usize = 4;
+
+  pub fn get_from_physical_device(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    requirements: &QueueFamilyRequirements,
+  ) -> Option<Self> {
+    let properties =
+      unsafe { instance.get_physical_device_queue_family_properties(physical_device) };
+    let supports_presentation = |i: usize| {
+      requirements
+        .presentation
+        .is_some_and(|supports| supports(physical_device, i as u32))
+    };
+
+    let mut presentation = None;
+    let mut graphics = None;
+    let mut compute = None; // first with compute
+    let mut dedicated_compute = None; // non graphics
+    let mut transfer = None; // non graphics and non compute
+    for (i, props) in properties.iter().enumerate() {
+      let family = QueueFamily {
+        index: i as u32,
+        queue_count: props.queue_count,
+        timestamp_valid_bits: props.timestamp_valid_bits,
+      };
+
+      // set presentation to the first supported family
+      if presentation.is_none() && supports_presentation(i) {
+        presentation = Some(family);
+      }
+
+      if props.queue_flags.contains(vk::QueueFlags::COMPUTE) && compute.is_none() {
+        compute = Some(family);
+      }
+
+      if props.queue_flags.contains(vk::QueueFlags::GRAPHICS) {
+        // set graphics to the first supported family
+        if graphics.is_none() {
+          graphics = Some(family);
+        }
+
+        // set presentation and graphics to the first family that supports both
+        if presentation.is_some_and(|presentation_family| {
+          presentation_family != family && graphics != presentation && supports_presentation(i)
+        }) {
+          graphics = Some(family);
+          presentation = Some(family);
+        }
+      } else if props.queue_flags.contains(vk::QueueFlags::COMPUTE) {
+        if dedicated_compute.is_none() {
+          dedicated_compute = Some(family);
+        }
+      } else if props.queue_flags.contains(vk::QueueFlags::TRANSFER) && transfer.is_none() {
+        transfer = Some(family);
+      }
+    }
+
+    if requirements.presentation.is_some() && presentation.is_none() {
+      return None;
+    }
+
+    if requirements.graphics {
+      let graphics_family = graphics?;
+      if requirements.compute {
+        compute = dedicated_compute;
+        // graphics family has to substitute compute
+        if compute.is_none()
+          && !properties[graphics_family.index as usize]
+            .queue_flags
+            .contains(vk::QueueFlags::COMPUTE)
+        {
+          return None;
+        }
+      } else {
+        compute = None;
+        // compute only families also support transfer operations
+        if transfer.is_none() {
+          transfer = dedicated_compute;
+        }
+      }
+    } else {
+      graphics = None;
+      if requirements.compute {
+        compute?;
+      } else {
+        compute = None;
+      }
+    }
+
+    // commonly used
+    let mut unique_indices: Vec<u32> = Vec::with_capacity(Self::MAX_FAMILY_COUNT);
+    for family in [graphics, compute, transfer, presentation]
+      .into_iter()
+      .flatten()
+    {
+      if !unique_indices.contains(&family.index) {
+        unique_indices.push(family.index);
+      }
+    }
+
+    Some(QueueFamilies {
+      graphics,
+      compute,
+      transfer,
+      presentation,
+      unique_indices: unique_indices.into_boxed_slice(),
+      compute_required: requirements.compute,
+    })
+  }
+
+  // family that substitutes any missing dedicated family
+  fn main(&self) -> QueueFamily {
+    self
+      .graphics
+      .or(self.compute)
+      .expect("Neither graphics nor compute families were required")
+  }
+
+  // number of used queue families that have to be shared with another use
+  pub fn shared_family_count(&self) -> usize {
+    let used = [
+      self.graphics.is_some(),
+      self.compute_required,
+      true, // transfer
+      self.presentation.is_some(),
+    ];
+    used.into_iter().filter(|&u| u).count() - self.unique_indices.len()
+  }
+
+  pub fn get_graphics_index(&self) -> u32 {
+    self
+      .graphics
+      .expect("Graphics queue family was not required")
+      .index
+  }
+
+  pub fn get_compute_index(&self) -> u32 {
+    self.compute.unwrap_or_else(|| self.main()).index
+  }
+
+  pub fn get_transfer_index(&self) -> u32 {
+    self.transfer.unwrap_or_else(|| self.main()).index
+  }
+
+  pub fn get_presentation_index(&self) -> u32 {
+    self
+      .presentation
+      .expect("Presentation queue family was not required")
+      .index
+  }
+
+  // used family with the given index
+  pub fn get(&self, index: u32) -> Option<QueueFamily> {
+    [
+      self.graphics,
+      self.compute,
+      self.transfer,
+      self.presentation,
+    ]
+    .into_iter()
+    .flatten()
+    .find(|family| family.index == index)
+  }
+
+  // Returns the (family index, queue index) of each used queue in order graphics, compute,
+  // transfer, presentation
+  // Substitute queues get retrieved from the same family if there are enough of them
+  fn queue_assignments(&self) -> [Option<(u32, u32)>; Self::MAX_FAMILY_COUNT] {
+    let mut assigned_count = [0u32; Self::MAX_FAMILY_COUNT];
+    let mut assign = |family: QueueFamily| {
+      let unique_i = self
+        .unique_indices
+        .iter()
+        .position(|&i| i == family.index)
+        .unwrap();
+      let queue_i = min(assigned_count[unique_i], family.queue_count - 1);
+      assigned_count[unique_i] += 1;
+      (family.index, queue_i)
+    };
+
+    let graphics = self.graphics.map(&mut assign);
+    let compute = if self.compute_required {
+      Some(assign(self.compute.unwrap_or_else(|| self.main())))
+    } else {
+      None
+    };
+    let transfer = Some(assign(self.transfer.unwrap_or_else(|| self.main())));
+    // presentation doesn't need its own queue if it is the same as graphics
+    let presentation = self.presentation.map(|family| {
+      if self.graphics == Some(family) {
+        graphics.unwrap()
+      } else {
+        assign(family)
+      }
+    });
+
+    [graphics, compute, transfer, presentation]
+  }
+}
+
+fn get_queue_create_info(
+  index: u32,
+  count: u32,
+  priorities_ptr: *const f32,
+) -> vk::DeviceQueueCreateInfo {
+  vk::DeviceQueueCreateInfo {
+    s_type: vk::StructureType::DEVICE_QUEUE_CREATE_INFO,
+    queue_family_index: index,
+    queue_count: count,
+    p_queue_priorities: priorities_ptr,
+    p_next: ptr::null(),
+    flags: vk::DeviceQueueCreateFlags::empty(),
+  }
+}
+
+// Queues that are not required by the application are null
+#[derive(Debug)]
+pub struct Queues {
+  pub graphics: vk::Queue,
+  pub compute: vk::Queue,
+  pub transfer: vk::Queue,
+  pub presentation: vk::Queue,
+}
+
+pub struct QueueCreateInfos {
+  // create infos contains a ptr to priorities, so it has to own it as well
+  _priorities: Pin<Box<[f32; QueueFamilies::MAX_FAMILY_COUNT]>>,
+  create_infos: Vec<vk::DeviceQueueCreateInfo>,
+}
+
+impl Deref for QueueCreateInfos {
+  type Target = Vec<vk::DeviceQueueCreateInfo>;
+
+  fn deref(&self) -> &Self::Target {
+    &self.create_infos
+  }
+}
+
+impl Queues {
+  pub fn get_queue_create_infos(queue_families: &QueueFamilies) -> QueueCreateInfos {
+    // use mid priorities for all queues
+    let priorities = Box::pin([0.5_f32; QueueFamilies::MAX_FAMILY_COUNT]);
+
+    // request as many queues from each family as there were assigned
+    let assignments = queue_families.queue_assignments();
+    let create_infos = queue_families
+      .unique_indices
+      .iter()
+      .map(|&family_i| {
+        let queue_count = assignments
+          .iter()
+          .flatten()
+          .filter(|(f, _)| *f == family_i)
+          .map(|(_, queue_i)| queue_i + 1)
+          .max()
+          .unwrap();
+        get_queue_create_info(family_i, queue_count, priorities.as_ptr())
+      })
+      .collect();
+
+    QueueCreateInfos {
+      _priorities: priorities,
+      create_infos,
+    }
+  }
+
+  // device should have been created with the create infos from get_queue_create_infos
+  pub unsafe fn retrieve(device: &ash::Device, queue_families: &QueueFamilies) -> Queues {
+    let [graphics, compute, transfer, presentation] =
+      queue_families
+        .queue_assignments()
+        .map(|assignment| match assignment {
+          Some((family_i, queue_i)) => device.get_device_queue(family_i, queue_i),
+          None => vk::Queue::null(),
+        });
+
+    Queues {
+      graphics,
+      compute,
+      transfer,
+      presentation,
+    }
+  }
+}
@@ -0,0 +1,384 @@
+use std::fmt;
+
+use ash::vk;
+
+use crate::utility::{c_char_array_to_string, error_chain_fmt, parse_vulkan_api_version};
+
+use super::PhysicalDeviceProperties;
+
+// environment variable that forces the selection of a specific physical device
+pub const DEVICE_SELECTOR_VAR: &str = "ABE_DEVICE";
+
+// Physical device chosen by the user instead of the one with the best score
+//
+// Parsed from ABE_DEVICE, which can be one of:
+// - "index:<n>" or "<n>": index of the device in enumeration order
+// - "id:<vendor>:<device>" or "<vendor>:<device>": vendor and device IDs in hexadecimal
+// - "uuid:<uuid>" or "<uuid>": deviceUUID as 32 hexadecimal digits (dashes are ignored)
+// - "name:<text>" or anything else: case insensitive substring of the device name
+//
+// The selected device still has to fulfill all requirements
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+  Index(usize),
+  Ids { vendor_id: u32, device_id: u32 },
+  Uuid([u8; vk::UUID_SIZE]),
+  Name(String),
+}
+
+impl DeviceSelector {
+  // Returns Ok(None) if the variable is not set or is empty
+  pub fn from_env() -> Result<Option<Self>, DeviceSelectionError> {
+    match std::env::var(DEVICE_SELECTOR_VAR) {
+      Ok(value) if !value.trim().is_empty() => Self::parse(value.trim()).map(Some),
+      _ => Ok(None),
+    }
+  }
+
+  pub fn parse(value: &str) -> Result<Self, DeviceSelectionError> {
+    let invalid = |reason| DeviceSelectionError::InvalidSelector {
+      value: value.to_owned(),
+      reason,
+    };
+
+    if let Some((prefix, rest)) = value.split_once(':') {
+      match prefix.to_ascii_lowercase().as_str() {
+        "index" => return parse_index(rest).ok_or_else(|| invalid("expected a device index")),
+        "id" => return parse_ids(rest).ok_or_else(|| invalid("expected <vendor>:<device> in hex")),
+        "uuid" => return parse_uuid(rest).ok_or_else(|| invalid("expected 32 hex digits")),
+        "name" => return Ok(Self::Name(rest.to_lowercase())),
+        _ => {}
+      }
+    }
+
+    if let Some(index) = parse_index(value) {
+      return Ok(index);
+    }
+    if let Some(ids) = parse_ids(value) {
+      return Ok(ids);
+    }
+    if let Some(uuid) = parse_uuid(value) {
+      return Ok(uuid);
+    }
+    Ok(Self::Name(value.to_lowercase()))
+  }
+
+  pub fn matches(&self, index: usize, properties: &PhysicalDeviceProperties) -> bool {
+    match self {
+      Self::Index(i) => *i == index,
+      Self::Ids {
+        vendor_id,
+        device_id,
+      } => properties.p10.vendor_id == *vendor_id && properties.p10.device_id == *device_id,
+      Self::Uuid(uuid) => properties.p11.device_uuid == *uuid,
+      Self::Name(name) => c_char_array_to_string(&properties.p10.device_name)
+        .to_lowercase()
+        .contains(name.as_str()),
+    }
+  }
+}
+
+impl fmt::Display for DeviceSelector {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Index(i) => write!(f, "index {}", i),
+      Self::Ids {
+        vendor_id,
+        device_id,
+      } => write!(
+        f,
+        "vendor ID {:#06x} and device ID {:#06x}",
+        vendor_id, device_id
+      ),
+      Self::Uuid(uuid) => write!(f, "UUID {}", format_uuid(uuid)),
+      Self::Name(name) => write!(f, "name containing \"{}\"", name),
+    }
+  }
+}
+
+// from_str_radix and parse accept a leading sign, so digits are checked beforehand
+fn all_digits(s: &str, radix: u32) -> bool {
+  !s.is_empty() && s.chars().all(|c| c.is_digit(radix))
+}
+
+fn parse_index(value: &str) -> Option<DeviceSelector> {
+  if !all_digits(value, 10) {
+    return None;
+  }
+  value.parse().ok().map(DeviceSelector::Index)
+}
+
+fn parse_ids(value: &str) -> Option<DeviceSelector> {
+  let (vendor, device) = value.split_once(':')?;
+  let parse_hex = |s: &str| {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if !all_digits(s, 16) || s.len() > 8 {
+      return None;
+    }
+    u32::from_str_radix(s, 16).ok()
+  };
+  Some(DeviceSelector::Ids {
+    vendor_id: parse_hex(vendor)?,
+    device_id: parse_hex(device)?,
+  })
+}
+
+fn parse_uuid(value: &str) -> Option<DeviceSelector> {
+  let digits: Vec<u8> = value.bytes().filter(|&b| b != b'-').collect();
+  if digits.len() != vk::UUID_SIZE * 2 || !digits.iter().all(u8::is_ascii_hexdigit) {
+    return None;
+  }
+  let mut uuid = [0; vk::UUID_SIZE];
+  for (byte, pair) in uuid.iter_mut().zip(digits.chunks(2)) {
+    *byte = u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok()?;
+  }
+  Some(DeviceSelector::Uuid(uuid))
+}
+
+pub fn format_uuid(uuid: &[u8; vk::UUID_SIZE]) -> String {
+  let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+  format!(
+    "{}-{}-{}-{}-{}",
+    &hex[0..8],
+    &hex[8..12],
+    &hex[12..16],
+    &hex[16..20],
+    &hex[20..32]
+  )
+}
+
+// why a physical device was not selected
+#[derive(Debug, Clone)]
+pub enum RejectionReason {
+  NotSelected,
+  ApiVersion { supported: u32, required: u32 },
+  MissingExtensions(Vec<String>),
+  MissingFeatures,
+  MissingQueueFamilies,
+  // the application specific checks failed, these log a more specific reason
+  NotSuitable,
+  QueryFailed(vk::Result),
+}
+
+impl fmt::Display for RejectionReason {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotSelected => write!(f, "does not match {}", DEVICE_SELECTOR_VAR),
+      Self::ApiVersion {
+        supported,
+        required,
+      } => write!(
+        f,
+        "API version {} is less than {} targeted by the application",
+        parse_vulkan_api_version(*supported),
+        parse_vulkan_api_version(*required)
+      ),
+      Self::MissingExtensions(extensions) => {
+        write!(f, "missing extensions {}", extensions.join(", "))
+      }
+      Self::MissingFeatures => write!(f, "does not support all required features"),
+      Self::MissingQueueFamilies => write!(f, "does not contain required queue families"),
+      Self::NotSuitable => write!(
+        f,
+        "failed application specific checks (see the log for details)"
+      ),
+      Self::QueryFailed(err) => write!(f, "failed to query device properties: {:?}", err),
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct RejectedDevice {
+  pub index: usize,
+  pub name: String,
+  pub vendor_id: u32,
+  pub device_id: u32,
+  pub device_uuid: [u8; vk::UUID_SIZE],
+  pub reason: RejectionReason,
+}
+
+impl RejectedDevice {
+  pub fn new(index: usize, properties: &PhysicalDeviceProperties, reason: RejectionReason) -> Self {
+    Self {
+      index,
+      name: c_char_array_to_string(&properties.p10.device_name),
+      vendor_id: properties.p10.vendor_id,
+      device_id: properties.p10.device_id,
+      device_uuid: properties.p11.device_uuid,
+      reason,
+    }
+  }
+}
+
+impl fmt::Display for RejectedDevice {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}: \"{}\" ({:04x}:{:04x}, UUID {}): {}",
+      self.index,
+      self.name,
+      self.vendor_id,
+      self.device_id,
+      format_uuid(&self.device_uuid),
+      self.reason
+    )
+  }
+}
+
+pub enum DeviceSelectionError {
+  Vulkan(vk::Result),
+  InvalidSelector {
+    value: String,
+    reason: &'static str,
+  },
+  NoSuitableDevice {
+    selector: Option<DeviceSelector>,
+    rejected: Vec<RejectedDevice>,
+  },
+}
+
+impl fmt::Display for DeviceSelectionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Vulkan(err) => write!(f, "Failed to query physical devices: {:?}", err),
+      Self::InvalidSelector { value, reason } => write!(
+        f,
+        "Invalid {} value \"{}\": {}",
+        DEVICE_SELECTOR_VAR, value, reason
+      ),
+      Self::NoSuitableDevice { selector, rejected } => {
+        match selector {
+          Some(selector) => write!(
+            f,
+            "No suitable physical device with {} ({} is set)",
+            selector, DEVICE_SELECTOR_VAR
+          )?,
+          None => write!(f, "No suitable physical device found")?,
+        }
+        if rejected.is_empty() {
+          write!(f, "\nNo physical devices are available")?;
+        }
+        for device in rejected {
+          write!(f, "\n  {}", device)?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl fmt::Debug for DeviceSelectionError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl std::error::Error for DeviceSelectionError {}
+
+impl From<vk::Result> for DeviceSelectionError {
+  fn from(value: vk::Result) -> Self {
+    DeviceSelectionError::Vulkan(value)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const UUID: [u8; vk::UUID_SIZE] = [
+    0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef,
+  ];
+
+  fn parse(value: &str) -> Option<DeviceSelector> {
+    DeviceSelector::parse(value).ok()
+  }
+
+  fn is_invalid(value: &str) -> bool {
+    matches!(
+      DeviceSelector::parse(value),
+      Err(DeviceSelectionError::InvalidSelector { .. })
+    )
+  }
+
+  #[test]
+  fn index_selectors() {
+    assert_eq!(parse("1"), Some(DeviceSelector::Index(1)));
+    assert_eq!(parse("index:0"), Some(DeviceSelector::Index(0)));
+    assert_eq!(parse("INDEX:12"), Some(DeviceSelector::Index(12)));
+    assert!(is_invalid("index:"));
+    assert!(is_invalid("index:+1"));
+    assert!(is_invalid("index:-1"));
+    assert!(is_invalid("index:one"));
+    // without a prefix, anything that is not a number is a name
+    assert_eq!(parse("+1"), Some(DeviceSelector::Name("+1".to_owned())));
+  }
+
+  #[test]
+  fn id_selectors() {
+    let ids = DeviceSelector::Ids {
+      vendor_id: 0x10de,
+      device_id: 0x2684,
+    };
+    assert_eq!(parse("10de:2684"), Some(ids.clone()));
+    assert_eq!(parse("0x10DE:0x2684"), Some(ids.clone()));
+    assert_eq!(parse("id:10de:2684"), Some(ids));
+    assert!(is_invalid("id:10de"));
+    assert!(is_invalid("id:+10de:+1234"));
+    assert!(is_invalid("id:10de:-1"));
+    assert!(is_invalid("id::1234"));
+    assert!(is_invalid("id:0x:1234"));
+    assert!(is_invalid("id:100000000:1"));
+    assert!(is_invalid("id:10dg:1234"));
+    assert_eq!(
+      parse("+10de:+1234"),
+      Some(DeviceSelector::Name("+10de:+1234".to_owned()))
+    );
+  }
+
+  #[test]
+  fn uuid_selectors() {
+    let uuid = DeviceSelector::Uuid(UUID);
+    assert_eq!(
+      parse("123456789abcdef0123456789abcdef"),
+      Some(DeviceSelector::Name(
+        "123456789abcdef0123456789abcdef".to_owned()
+      ))
+    );
+    assert_eq!(
+      parse("123456789abcdef00123456789abcdef"),
+      Some(uuid.clone())
+    );
+    assert_eq!(
+      parse("uuid:12345678-9ABC-DEF0-0123-456789ABCDEF"),
+      Some(uuid.clone())
+    );
+    assert_eq!(parse(&format_uuid(&UUID)), Some(uuid));
+    assert!(is_invalid("uuid:123456789abcdef0123456789abcde"));
+    assert!(is_invalid("uuid:123456789abcdef00123456789abcdeg"));
+    assert!(is_invalid("uuid:+f3456789abcdef00123456789abcdef"));
+  }
+
+  #[test]
+  fn name_selectors() {
+    assert_eq!(
+      parse("llvmpipe"),
+      Some(DeviceSelector::Name("llvmpipe".to_owned()))
+    );
+    assert_eq!(
+      parse("Name:GeForce RTX"),
+      Some(DeviceSelector::Name("geforce rtx".to_owned()))
+    );
+    // a name prefix makes any value a name
+    assert_eq!(parse("name:0"), Some(DeviceSelector::Name("0".to_owned())));
+    // unknown prefixes are part of the name
+    assert_eq!(
+      parse("AMD: Radeon"),
+      Some(DeviceSelector::Name("amd: radeon".to_owned()))
+    );
+  }
+
+  #[test]
+  fn uuids_are_formatted_with_dashes() {
+    assert_eq!(format_uuid(&UUID), "12345678-9abc-def0-0123-456789abcdef");
+  }
+}
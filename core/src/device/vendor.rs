@@ -1,6 +1,10 @@
+use std::fmt;
+
 use crate::utility;
 
 // implements some display properties for vendors
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Vendor {
   NVIDIA,
   AMD,
@@ -48,16 +52,16 @@ impl Vendor {
   }
 }
 
-impl ToString for Vendor {
-  fn to_string(&self) -> String {
+impl fmt::Display for Vendor {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
-      Self::NVIDIA => "NVIDIA".to_owned(),
-      Self::AMD => "AMD".to_owned(),
-      Self::ARM => "ARM".to_owned(),
-      Self::INTEL => "INTEL".to_owned(),
-      Self::ImgTec => "ImgTec".to_owned(),
-      Self::Qualcomm => "Qualcomm".to_owned(),
-      Self::Unknown(id) => format!("Unknown ({})", id),
+      Self::NVIDIA => write!(f, "NVIDIA"),
+      Self::AMD => write!(f, "AMD"),
+      Self::ARM => write!(f, "ARM"),
+      Self::INTEL => write!(f, "INTEL"),
+      Self::ImgTec => write!(f, "ImgTec"),
+      Self::Qualcomm => write!(f, "Qualcomm"),
+      Self::Unknown(id) => write!(f, "Unknown ({})", id),
     }
   }
 }
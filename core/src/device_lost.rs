@@ -0,0 +1,84 @@
+// Helpers for recovering from a lost device
+//
+// A device can be lost because of a driver bug, a GPU reset or a timeout, after which every
+// operation on it returns vk::Result::ERROR_DEVICE_LOST. The only way to recover is to destroy all
+// objects created from the logical device and create them (and the device) again, possibly on a
+// different physical device.
+// As losing a device on purpose is hard, FakeDeviceLost can make an operation report
+// ERROR_DEVICE_LOST in order to test recovery paths.
+
+use ash::vk;
+
+// environment variable with the number of the checked operation that fails (counting from 1)
+pub const FAKE_DEVICE_LOST_VAR: &str = "ABE_FAKE_DEVICE_LOST";
+
+// Makes one operation fail with ERROR_DEVICE_LOST
+// Should outlive the objects that get recreated, so that the loss only happens once
+#[derive(Debug, Default)]
+pub struct FakeDeviceLost {
+  // number of checks that still succeed, None if disabled or already triggered
+  remaining: Option<u64>,
+}
+
+impl FakeDeviceLost {
+  // Nth check fails, None disables the injection
+  pub fn new(fail_at: Option<u64>) -> Self {
+    Self {
+      remaining: fail_at.map(|n| n.saturating_sub(1)),
+    }
+  }
+
+  pub fn from_env() -> Self {
+    let fail_at = match std::env::var(FAKE_DEVICE_LOST_VAR) {
+      Ok(value) => match value.parse::<u64>() {
+        Ok(n) if n > 0 => {
+          log::warn!("Faking a device loss at operation {}", n);
+          Some(n)
+        }
+        _ => {
+          log::warn!(
+            "Ignoring {}={:?}, expected a number greater than 0",
+            FAKE_DEVICE_LOST_VAR,
+            value
+          );
+          None
+        }
+      },
+      Err(_) => None,
+    };
+    Self::new(fail_at)
+  }
+
+  // counts one operation, should be called before doing it
+  pub fn check(&mut self) -> Result<(), vk::Result> {
+    match self.remaining {
+      Some(0) => {
+        self.remaining = None;
+        log::warn!("Injecting a fake device loss");
+        Err(vk::Result::ERROR_DEVICE_LOST)
+      }
+      Some(n) => {
+        self.remaining = Some(n - 1);
+        Ok(())
+      }
+      None => Ok(()),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn fails_once_at_the_given_operation() {
+    let mut fake = FakeDeviceLost::new(Some(3));
+    assert_eq!(fake.check(), Ok(()));
+    assert_eq!(fake.check(), Ok(()));
+    assert_eq!(fake.check(), Err(vk::Result::ERROR_DEVICE_LOST));
+    assert_eq!(fake.check(), Ok(()));
+
+    let mut disabled = FakeDeviceLost::new(None);
+    assert!((0..10).all(|_| disabled.check().is_ok()));
+  }
+}
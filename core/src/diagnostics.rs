@@ -0,0 +1,428 @@
+// Diagnostics of device losses
+//
+// ERROR_DEVICE_LOST alone doesn't say what went wrong. If VK_EXT_device_fault is enabled (it is an
+// optional extension of every DeviceRequirements by default), the implementation can be asked for
+// a description of the fault, the addresses involved and vendor specific codes.
+// Otherwise, breadcrumbs can be recorded around each pass: a host visible buffer where the device
+// writes (with vkCmdFillBuffer) when it starts and finishes each pass. After a loss, passes that
+// started without finishing were executing. This is best effort, as the contents of device
+// writes are not guaranteed to reach host memory once the device is lost.
+
+use std::{ffi::CStr, fmt, ptr, sync::Arc};
+
+use ash::vk;
+
+use crate::{memory::MappedMemory, utility::c_char_array_to_string, Device, Owned, PhysicalDevice};
+
+pub const DEVICE_FAULT_EXTENSION: &CStr = vk::ExtDeviceFaultFn::name();
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MarkerId(usize);
+
+// values written to the breadcrumb of each marker
+const NOT_STARTED: u32 = 0;
+const STARTED: u32 = 1;
+const FINISHED: u32 = 2;
+const MARKER_SIZE: u64 = std::mem::size_of::<u32>() as u64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarkerState {
+  NotStarted,
+  Executing,
+  Finished,
+  // the breadcrumb contains something that was not written by a marker
+  Unknown(u32),
+}
+
+impl MarkerState {
+  fn from_value(value: u32) -> Self {
+    match value {
+      NOT_STARTED => Self::NotStarted,
+      STARTED => Self::Executing,
+      FINISHED => Self::Finished,
+      other => Self::Unknown(other),
+    }
+  }
+}
+
+impl fmt::Display for MarkerState {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NotStarted => f.write_str("not started"),
+      Self::Executing => f.write_str("executing"),
+      Self::Finished => f.write_str("finished"),
+      Self::Unknown(value) => write!(f, "unknown ({})", value),
+    }
+  }
+}
+
+// state of each marker in the order they were begun
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BreadcrumbReport {
+  pub markers: Vec<(String, MarkerState)>,
+}
+
+impl BreadcrumbReport {
+  fn new(names: &[String], values: &[u32]) -> Self {
+    Self {
+      markers: names
+        .iter()
+        .zip(values)
+        .map(|(name, &value)| (name.clone(), MarkerState::from_value(value)))
+        .collect(),
+    }
+  }
+
+  pub fn executing(&self) -> impl Iterator<Item = &str> {
+    self
+      .markers
+      .iter()
+      .filter(|(_, state)| *state == MarkerState::Executing)
+      .map(|(name, _)| name.as_str())
+  }
+}
+
+impl fmt::Display for BreadcrumbReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (i, (name, state)) in self.markers.iter().enumerate() {
+      if i > 0 {
+        f.write_str(", ")?;
+      }
+      write!(f, "\"{}\": {}", name, state)?;
+    }
+    Ok(())
+  }
+}
+
+// Markers written by the device around named parts of command buffers, see the module comment
+pub struct Breadcrumbs {
+  buffer: Owned<vk::Buffer>,
+  // breadcrumb i is at offset 4i
+  mapped: MappedMemory,
+  // maximum number of markers
+  capacity: usize,
+  names: Vec<String>,
+  // freed after the buffer
+  _memory: Owned<vk::DeviceMemory>,
+  device: Arc<Device>,
+}
+
+impl Breadcrumbs {
+  // None if VK_EXT_device_fault is enabled, which makes breadcrumbs unnecessary
+  pub fn new_if_needed(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+    capacity: usize,
+  ) -> Result<Option<Self>, vk::Result> {
+    if device.device_fault().is_some() {
+      return Ok(None);
+    }
+    Self::new(device, physical_device, capacity).map(Some)
+  }
+
+  // the buffer can be written by any queue family of physical_device
+  pub fn new(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+    capacity: usize,
+  ) -> Result<Self, vk::Result> {
+    let size = capacity.max(1) as u64 * MARKER_SIZE;
+    let families = &physical_device.queue_families.unique_indices;
+    let create_info = vk::BufferCreateInfo {
+      s_type: vk::StructureType::BUFFER_CREATE_INFO,
+      p_next: ptr::null(),
+      flags: vk::BufferCreateFlags::empty(),
+      size,
+      usage: vk::BufferUsageFlags::TRANSFER_DST,
+      sharing_mode: if families.len() > 1 {
+        vk::SharingMode::CONCURRENT
+      } else {
+        vk::SharingMode::EXCLUSIVE
+      },
+      queue_family_index_count: families.len() as u32,
+      p_queue_family_indices: families.as_ptr(),
+    };
+    let buffer = unsafe {
+      let buffer = device.create_buffer(&create_info, None)?;
+      Owned::from_raw(device, buffer)
+    };
+
+    // coherent memory doesn't need to be invalidated, which may fail after a loss
+    let requirements = unsafe { device.get_buffer_memory_requirements(*buffer) };
+    let memory_flags =
+      vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT;
+    let memory_type = physical_device
+      .find_memory_type(requirements.memory_type_bits, memory_flags)
+      .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+    let allocate_info = vk::MemoryAllocateInfo {
+      s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+      p_next: ptr::null(),
+      allocation_size: requirements.size,
+      memory_type_index: memory_type as u32,
+    };
+    let (memory, mapped) = unsafe {
+      let memory = device.allocate_memory(&allocate_info, None)?;
+      let memory = Owned::from_raw(device, memory);
+      device.bind_buffer_memory(*buffer, *memory, 0)?;
+      let mapped = MappedMemory::map(
+        device,
+        *memory,
+        requirements.size,
+        memory_flags,
+        physical_device.properties.p10.limits.non_coherent_atom_size,
+      )?;
+      ptr::write_bytes(mapped.as_ptr(), 0, size as usize);
+      (memory, mapped.slice(0, size))
+    };
+
+    Ok(Self {
+      buffer,
+      mapped,
+      capacity,
+      names: Vec::with_capacity(capacity),
+      _memory: memory,
+      device: device.clone(),
+    })
+  }
+
+  // forgets all markers, should be called before their command buffers get recorded again
+  pub fn clear(&mut self) {
+    self.names.clear();
+  }
+
+  // Marks every breadcrumb as not started, should be recorded at the start of the first command
+  // buffer of each submission (outside a render pass instance)
+  pub unsafe fn reset(&self, cb: vk::CommandBuffer) {
+    self
+      .device
+      .cmd_fill_buffer(cb, *self.buffer, 0, vk::WHOLE_SIZE, NOT_STARTED);
+    // markers are written after the reset
+    self.barrier(
+      cb,
+      vk::PipelineStageFlags2::CLEAR,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    );
+  }
+
+  // Starts a marker, should be recorded outside a render pass instance
+  // panics if more markers than capacity are begun without calling clear
+  pub unsafe fn begin(&mut self, cb: vk::CommandBuffer, name: &str) -> MarkerId {
+    assert!(
+      self.names.len() < self.capacity,
+      "Breadcrumb capacity ({}) exceeded",
+      self.capacity
+    );
+    let id = MarkerId(self.names.len());
+    self.names.push(name.to_owned());
+    self.write(cb, id, STARTED);
+    id
+  }
+
+  // Finishes a marker once all previous commands complete, which serializes the command buffer
+  // Should be recorded outside a render pass instance
+  pub unsafe fn end(&self, cb: vk::CommandBuffer, id: MarkerId) {
+    self.barrier(
+      cb,
+      vk::PipelineStageFlags2::ALL_COMMANDS,
+      vk::AccessFlags2::MEMORY_WRITE,
+    );
+    self.write(cb, id, FINISHED);
+  }
+
+  unsafe fn write(&self, cb: vk::CommandBuffer, id: MarkerId, value: u32) {
+    self.device.cmd_fill_buffer(
+      cb,
+      *self.buffer,
+      id.0 as u64 * MARKER_SIZE,
+      MARKER_SIZE,
+      value,
+    );
+  }
+
+  // makes breadcrumb writes wait for previous commands
+  unsafe fn barrier(
+    &self,
+    cb: vk::CommandBuffer,
+    src_stage_mask: vk::PipelineStageFlags2,
+    src_access_mask: vk::AccessFlags2,
+  ) {
+    let barrier = vk::MemoryBarrier2 {
+      s_type: vk::StructureType::MEMORY_BARRIER_2,
+      p_next: ptr::null(),
+      src_stage_mask,
+      src_access_mask,
+      dst_stage_mask: vk::PipelineStageFlags2::CLEAR,
+      dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+    };
+    let dependency_info = vk::DependencyInfo {
+      s_type: vk::StructureType::DEPENDENCY_INFO,
+      p_next: ptr::null(),
+      dependency_flags: vk::DependencyFlags::empty(),
+      memory_barrier_count: 1,
+      p_memory_barriers: &barrier,
+      buffer_memory_barrier_count: 0,
+      p_buffer_memory_barriers: ptr::null(),
+      image_memory_barrier_count: 0,
+      p_image_memory_barriers: ptr::null(),
+    };
+    self.device.cmd_pipeline_barrier2(cb, &dependency_info);
+  }
+
+  // reads the last values written by the device, meant to be called after a device loss
+  pub fn report(&self) -> BreadcrumbReport {
+    let values: Vec<u32> = (0..self.names.len())
+      .map(|i| unsafe { (self.mapped.as_ptr() as *const u32).add(i).read_volatile() })
+      .collect();
+    BreadcrumbReport::new(&self.names, &values)
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VendorFault {
+  pub description: String,
+  pub code: u64,
+  pub data: u64,
+}
+
+// what VK_EXT_device_fault reports about a device loss
+#[derive(Clone, Debug)]
+pub struct DeviceFaultReport {
+  pub description: String,
+  pub addresses: Vec<vk::DeviceFaultAddressInfoEXT>,
+  pub vendor_faults: Vec<VendorFault>,
+}
+
+impl fmt::Display for DeviceFaultReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.description)?;
+    for address in self.addresses.iter() {
+      write!(
+        f,
+        "\n  {:?} at {:#x} (precision {:#x})",
+        address.address_type, address.reported_address, address.address_precision
+      )?;
+    }
+    for fault in self.vendor_faults.iter() {
+      write!(
+        f,
+        "\n  vendor fault {:#x} (data {:#x}): {}",
+        fault.code, fault.data, fault.description
+      )?;
+    }
+    Ok(())
+  }
+}
+
+// Queries VK_EXT_device_fault, should be called after a device loss
+// Returns None if the extension is not enabled
+// The vendor binary crash dump is not retrieved
+pub fn query_device_fault(device: &Device) -> Result<Option<DeviceFaultReport>, vk::Result> {
+  let Some(device_fault) = device.device_fault() else {
+    return Ok(None);
+  };
+
+  let mut counts = vk::DeviceFaultCountsEXT::default();
+  unsafe {
+    (device_fault.get_device_fault_info_ext)(device.handle(), &mut counts, ptr::null_mut())
+      .result()?;
+  }
+
+  let mut addresses =
+    vec![vk::DeviceFaultAddressInfoEXT::default(); counts.address_info_count as usize];
+  let mut vendor_infos =
+    vec![vk::DeviceFaultVendorInfoEXT::default(); counts.vendor_info_count as usize];
+  counts.vendor_binary_size = 0;
+  let mut info = vk::DeviceFaultInfoEXT {
+    s_type: vk::StructureType::DEVICE_FAULT_INFO_EXT,
+    p_next: ptr::null_mut(),
+    description: [0; vk::MAX_DESCRIPTION_SIZE],
+    p_address_infos: addresses.as_mut_ptr(),
+    p_vendor_infos: vendor_infos.as_mut_ptr(),
+    p_vendor_binary_data: ptr::null_mut(),
+  };
+  // INCOMPLETE only means that the vendor binary was not retrieved
+  match unsafe { (device_fault.get_device_fault_info_ext)(device.handle(), &mut counts, &mut info) }
+  {
+    vk::Result::SUCCESS | vk::Result::INCOMPLETE => {}
+    err => return Err(err),
+  }
+  addresses.truncate(counts.address_info_count as usize);
+  vendor_infos.truncate(counts.vendor_info_count as usize);
+
+  Ok(Some(DeviceFaultReport {
+    description: c_char_array_to_string(&info.description),
+    addresses,
+    vendor_faults: vendor_infos
+      .iter()
+      .map(|vendor| VendorFault {
+        description: c_char_array_to_string(&vendor.description),
+        code: vendor.vendor_fault_code,
+        data: vendor.vendor_fault_data,
+      })
+      .collect(),
+  }))
+}
+
+// Logs everything that is known about a device loss: the device fault report if
+// VK_EXT_device_fault is enabled and the state of each breadcrumb marker
+pub fn log_device_lost(device: &Device, breadcrumbs: &[&Breadcrumbs]) {
+  match query_device_fault(device) {
+    Ok(Some(report)) => log::error!("Device fault: {}", report),
+    Ok(None) => {}
+    Err(err) => log::error!("Failed to query device fault information: {:?}", err),
+  }
+
+  for report in breadcrumbs.iter().map(|breadcrumbs| breadcrumbs.report()) {
+    let executing: Vec<&str> = report.executing().collect();
+    if executing.is_empty() {
+      log::error!(
+        "Device lost while no marked pass was executing ({})",
+        report
+      );
+    } else {
+      log::error!("Device lost while executing {:?} ({})", executing, report);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn breadcrumbs_report_executing_markers() {
+    let names = ["clear", "mandelbrot", "copy"].map(String::from);
+    let report = BreadcrumbReport::new(&names, &[FINISHED, STARTED, NOT_STARTED]);
+    assert_eq!(report.executing().collect::<Vec<_>>(), vec!["mandelbrot"]);
+    assert_eq!(
+      report.to_string(),
+      "\"clear\": finished, \"mandelbrot\": executing, \"copy\": not started"
+    );
+
+    let garbage = BreadcrumbReport::new(&names[..1], &[7]);
+    assert_eq!(garbage.markers[0].1, MarkerState::Unknown(7));
+    assert_eq!(garbage.executing().count(), 0);
+  }
+
+  #[test]
+  fn fault_report_lists_addresses_and_vendor_faults() {
+    let report = DeviceFaultReport {
+      description: "Page fault".to_owned(),
+      addresses: vec![vk::DeviceFaultAddressInfoEXT {
+        address_type: vk::DeviceFaultAddressTypeEXT::READ_INVALID,
+        reported_address: 0x1000,
+        address_precision: 0x100,
+      }],
+      vendor_faults: vec![VendorFault {
+        description: "MMU fault".to_owned(),
+        code: 0x2a,
+        data: 0,
+      }],
+    };
+    assert_eq!(
+      report.to_string(),
+      "Page fault\n  READ_INVALID at 0x1000 (precision 0x100)\n  vendor fault 0x2a (data 0x0): MMU \
+       fault"
+    );
+  }
+}
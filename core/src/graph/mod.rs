@@ -0,0 +1,655 @@
+// Declarative description of the work submitted each frame (or run)
+//
+// Passes declare the queue type they need and how they use each image and buffer. Compiling the
+// graph orders the passes, groups consecutive passes that run on the same queue into a single
+// submission, emits the layout transitions, memory barriers and queue family ownership transfers
+// between them (through a ResourceTracker), and records one command buffer per submission.
+// Submissions are chained with a timeline semaphore, so a compiled graph can be submitted
+// repeatedly and waited for by the host.
+//
+// If profiling is enabled, each recorded pass is wrapped in a timestamp scope, so that its GPU
+// time can be retrieved after the submission completes.
+// If loss diagnostics are enabled, a device loss during submit or wait gets logged with the
+// device fault information or, if VK_EXT_device_fault is not enabled, with the passes that were
+// executing according to breadcrumbs recorded around each pass (see diagnostics.rs).
+
+mod schedule;
+
+use schedule::{dependencies, schedule, ResourceAccess};
+
+use std::{collections::HashMap, ffi::c_void, ptr, sync::Arc};
+
+use ash::vk;
+
+use crate::{
+  diagnostics::{log_device_lost, Breadcrumbs},
+  profiler::{ScopeTiming, TimestampProfiler},
+  tracker::{is_write, Barriers, ResourceTracker, Usage},
+  Device, Owned, PhysicalDevice, QueueFamilies, Queues,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueType {
+  Graphics,
+  Compute,
+  Transfer,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Resource {
+  Image(vk::Image),
+  Buffer(vk::Buffer),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ResourceUsage {
+  resource: Resource,
+  // UNDEFINED for buffers
+  layout: vk::ImageLayout,
+  stage_mask: vk::PipelineStageFlags2,
+  access_mask: vk::AccessFlags2,
+  discard: bool,
+}
+
+type RecordFn<'a> = Box<dyn FnOnce(&ash::Device, vk::CommandBuffer) + 'a>;
+
+struct Pass<'a> {
+  name: String,
+  family: u32,
+  usages: Vec<ResourceUsage>,
+  record: RecordFn<'a>,
+  // doesn't record any commands and so is not profiled or marked
+  barriers_only: bool,
+}
+
+// Graph that is being built, see the module comment
+//
+// Record functions can borrow objects that outlive the graph construction, as they are called
+// (once) by compile.
+pub struct RenderGraph<'a> {
+  device: Arc<Device>,
+  // (queue type, queue, family index), queue types of the same family share a queue
+  queue_types: [(QueueType, vk::Queue, u32); 3],
+  tracker: ResourceTracker,
+  passes: Vec<Pass<'a>>,
+  // set if profiling or loss diagnostics are enabled
+  physical_device: Option<&'a PhysicalDevice>,
+  profiling: bool,
+  loss_diagnostics: bool,
+}
+
+impl<'a> RenderGraph<'a> {
+  // queue types whose family was not required can't be used by passes
+  pub fn new(device: &Arc<Device>, queue_families: &QueueFamilies, queues: &Queues) -> Self {
+    let graphics_index = queue_families.graphics.map(|family| family.index);
+    let compute_index = queue_families.get_compute_index();
+    let transfer_index = queue_families.get_transfer_index();
+    let mut queue_types = [
+      (
+        QueueType::Graphics,
+        queues.graphics,
+        graphics_index.unwrap_or(vk::QUEUE_FAMILY_IGNORED),
+      ),
+      (QueueType::Compute, queues.compute, compute_index),
+      (QueueType::Transfer, queues.transfer, transfer_index),
+    ];
+    // Different queue types can be different queues of the same family. Barriers between passes
+    // of the same family don't synchronize different queues, so all of them use the first one.
+    for i in 1..queue_types.len() {
+      if let Some(first) = queue_types[..i]
+        .iter()
+        .find(|(_, queue, family)| *family == queue_types[i].2 && *queue != vk::Queue::null())
+      {
+        queue_types[i].1 = first.1;
+      }
+    }
+
+    Self {
+      device: device.clone(),
+      queue_types,
+      tracker: ResourceTracker::new(),
+      passes: Vec::new(),
+      physical_device: None,
+      profiling: false,
+      loss_diagnostics: false,
+    }
+  }
+
+  // wraps every recorded pass in a timestamp scope, see CompiledGraph::timings
+  // requires the hostQueryReset feature
+  pub fn enable_profiling(&mut self, physical_device: &'a PhysicalDevice) {
+    self.physical_device = Some(physical_device);
+    self.profiling = true;
+  }
+
+  // logs what is known about device losses reported by CompiledGraph::submit and wait, recording
+  // breadcrumbs around every recorded pass if VK_EXT_device_fault is not enabled
+  pub fn enable_loss_diagnostics(&mut self, physical_device: &'a PhysicalDevice) {
+    self.physical_device = Some(physical_device);
+    self.loss_diagnostics = true;
+  }
+
+  // images start in the UNDEFINED layout without being owned by any queue family, and should be
+  // in the same state each time the graph is submitted (the first usage can discard them)
+  pub fn import_image(&mut self, image: vk::Image, subresource_range: vk::ImageSubresourceRange) {
+    self.tracker.track_image(image, subresource_range);
+  }
+
+  pub fn import_buffer(&mut self, buffer: vk::Buffer) {
+    self.tracker.track_buffer(buffer);
+  }
+
+  pub fn add_pass(&mut self, name: &str, queue_type: QueueType) -> PassBuilder<'_, 'a> {
+    let &(_, queue, family) = self
+      .queue_types
+      .iter()
+      .find(|(ty, _, _)| *ty == queue_type)
+      .unwrap();
+    assert!(
+      queue != vk::Queue::null(),
+      "{:?} queue was not retrieved",
+      queue_type
+    );
+    PassBuilder {
+      graph: self,
+      name: name.to_owned(),
+      family,
+      usages: Vec::new(),
+    }
+  }
+
+  fn queue_of_family(&self, family: u32) -> vk::Queue {
+    self
+      .queue_types
+      .iter()
+      .find(|(_, _, f)| *f == family)
+      .unwrap()
+      .1
+  }
+
+  // orders passes, computes their barriers and records the command buffers
+  pub fn compile(mut self) -> Result<CompiledGraph, vk::Result> {
+    let accesses: Vec<Vec<ResourceAccess<Resource>>> = self
+      .passes
+      .iter()
+      .map(|pass| {
+        pass
+          .usages
+          .iter()
+          .map(|usage| ResourceAccess {
+            resource: usage.resource,
+            layout: usage.layout,
+            writes: usage.discard || is_write(usage.access_mask),
+          })
+          .collect()
+      })
+      .collect();
+    let families: Vec<u32> = self.passes.iter().map(|pass| pass.family).collect();
+    let order = schedule(&families, &dependencies(&accesses));
+
+    // consecutive passes of the same family share a submission
+    let mut plans: Vec<BatchPlan> = Vec::new();
+    for &i in order.iter() {
+      match plans.last_mut() {
+        Some(plan) if plan.family == families[i] => plan.passes.push(i),
+        _ => plans.push(BatchPlan {
+          family: families[i],
+          passes: vec![i],
+          wait_stages: vk::PipelineStageFlags2::NONE,
+          releases: Barriers::default(),
+        }),
+      }
+    }
+
+    // barriers before each pass, releases are recorded at the end of the submission that last
+    // used the resource
+    let mut pass_barriers = vec![Barriers::default(); self.passes.len()];
+    let mut last_batch: HashMap<Resource, usize> = HashMap::new();
+    for b in 0..plans.len() {
+      for p in 0..plans[b].passes.len() {
+        let i = plans[b].passes[p];
+        for usage in self.passes[i].usages.iter() {
+          let tracker_usage = Usage {
+            queue_family: plans[b].family,
+            stage_mask: usage.stage_mask,
+            access_mask: usage.access_mask,
+            discard: usage.discard,
+          };
+          let transition = match usage.resource {
+            Resource::Image(image) => self.tracker.use_image(image, usage.layout, tracker_usage),
+            Resource::Buffer(buffer) => self.tracker.use_buffer(buffer, tracker_usage),
+          };
+          if !transition.release.is_empty() {
+            plans[last_batch[&usage.resource]]
+              .releases
+              .extend(transition.release);
+          }
+          pass_barriers[i].extend(transition.barriers);
+          // host accesses are not part of the submission
+          plans[b].wait_stages |= usage.stage_mask & !vk::PipelineStageFlags2::HOST;
+          last_batch.insert(usage.resource, b);
+        }
+      }
+    }
+
+    let mut pools: Vec<(u32, Owned<vk::CommandPool>)> = Vec::new();
+    for plan in plans.iter() {
+      if !pools.iter().any(|(family, _)| *family == plan.family) {
+        pools.push((plan.family, create_command_pool(&self.device, plan.family)?));
+      }
+    }
+
+    let profiled: Vec<bool> = self.passes.iter().map(|pass| !pass.barriers_only).collect();
+    let scope_count = profiled.iter().filter(|&&p| p).count();
+    let mut profiler = match self.physical_device {
+      Some(physical_device) if self.profiling => Some(TimestampProfiler::new(
+        &self.device,
+        physical_device,
+        scope_count,
+      )?),
+      _ => None,
+    };
+    let mut breadcrumbs = match self.physical_device {
+      Some(physical_device) if self.loss_diagnostics => {
+        Breadcrumbs::new_if_needed(&self.device, physical_device, scope_count)?
+      }
+      _ => None,
+    };
+
+    let (pass_names, mut records): (Vec<String>, Vec<Option<RecordFn>>) = self
+      .passes
+      .drain(..)
+      .map(|pass| (pass.name, Some(pass.record)))
+      .unzip();
+    let mut batches = Vec::with_capacity(plans.len());
+    for (b, plan) in plans.iter().enumerate() {
+      let pool = &pools.iter().find(|(f, _)| *f == plan.family).unwrap().1;
+      let command_buffer = allocate_command_buffer(&self.device, **pool)?;
+      let names: Vec<String> = plan.passes.iter().map(|&i| pass_names[i].clone()).collect();
+      log::debug!(
+        "Render graph submission (family {}): {:?}",
+        plan.family,
+        names
+      );
+
+      unsafe {
+        begin_command_buffer(&self.device, command_buffer)?;
+        // submissions execute one after the other, so only the first one has to reset breadcrumbs
+        if let (Some(breadcrumbs), 0) = (breadcrumbs.as_ref(), b) {
+          breadcrumbs.reset(command_buffer);
+        }
+        for &i in plan.passes.iter() {
+          pass_barriers[i].record(&self.device, command_buffer);
+          let marker = match breadcrumbs.as_mut() {
+            Some(breadcrumbs) if profiled[i] => {
+              Some(breadcrumbs.begin(command_buffer, &pass_names[i]))
+            }
+            _ => None,
+          };
+          let scope = match profiler.as_mut() {
+            Some(profiler) if profiled[i] => {
+              Some(profiler.begin_scope(command_buffer, &pass_names[i], plan.family))
+            }
+            _ => None,
+          };
+          (records[i].take().unwrap())(&self.device, command_buffer);
+          if let (Some(profiler), Some(scope)) = (profiler.as_ref(), scope) {
+            profiler.end_scope(command_buffer, scope);
+          }
+          if let (Some(breadcrumbs), Some(marker)) = (breadcrumbs.as_ref(), marker) {
+            breadcrumbs.end(command_buffer, marker);
+          }
+        }
+        plan.releases.record(&self.device, command_buffer);
+        self.device.end_command_buffer(command_buffer)?;
+      }
+
+      batches.push(Batch {
+        queue: self.queue_of_family(plan.family),
+        command_buffer,
+        wait_stage: if plan.wait_stages.is_empty() {
+          vk::PipelineStageFlags2::ALL_COMMANDS
+        } else {
+          plan.wait_stages
+        },
+        pass_names: names,
+      });
+    }
+
+    Ok(CompiledGraph {
+      timeline: create_timeline_semaphore(&self.device)?,
+      last_value: 0,
+      batches,
+      profiler,
+      breadcrumbs,
+      loss_diagnostics: self.loss_diagnostics,
+      _pools: pools.into_iter().map(|(_, pool)| pool).collect(),
+      device: self.device,
+    })
+  }
+}
+
+// declares the resources used by a pass
+pub struct PassBuilder<'g, 'a> {
+  graph: &'g mut RenderGraph<'a>,
+  name: String,
+  family: u32,
+  usages: Vec<ResourceUsage>,
+}
+
+impl<'a> PassBuilder<'_, 'a> {
+  fn usage(
+    mut self,
+    resource: Resource,
+    layout: vk::ImageLayout,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+    discard: bool,
+  ) -> Self {
+    self.usages.push(ResourceUsage {
+      resource,
+      layout,
+      stage_mask,
+      access_mask,
+      discard,
+    });
+    self
+  }
+
+  // image is used in layout with these stages and accesses (reads or writes)
+  pub fn image(
+    self,
+    image: vk::Image,
+    layout: vk::ImageLayout,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+  ) -> Self {
+    let resource = Resource::Image(image);
+    self.usage(resource, layout, stage_mask, access_mask, false)
+  }
+
+  // same as image, but previous contents are not needed
+  pub fn discard_image(
+    self,
+    image: vk::Image,
+    layout: vk::ImageLayout,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+  ) -> Self {
+    let resource = Resource::Image(image);
+    self.usage(resource, layout, stage_mask, access_mask, true)
+  }
+
+  pub fn buffer(
+    self,
+    buffer: vk::Buffer,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+  ) -> Self {
+    let resource = Resource::Buffer(buffer);
+    let layout = vk::ImageLayout::UNDEFINED;
+    self.usage(resource, layout, stage_mask, access_mask, false)
+  }
+
+  pub fn discard_buffer(
+    self,
+    buffer: vk::Buffer,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+  ) -> Self {
+    let resource = Resource::Buffer(buffer);
+    let layout = vk::ImageLayout::UNDEFINED;
+    self.usage(resource, layout, stage_mask, access_mask, true)
+  }
+
+  // adds the pass to the graph, record is called during compile with the command buffer of its
+  // submission, after the barriers for the declared usages
+  pub fn record<F: FnOnce(&ash::Device, vk::CommandBuffer) + 'a>(self, record: F) {
+    self.graph.passes.push(Pass {
+      name: self.name,
+      family: self.family,
+      usages: self.usages,
+      record: Box::new(record),
+      barriers_only: false,
+    });
+  }
+
+  // adds a pass that only makes the declared usages available (for example host reads)
+  pub fn barriers_only(self) {
+    self.graph.passes.push(Pass {
+      name: self.name,
+      family: self.family,
+      usages: self.usages,
+      record: Box::new(|_, _| {}),
+      barriers_only: true,
+    });
+  }
+}
+
+struct BatchPlan {
+  family: u32,
+  passes: Vec<usize>,
+  // device stages of all usages in the batch
+  wait_stages: vk::PipelineStageFlags2,
+  releases: Barriers,
+}
+
+struct Batch {
+  queue: vk::Queue,
+  command_buffer: vk::CommandBuffer,
+  // the wait on the previous submission chains with the acquire barriers at these stages
+  wait_stage: vk::PipelineStageFlags2,
+  pass_names: Vec<String>,
+}
+
+// Recorded graph
+//
+// Each submission signals the next value of the timeline semaphore and waits for the previous
+// value (including the last one of the previous graph submission), as values of a timeline
+// semaphore have to be signaled in increasing order. This means that submissions execute one
+// after the other, even if they are on different queues.
+pub struct CompiledGraph {
+  timeline: Owned<vk::Semaphore>,
+  // value signaled by the last submission
+  last_value: u64,
+  batches: Vec<Batch>,
+  profiler: Option<TimestampProfiler>,
+  // None if loss diagnostics are disabled or VK_EXT_device_fault is enabled
+  breadcrumbs: Option<Breadcrumbs>,
+  loss_diagnostics: bool,
+  // command buffers are freed with their pools
+  _pools: Vec<Owned<vk::CommandPool>>,
+  device: Arc<Device>,
+}
+
+impl CompiledGraph {
+  // pass names of each submission, in execution order
+  pub fn submissions(&self) -> impl Iterator<Item = &[String]> {
+    self.batches.iter().map(|batch| batch.pass_names.as_slice())
+  }
+
+  pub fn semaphore(&self) -> vk::Semaphore {
+    *self.timeline
+  }
+
+  // submits all passes and returns the value that gets signaled when all of them complete
+  // can be called again before the previous submission completes, unless profiling is enabled, in
+  // which case it waits for it in order to reset the timestamp queries
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn submit(&mut self) -> Result<u64, vk::Result> {
+    if let Some(profiler) = self.profiler.as_ref() {
+      self.wait(self.last_value)?;
+      unsafe { profiler.reset() };
+    }
+    for batch in self.batches.iter() {
+      let wait = [timeline_submit_info(
+        *self.timeline,
+        self.last_value,
+        batch.wait_stage,
+      )];
+      let signal = [timeline_submit_info(
+        *self.timeline,
+        self.last_value + 1,
+        vk::PipelineStageFlags2::ALL_COMMANDS,
+      )];
+      let command_buffer_info = vk::CommandBufferSubmitInfo {
+        s_type: vk::StructureType::COMMAND_BUFFER_SUBMIT_INFO,
+        p_next: ptr::null(),
+        command_buffer: batch.command_buffer,
+        device_mask: 0,
+      };
+      // waiting for 0 always succeeds
+      let wait: &[vk::SemaphoreSubmitInfo] = if self.last_value == 0 { &[] } else { &wait };
+      let submit_info = vk::SubmitInfo2 {
+        s_type: vk::StructureType::SUBMIT_INFO_2,
+        p_next: ptr::null(),
+        flags: vk::SubmitFlags::empty(),
+        wait_semaphore_info_count: wait.len() as u32,
+        p_wait_semaphore_infos: wait.as_ptr(),
+        command_buffer_info_count: 1,
+        p_command_buffer_infos: &command_buffer_info,
+        signal_semaphore_info_count: signal.len() as u32,
+        p_signal_semaphore_infos: signal.as_ptr(),
+      };
+      let result = unsafe {
+        self
+          .device
+          .queue_submit2(batch.queue, &[submit_info], vk::Fence::null())
+      };
+      self.diagnose(result)?;
+      self.last_value += 1;
+    }
+    Ok(self.last_value)
+  }
+
+  // blocks until a value returned by submit is reached
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn wait(&self, value: u64) -> Result<(), vk::Result> {
+    self.diagnose(self.wait_timeline(value))
+  }
+
+  fn wait_timeline(&self, value: u64) -> Result<(), vk::Result> {
+    let wait_info = vk::SemaphoreWaitInfo {
+      s_type: vk::StructureType::SEMAPHORE_WAIT_INFO,
+      p_next: ptr::null(),
+      flags: vk::SemaphoreWaitFlags::empty(),
+      semaphore_count: 1,
+      p_semaphores: &*self.timeline,
+      p_values: &value,
+    };
+    unsafe { self.device.wait_semaphores(&wait_info, u64::MAX) }
+  }
+
+  // logs what is known about a device loss if loss diagnostics are enabled
+  fn diagnose(&self, result: Result<(), vk::Result>) -> Result<(), vk::Result> {
+    if result == Err(vk::Result::ERROR_DEVICE_LOST) && self.loss_diagnostics {
+      let breadcrumbs: Vec<&Breadcrumbs> = self.breadcrumbs.iter().collect();
+      log_device_lost(&self.device, &breadcrumbs);
+    }
+    result
+  }
+
+  pub fn submit_and_wait(&mut self) -> Result<(), vk::Result> {
+    let value = self.submit()?;
+    self.wait(value)
+  }
+
+  // GPU time of each recorded pass in execution order, None if profiling was not enabled
+  // should only be called after a submission completes, reports the last completed one
+  pub fn timings(&self) -> Result<Option<Vec<ScopeTiming>>, vk::Result> {
+    self
+      .profiler
+      .as_ref()
+      .map(|profiler| profiler.results())
+      .transpose()
+  }
+}
+
+impl Drop for CompiledGraph {
+  fn drop(&mut self) {
+    // command buffers may still be in use, a device loss was already diagnosed when reported
+    if let Err(err) = self.wait_timeline(self.last_value) {
+      log::error!(
+        "Failed to wait for the render graph to complete during drop: {:?}",
+        err
+      );
+    }
+  }
+}
+
+fn timeline_submit_info(
+  semaphore: vk::Semaphore,
+  value: u64,
+  stage_mask: vk::PipelineStageFlags2,
+) -> vk::SemaphoreSubmitInfo {
+  vk::SemaphoreSubmitInfo {
+    s_type: vk::StructureType::SEMAPHORE_SUBMIT_INFO,
+    p_next: ptr::null(),
+    semaphore,
+    value,
+    stage_mask,
+    device_index: 0,
+  }
+}
+
+fn create_timeline_semaphore(device: &Arc<Device>) -> Result<Owned<vk::Semaphore>, vk::Result> {
+  let type_info = vk::SemaphoreTypeCreateInfo {
+    s_type: vk::StructureType::SEMAPHORE_TYPE_CREATE_INFO,
+    p_next: ptr::null(),
+    semaphore_type: vk::SemaphoreType::TIMELINE,
+    initial_value: 0,
+  };
+  let create_info = vk::SemaphoreCreateInfo {
+    s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+    p_next: &type_info as *const _ as *const c_void,
+    flags: vk::SemaphoreCreateFlags::empty(),
+  };
+  unsafe {
+    let semaphore = device.create_semaphore(&create_info, None)?;
+    Ok(Owned::from_raw(device, semaphore))
+  }
+}
+
+fn create_command_pool(
+  device: &Arc<Device>,
+  queue_family_index: u32,
+) -> Result<Owned<vk::CommandPool>, vk::Result> {
+  let create_info = vk::CommandPoolCreateInfo {
+    s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
+    p_next: ptr::null(),
+    flags: vk::CommandPoolCreateFlags::empty(),
+    queue_family_index,
+  };
+  unsafe {
+    let pool = device.create_command_pool(&create_info, None)?;
+    Ok(Owned::from_raw(device, pool))
+  }
+}
+
+fn allocate_command_buffer(
+  device: &ash::Device,
+  command_pool: vk::CommandPool,
+) -> Result<vk::CommandBuffer, vk::Result> {
+  let allocate_info = vk::CommandBufferAllocateInfo {
+    s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
+    p_next: ptr::null(),
+    command_buffer_count: 1,
+    command_pool,
+    level: vk::CommandBufferLevel::PRIMARY,
+  };
+  Ok(unsafe { device.allocate_command_buffers(&allocate_info)? }[0])
+}
+
+// recorded once and submitted many times, possibly while still pending
+unsafe fn begin_command_buffer(
+  device: &ash::Device,
+  cb: vk::CommandBuffer,
+) -> Result<(), vk::Result> {
+  let begin_info = vk::CommandBufferBeginInfo {
+    s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+    p_next: ptr::null(),
+    flags: vk::CommandBufferUsageFlags::SIMULTANEOUS_USE,
+    p_inheritance_info: ptr::null(),
+  };
+  device.begin_command_buffer(cb, &begin_info)
+}
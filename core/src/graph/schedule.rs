@@ -0,0 +1,106 @@
+use ash::vk;
+
+// how a pass uses a resource, as far as ordering is concerned
+#[derive(Clone, Copy, Debug)]
+pub struct ResourceAccess<K> {
+  pub resource: K,
+  pub layout: vk::ImageLayout,
+  // writes the resource or discards its contents
+  pub writes: bool,
+}
+
+// two usages can be reordered only if both read the resource in the same layout
+fn conflict<K: PartialEq>(a: &ResourceAccess<K>, b: &ResourceAccess<K>) -> bool {
+  a.resource == b.resource && (a.writes || b.writes || a.layout != b.layout)
+}
+
+// for each pass, earlier passes that have to execute before it
+pub fn dependencies<K: PartialEq>(passes: &[Vec<ResourceAccess<K>>]) -> Vec<Vec<usize>> {
+  passes
+    .iter()
+    .enumerate()
+    .map(|(i, accesses)| {
+      (0..i)
+        .filter(|&j| {
+          accesses
+            .iter()
+            .any(|a| passes[j].iter().any(|b| conflict(a, b)))
+        })
+        .collect()
+    })
+    .collect()
+}
+
+// Returns an execution order of the passes that respects their dependencies
+//
+// Passes that are ready are taken in declaration order, but a pass on the same queue as the last
+// scheduled one is preferred, so that consecutive passes can share a submission.
+pub fn schedule(queues: &[u32], dependencies: &[Vec<usize>]) -> Vec<usize> {
+  debug_assert_eq!(queues.len(), dependencies.len());
+  let mut scheduled = vec![false; queues.len()];
+  let mut order: Vec<usize> = Vec::with_capacity(queues.len());
+
+  while order.len() < queues.len() {
+    let ready = |i: &usize| !scheduled[*i] && dependencies[*i].iter().all(|&d| scheduled[d]);
+    let current_queue = order.last().map(|&last| queues[last]);
+    let next = (0..queues.len())
+      .filter(ready)
+      .find(|&i| Some(queues[i]) == current_queue)
+      .or_else(|| (0..queues.len()).find(ready))
+      .expect("Pass dependencies contain a cycle");
+    scheduled[next] = true;
+    order.push(next);
+  }
+  order
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn read(resource: u32) -> ResourceAccess<u32> {
+    ResourceAccess {
+      resource,
+      layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+      writes: false,
+    }
+  }
+
+  fn write(resource: u32) -> ResourceAccess<u32> {
+    ResourceAccess {
+      resource,
+      layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+      writes: true,
+    }
+  }
+
+  #[test]
+  fn only_conflicting_usages_are_dependencies() {
+    let passes = vec![
+      vec![write(0)],
+      vec![read(0), write(1)],
+      vec![read(0)],
+      vec![write(0)],
+    ];
+    let deps = dependencies(&passes);
+    assert_eq!(deps[1], vec![0]);
+    // reads of the same layout don't depend on each other
+    assert_eq!(deps[2], vec![0]);
+    assert_eq!(deps[3], vec![0, 1, 2]);
+  }
+
+  #[test]
+  fn passes_on_the_same_queue_are_grouped() {
+    // 0 (compute) -> 1 (transfer), 2 (compute) is independent
+    let queues = [0, 1, 0];
+    let deps = vec![vec![], vec![0], vec![]];
+    assert_eq!(schedule(&queues, &deps), vec![0, 2, 1]);
+  }
+
+  #[test]
+  fn dependencies_are_respected() {
+    let queues = [0, 1, 0, 1];
+    let deps = vec![vec![], vec![0], vec![1], vec![]];
+    assert_eq!(schedule(&queues, &deps), vec![0, 1, 3, 2]);
+  }
+}
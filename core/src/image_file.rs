@@ -0,0 +1,275 @@
+// Saving image data read back from the device to files
+//
+// The file format is chosen by the path extension: ".exr" (OpenEXR) and ".hdr" (Radiance HDR)
+// store 32 bit floats, while every other extension is left to image::save_buffer. Texel values are
+// saved as they are in the image, except for sRGB formats, which get decoded to linear values when
+// saved as floats. Float formats can't be stored by most other file formats (PNG included), so they
+// get clamped to [0, 1] and saved with 16 bits per channel.
+
+use std::{fs::File, io::BufWriter, path::Path};
+
+use ash::vk;
+use image::{codecs::hdr::HdrEncoder, ColorType, ImageResult, Rgb};
+
+// formats of images that can be read back and saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TexelFormat {
+  R8G8B8A8Unorm,
+  R8G8B8A8Srgb,
+  R16Unorm,
+  R16G16B16A16Unorm,
+  R16G16B16A16Sfloat,
+  R32G32B32A32Sfloat,
+}
+
+impl TexelFormat {
+  pub const ALL: [TexelFormat; 6] = [
+    TexelFormat::R8G8B8A8Unorm,
+    TexelFormat::R8G8B8A8Srgb,
+    TexelFormat::R16Unorm,
+    TexelFormat::R16G16B16A16Unorm,
+    TexelFormat::R16G16B16A16Sfloat,
+    TexelFormat::R32G32B32A32Sfloat,
+  ];
+
+  // Vulkan format name without the VK_FORMAT_ prefix
+  pub fn name(self) -> &'static str {
+    match self {
+      TexelFormat::R8G8B8A8Unorm => "R8G8B8A8_UNORM",
+      TexelFormat::R8G8B8A8Srgb => "R8G8B8A8_SRGB",
+      TexelFormat::R16Unorm => "R16_UNORM",
+      TexelFormat::R16G16B16A16Unorm => "R16G16B16A16_UNORM",
+      TexelFormat::R16G16B16A16Sfloat => "R16G16B16A16_SFLOAT",
+      TexelFormat::R32G32B32A32Sfloat => "R32G32B32A32_SFLOAT",
+    }
+  }
+
+  // case insensitive
+  pub fn from_name(name: &str) -> Option<Self> {
+    Self::ALL
+      .into_iter()
+      .find(|format| format.name().eq_ignore_ascii_case(name))
+  }
+
+  pub fn vk_format(self) -> vk::Format {
+    match self {
+      TexelFormat::R8G8B8A8Unorm => vk::Format::R8G8B8A8_UNORM,
+      TexelFormat::R8G8B8A8Srgb => vk::Format::R8G8B8A8_SRGB,
+      TexelFormat::R16Unorm => vk::Format::R16_UNORM,
+      TexelFormat::R16G16B16A16Unorm => vk::Format::R16G16B16A16_UNORM,
+      TexelFormat::R16G16B16A16Sfloat => vk::Format::R16G16B16A16_SFLOAT,
+      TexelFormat::R32G32B32A32Sfloat => vk::Format::R32G32B32A32_SFLOAT,
+    }
+  }
+
+  // size of one texel in bytes
+  pub fn texel_size(self) -> u64 {
+    match self {
+      TexelFormat::R16Unorm => 2,
+      TexelFormat::R8G8B8A8Unorm | TexelFormat::R8G8B8A8Srgb => 4,
+      TexelFormat::R16G16B16A16Unorm | TexelFormat::R16G16B16A16Sfloat => 8,
+      TexelFormat::R32G32B32A32Sfloat => 16,
+    }
+  }
+
+  pub fn is_float(self) -> bool {
+    matches!(
+      self,
+      TexelFormat::R16G16B16A16Sfloat | TexelFormat::R32G32B32A32Sfloat
+    )
+  }
+
+  // equivalent color type of the texel data (half floats don't have one and get converted to f32)
+  pub fn color_type(self) -> ColorType {
+    match self {
+      TexelFormat::R8G8B8A8Unorm | TexelFormat::R8G8B8A8Srgb => ColorType::Rgba8,
+      TexelFormat::R16Unorm => ColorType::L16,
+      TexelFormat::R16G16B16A16Unorm => ColorType::Rgba16,
+      TexelFormat::R16G16B16A16Sfloat | TexelFormat::R32G32B32A32Sfloat => ColorType::Rgba32F,
+    }
+  }
+
+  // Converts tightly packed texels to RGBA floats
+  // single channel formats are converted to gray with an opaque alpha
+  pub fn to_rgba32f(self, data: &[u8]) -> Vec<f32> {
+    let u16_at = |texel: &[u8], i: usize| u16::from_le_bytes([texel[i * 2], texel[i * 2 + 1]]);
+    let mut rgba = Vec::with_capacity(data.len() / self.texel_size() as usize * 4);
+    for texel in data.chunks_exact(self.texel_size() as usize) {
+      let values: [f32; 4] = match self {
+        TexelFormat::R8G8B8A8Unorm => [0, 1, 2, 3].map(|i| texel[i] as f32 / 255.0),
+        TexelFormat::R8G8B8A8Srgb => {
+          let [r, g, b, a] = [0, 1, 2, 3].map(|i| texel[i] as f32 / 255.0);
+          // alpha is always linear
+          [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+        }
+        TexelFormat::R16Unorm => {
+          let v = u16_at(texel, 0) as f32 / 65535.0;
+          [v, v, v, 1.0]
+        }
+        TexelFormat::R16G16B16A16Unorm => [0, 1, 2, 3].map(|i| u16_at(texel, i) as f32 / 65535.0),
+        TexelFormat::R16G16B16A16Sfloat => [0, 1, 2, 3].map(|i| f16_to_f32(u16_at(texel, i))),
+        TexelFormat::R32G32B32A32Sfloat => [0, 1, 2, 3].map(|i| {
+          f32::from_le_bytes([
+            texel[i * 4],
+            texel[i * 4 + 1],
+            texel[i * 4 + 2],
+            texel[i * 4 + 3],
+          ])
+        }),
+      };
+      rgba.extend_from_slice(&values);
+    }
+    rgba
+  }
+}
+
+pub fn srgb_to_linear(v: f32) -> f32 {
+  if v <= 0.04045 {
+    v / 12.92
+  } else {
+    ((v + 0.055) / 1.055).powf(2.4)
+  }
+}
+
+// IEEE 754 half precision bits to f32
+pub fn f16_to_f32(bits: u16) -> f32 {
+  let exponent = (bits >> 10) & 0x1F;
+  let mantissa = (bits & 0x3FF) as u32;
+  let magnitude = match exponent {
+    // zero or subnormal
+    0 => mantissa as f32 / (1 << 24) as f32,
+    // infinity or NaN
+    0x1F => f32::from_bits(0x7F80_0000 | (mantissa << 13)),
+    // rebias the exponent from 15 to 127
+    _ => f32::from_bits(((exponent as u32 + 112) << 23) | (mantissa << 13)),
+  };
+  if bits & 0x8000 != 0 {
+    -magnitude
+  } else {
+    magnitude
+  }
+}
+
+// Saves tightly packed texels of a width x height image, see the module comment for how the
+// file format is chosen
+// Texel data is expected to be little endian, like on every platform the examples run on
+pub fn save_image(
+  path: &Path,
+  data: &[u8],
+  width: u32,
+  height: u32,
+  format: TexelFormat,
+) -> ImageResult<()> {
+  let extension = path
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .map(|extension| extension.to_ascii_lowercase());
+
+  match extension.as_deref() {
+    Some("exr") => {
+      let bytes: Vec<u8> = format
+        .to_rgba32f(data)
+        .into_iter()
+        .flat_map(f32::to_ne_bytes)
+        .collect();
+      image::save_buffer(path, &bytes, width, height, ColorType::Rgba32F)
+    }
+    Some("hdr") => {
+      // Radiance HDR doesn't have an alpha channel
+      let pixels: Vec<Rgb<f32>> = format
+        .to_rgba32f(data)
+        .chunks_exact(4)
+        .map(|rgba| Rgb([rgba[0], rgba[1], rgba[2]]))
+        .collect();
+      let file = BufWriter::new(File::create(path)?);
+      HdrEncoder::new(file).encode(&pixels, width as usize, height as usize)
+    }
+    _ if format.is_float() => {
+      let bytes: Vec<u8> = format
+        .to_rgba32f(data)
+        .into_iter()
+        .flat_map(|v| ((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_ne_bytes())
+        .collect();
+      image::save_buffer(path, &bytes, width, height, ColorType::Rgba16)
+    }
+    _ => image::save_buffer(path, data, width, height, format.color_type()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn half_floats_are_converted() {
+    assert_eq!(f16_to_f32(0x0000), 0.0);
+    assert_eq!(f16_to_f32(0x3C00), 1.0);
+    assert_eq!(f16_to_f32(0x3800), 0.5);
+    assert_eq!(f16_to_f32(0xC000), -2.0);
+    assert_eq!(f16_to_f32(0x7BFF), 65504.0);
+    // smallest subnormal
+    assert_eq!(f16_to_f32(0x0001), 2.0f32.powi(-24));
+    assert_eq!(f16_to_f32(0x7C00), f32::INFINITY);
+    assert!(f16_to_f32(0x7E00).is_nan());
+  }
+
+  #[test]
+  fn texels_are_converted_to_rgba() {
+    let gray = 32768u16.to_le_bytes();
+    assert_eq!(
+      TexelFormat::R16Unorm.to_rgba32f(&[gray, [0, 0]].concat()),
+      [
+        32768.0 / 65535.0,
+        32768.0 / 65535.0,
+        32768.0 / 65535.0,
+        1.0,
+        0.0,
+        0.0,
+        0.0,
+        1.0
+      ]
+    );
+
+    let srgb = TexelFormat::R8G8B8A8Srgb.to_rgba32f(&[255, 0, 188, 128]);
+    assert_eq!(srgb[..2], [1.0, 0.0]);
+    assert!((srgb[2] - 0.5).abs() < 0.01);
+    assert_eq!(srgb[3], 128.0 / 255.0);
+
+    let half: Vec<u8> = [0x3C00u16, 0x3800, 0x0000, 0x4000]
+      .into_iter()
+      .flat_map(u16::to_le_bytes)
+      .collect();
+    assert_eq!(
+      TexelFormat::R16G16B16A16Sfloat.to_rgba32f(&half),
+      [1.0, 0.5, 0.0, 2.0]
+    );
+  }
+
+  #[test]
+  fn formats_are_found_by_name() {
+    for format in TexelFormat::ALL {
+      assert_eq!(TexelFormat::from_name(format.name()), Some(format));
+    }
+    assert_eq!(
+      TexelFormat::from_name("r16g16b16a16_sfloat"),
+      Some(TexelFormat::R16G16B16A16Sfloat)
+    );
+    assert_eq!(TexelFormat::from_name("R8G8B8_UNORM"), None);
+  }
+
+  #[test]
+  fn float_images_keep_their_values_in_exr_files() {
+    let path = std::env::temp_dir().join(format!("abe_image_file_{}.exr", std::process::id()));
+    let texels: Vec<u8> = [2.5f32, 0.25, -1.0, 1.0, 0.0, 100.0, 0.125, 0.5]
+      .into_iter()
+      .flat_map(f32::to_le_bytes)
+      .collect();
+    save_image(&path, &texels, 2, 1, TexelFormat::R32G32B32A32Sfloat).unwrap();
+
+    let saved = image::open(&path).unwrap().into_rgba32f();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(saved.dimensions(), (2, 1));
+    assert_eq!(saved.get_pixel(0, 0).0, [2.5, 0.25, -1.0, 1.0]);
+    assert_eq!(saved.get_pixel(1, 0).0, [0.0, 100.0, 0.125, 0.5]);
+  }
+}
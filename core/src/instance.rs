@@ -0,0 +1,195 @@
+use ash::vk;
+use std::{
+  ffi::{c_char, CStr},
+  ptr,
+};
+
+use crate::utility;
+
+// Information passed to the Vulkan implementation during instance creation
+pub struct ApplicationInfo<'a> {
+  pub name: &'a CStr,
+  pub version: u32,
+  // Vulkan API version required to run the program
+  // You may have to use an older API version if you want to support devices that do not yet
+  // support the recent versions. You can see in the documentation what is the minimum supported
+  // version for each extension, feature or API call.
+  pub api_version: u32,
+}
+
+fn check_target_api_version(entry: &ash::Entry, target_api_version: u32) -> Result<(), vk::Result> {
+  let max_supported_version = match entry.try_enumerate_instance_version() {
+    // Vulkan 1.1+
+    Ok(opt) => opt.unwrap_or(vk::API_VERSION_1_0),
+    // Vulkan 1.0
+    Err(_) => vk::API_VERSION_1_0,
+  };
+
+  log::info!(
+    "Vulkan library max supported version: {}",
+    utility::parse_vulkan_api_version(max_supported_version)
+  );
+
+  if max_supported_version < target_api_version {
+    log::error!(
+      "Vulkan implementation API maximum supported version is less than the one targeted by the application."
+    );
+    return Err(vk::Result::ERROR_INCOMPATIBLE_DRIVER);
+  }
+
+  Ok(())
+}
+
+// Returns a subset of unavailable extensions
+fn filter_unavailable_extensions<'a>(
+  available: Vec<vk::ExtensionProperties>,
+  required: &'a [&'a CStr],
+) -> Box<[&'a &'a CStr]> {
+  required
+    .iter()
+    .filter(|&req| {
+      !available
+        .iter()
+        .any(|av| utility::i8_array_as_cstr(&av.extension_name).unwrap() == *req)
+    })
+    .collect()
+}
+
+fn check_instance_extension_support(
+  entry: &ash::Entry,
+  required_extensions: &[&CStr],
+) -> Result<(), vk::Result> {
+  log::info!(
+    "Required Instance extensions by the application: {:?}",
+    required_extensions
+  );
+
+  let unavailable_extensions = filter_unavailable_extensions(
+    entry.enumerate_instance_extension_properties(None)?,
+    required_extensions,
+  );
+  if !unavailable_extensions.is_empty() {
+    log::error!(
+      "Some instance extensions are not available: {:?}",
+      unavailable_extensions
+    );
+    return Err(vk::Result::ERROR_EXTENSION_NOT_PRESENT);
+  }
+
+  Ok(())
+}
+
+fn get_app_info(app: &ApplicationInfo) -> vk::ApplicationInfo {
+  vk::ApplicationInfo {
+    s_type: vk::StructureType::APPLICATION_INFO,
+    api_version: app.api_version,
+    p_application_name: app.name.as_ptr(),
+    application_version: app.version,
+    p_engine_name: ptr::null(),
+    engine_version: vk::make_api_version(0, 1, 0, 0),
+    p_next: ptr::null(),
+  }
+}
+
+// Creates an instance with all additional extensions required by the application (for example
+// the ones needed for creating a window surface)
+#[cfg(feature = "vl")]
+pub fn create_instance(
+  entry: &ash::Entry,
+  app: &ApplicationInfo,
+  additional_extensions: &[&CStr],
+) -> Result<(ash::Instance, crate::DebugUtils), vk::Result> {
+  use std::{ffi::c_void, ptr::addr_of, sync::Arc};
+
+  use crate::validation_layers::{
+    self, DebugUtils, ValidationMessages, ValidationMode, ADDITIONAL_VALIDATION_FEATURES,
+  };
+
+  check_target_api_version(entry, app.api_version)?;
+
+  let mut required_extensions = vec![ash::extensions::ext::DebugUtils::name()];
+  required_extensions.extend_from_slice(additional_extensions);
+  check_instance_extension_support(entry, &required_extensions)?;
+  // required to be alive until the end of instance creation
+  let required_extensions_ptrs: Vec<*const c_char> =
+    required_extensions.iter().map(|v| v.as_ptr()).collect();
+
+  let app_info = get_app_info(app);
+
+  // valid until the end of scope
+  let validation_layers = validation_layers::get_supported_validation_layers(entry)?;
+  let vl_pointers: Vec<*const c_char> =
+    validation_layers.iter().map(|name| name.as_ptr()).collect();
+
+  // receives messages from both messengers, ends up owned by DebugUtils
+  let messages = Arc::new(ValidationMessages::new(ValidationMode::from_env()));
+
+  // required to be passed in instance creation p_next chain
+  let debug_create_info = DebugUtils::get_debug_messenger_create_info(&messages);
+
+  // enable/disable some validation features by passing a ValidationFeaturesEXT struct
+  let additional_features = vk::ValidationFeaturesEXT {
+    s_type: vk::StructureType::VALIDATION_FEATURES_EXT,
+    p_next: addr_of!(debug_create_info) as *const c_void,
+    enabled_validation_feature_count: ADDITIONAL_VALIDATION_FEATURES.len() as u32,
+    p_enabled_validation_features: ADDITIONAL_VALIDATION_FEATURES.as_ptr(),
+    disabled_validation_feature_count: 0,
+    p_disabled_validation_features: ptr::null(),
+  };
+
+  let create_info = vk::InstanceCreateInfo {
+    s_type: vk::StructureType::INSTANCE_CREATE_INFO,
+    p_next: addr_of!(additional_features) as *const c_void,
+    p_application_info: &app_info,
+    pp_enabled_layer_names: vl_pointers.as_ptr(),
+    enabled_layer_count: vl_pointers.len() as u32,
+    pp_enabled_extension_names: required_extensions_ptrs.as_ptr(),
+    enabled_extension_count: required_extensions_ptrs.len() as u32,
+    flags: vk::InstanceCreateFlags::empty(),
+  };
+
+  log::debug!("Creating Instance");
+  let instance: ash::Instance = unsafe { entry.create_instance(&create_info, None)? };
+
+  log::debug!("Creating Debug Utils");
+  let debug_utils = match DebugUtils::create(entry, &instance, debug_create_info, messages.clone())
+  {
+    Ok(debug_utils) => debug_utils,
+    Err(err) => {
+      unsafe { instance.destroy_instance(None) };
+      return Err(err);
+    }
+  };
+
+  Ok((instance, debug_utils))
+}
+
+#[cfg(not(feature = "vl"))]
+pub fn create_instance(
+  entry: &ash::Entry,
+  app: &ApplicationInfo,
+  additional_extensions: &[&CStr],
+) -> Result<ash::Instance, vk::Result> {
+  check_target_api_version(entry, app.api_version)?;
+
+  check_instance_extension_support(entry, additional_extensions)?;
+  // required to be alive until the end of instance creation
+  let required_extensions_ptrs: Vec<*const c_char> =
+    additional_extensions.iter().map(|v| v.as_ptr()).collect();
+
+  let app_info = get_app_info(app);
+
+  let create_info = vk::InstanceCreateInfo {
+    s_type: vk::StructureType::INSTANCE_CREATE_INFO,
+    p_next: ptr::null(),
+    p_application_info: &app_info,
+    pp_enabled_layer_names: ptr::null(),
+    enabled_layer_count: 0,
+    pp_enabled_extension_names: required_extensions_ptrs.as_ptr(),
+    enabled_extension_count: required_extensions_ptrs.len() as u32,
+    flags: vk::InstanceCreateFlags::empty(),
+  };
+
+  log::debug!("Creating Instance");
+  unsafe { entry.create_instance(&create_info, None) }
+}
@@ -0,0 +1,50 @@
+// Vulkan boilerplate shared between all examples: loading the library, creating an instance with
+// validation layers, selecting a physical device that fulfills the application requirements,
+// creating a logical device, sub-allocating device memory, streaming uploads through a staging
+// buffer, tracking resource states for barriers, scheduling passes in a render graph, timing GPU
+// work with timestamp queries, benchmarking repeated submissions, counting draw statistics with
+// occlusion and pipeline statistics queries, faking and diagnosing device losses, managing a
+// pipeline cache, patching storage image formats in SPIR-V, saving read back images to files and
+// owning Vulkan objects that get destroyed when dropped
+
+// unsafe functions follow Vulkan's usual rules (valid handles, external synchronization, etc.)
+#![allow(clippy::missing_safety_doc)]
+
+pub mod bench;
+pub mod device;
+pub mod device_lost;
+pub mod diagnostics;
+mod entry;
+pub mod graph;
+pub mod image_file;
+mod instance;
+pub mod memory;
+pub mod owned;
+pub mod pipeline_cache;
+pub mod profiler;
+pub mod queries;
+pub mod spirv;
+pub mod tracker;
+pub mod upload;
+pub mod utility;
+
+// validation layers module will only exist if validation layers are enabled
+#[cfg(feature = "vl")]
+mod validation_layers;
+
+pub use device::{
+  create_logical_device, get_extended_features, get_extended_properties, DeviceRequirements,
+  DeviceSelectionError, DeviceSelector, PhysicalDevice, PhysicalDeviceFeatures,
+  PhysicalDeviceProperties, QueueFamilies, QueueFamilyRequirements, Queues,
+};
+pub use entry::get_entry;
+pub use instance::{create_instance, ApplicationInfo};
+pub use memory::{Allocation, AllocatorError, MemoryAllocator, OwnedAllocation, SharedAllocator};
+pub use owned::{Device, Instance, Owned};
+pub use pipeline_cache::{create_pipeline_cache, save_pipeline_cache};
+#[cfg(feature = "vl")]
+pub use validation_layers::{
+  get_supported_validation_layers, DebugUtils, ValidationError, ValidationMessage,
+  ValidationMessages, ValidationMode, ValidationObject, ValidationSummary,
+  ADDITIONAL_VALIDATION_FEATURES, VALIDATION_LAYERS, VALIDATION_MODE_VAR,
+};
@@ -0,0 +1,728 @@
+use std::{ffi::c_void, fmt, ptr};
+
+use ash::vk;
+
+use crate::{utility::error_chain_fmt, PhysicalDevice};
+
+use super::{
+  budget::{prioritize_by_budget, MemoryBudgetQuery},
+  dedicated::{buffer_memory_requirements, image_memory_requirements, DedicatedAllocation},
+  free_list::{align_up, FreeList, Range},
+  mapped::MappedMemory,
+  planner::{
+    isolated_size_and_alignment, memory_type_candidates, MemoryLimits, ResourceKind,
+    ResourceRequirements,
+  },
+};
+
+// size of the vk::DeviceMemory objects that get sub-allocated
+// smaller if the heap or the device doesn't allow it
+pub const DEFAULT_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+// resources that take more than this fraction of a block get their own vk::DeviceMemory
+const DEDICATED_BLOCK_FRACTION: u64 = 2;
+
+// A range of some vk::DeviceMemory handed out by the allocator
+// Should be returned with MemoryAllocator::free (only once)
+pub struct Allocation {
+  pub memory: vk::DeviceMemory,
+  pub offset: u64,
+  pub size: u64,
+  pub memory_type: usize,
+  // the allocation range, if the memory type is host visible
+  mapped: Option<MappedMemory>,
+  // range in the block, can be bigger than the requested size because of granularity or
+  // nonCoherentAtomSize
+  reserved: Range,
+}
+
+impl Allocation {
+  // host visible memory blocks stay mapped for their entire lifetime
+  // non-coherent allocations don't share atoms with others, so they can be flushed and
+  // invalidated independently
+  pub fn mapped(&self) -> Option<&MappedMemory> {
+    self.mapped.as_ref()
+  }
+}
+
+pub enum AllocatorError {
+  // no memory type supports the resource with the required properties
+  NoSuitableMemoryType,
+  // allocation doesn't fit in any memory type that supports it
+  TooBig { size: u64, max: u64 },
+  // maxMemoryAllocationCount was reached and no existing block has enough free space
+  TooManyAllocations { max: u32 },
+  // allocation, map or bind failure
+  Vulkan(vk::Result),
+}
+
+impl fmt::Display for AllocatorError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NoSuitableMemoryType => {
+        write!(f, "No memory type supports the resource with the required properties")
+      }
+      Self::TooBig { size, max } => write!(
+        f,
+        "Allocation size ({}) is bigger than the maximum allowed for any supported memory type ({})",
+        size, max
+      ),
+      Self::TooManyAllocations { max } => write!(
+        f,
+        "The limit of device memory objects ({}) was reached",
+        max
+      ),
+      Self::Vulkan(err) => write!(f, "Failed to allocate device memory: {:?}", err),
+    }
+  }
+}
+
+impl fmt::Debug for AllocatorError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl std::error::Error for AllocatorError {}
+
+impl From<vk::Result> for AllocatorError {
+  fn from(value: vk::Result) -> Self {
+    AllocatorError::Vulkan(value)
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryTypeStats {
+  // vk::DeviceMemory objects
+  pub blocks: u32,
+  pub allocations: u32,
+  pub block_bytes: u64,
+  pub used_bytes: u64,
+  pub largest_free_range: u64,
+}
+
+impl MemoryTypeStats {
+  fn add(&mut self, other: &MemoryTypeStats) {
+    self.blocks += other.blocks;
+    self.allocations += other.allocations;
+    self.block_bytes += other.block_bytes;
+    self.used_bytes += other.used_bytes;
+    self.largest_free_range = self.largest_free_range.max(other.largest_free_range);
+  }
+}
+
+impl fmt::Display for MemoryTypeStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} allocations using {} of {} bytes in {} blocks (largest free range: {} bytes)",
+      self.allocations, self.used_bytes, self.block_bytes, self.blocks, self.largest_free_range
+    )
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct AllocatorStats {
+  // indexed by memory type
+  pub memory_types: Vec<MemoryTypeStats>,
+  pub max_memory_allocation_count: u32,
+}
+
+impl AllocatorStats {
+  pub fn total(&self) -> MemoryTypeStats {
+    let mut total = MemoryTypeStats::default();
+    for stats in self.memory_types.iter() {
+      total.add(stats);
+    }
+    total
+  }
+}
+
+impl fmt::Display for AllocatorStats {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let total = self.total();
+    write!(
+      f,
+      "{} (device memory objects: {}/{})",
+      total, total.blocks, self.max_memory_allocation_count
+    )?;
+    for (i, stats) in self.memory_types.iter().enumerate() {
+      if stats.blocks > 0 {
+        write!(f, "\n  type {}: {}", i, stats)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+struct MemoryBlock {
+  memory: vk::DeviceMemory,
+  size: u64,
+  mapped: Option<MappedMemory>,
+  free_list: FreeList,
+  allocation_count: u32,
+  used: u64,
+  // only holds one resource and gets freed as soon as it is unused
+  dedicated: bool,
+}
+
+// Sub-allocates resources from a few big vk::DeviceMemory blocks per memory type
+//
+// Blocks are only created when no existing block of a suitable memory type has enough free space,
+// which keeps the number of device memory objects low (some implementations only allow 4096).
+// Freed ranges are merged with their neighbours and reused by later allocations.
+// Memory types whose heap has enough remaining budget (VK_EXT_memory_budget) are preferred.
+// Only device memory objects created by this allocator are counted against
+// maxMemoryAllocationCount.
+pub struct MemoryAllocator {
+  limits: MemoryLimits,
+  budget_query: MemoryBudgetQuery,
+  pools: Vec<Vec<MemoryBlock>>,
+  block_count: u32,
+  max_memory_allocation_count: u32,
+  block_size: u64,
+}
+
+impl MemoryAllocator {
+  pub fn new(physical_device: &PhysicalDevice) -> Self {
+    Self::with_block_size(physical_device, DEFAULT_BLOCK_SIZE)
+  }
+
+  pub fn with_block_size(physical_device: &PhysicalDevice, block_size: u64) -> Self {
+    let limits = MemoryLimits::new(physical_device);
+    Self {
+      pools: (0..limits.memory_properties.memory_type_count)
+        .map(|_| Vec::new())
+        .collect(),
+      limits,
+      budget_query: physical_device.memory_budget_query().clone(),
+      block_count: 0,
+      max_memory_allocation_count: physical_device
+        .properties
+        .p10
+        .limits
+        .max_memory_allocation_count,
+      block_size,
+    }
+  }
+
+  fn memory_type_block_size(&self, memory_type: usize) -> u64 {
+    // small heaps (like 256 MiB BAR) shouldn't be taken by a single block
+    self
+      .block_size
+      .min(self.limits.heap_size(memory_type) / 8)
+      .min(self.limits.max_memory_allocation_size)
+  }
+
+  pub fn allocate(
+    &mut self,
+    device: &ash::Device,
+    requirements: vk::MemoryRequirements,
+    kind: ResourceKind,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Result<Allocation, AllocatorError> {
+    self.allocate_with(
+      device,
+      requirements,
+      kind,
+      required_properties,
+      optional_properties,
+      None,
+    )
+  }
+
+  // dedicated_info is set if the allocation should be a vk::DeviceMemory dedicated to a resource
+  fn allocate_with(
+    &mut self,
+    device: &ash::Device,
+    requirements: vk::MemoryRequirements,
+    kind: ResourceKind,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+    dedicated_info: Option<&vk::MemoryDedicatedAllocateInfo>,
+  ) -> Result<Allocation, AllocatorError> {
+    let resource = ResourceRequirements::new(requirements, kind);
+    let (size, alignment) = if dedicated_info.is_some() {
+      // dedicated allocations should have exactly the size of the resource
+      (requirements.size, requirements.alignment)
+    } else {
+      isolated_size_and_alignment(&resource, self.limits.buffer_image_granularity)
+    };
+
+    let mut memory_types = memory_type_candidates(
+      &self.limits.memory_properties,
+      requirements.memory_type_bits,
+      required_properties,
+      optional_properties,
+    );
+    if memory_types.is_empty() {
+      return Err(AllocatorError::NoSuitableMemoryType);
+    }
+    // prefer memory types whose heap still has enough budget for the allocation
+    let budget = self.budget_query.query();
+    prioritize_by_budget(&mut memory_types, size, &self.limits, &budget);
+
+    let mut error = None;
+    for &memory_type in memory_types.iter() {
+      let (size, alignment) = if dedicated_info.is_some() {
+        (size, alignment)
+      } else {
+        self.host_access_size_and_alignment(memory_type, size, alignment)
+      };
+
+      // try reusing existing blocks first
+      if dedicated_info.is_none() {
+        if let Some(allocation) = Self::allocate_from_pool(
+          &mut self.pools[memory_type],
+          memory_type,
+          size,
+          alignment,
+          requirements.size,
+        ) {
+          return Ok(allocation);
+        }
+      }
+
+      // existing blocks of other memory types may still have space
+      if self.block_count >= self.max_memory_allocation_count {
+        error = Some(AllocatorError::TooManyAllocations {
+          max: self.max_memory_allocation_count,
+        });
+        continue;
+      }
+
+      let type_block_size = self.memory_type_block_size(memory_type);
+      let dedicated = dedicated_info.is_some() || size > type_block_size / DEDICATED_BLOCK_FRACTION;
+      let block_size = if dedicated { size } else { type_block_size };
+      let max = self.limits.max_allocation_size(memory_type);
+      if block_size > max {
+        error.get_or_insert(AllocatorError::TooBig { size, max });
+        continue;
+      }
+
+      budget.check(&self.limits, memory_type, block_size);
+      let p_next = dedicated_info.map_or(ptr::null(), |info| {
+        info as *const vk::MemoryDedicatedAllocateInfo as *const c_void
+      });
+      match self.create_block(device, memory_type, block_size, dedicated, p_next) {
+        Ok(mut block) => {
+          let range = block.free_list.allocate(size, alignment).unwrap();
+          let allocation = Self::reserve(&mut block, memory_type, range, requirements.size);
+          self.pools[memory_type].push(block);
+          self.block_count += 1;
+          return Ok(allocation);
+        }
+        Err(
+          err @ (vk::Result::ERROR_OUT_OF_DEVICE_MEMORY | vk::Result::ERROR_OUT_OF_HOST_MEMORY),
+        ) => {
+          // other memory types may be in a different heap
+          log::debug!(
+            "Failed to allocate block of memory type {}: {:?}",
+            memory_type,
+            err
+          );
+          error = Some(AllocatorError::Vulkan(err));
+        }
+        Err(err) => return Err(err.into()),
+      }
+    }
+
+    Err(error.unwrap())
+  }
+
+  // non-coherent allocations get whole atoms so that flushing or invalidating one of them doesn't
+  // affect its neighbours (dedicated allocations have no neighbours)
+  fn host_access_size_and_alignment(
+    &self,
+    memory_type: usize,
+    size: u64,
+    alignment: u64,
+  ) -> (u64, u64) {
+    if self.limits.is_non_coherent(memory_type) {
+      let atom_size = self.limits.non_coherent_atom_size;
+      (align_up(size, atom_size), alignment.max(atom_size))
+    } else {
+      (size, alignment)
+    }
+  }
+
+  // first non-dedicated block of the pool with enough free space
+  fn allocate_from_pool(
+    pool: &mut [MemoryBlock],
+    memory_type: usize,
+    size: u64,
+    alignment: u64,
+    requested_size: u64,
+  ) -> Option<Allocation> {
+    pool
+      .iter_mut()
+      .filter(|block| !block.dedicated)
+      .find_map(|block| {
+        let range = block.free_list.allocate(size, alignment)?;
+        Some(Self::reserve(block, memory_type, range, requested_size))
+      })
+  }
+
+  fn reserve(
+    block: &mut MemoryBlock,
+    memory_type: usize,
+    reserved: Range,
+    size: u64,
+  ) -> Allocation {
+    block.allocation_count += 1;
+    block.used += reserved.size;
+    Allocation {
+      memory: block.memory,
+      offset: reserved.offset,
+      size,
+      memory_type,
+      mapped: block
+        .mapped
+        .map(|mapped| mapped.slice(reserved.offset, size)),
+      reserved,
+    }
+  }
+
+  fn create_block(
+    &self,
+    device: &ash::Device,
+    memory_type: usize,
+    size: u64,
+    dedicated: bool,
+    p_next: *const c_void,
+  ) -> Result<MemoryBlock, vk::Result> {
+    log::debug!(
+      "Allocating {}memory block of {} bytes with memory type {}",
+      if dedicated { "dedicated " } else { "" },
+      size,
+      memory_type
+    );
+    let allocate_info = vk::MemoryAllocateInfo {
+      s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
+      p_next,
+      allocation_size: size,
+      memory_type_index: memory_type as u32,
+    };
+    let memory = unsafe { device.allocate_memory(&allocate_info, None)? };
+
+    // a memory object can only be mapped once, so host visible blocks are mapped as a whole
+    let memory_flags = self.limits.memory_type_flags(memory_type);
+    let mapped = if memory_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+      match unsafe {
+        MappedMemory::map(
+          device,
+          memory,
+          size,
+          memory_flags,
+          self.limits.non_coherent_atom_size,
+        )
+      } {
+        Ok(mapped) => Some(mapped),
+        Err(err) => {
+          unsafe { device.free_memory(memory, None) };
+          return Err(err);
+        }
+      }
+    } else {
+      None
+    };
+
+    Ok(MemoryBlock {
+      memory,
+      size,
+      mapped,
+      free_list: FreeList::new(size),
+      allocation_count: 0,
+      used: 0,
+      dedicated,
+    })
+  }
+
+  // allocates a vk::DeviceMemory dedicated to the resource if the implementation prefers it
+  // if only preferred, falls back to sub-allocating when there are no device memory objects left
+  #[allow(clippy::too_many_arguments)]
+  fn allocate_resource(
+    &mut self,
+    device: &ash::Device,
+    requirements: vk::MemoryRequirements,
+    kind: ResourceKind,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+    dedicated: DedicatedAllocation,
+    dedicated_info: vk::MemoryDedicatedAllocateInfo,
+  ) -> Result<Allocation, AllocatorError> {
+    if !dedicated.is_wanted() {
+      return self.allocate(
+        device,
+        requirements,
+        kind,
+        required_properties,
+        optional_properties,
+      );
+    }
+
+    let result = self.allocate_with(
+      device,
+      requirements,
+      kind,
+      required_properties,
+      optional_properties,
+      Some(&dedicated_info),
+    );
+    match result {
+      Err(AllocatorError::TooManyAllocations { .. })
+        if dedicated == DedicatedAllocation::Preferred =>
+      {
+        log::debug!(
+          "No device memory objects left for a dedicated allocation, sub-allocating instead"
+        );
+        self.allocate(
+          device,
+          requirements,
+          kind,
+          required_properties,
+          optional_properties,
+        )
+      }
+      result => result,
+    }
+  }
+
+  // allocates memory for a buffer and binds it
+  pub unsafe fn allocate_buffer(
+    &mut self,
+    device: &ash::Device,
+    buffer: vk::Buffer,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Result<Allocation, AllocatorError> {
+    let (requirements, dedicated) = buffer_memory_requirements(device, buffer);
+    log::debug!(
+      "Dedicated allocation for buffer {:?}: {}",
+      buffer,
+      dedicated
+    );
+    let dedicated_info = vk::MemoryDedicatedAllocateInfo {
+      s_type: vk::StructureType::MEMORY_DEDICATED_ALLOCATE_INFO,
+      p_next: ptr::null(),
+      image: vk::Image::null(),
+      buffer,
+    };
+    let allocation = self.allocate_resource(
+      device,
+      requirements,
+      ResourceKind::Linear,
+      required_properties,
+      optional_properties,
+      dedicated,
+      dedicated_info,
+    )?;
+    if let Err(err) = device.bind_buffer_memory(buffer, allocation.memory, allocation.offset) {
+      self.free(device, &allocation);
+      return Err(err.into());
+    }
+    Ok(allocation)
+  }
+
+  // allocates memory for an image with optimal tiling and binds it
+  pub unsafe fn allocate_image(
+    &mut self,
+    device: &ash::Device,
+    image: vk::Image,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Result<Allocation, AllocatorError> {
+    let (requirements, dedicated) = image_memory_requirements(device, image);
+    log::debug!("Dedicated allocation for image {:?}: {}", image, dedicated);
+    let dedicated_info = vk::MemoryDedicatedAllocateInfo {
+      s_type: vk::StructureType::MEMORY_DEDICATED_ALLOCATE_INFO,
+      p_next: ptr::null(),
+      image,
+      buffer: vk::Buffer::null(),
+    };
+    let allocation = self.allocate_resource(
+      device,
+      requirements,
+      ResourceKind::Optimal,
+      required_properties,
+      optional_properties,
+      dedicated,
+      dedicated_info,
+    )?;
+    if let Err(err) = device.bind_image_memory(image, allocation.memory, allocation.offset) {
+      self.free(device, &allocation);
+      return Err(err.into());
+    }
+    Ok(allocation)
+  }
+
+  // the resource bound to the allocation should have been destroyed or not be used anymore
+  // each allocation should only be freed once
+  pub unsafe fn free(&mut self, device: &ash::Device, allocation: &Allocation) {
+    if let Some(block) = Self::release(&mut self.pools[allocation.memory_type], allocation) {
+      log::debug!(
+        "Freeing memory block of {} bytes with memory type {}",
+        block.size,
+        allocation.memory_type
+      );
+      // memory gets implicitly unmapped
+      device.free_memory(block.memory, None);
+      self.block_count -= 1;
+    }
+  }
+
+  // returns the block removed from the pool if it should be destroyed
+  fn release(pool: &mut Vec<MemoryBlock>, allocation: &Allocation) -> Option<MemoryBlock> {
+    let block_i = pool
+      .iter()
+      .position(|block| block.memory == allocation.memory)
+      .expect("Allocation was not created by this allocator");
+
+    let block = &mut pool[block_i];
+    block.free_list.free(allocation.reserved);
+    block.allocation_count -= 1;
+    block.used -= allocation.reserved.size;
+
+    if block.allocation_count > 0 {
+      return None;
+    }
+    debug_assert!(block.free_list.is_unused());
+    // keep one empty block around so that allocating and freeing in a loop doesn't create and
+    // destroy device memory each time
+    let keep = !block.dedicated
+      && !pool
+        .iter()
+        .enumerate()
+        .any(|(i, other)| i != block_i && !other.dedicated && other.allocation_count == 0);
+    (!keep).then(|| pool.swap_remove(block_i))
+  }
+
+  pub fn stats(&self) -> AllocatorStats {
+    AllocatorStats {
+      memory_types: self
+        .pools
+        .iter()
+        .map(|pool| {
+          let mut stats = MemoryTypeStats::default();
+          for block in pool.iter() {
+            stats.add(&MemoryTypeStats {
+              blocks: 1,
+              allocations: block.allocation_count,
+              block_bytes: block.size,
+              used_bytes: block.used,
+              largest_free_range: block.free_list.largest_free_range(),
+            });
+          }
+          stats
+        })
+        .collect(),
+      max_memory_allocation_count: self.max_memory_allocation_count,
+    }
+  }
+
+  // frees all blocks, allocations that haven't been freed become invalid
+  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
+    let stats = self.stats();
+    let leaked = stats.total().allocations;
+    if leaked > 0 {
+      log::warn!(
+        "Destroying memory allocator with {} allocations in use",
+        leaked
+      );
+    }
+    log::debug!("Memory allocator usage before destruction: {}", stats);
+
+    for pool in self.pools.iter_mut() {
+      for block in pool.drain(..) {
+        device.free_memory(block.memory, None);
+      }
+    }
+    self.block_count = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use ash::vk::Handle;
+
+  use super::*;
+
+  fn block(memory: u64, size: u64, dedicated: bool) -> MemoryBlock {
+    MemoryBlock {
+      memory: vk::DeviceMemory::from_raw(memory),
+      size,
+      mapped: None,
+      free_list: FreeList::new(size),
+      allocation_count: 0,
+      used: 0,
+      dedicated,
+    }
+  }
+
+  fn allocate(pool: &mut [MemoryBlock], size: u64) -> Option<Allocation> {
+    MemoryAllocator::allocate_from_pool(pool, 0, size, 16, size)
+  }
+
+  #[test]
+  fn freed_ranges_are_reused() {
+    let mut pool = vec![block(1, 256, false)];
+    let a = allocate(&mut pool, 128).unwrap();
+    let b = allocate(&mut pool, 128).unwrap();
+    assert_eq!((a.offset, b.offset), (0, 128));
+    assert!(allocate(&mut pool, 16).is_none());
+
+    assert!(MemoryAllocator::release(&mut pool, &a).is_none());
+    assert_eq!((pool[0].allocation_count, pool[0].used), (1, 128));
+    let c = allocate(&mut pool, 64).unwrap();
+    assert_eq!((c.memory, c.offset), (a.memory, 0));
+    assert_eq!((pool[0].allocation_count, pool[0].used), (2, 192));
+  }
+
+  #[test]
+  fn allocations_use_the_first_block_with_space() {
+    let mut pool = vec![
+      block(1, 64, false),
+      block(2, 512, true),
+      block(3, 256, false),
+    ];
+    let a = allocate(&mut pool, 48).unwrap();
+    assert_eq!(a.memory, vk::DeviceMemory::from_raw(1));
+    // dedicated blocks are skipped even if they have space
+    let b = allocate(&mut pool, 48).unwrap();
+    assert_eq!((b.memory, b.offset), (vk::DeviceMemory::from_raw(3), 0));
+    assert_eq!(pool[1].allocation_count, 0);
+
+    assert!(allocate(&mut pool, 512).is_none());
+  }
+
+  #[test]
+  fn one_empty_block_is_kept() {
+    let mut pool = vec![block(1, 256, false), block(2, 256, false)];
+    let a = allocate(&mut pool, 256).unwrap();
+    let b = allocate(&mut pool, 256).unwrap();
+
+    // the first block to become empty is kept for later allocations
+    assert!(MemoryAllocator::release(&mut pool, &a).is_none());
+    assert_eq!(pool.len(), 2);
+    assert!(pool[0].free_list.is_unused());
+
+    // the second one is released
+    let released = MemoryAllocator::release(&mut pool, &b).unwrap();
+    assert_eq!(released.memory, b.memory);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool[0].memory, a.memory);
+  }
+
+  #[test]
+  fn dedicated_blocks_are_released_when_empty() {
+    let mut pool = vec![block(1, 1024, true)];
+    let range = pool[0].free_list.allocate(1024, 1).unwrap();
+    let allocation = MemoryAllocator::reserve(&mut pool[0], 0, range, 1000);
+    assert_eq!(allocation.size, 1000);
+
+    let released = MemoryAllocator::release(&mut pool, &allocation).unwrap();
+    assert!(released.dedicated);
+    assert!(pool.is_empty());
+  }
+}
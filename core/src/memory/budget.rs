@@ -0,0 +1,246 @@
+use std::{
+  ffi::{c_void, CStr},
+  fmt,
+  ptr::{self, addr_of_mut},
+};
+
+use ash::vk;
+
+use crate::utility;
+
+use super::planner::MemoryLimits;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeapBudget {
+  pub size: u64,
+  // how much the process can allocate from the heap before allocations may fail or cause
+  // performance degradation
+  pub budget: u64,
+  // how much the process is currently using (including other APIs and the implementation)
+  pub usage: u64,
+}
+
+impl HeapBudget {
+  pub fn available(&self) -> u64 {
+    self.budget.saturating_sub(self.usage)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBudget {
+  // indexed by heap
+  pub heaps: Vec<HeapBudget>,
+  // without VK_EXT_memory_budget, budgets are the heap sizes and usage is unknown (0)
+  pub from_extension: bool,
+}
+
+impl MemoryBudget {
+  pub fn from_heap_sizes(memory_properties: &vk::PhysicalDeviceMemoryProperties) -> Self {
+    Self {
+      heaps: memory_properties.memory_heaps[0..memory_properties.memory_heap_count as usize]
+        .iter()
+        .map(|heap| HeapBudget {
+          size: heap.size,
+          budget: heap.size,
+          usage: 0,
+        })
+        .collect(),
+      from_extension: false,
+    }
+  }
+
+  pub fn memory_type_heap(&self, limits: &MemoryLimits, memory_type: usize) -> &HeapBudget {
+    let heap_index = limits.memory_properties.memory_types[memory_type].heap_index;
+    &self.heaps[heap_index as usize]
+  }
+
+  // whether an allocation of this size still fits in the remaining budget of the memory type heap
+  pub fn fits(&self, limits: &MemoryLimits, memory_type: usize, size: u64) -> bool {
+    size <= self.memory_type_heap(limits, memory_type).available()
+  }
+
+  // logs a warning and returns false if the allocation doesn't fit in the budget
+  pub fn check(&self, limits: &MemoryLimits, memory_type: usize, size: u64) -> bool {
+    let fits = self.fits(limits, memory_type, size);
+    if !fits {
+      let heap = self.memory_type_heap(limits, memory_type);
+      log::warn!(
+        "Allocating {} bytes with memory type {} exceeds the heap memory budget \
+        ({} of {} bytes in use)",
+        size,
+        memory_type,
+        heap.usage,
+        heap.budget
+      );
+    }
+    fits
+  }
+}
+
+impl fmt::Display for MemoryBudget {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if !self.from_extension {
+      write!(f, "(VK_EXT_memory_budget not supported, using heap sizes)")?;
+    }
+    for (i, heap) in self.heaps.iter().enumerate() {
+      write!(
+        f,
+        "\n  heap {}: {} of {} bytes in use (heap size: {})",
+        i, heap.usage, heap.budget, heap.size
+      )?;
+    }
+    Ok(())
+  }
+}
+
+// stable sort that moves memory types with enough remaining budget for the allocation first
+// the relative order of the memory types (their preference) is kept otherwise
+pub fn prioritize_by_budget(
+  memory_types: &mut [usize],
+  size: u64,
+  limits: &MemoryLimits,
+  budget: &MemoryBudget,
+) {
+  memory_types.sort_by_key(|&memory_type| !budget.fits(limits, memory_type, size));
+}
+
+pub fn supports_memory_budget(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+) -> bool {
+  let name: &CStr = vk::ExtMemoryBudgetFn::name();
+  match unsafe { instance.enumerate_device_extension_properties(physical_device) } {
+    Ok(properties) => properties
+      .iter()
+      .any(|props| utility::i8_array_as_cstr(&props.extension_name) == Ok(name)),
+    Err(err) => {
+      log::warn!("Failed to query device extensions: {:?}", err);
+      false
+    }
+  }
+}
+
+// Queries the current memory budget of a physical device
+// Budgets change during execution (also because of other processes), so they should be queried
+// again before making decisions
+#[derive(Clone)]
+pub struct MemoryBudgetQuery {
+  instance: ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  memory_properties: vk::PhysicalDeviceMemoryProperties,
+  supported: bool,
+}
+
+impl MemoryBudgetQuery {
+  pub fn new(
+    instance: &ash::Instance,
+    physical_device: vk::PhysicalDevice,
+    memory_properties: vk::PhysicalDeviceMemoryProperties,
+  ) -> Self {
+    let supported = supports_memory_budget(instance, physical_device);
+    if !supported {
+      log::info!("VK_EXT_memory_budget is not supported, memory budgets will be the heap sizes");
+    }
+    Self {
+      instance: instance.clone(),
+      physical_device,
+      memory_properties,
+      supported,
+    }
+  }
+
+  pub fn is_supported(&self) -> bool {
+    self.supported
+  }
+
+  pub fn query(&self) -> MemoryBudget {
+    if !self.supported {
+      return MemoryBudget::from_heap_sizes(&self.memory_properties);
+    }
+
+    let mut budget_properties = vk::PhysicalDeviceMemoryBudgetPropertiesEXT {
+      s_type: vk::StructureType::PHYSICAL_DEVICE_MEMORY_BUDGET_PROPERTIES_EXT,
+      p_next: ptr::null_mut(),
+      heap_budget: [0; vk::MAX_MEMORY_HEAPS],
+      heap_usage: [0; vk::MAX_MEMORY_HEAPS],
+    };
+    let mut properties = vk::PhysicalDeviceMemoryProperties2 {
+      s_type: vk::StructureType::PHYSICAL_DEVICE_MEMORY_PROPERTIES_2,
+      p_next: addr_of_mut!(budget_properties) as *mut c_void,
+      memory_properties: vk::PhysicalDeviceMemoryProperties::default(),
+    };
+    unsafe {
+      self
+        .instance
+        .get_physical_device_memory_properties2(self.physical_device, &mut properties);
+    }
+
+    let heap_count = properties.memory_properties.memory_heap_count as usize;
+    MemoryBudget {
+      heaps: (0..heap_count)
+        .map(|i| HeapBudget {
+          size: properties.memory_properties.memory_heaps[i].size,
+          budget: budget_properties.heap_budget[i],
+          usage: budget_properties.heap_usage[i],
+        })
+        .collect(),
+      from_extension: true,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn limits() -> MemoryLimits {
+    let mut memory_properties = vk::PhysicalDeviceMemoryProperties {
+      memory_type_count: 3,
+      memory_heap_count: 2,
+      ..Default::default()
+    };
+    for (i, heap_index) in [0, 1, 0].into_iter().enumerate() {
+      memory_properties.memory_types[i].heap_index = heap_index;
+    }
+    MemoryLimits {
+      buffer_image_granularity: 1,
+      max_memory_allocation_size: u64::MAX,
+      non_coherent_atom_size: 1,
+      memory_properties,
+    }
+  }
+
+  fn budget(heaps: &[(u64, u64)]) -> MemoryBudget {
+    MemoryBudget {
+      heaps: heaps
+        .iter()
+        .map(|&(budget, usage)| HeapBudget {
+          size: budget,
+          budget,
+          usage,
+        })
+        .collect(),
+      from_extension: true,
+    }
+  }
+
+  #[test]
+  fn memory_types_with_budget_come_first() {
+    let budget = budget(&[(1000, 900), (1000, 0)]);
+    let mut memory_types = [0, 1, 2];
+    prioritize_by_budget(&mut memory_types, 500, &limits(), &budget);
+    assert_eq!(memory_types, [1, 0, 2]);
+
+    // order is kept if everything fits
+    let mut memory_types = [2, 1, 0];
+    prioritize_by_budget(&mut memory_types, 100, &limits(), &budget);
+    assert_eq!(memory_types, [2, 1, 0]);
+  }
+
+  #[test]
+  fn usage_over_budget_has_nothing_available() {
+    let budget = budget(&[(1000, 1200)]);
+    assert_eq!(budget.heaps[0].available(), 0);
+    assert!(!budget.fits(&limits(), 0, 1));
+  }
+}
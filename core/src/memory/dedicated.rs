@@ -0,0 +1,93 @@
+use std::{ffi::c_void, fmt, ptr};
+
+use ash::vk;
+
+// whether the implementation wants a resource to have a vk::DeviceMemory of its own
+// (usually big render targets or images that benefit from implementation specific compression)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedicatedAllocation {
+  NotNeeded,
+  Preferred,
+  Required,
+}
+
+impl DedicatedAllocation {
+  fn from_requirements(requirements: &vk::MemoryDedicatedRequirements) -> Self {
+    if requirements.requires_dedicated_allocation == vk::TRUE {
+      Self::Required
+    } else if requirements.prefers_dedicated_allocation == vk::TRUE {
+      Self::Preferred
+    } else {
+      Self::NotNeeded
+    }
+  }
+
+  pub fn is_wanted(&self) -> bool {
+    *self != Self::NotNeeded
+  }
+}
+
+impl fmt::Display for DedicatedAllocation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::NotNeeded => "not needed",
+      Self::Preferred => "preferred",
+      Self::Required => "required",
+    })
+  }
+}
+
+fn requirements_2(dedicated: &mut vk::MemoryDedicatedRequirements) -> vk::MemoryRequirements2 {
+  vk::MemoryRequirements2 {
+    s_type: vk::StructureType::MEMORY_REQUIREMENTS_2,
+    p_next: dedicated as *mut vk::MemoryDedicatedRequirements as *mut c_void,
+    memory_requirements: vk::MemoryRequirements::default(),
+  }
+}
+
+fn dedicated_requirements() -> vk::MemoryDedicatedRequirements {
+  vk::MemoryDedicatedRequirements {
+    s_type: vk::StructureType::MEMORY_DEDICATED_REQUIREMENTS,
+    p_next: ptr::null_mut(),
+    prefers_dedicated_allocation: vk::FALSE,
+    requires_dedicated_allocation: vk::FALSE,
+  }
+}
+
+// memory requirements of an image and whether it should get a dedicated allocation
+pub unsafe fn image_memory_requirements(
+  device: &ash::Device,
+  image: vk::Image,
+) -> (vk::MemoryRequirements, DedicatedAllocation) {
+  let info = vk::ImageMemoryRequirementsInfo2 {
+    s_type: vk::StructureType::IMAGE_MEMORY_REQUIREMENTS_INFO_2,
+    p_next: ptr::null(),
+    image,
+  };
+  let mut dedicated = dedicated_requirements();
+  let mut requirements = requirements_2(&mut dedicated);
+  device.get_image_memory_requirements2(&info, &mut requirements);
+  (
+    requirements.memory_requirements,
+    DedicatedAllocation::from_requirements(&dedicated),
+  )
+}
+
+// memory requirements of a buffer and whether it should get a dedicated allocation
+pub unsafe fn buffer_memory_requirements(
+  device: &ash::Device,
+  buffer: vk::Buffer,
+) -> (vk::MemoryRequirements, DedicatedAllocation) {
+  let info = vk::BufferMemoryRequirementsInfo2 {
+    s_type: vk::StructureType::BUFFER_MEMORY_REQUIREMENTS_INFO_2,
+    p_next: ptr::null(),
+    buffer,
+  };
+  let mut dedicated = dedicated_requirements();
+  let mut requirements = requirements_2(&mut dedicated);
+  device.get_buffer_memory_requirements2(&info, &mut requirements);
+  (
+    requirements.memory_requirements,
+    DedicatedAllocation::from_requirements(&dedicated),
+  )
+}
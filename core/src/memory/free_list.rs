@@ -0,0 +1,197 @@
+// Keeps track of which parts of a memory block are not in use
+// Free ranges are sorted by offset and never adjacent to each other (they are merged on free)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+  pub offset: u64,
+  pub size: u64,
+}
+
+impl Range {
+  pub fn end(&self) -> u64 {
+    self.offset + self.size
+  }
+}
+
+pub struct FreeList {
+  size: u64,
+  free: Vec<Range>,
+}
+
+// alignment should be a power of 2
+pub fn align_up(value: u64, alignment: u64) -> u64 {
+  debug_assert!(alignment.is_power_of_two());
+  (value + alignment - 1) & !(alignment - 1)
+}
+
+impl FreeList {
+  pub fn new(size: u64) -> Self {
+    Self {
+      size,
+      free: vec![Range { offset: 0, size }],
+    }
+  }
+
+  // first fit, returns the reserved range
+  // padding before the aligned offset stays free
+  pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<Range> {
+    assert!(size > 0);
+    let (i, offset) = self.free.iter().enumerate().find_map(|(i, range)| {
+      let offset = align_up(range.offset, alignment);
+      (offset + size <= range.end()).then_some((i, offset))
+    })?;
+
+    let range = self.free[i];
+    let before = Range {
+      offset: range.offset,
+      size: offset - range.offset,
+    };
+    let after = Range {
+      offset: offset + size,
+      size: range.end() - (offset + size),
+    };
+    match (before.size > 0, after.size > 0) {
+      (true, true) => {
+        self.free[i] = before;
+        self.free.insert(i + 1, after);
+      }
+      (true, false) => self.free[i] = before,
+      (false, true) => self.free[i] = after,
+      (false, false) => {
+        self.free.remove(i);
+      }
+    }
+
+    Some(Range { offset, size })
+  }
+
+  // range should have been returned by allocate and not freed since
+  pub fn free(&mut self, range: Range) {
+    assert!(range.end() <= self.size);
+    let i = self.free.partition_point(|free| free.offset < range.offset);
+    debug_assert!(i == 0 || self.free[i - 1].end() <= range.offset);
+    debug_assert!(i == self.free.len() || range.end() <= self.free[i].offset);
+
+    let merges_previous = i > 0 && self.free[i - 1].end() == range.offset;
+    let merges_next = i < self.free.len() && range.end() == self.free[i].offset;
+    match (merges_previous, merges_next) {
+      (true, true) => {
+        self.free[i - 1].size += range.size + self.free[i].size;
+        self.free.remove(i);
+      }
+      (true, false) => self.free[i - 1].size += range.size,
+      (false, true) => {
+        self.free[i].offset = range.offset;
+        self.free[i].size += range.size;
+      }
+      (false, false) => self.free.insert(i, range),
+    }
+  }
+
+  pub fn is_unused(&self) -> bool {
+    self.free.len() == 1 && self.free[0].size == self.size
+  }
+
+  pub fn largest_free_range(&self) -> u64 {
+    self.free.iter().map(|range| range.size).max().unwrap_or(0)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn range(offset: u64, size: u64) -> Range {
+    Range { offset, size }
+  }
+
+  // allocates [0, 30) in three ranges of 10 bytes inside a list of 40
+  fn three_allocations() -> (FreeList, [Range; 3]) {
+    let mut list = FreeList::new(40);
+    let allocations = [0, 1, 2].map(|_| list.allocate(10, 1).unwrap());
+    assert_eq!(allocations, [range(0, 10), range(10, 10), range(20, 10)]);
+    assert_eq!(list.free, [range(30, 10)]);
+    (list, allocations)
+  }
+
+  #[test]
+  fn free_without_free_neighbours_inserts_a_range() {
+    let (mut list, [_, b, _]) = three_allocations();
+    list.free(b);
+    assert_eq!(list.free, [range(10, 10), range(30, 10)]);
+    assert_eq!(list.largest_free_range(), 10);
+  }
+
+  #[test]
+  fn free_merges_with_previous_range() {
+    let (mut list, [a, b, _]) = three_allocations();
+    list.free(a);
+    list.free(b);
+    assert_eq!(list.free, [range(0, 20), range(30, 10)]);
+  }
+
+  #[test]
+  fn free_merges_with_next_range() {
+    let (mut list, [_, b, c]) = three_allocations();
+    list.free(c);
+    assert_eq!(list.free, [range(20, 20)]);
+    list.free(b);
+    assert_eq!(list.free, [range(10, 30)]);
+  }
+
+  #[test]
+  fn free_merges_with_both_ranges() {
+    let (mut list, [a, b, c]) = three_allocations();
+    list.free(a);
+    list.free(c);
+    assert_eq!(list.free, [range(0, 10), range(20, 20)]);
+    list.free(b);
+    assert_eq!(list.free, [range(0, 40)]);
+    assert!(list.is_unused());
+  }
+
+  #[test]
+  fn alignment_padding_stays_free() {
+    let mut list = FreeList::new(256);
+    assert_eq!(list.allocate(3, 1), Some(range(0, 3)));
+    assert_eq!(list.allocate(16, 64), Some(range(64, 16)));
+    assert_eq!(list.free, [range(3, 61), range(80, 176)]);
+
+    // the padding gets used by later allocations that fit
+    assert_eq!(list.allocate(8, 8), Some(range(8, 8)));
+    assert_eq!(list.free, [range(3, 5), range(16, 48), range(80, 176)]);
+  }
+
+  #[test]
+  fn allocation_fails_when_no_range_is_big_enough() {
+    let mut list = FreeList::new(64);
+    assert_eq!(list.allocate(65, 1), None);
+    assert_eq!(list.allocate(32, 1), Some(range(0, 32)));
+    assert_eq!(list.allocate(16, 1), Some(range(32, 16)));
+    // 16 bytes are free but not aligned to 32
+    assert_eq!(list.allocate(16, 32), None);
+    assert_eq!(list.allocate(16, 16), Some(range(48, 16)));
+    assert!(list.free.is_empty());
+    assert_eq!(list.allocate(1, 1), None);
+    assert_eq!(list.largest_free_range(), 0);
+  }
+
+  #[test]
+  fn list_is_unused_after_freeing_everything() {
+    let mut list = FreeList::new(1024);
+    assert!(list.is_unused());
+    let allocations: Vec<Range> = [(100, 1), (7, 4), (256, 256), (33, 16), (64, 1)]
+      .into_iter()
+      .map(|(size, alignment)| list.allocate(size, alignment).unwrap())
+      .collect();
+    assert!(!list.is_unused());
+
+    // free in an order that exercises every kind of merge
+    for i in [3, 0, 4, 2, 1] {
+      assert!(!list.is_unused());
+      list.free(allocations[i]);
+    }
+    assert!(list.is_unused());
+    assert_eq!(list.free, [range(0, 1024)]);
+  }
+}
@@ -0,0 +1,214 @@
+use std::ptr::{self, NonNull};
+
+use ash::vk;
+
+use super::free_list::align_up;
+
+// rounds a range so that it can be used in a vk::MappedMemoryRange of non-coherent memory
+// offset gets rounded down and the end up to a multiple of atom_size, clamped to the memory size
+// (ranges that end at the end of the memory don't need a multiple of atom_size as their size)
+pub fn atom_aligned_range(offset: u64, size: u64, atom_size: u64, memory_size: u64) -> (u64, u64) {
+  debug_assert!(atom_size.is_power_of_two());
+  debug_assert!(offset + size <= memory_size);
+  let start = offset & !(atom_size - 1);
+  let end = align_up(offset + size, atom_size).min(memory_size);
+  (start, end - start)
+}
+
+// A persistently mapped range of a host visible vk::DeviceMemory
+//
+// Reads and writes through this type take care of invalidating and flushing the accessed range if
+// the memory type is not HOST_COHERENT, following nonCoherentAtomSize.
+// Allocations of the same non-coherent memory should not share atoms (the allocator aligns them),
+// otherwise invalidating one range can discard host writes to another.
+#[derive(Clone, Copy)]
+pub struct MappedMemory {
+  memory: vk::DeviceMemory,
+  // range in memory
+  offset: u64,
+  size: u64,
+  // points to offset
+  ptr: NonNull<u8>,
+  memory_size: u64,
+  // None if the memory is HOST_COHERENT
+  non_coherent_atom_size: Option<u64>,
+}
+
+// the mapping is valid for all threads, synchronizing accesses is up to the user
+unsafe impl Send for MappedMemory {}
+unsafe impl Sync for MappedMemory {}
+
+impl MappedMemory {
+  // ptr should point to the start of memory, which should stay mapped while this object is used
+  pub unsafe fn from_raw(
+    memory: vk::DeviceMemory,
+    memory_size: u64,
+    ptr: NonNull<u8>,
+    memory_flags: vk::MemoryPropertyFlags,
+    non_coherent_atom_size: u64,
+  ) -> Self {
+    debug_assert!(memory_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE));
+    Self {
+      memory,
+      offset: 0,
+      size: memory_size,
+      ptr,
+      memory_size,
+      non_coherent_atom_size: (!memory_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT))
+        .then_some(non_coherent_atom_size),
+    }
+  }
+
+  // maps the whole memory, which stays mapped until unmap is called or the memory is freed
+  pub unsafe fn map(
+    device: &ash::Device,
+    memory: vk::DeviceMemory,
+    memory_size: u64,
+    memory_flags: vk::MemoryPropertyFlags,
+    non_coherent_atom_size: u64,
+  ) -> Result<Self, vk::Result> {
+    let ptr = device.map_memory(memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())?;
+    Ok(Self::from_raw(
+      memory,
+      memory_size,
+      NonNull::new(ptr as *mut u8).unwrap(),
+      memory_flags,
+      non_coherent_atom_size,
+    ))
+  }
+
+  // should only be called on objects returned by map, invalidates all copies
+  pub unsafe fn unmap(self, device: &ash::Device) {
+    device.unmap_memory(self.memory);
+  }
+
+  // view of part of this range, offset is relative to the start of this range
+  pub fn slice(&self, offset: u64, size: u64) -> Self {
+    assert!(offset + size <= self.size);
+    Self {
+      offset: self.offset + offset,
+      size,
+      ptr: unsafe { self.ptr.add(offset as usize) },
+      ..*self
+    }
+  }
+
+  pub fn memory(&self) -> vk::DeviceMemory {
+    self.memory
+  }
+
+  pub fn size(&self) -> u64 {
+    self.size
+  }
+
+  pub fn is_coherent(&self) -> bool {
+    self.non_coherent_atom_size.is_none()
+  }
+
+  // reads and writes through this pointer don't flush or invalidate automatically
+  pub fn as_ptr(&self) -> *mut u8 {
+    self.ptr.as_ptr()
+  }
+
+  fn memory_range(&self, offset: u64, size: u64, atom_size: u64) -> vk::MappedMemoryRange {
+    assert!(offset + size <= self.size);
+    let (offset, size) =
+      atom_aligned_range(self.offset + offset, size, atom_size, self.memory_size);
+    vk::MappedMemoryRange {
+      s_type: vk::StructureType::MAPPED_MEMORY_RANGE,
+      p_next: ptr::null(),
+      memory: self.memory,
+      offset,
+      size,
+    }
+  }
+
+  // makes host writes in the range available to the device (does nothing if coherent)
+  pub unsafe fn flush(
+    &self,
+    device: &ash::Device,
+    offset: u64,
+    size: u64,
+  ) -> Result<(), vk::Result> {
+    if let Some(atom_size) = self.non_coherent_atom_size {
+      device.flush_mapped_memory_ranges(&[self.memory_range(offset, size, atom_size)])?;
+    }
+    Ok(())
+  }
+
+  // makes device writes in the range visible to the host (does nothing if coherent)
+  pub unsafe fn invalidate(
+    &self,
+    device: &ash::Device,
+    offset: u64,
+    size: u64,
+  ) -> Result<(), vk::Result> {
+    if let Some(atom_size) = self.non_coherent_atom_size {
+      device.invalidate_mapped_memory_ranges(&[self.memory_range(offset, size, atom_size)])?;
+    }
+    Ok(())
+  }
+
+  // copies data into the range at offset and flushes it
+  pub unsafe fn write(
+    &self,
+    device: &ash::Device,
+    offset: u64,
+    data: &[u8],
+  ) -> Result<(), vk::Result> {
+    assert!(offset + data.len() as u64 <= self.size);
+    ptr::copy_nonoverlapping(
+      data.as_ptr(),
+      self.ptr.as_ptr().add(offset as usize),
+      data.len(),
+    );
+    self.flush(device, offset, data.len() as u64)
+  }
+
+  // copies a value into the range at offset and flushes it
+  pub unsafe fn write_value<T: Copy>(
+    &self,
+    device: &ash::Device,
+    offset: u64,
+    value: &T,
+  ) -> Result<(), vk::Result> {
+    let data = std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>());
+    self.write(device, offset, data)
+  }
+
+  // invalidates size bytes at offset and gives them to f
+  // the device should not be writing to the range at the same time
+  pub unsafe fn read<R, F: FnOnce(&[u8]) -> R>(
+    &self,
+    device: &ash::Device,
+    offset: u64,
+    size: u64,
+    f: F,
+  ) -> Result<R, vk::Result> {
+    self.invalidate(device, offset, size)?;
+    let data = std::slice::from_raw_parts(
+      self.ptr.as_ptr().add(offset as usize) as *const u8,
+      size as usize,
+    );
+    Ok(f(data))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ranges_are_rounded_to_atoms() {
+    assert_eq!(atom_aligned_range(0, 10, 64, 1024), (0, 64));
+    assert_eq!(atom_aligned_range(70, 10, 64, 1024), (64, 64));
+    assert_eq!(atom_aligned_range(60, 10, 64, 1024), (0, 128));
+    assert_eq!(atom_aligned_range(128, 128, 64, 1024), (128, 128));
+  }
+
+  #[test]
+  fn ranges_are_clamped_to_the_memory_size() {
+    assert_eq!(atom_aligned_range(1000, 10, 64, 1010), (960, 50));
+    assert_eq!(atom_aligned_range(0, 1010, 64, 1010), (0, 1010));
+  }
+}
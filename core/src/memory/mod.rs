@@ -0,0 +1,22 @@
+// Device memory management shared by the examples
+
+mod allocator;
+mod budget;
+mod dedicated;
+mod free_list;
+mod mapped;
+mod planner;
+mod shared;
+
+pub use allocator::{
+  Allocation, AllocatorError, AllocatorStats, MemoryAllocator, MemoryTypeStats, DEFAULT_BLOCK_SIZE,
+};
+pub use budget::{prioritize_by_budget, HeapBudget, MemoryBudget, MemoryBudgetQuery};
+pub use dedicated::{buffer_memory_requirements, image_memory_requirements, DedicatedAllocation};
+pub(crate) use free_list::align_up;
+pub use mapped::{atom_aligned_range, MappedMemory};
+pub use planner::{
+  isolated_size_and_alignment, memory_type_candidates, plan_allocation, AllocationPlan,
+  MemoryLimits, PlanError, ResourceKind, ResourceRequirements,
+};
+pub use shared::{OwnedAllocation, SharedAllocator};
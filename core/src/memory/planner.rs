@@ -0,0 +1,485 @@
+// Computes where resources go in device memory without touching the device
+//
+// Everything in this module only works with plain data (memory requirements, device limits and
+// memory properties), so it can be tested without a Vulkan implementation.
+
+use std::fmt;
+
+use ash::vk;
+
+use crate::{utility::error_chain_fmt, PhysicalDevice};
+
+use super::free_list::align_up;
+
+// the kind of resource that is going to be bound to the allocation
+// buffers and linear images cannot share a "page" (bufferImageGranularity) with optimal images
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceKind {
+  Linear,
+  Optimal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceRequirements {
+  pub size: u64,
+  // power of 2
+  pub alignment: u64,
+  pub memory_type_bits: u32,
+  pub kind: ResourceKind,
+}
+
+impl ResourceRequirements {
+  pub fn new(requirements: vk::MemoryRequirements, kind: ResourceKind) -> Self {
+    Self {
+      size: requirements.size,
+      alignment: requirements.alignment,
+      memory_type_bits: requirements.memory_type_bits,
+      kind,
+    }
+  }
+}
+
+// device values that restrict how memory can be allocated
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryLimits {
+  pub buffer_image_granularity: u64,
+  pub max_memory_allocation_size: u64,
+  // flushed and invalidated ranges of non-coherent memory must be aligned to this
+  pub non_coherent_atom_size: u64,
+  pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+}
+
+impl MemoryLimits {
+  pub fn new(physical_device: &PhysicalDevice) -> Self {
+    Self {
+      buffer_image_granularity: physical_device
+        .properties
+        .p10
+        .limits
+        .buffer_image_granularity,
+      max_memory_allocation_size: physical_device.properties.p11.max_memory_allocation_size,
+      non_coherent_atom_size: physical_device.properties.p10.limits.non_coherent_atom_size,
+      memory_properties: physical_device.mem_properties,
+    }
+  }
+
+  pub fn heap_size(&self, memory_type: usize) -> u64 {
+    let heap_index = self.memory_properties.memory_types[memory_type].heap_index;
+    self.memory_properties.memory_heaps[heap_index as usize].size
+  }
+
+  pub fn memory_type_flags(&self, memory_type: usize) -> vk::MemoryPropertyFlags {
+    self.memory_properties.memory_types[memory_type].property_flags
+  }
+
+  // host visible memory that is not coherent is flushed and invalidated in whole atoms
+  pub fn is_non_coherent(&self, memory_type: usize) -> bool {
+    let flags = self.memory_type_flags(memory_type);
+    flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE)
+      && !flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+  }
+
+  // biggest allocation that a memory type can hold
+  pub fn max_allocation_size(&self, memory_type: usize) -> u64 {
+    self
+      .max_memory_allocation_size
+      .min(self.heap_size(memory_type))
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocationPlan {
+  // offset of each resource, in the same order they were given
+  pub offsets: Vec<u64>,
+  pub size: u64,
+  // memory types that support all resources and can hold the whole allocation, most preferred
+  // first
+  pub memory_types: Vec<usize>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PlanError {
+  // the resources don't have any memory type in common that has the required properties
+  NoSuitableMemoryType,
+  // allocation doesn't fit in any memory type that supports it
+  TooBig { size: u64, max: u64 },
+}
+
+impl fmt::Display for PlanError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NoSuitableMemoryType => write!(
+        f,
+        "No memory type supports all resources with the required properties"
+      ),
+      Self::TooBig { size, max } => write!(
+        f,
+        "Allocation size ({}) is bigger than the maximum allowed for any supported memory type ({})",
+        size, max
+      ),
+    }
+  }
+}
+
+impl fmt::Debug for PlanError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl std::error::Error for PlanError {}
+
+// memory types allowed by memory_type_bits that have the required properties
+// types that also have the optional properties come first, otherwise types are in device order
+pub fn memory_type_candidates(
+  memory_properties: &vk::PhysicalDeviceMemoryProperties,
+  memory_type_bits: u32,
+  required_properties: vk::MemoryPropertyFlags,
+  optional_properties: vk::MemoryPropertyFlags,
+) -> Vec<usize> {
+  let supports = |i: usize, properties: vk::MemoryPropertyFlags| {
+    memory_type_bits & (1 << i) > 0
+      && memory_properties.memory_types[i]
+        .property_flags
+        .contains(properties)
+  };
+  let count = memory_properties.memory_type_count as usize;
+  let optimal = (0..count).filter(|&i| supports(i, required_properties | optional_properties));
+  let others = (0..count).filter(|&i| {
+    supports(i, required_properties) && !supports(i, required_properties | optional_properties)
+  });
+  optimal.chain(others).collect()
+}
+
+// size and alignment a resource should take when placed next to unknown resources (for example
+// when sub-allocating)
+// optimal resources take whole granularity pages so that no linear resource can share them
+pub fn isolated_size_and_alignment(
+  resource: &ResourceRequirements,
+  granularity: u64,
+) -> (u64, u64) {
+  if resource.kind == ResourceKind::Optimal && granularity > 1 {
+    (
+      align_up(resource.size, granularity),
+      resource.alignment.max(granularity),
+    )
+  } else {
+    (resource.size, resource.alignment)
+  }
+}
+
+// places resources one after the other in a single allocation
+// resources of different kinds that would share a bufferImageGranularity page get pushed to the
+// next page
+pub fn plan_allocation(
+  resources: &[ResourceRequirements],
+  required_properties: vk::MemoryPropertyFlags,
+  optional_properties: vk::MemoryPropertyFlags,
+  limits: &MemoryLimits,
+) -> Result<AllocationPlan, PlanError> {
+  assert!(!resources.is_empty());
+
+  // a memory type can only be used if all resources support it
+  let memory_type_bits = resources
+    .iter()
+    .fold(u32::MAX, |bits, resource| bits & resource.memory_type_bits);
+
+  let granularity = limits.buffer_image_granularity.max(1);
+  let mut offsets = Vec::with_capacity(resources.len());
+  let mut end = 0u64;
+  let mut previous_kind = None;
+  for resource in resources.iter() {
+    debug_assert!(resource.size > 0 && resource.alignment.is_power_of_two());
+
+    let mut offset = align_up(end, resource.alignment);
+    // the previous resource is always the last one that ends, so it is the only one that can
+    // share a page with this one (resources before it would have been pushed as well)
+    if previous_kind.is_some_and(|kind| kind != resource.kind)
+      && (end - 1) / granularity == offset / granularity
+    {
+      offset = align_up(offset, granularity);
+    }
+
+    offsets.push(offset);
+    end = offset.checked_add(resource.size).ok_or(PlanError::TooBig {
+      size: u64::MAX,
+      max: limits.max_memory_allocation_size,
+    })?;
+    previous_kind = Some(resource.kind);
+  }
+
+  let candidates = memory_type_candidates(
+    &limits.memory_properties,
+    memory_type_bits,
+    required_properties,
+    optional_properties,
+  );
+  if candidates.is_empty() {
+    return Err(PlanError::NoSuitableMemoryType);
+  }
+
+  let max = candidates
+    .iter()
+    .map(|&memory_type| limits.max_allocation_size(memory_type))
+    .max()
+    .unwrap();
+  let memory_types: Vec<usize> = candidates
+    .into_iter()
+    .filter(|&memory_type| end <= limits.max_allocation_size(memory_type))
+    .collect();
+  if memory_types.is_empty() {
+    return Err(PlanError::TooBig { size: end, max });
+  }
+
+  Ok(AllocationPlan {
+    offsets,
+    size: end,
+    memory_types,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  const MIB: u64 = 1024 * 1024;
+
+  fn memory_properties(
+    types: &[(vk::MemoryPropertyFlags, u32)],
+    heaps: &[u64],
+  ) -> vk::PhysicalDeviceMemoryProperties {
+    let mut properties = vk::PhysicalDeviceMemoryProperties {
+      memory_type_count: types.len() as u32,
+      memory_heap_count: heaps.len() as u32,
+      ..Default::default()
+    };
+    for (i, &(property_flags, heap_index)) in types.iter().enumerate() {
+      properties.memory_types[i] = vk::MemoryType {
+        property_flags,
+        heap_index,
+      };
+    }
+    for (i, &size) in heaps.iter().enumerate() {
+      properties.memory_heaps[i] = vk::MemoryHeap {
+        size,
+        flags: vk::MemoryHeapFlags::empty(),
+      };
+    }
+    properties
+  }
+
+  // a common discrete GPU layout
+  fn limits(buffer_image_granularity: u64) -> MemoryLimits {
+    MemoryLimits {
+      buffer_image_granularity,
+      max_memory_allocation_size: 1024 * MIB,
+      non_coherent_atom_size: 64,
+      memory_properties: memory_properties(
+        &[
+          (vk::MemoryPropertyFlags::DEVICE_LOCAL, 0),
+          (
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            1,
+          ),
+          (
+            vk::MemoryPropertyFlags::HOST_VISIBLE
+              | vk::MemoryPropertyFlags::HOST_COHERENT
+              | vk::MemoryPropertyFlags::HOST_CACHED,
+            1,
+          ),
+          (
+            vk::MemoryPropertyFlags::DEVICE_LOCAL
+              | vk::MemoryPropertyFlags::HOST_VISIBLE
+              | vk::MemoryPropertyFlags::HOST_COHERENT,
+            2,
+          ),
+        ],
+        &[4096 * MIB, 8192 * MIB, 256 * MIB],
+      ),
+    }
+  }
+
+  fn resource(size: u64, alignment: u64, kind: ResourceKind) -> ResourceRequirements {
+    ResourceRequirements {
+      size,
+      alignment,
+      memory_type_bits: u32::MAX,
+      kind,
+    }
+  }
+
+  fn plan_any(
+    resources: &[ResourceRequirements],
+    limits: &MemoryLimits,
+  ) -> Result<AllocationPlan, PlanError> {
+    plan_allocation(
+      resources,
+      vk::MemoryPropertyFlags::empty(),
+      vk::MemoryPropertyFlags::empty(),
+      limits,
+    )
+  }
+
+  #[test]
+  fn packs_resources_following_alignment() {
+    let plan = plan_any(
+      &[
+        resource(100, 16, ResourceKind::Linear),
+        resource(10, 256, ResourceKind::Linear),
+        resource(1, 1, ResourceKind::Linear),
+      ],
+      &limits(1),
+    )
+    .unwrap();
+    assert_eq!(plan.offsets, [0, 256, 266]);
+    assert_eq!(plan.size, 267);
+  }
+
+  #[test]
+  fn separates_linear_and_optimal_resources() {
+    let plan = plan_any(
+      &[
+        resource(100, 16, ResourceKind::Linear),
+        resource(1000, 64, ResourceKind::Optimal),
+        resource(8, 4, ResourceKind::Optimal),
+        resource(8, 4, ResourceKind::Linear),
+      ],
+      &limits(1024),
+    )
+    .unwrap();
+    assert_eq!(plan.offsets, [0, 1024, 2024, 2048]);
+    assert_eq!(plan.size, 2056);
+  }
+
+  #[test]
+  fn does_not_pad_resources_on_different_pages() {
+    let plan = plan_any(
+      &[
+        resource(1024, 16, ResourceKind::Linear),
+        resource(16, 16, ResourceKind::Optimal),
+      ],
+      &limits(1024),
+    )
+    .unwrap();
+    assert_eq!(plan.offsets, [0, 1024]);
+  }
+
+  #[test]
+  fn intersects_memory_type_bits() {
+    let mut a = resource(16, 16, ResourceKind::Linear);
+    a.memory_type_bits = 0b0111;
+    let mut b = resource(16, 16, ResourceKind::Linear);
+    b.memory_type_bits = 0b1110;
+    let plan = plan_any(&[a, b], &limits(1)).unwrap();
+    assert_eq!(plan.memory_types, [1, 2]);
+
+    b.memory_type_bits = 0b1000;
+    assert_eq!(
+      plan_any(&[a, b], &limits(1)),
+      Err(PlanError::NoSuitableMemoryType)
+    );
+  }
+
+  #[test]
+  fn prefers_optional_properties() {
+    let plan = plan_allocation(
+      &[resource(16, 16, ResourceKind::Linear)],
+      vk::MemoryPropertyFlags::HOST_VISIBLE,
+      vk::MemoryPropertyFlags::HOST_CACHED,
+      &limits(1),
+    )
+    .unwrap();
+    assert_eq!(plan.memory_types, [2, 1, 3]);
+  }
+
+  #[test]
+  fn skips_memory_types_that_are_too_small() {
+    let plan = plan_allocation(
+      &[resource(512 * MIB, 16, ResourceKind::Optimal)],
+      vk::MemoryPropertyFlags::DEVICE_LOCAL,
+      vk::MemoryPropertyFlags::HOST_VISIBLE,
+      &limits(1),
+    )
+    .unwrap();
+    // type 3 is in the 256 MiB heap
+    assert_eq!(plan.memory_types, [0]);
+
+    assert_eq!(
+      plan_any(
+        &[resource(2048 * MIB, 16, ResourceKind::Linear)],
+        &limits(1)
+      ),
+      Err(PlanError::TooBig {
+        size: 2048 * MIB,
+        max: 1024 * MIB
+      })
+    );
+  }
+
+  #[test]
+  fn isolated_optimal_resources_take_whole_pages() {
+    let image = resource(1000, 64, ResourceKind::Optimal);
+    assert_eq!(isolated_size_and_alignment(&image, 1024), (1024, 1024));
+    assert_eq!(isolated_size_and_alignment(&image, 1), (1000, 64));
+    let buffer = resource(1000, 64, ResourceKind::Linear);
+    assert_eq!(isolated_size_and_alignment(&buffer, 1024), (1000, 64));
+  }
+
+  fn arbitrary_resource() -> impl Strategy<Value = ResourceRequirements> {
+    (1..100_000u64, 0..12u32, 1..16u32, any::<bool>()).prop_map(
+      |(size, alignment_log2, memory_type_bits, optimal)| ResourceRequirements {
+        size,
+        alignment: 1 << alignment_log2,
+        memory_type_bits,
+        kind: if optimal {
+          ResourceKind::Optimal
+        } else {
+          ResourceKind::Linear
+        },
+      },
+    )
+  }
+
+  proptest! {
+    #[test]
+    fn plans_are_valid(
+      resources in prop::collection::vec(arbitrary_resource(), 1..16),
+      granularity_log2 in 0..16u32,
+    ) {
+      let limits = limits(1 << granularity_log2);
+      let common_bits = resources.iter().fold(0b1111, |bits, r| bits & r.memory_type_bits);
+      let plan = match plan_any(&resources, &limits) {
+        Ok(plan) => plan,
+        Err(err) => {
+          prop_assert_eq!(err, PlanError::NoSuitableMemoryType);
+          prop_assert_eq!(common_bits, 0);
+          return Ok(());
+        }
+      };
+
+      prop_assert_eq!(plan.offsets.len(), resources.len());
+      prop_assert!(!plan.memory_types.is_empty());
+      for &memory_type in plan.memory_types.iter() {
+        prop_assert!(common_bits & (1 << memory_type) > 0);
+      }
+
+      for (i, (resource, &offset)) in resources.iter().zip(plan.offsets.iter()).enumerate() {
+        prop_assert_eq!(offset % resource.alignment, 0);
+        prop_assert!(offset + resource.size <= plan.size);
+
+        for (other, &other_offset) in resources[..i].iter().zip(plan.offsets.iter()) {
+          // no overlap
+          prop_assert!(other_offset + other.size <= offset);
+
+          // no shared granularity pages between different kinds
+          if other.kind != resource.kind {
+            let granularity = limits.buffer_image_granularity;
+            let other_last_page = (other_offset + other.size - 1) / granularity;
+            prop_assert!(other_last_page < offset / granularity);
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,132 @@
+use std::{
+  ops::Deref,
+  sync::{Arc, Mutex, MutexGuard},
+};
+
+use ash::vk;
+
+use crate::owned::Device;
+
+use super::{
+  allocator::{Allocation, AllocatorError, AllocatorStats, MemoryAllocator},
+  planner::ResourceKind,
+};
+
+// MemoryAllocator that can be shared by the allocations it hands out, so that they can free
+// themselves when dropped
+// Its memory blocks are freed when the allocator and all of its allocations are dropped
+pub struct SharedAllocator {
+  allocator: Mutex<MemoryAllocator>,
+  device: Arc<Device>,
+}
+
+impl SharedAllocator {
+  pub fn new(device: &Arc<Device>, allocator: MemoryAllocator) -> Arc<Self> {
+    Arc::new(Self {
+      allocator: Mutex::new(allocator),
+      device: device.clone(),
+    })
+  }
+
+  // allocations only panic while holding the lock on invalid usage, so the allocator can still be
+  // used after a panic
+  fn lock(&self) -> MutexGuard<'_, MemoryAllocator> {
+    self
+      .allocator
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn own(self: &Arc<Self>, allocation: Allocation) -> OwnedAllocation {
+    OwnedAllocation {
+      allocation,
+      allocator: self.clone(),
+    }
+  }
+
+  pub fn stats(&self) -> AllocatorStats {
+    self.lock().stats()
+  }
+
+  pub fn allocate(
+    self: &Arc<Self>,
+    requirements: vk::MemoryRequirements,
+    kind: ResourceKind,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Result<OwnedAllocation, AllocatorError> {
+    let allocation = self.lock().allocate(
+      &self.device,
+      requirements,
+      kind,
+      required_properties,
+      optional_properties,
+    )?;
+    Ok(self.own(allocation))
+  }
+
+  // allocates memory for a buffer and binds it
+  // the buffer should be destroyed (or not be in use) before the allocation gets dropped
+  pub unsafe fn allocate_buffer(
+    self: &Arc<Self>,
+    buffer: vk::Buffer,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Result<OwnedAllocation, AllocatorError> {
+    let allocation = self.lock().allocate_buffer(
+      &self.device,
+      buffer,
+      required_properties,
+      optional_properties,
+    )?;
+    Ok(self.own(allocation))
+  }
+
+  // allocates memory for an image with optimal tiling and binds it
+  // the image should be destroyed (or not be in use) before the allocation gets dropped
+  pub unsafe fn allocate_image(
+    self: &Arc<Self>,
+    image: vk::Image,
+    required_properties: vk::MemoryPropertyFlags,
+    optional_properties: vk::MemoryPropertyFlags,
+  ) -> Result<OwnedAllocation, AllocatorError> {
+    let allocation = self.lock().allocate_image(
+      &self.device,
+      image,
+      required_properties,
+      optional_properties,
+    )?;
+    Ok(self.own(allocation))
+  }
+}
+
+impl Drop for SharedAllocator {
+  fn drop(&mut self) {
+    let allocator = self
+      .allocator
+      .get_mut()
+      .unwrap_or_else(|poisoned| poisoned.into_inner());
+    unsafe { allocator.destroy_self(&self.device) };
+  }
+}
+
+// Allocation that returns its range to the allocator when dropped
+pub struct OwnedAllocation {
+  allocation: Allocation,
+  allocator: Arc<SharedAllocator>,
+}
+
+impl Deref for OwnedAllocation {
+  type Target = Allocation;
+
+  fn deref(&self) -> &Self::Target {
+    &self.allocation
+  }
+}
+
+impl Drop for OwnedAllocation {
+  fn drop(&mut self) {
+    let device = &self.allocator.device;
+    unsafe { self.allocator.lock().free(device, &self.allocation) };
+  }
+}
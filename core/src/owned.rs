@@ -0,0 +1,228 @@
+// Owned wrappers around Vulkan objects that get destroyed when dropped
+//
+// Every object keeps an Arc to its parent (objects to the device, the device to the instance), so
+// parents can only be destroyed after all of their children, whatever the order in which the
+// wrappers are dropped. This means that an early return (or a panic) during initialization
+// destroys everything that was created up to that point.
+// Objects are not checked for being in use when dropped: owners that submit work should wait for
+// the device (or the relevant fences) to become idle before dropping them.
+
+use std::{
+  ffi::CStr,
+  mem::{self, ManuallyDrop},
+  ops::Deref,
+  sync::Arc,
+};
+
+use ash::vk;
+
+use crate::{
+  create_instance, create_logical_device, diagnostics::DEVICE_FAULT_EXTENSION, ApplicationInfo,
+  DeviceRequirements, PhysicalDevice, Queues,
+};
+
+// ash::Instance (with validation layers if enabled) that gets destroyed when dropped
+pub struct Instance {
+  instance: ash::Instance,
+  #[cfg(feature = "vl")]
+  debug_utils: crate::DebugUtils,
+  // has to be alive while the instance is in use
+  entry: ash::Entry,
+}
+
+impl Instance {
+  pub fn create(
+    entry: ash::Entry,
+    app: &ApplicationInfo,
+    additional_extensions: &[&CStr],
+  ) -> Result<Arc<Self>, vk::Result> {
+    #[cfg(feature = "vl")]
+    let (instance, debug_utils) = create_instance(&entry, app, additional_extensions)?;
+    #[cfg(not(feature = "vl"))]
+    let instance = create_instance(&entry, app, additional_extensions)?;
+
+    Ok(Arc::new(Self {
+      instance,
+      #[cfg(feature = "vl")]
+      debug_utils,
+      entry,
+    }))
+  }
+
+  pub fn entry(&self) -> &ash::Entry {
+    &self.entry
+  }
+
+  #[cfg(feature = "vl")]
+  pub fn debug_utils(&self) -> &crate::DebugUtils {
+    &self.debug_utils
+  }
+}
+
+impl Deref for Instance {
+  type Target = ash::Instance;
+
+  fn deref(&self) -> &Self::Target {
+    &self.instance
+  }
+}
+
+impl Drop for Instance {
+  fn drop(&mut self) {
+    log::debug!("Destroying instance");
+    unsafe {
+      #[cfg(feature = "vl")]
+      self.debug_utils.destroy_self();
+      self.instance.destroy_instance(None);
+    }
+  }
+}
+
+// ash::Device that gets destroyed when dropped (after all objects created from it)
+pub struct Device {
+  device: ash::Device,
+  // loaded if VK_EXT_device_fault is enabled
+  device_fault: Option<vk::ExtDeviceFaultFn>,
+  _instance: Arc<Instance>,
+}
+
+impl Device {
+  pub fn create(
+    instance: &Arc<Instance>,
+    physical_device: &PhysicalDevice,
+    requirements: &DeviceRequirements,
+  ) -> Result<(Arc<Self>, Queues), vk::Result> {
+    let (device, queues) = create_logical_device(instance, physical_device, requirements)?;
+    let device_fault = requirements
+      .enabled_extensions(physical_device)
+      .contains(&DEVICE_FAULT_EXTENSION)
+      .then(|| {
+        vk::ExtDeviceFaultFn::load(|name| unsafe {
+          mem::transmute((instance.fp_v1_0().get_device_proc_addr)(
+            device.handle(),
+            name.as_ptr(),
+          ))
+        })
+      });
+    Ok((
+      Arc::new(Self {
+        device,
+        device_fault,
+        _instance: instance.clone(),
+      }),
+      queues,
+    ))
+  }
+
+  // None if VK_EXT_device_fault is not enabled
+  pub fn device_fault(&self) -> Option<&vk::ExtDeviceFaultFn> {
+    self.device_fault.as_ref()
+  }
+}
+
+impl Deref for Device {
+  type Target = ash::Device;
+
+  fn deref(&self) -> &Self::Target {
+    &self.device
+  }
+}
+
+impl Drop for Device {
+  fn drop(&mut self) {
+    log::debug!("Destroying logical device");
+    unsafe {
+      // this only fails if the device is lost, in which case it can be destroyed anyway
+      if let Err(err) = self.device.device_wait_idle() {
+        log::error!(
+          "Failed to wait for the device to become idle during drop: {:?}",
+          err
+        );
+      }
+      // also implicitly destroys all associated queues
+      self.device.destroy_device(None);
+    }
+  }
+}
+
+// Vulkan handles that are destroyed through the device that created them
+pub trait DeviceObject: Copy {
+  unsafe fn destroy(self, device: &ash::Device);
+}
+
+macro_rules! device_objects {
+  ($($handle:ty => $destroy:ident),* $(,)?) => {
+    $(
+      impl DeviceObject for $handle {
+        unsafe fn destroy(self, device: &ash::Device) {
+          device.$destroy(self, None);
+        }
+      }
+    )*
+  };
+}
+
+device_objects!(
+  vk::Buffer => destroy_buffer,
+  vk::BufferView => destroy_buffer_view,
+  vk::CommandPool => destroy_command_pool,
+  vk::DescriptorPool => destroy_descriptor_pool,
+  vk::DescriptorSetLayout => destroy_descriptor_set_layout,
+  vk::DeviceMemory => free_memory,
+  vk::Event => destroy_event,
+  vk::Fence => destroy_fence,
+  vk::Framebuffer => destroy_framebuffer,
+  vk::Image => destroy_image,
+  vk::ImageView => destroy_image_view,
+  vk::Pipeline => destroy_pipeline,
+  vk::PipelineCache => destroy_pipeline_cache,
+  vk::PipelineLayout => destroy_pipeline_layout,
+  vk::QueryPool => destroy_query_pool,
+  vk::RenderPass => destroy_render_pass,
+  vk::Sampler => destroy_sampler,
+  vk::Semaphore => destroy_semaphore,
+  vk::ShaderModule => destroy_shader_module,
+);
+
+// A Vulkan handle that gets destroyed when dropped
+// Derefs to the raw handle, so it can be used wherever the handle is expected
+pub struct Owned<T: DeviceObject> {
+  handle: T,
+  device: Arc<Device>,
+}
+
+impl<T: DeviceObject> Owned<T> {
+  // handle should have been created from device and not be owned by anything else
+  pub unsafe fn from_raw(device: &Arc<Device>, handle: T) -> Self {
+    Self {
+      handle,
+      device: device.clone(),
+    }
+  }
+
+  pub fn device(&self) -> &Arc<Device> {
+    &self.device
+  }
+
+  // gives back ownership of the handle without destroying it
+  pub fn into_raw(self) -> T {
+    let this = ManuallyDrop::new(self);
+    // the device Arc still has to be released
+    unsafe { drop(std::ptr::read(&this.device)) };
+    this.handle
+  }
+}
+
+impl<T: DeviceObject> Deref for Owned<T> {
+  type Target = T;
+
+  fn deref(&self) -> &Self::Target {
+    &self.handle
+  }
+}
+
+impl<T: DeviceObject> Drop for Owned<T> {
+  fn drop(&mut self) {
+    unsafe { self.handle.destroy(&self.device) }
+  }
+}
@@ -1,10 +1,11 @@
 use core::slice;
 use std::{
+  ffi::c_void,
   fs::{self, File},
   hash::{DefaultHasher, Hash, Hasher},
   io::{self, Read, Write},
   mem::{self, size_of},
-  os::raw::c_void,
+  path::Path,
   ptr::{self, addr_of},
 };
 
@@ -16,10 +17,7 @@ use crate::device::PhysicalDevice;
 // this is not that reliable but its better than not having it
 const MAGIC: u32 = 0x74c1887f;
 
-const TEMP_PATH: &str = "./pipeline_cache.temp";
-const PATH: &str = "pipeline_cache";
-
-fn hash_data(data: &Vec<u8>) -> u64 {
+fn hash_data(data: &[u8]) -> u64 {
   let mut hasher = DefaultHasher::new();
   data.hash(&mut hasher);
   hasher.finish()
@@ -41,8 +39,8 @@ struct PipelineCacheHeader {
 }
 
 impl PipelineCacheHeader {
-  pub fn generate(physical_device: &PhysicalDevice, data: &Vec<u8>) -> Self {
-    let props = physical_device.get_properties();
+  pub fn generate(physical_device: &PhysicalDevice, data: &[u8]) -> Self {
+    let props = &physical_device.properties.p10;
     Self {
       magic: MAGIC,
       vendor_id: props.vendor_id,
@@ -56,7 +54,7 @@ impl PipelineCacheHeader {
   }
 
   fn is_compatible(&self, physical_device: &PhysicalDevice) -> bool {
-    let props = physical_device.get_properties();
+    let props = &physical_device.properties.p10;
 
     self.magic == MAGIC
       && self.vendor_id == props.vendor_id
@@ -66,12 +64,12 @@ impl PipelineCacheHeader {
       && self.cache_uuid == props.pipeline_cache_uuid
   }
 
-  fn bytes<'a>(&self) -> &'a [u8] {
+  fn bytes(&self) -> &[u8] {
     unsafe { slice::from_raw_parts(addr_of!(*self) as *const u8, size_of::<Self>()) }
   }
 
   unsafe fn from_bytes(bytes: [u8; size_of::<Self>()]) -> PipelineCacheHeader {
-    mem::transmute(bytes)
+    mem::transmute::<[u8; size_of::<Self>()], PipelineCacheHeader>(bytes)
   }
 }
 
@@ -80,33 +78,40 @@ pub fn save_pipeline_cache(
   device: &ash::Device,
   physical_device: &PhysicalDevice,
   pipeline_cache: vk::PipelineCache,
+  path: &Path,
 ) -> io::Result<()> {
   let data = unsafe {
     device
       .get_pipeline_cache_data(pipeline_cache)
-      .expect("Failed to get pipeline cache data")
+      .map_err(io::Error::other)?
   };
   let header = PipelineCacheHeader::generate(physical_device, &data);
 
+  // write to a temporary file first so that the cache doesn't get corrupted if the application
+  // exits in the middle of writing
+  let temp_path = path.with_extension("temp");
   {
-    let mut temp = File::create(TEMP_PATH)?;
+    let mut temp = File::create(&temp_path)?;
     temp.write_all(header.bytes())?;
     temp.write_all(data.as_slice())?;
     temp.sync_data()?;
   }
 
-  fs::copy(TEMP_PATH, PATH)?;
-  fs::remove_file(TEMP_PATH)?;
+  fs::copy(&temp_path, path)?;
+  fs::remove_file(&temp_path)?;
 
   Ok(())
 }
 
+// Creates a pipeline cache with the data of a previously saved file, if it exists and is valid
+// Also returns if the data was read from the file
 pub fn create_pipeline_cache(
   device: &ash::Device,
   physical_device: &PhysicalDevice,
-) -> (vk::PipelineCache, bool) {
+  path: &Path,
+) -> Result<(vk::PipelineCache, bool), vk::Result> {
   // tries to create a pipeline cache from an existing file
-  let cache_result = match try_read_pipeline_cache_data_from_file(physical_device) {
+  let cache_result = match try_read_pipeline_cache_data_from_file(physical_device, path) {
     Ok(data) => {
       let create_info = vk::PipelineCacheCreateInfo {
         s_type: vk::StructureType::PIPELINE_CACHE_CREATE_INFO,
@@ -117,13 +122,11 @@ pub fn create_pipeline_cache(
       };
       let result = unsafe { device.create_pipeline_cache(&create_info, None) };
 
-      result.or_else(|err| {
+      result.map_err(|err| {
         log::error!(
           "Pipeline cache file data was retrieved however pipeline creation operation failed: {:?}",
           err
         );
-
-        Err(())
       })
     }
     Err(err) => {
@@ -140,7 +143,7 @@ pub fn create_pipeline_cache(
   };
 
   match cache_result {
-    Ok(cache) => (cache, true),
+    Ok(cache) => Ok((cache, true)),
     Err(()) => {
       let create_info = vk::PipelineCacheCreateInfo {
         s_type: vk::StructureType::PIPELINE_CACHE_CREATE_INFO,
@@ -150,18 +153,17 @@ pub fn create_pipeline_cache(
         p_initial_data: ptr::null(),
       };
 
-      let cache = unsafe {
-        device
-          .create_pipeline_cache(&create_info, None)
-          .expect("Failed to create a pipeline cache with no initial data")
-      };
-      (cache, false)
+      let cache = unsafe { device.create_pipeline_cache(&create_info, None)? };
+      Ok((cache, false))
     }
   }
 }
 
-fn try_read_pipeline_cache_data_from_file(physical_device: &PhysicalDevice) -> io::Result<Vec<u8>> {
-  let mut file = File::open(PATH)?;
+fn try_read_pipeline_cache_data_from_file(
+  physical_device: &PhysicalDevice,
+  path: &Path,
+) -> io::Result<Vec<u8>> {
+  let mut file = File::open(path)?;
 
   let mut header_bytes = [0u8; size_of::<PipelineCacheHeader>()];
   file.read_exact(&mut header_bytes)?;
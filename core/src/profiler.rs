@@ -0,0 +1,303 @@
+// GPU timing of named scopes with timestamp queries
+//
+// Each scope writes two timestamps into a query pool, one before and one after the commands it
+// contains. Timestamps are converted to time with the device timestampPeriod, taking into account
+// that only the lower timestampValidBits of each value are meaningful. Families that don't support
+// timestamps (timestampValidBits == 0) still get their scopes registered, but nothing is recorded
+// and their duration is reported as unknown.
+// Queries are reset from the host (which requires the hostQueryReset feature), as
+// vkCmdResetQueryPool can't be recorded in command buffers of transfer only families.
+
+use std::{fmt::Write, ptr, sync::Arc, time::Duration};
+
+use ash::vk;
+
+use crate::{Device, Owned, PhysicalDevice, QueueFamilies};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScopeId(usize);
+
+struct Scope {
+  name: String,
+  // valid bits of the family that recorded the scope
+  valid_bits: u32,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScopeTiming {
+  pub name: String,
+  // None if the queue family doesn't support timestamps
+  pub duration: Option<Duration>,
+}
+
+pub struct TimestampProfiler {
+  pool: Owned<vk::QueryPool>,
+  // maximum number of scopes
+  capacity: usize,
+  // scope i uses queries 2i and 2i + 1
+  scopes: Vec<Scope>,
+  // (family index, timestamp valid bits)
+  valid_bits: Vec<(u32, u32)>,
+  // nanoseconds per tick
+  timestamp_period: f64,
+  device: Arc<Device>,
+}
+
+impl TimestampProfiler {
+  pub fn new(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+    capacity: usize,
+  ) -> Result<Self, vk::Result> {
+    let create_info = vk::QueryPoolCreateInfo {
+      s_type: vk::StructureType::QUERY_POOL_CREATE_INFO,
+      p_next: ptr::null(),
+      flags: vk::QueryPoolCreateFlags::empty(),
+      query_type: vk::QueryType::TIMESTAMP,
+      query_count: (capacity * 2) as u32,
+      pipeline_statistics: vk::QueryPipelineStatisticFlags::empty(),
+    };
+    let pool = unsafe {
+      let pool = device.create_query_pool(&create_info, None)?;
+      Owned::from_raw(device, pool)
+    };
+
+    Ok(Self {
+      pool,
+      capacity,
+      scopes: Vec::with_capacity(capacity),
+      valid_bits: family_valid_bits(&physical_device.queue_families),
+      timestamp_period: physical_device.properties.p10.limits.timestamp_period as f64,
+      device: device.clone(),
+    })
+  }
+
+  // forgets all scopes, should be called before their command buffers get recorded again
+  pub fn clear(&mut self) {
+    self.scopes.clear();
+  }
+
+  // Resets the queries of every scope from the host, should be called before each submission of
+  // the command buffers that contain them, when none of them is executing
+  pub unsafe fn reset(&self) {
+    self
+      .device
+      .reset_query_pool(*self.pool, 0, (self.capacity * 2) as u32);
+  }
+
+  // Starts a scope in a command buffer allocated from queue_family
+  // panics if more scopes than capacity are begun without calling clear
+  pub unsafe fn begin_scope(
+    &mut self,
+    cb: vk::CommandBuffer,
+    name: &str,
+    queue_family: u32,
+  ) -> ScopeId {
+    assert!(
+      self.scopes.len() < self.capacity,
+      "Timestamp profiler capacity ({}) exceeded",
+      self.capacity
+    );
+    let valid_bits = self
+      .valid_bits
+      .iter()
+      .find(|(family, _)| *family == queue_family)
+      .map_or(0, |(_, bits)| *bits);
+    let id = ScopeId(self.scopes.len());
+    self.scopes.push(Scope {
+      name: name.to_owned(),
+      valid_bits,
+    });
+
+    if valid_bits > 0 {
+      let first = (id.0 * 2) as u32;
+      self.device.cmd_write_timestamp2(
+        cb,
+        vk::PipelineStageFlags2::ALL_COMMANDS,
+        *self.pool,
+        first,
+      );
+    }
+    id
+  }
+
+  // ends a scope in the same command buffer that began it
+  pub unsafe fn end_scope(&self, cb: vk::CommandBuffer, scope: ScopeId) {
+    if self.scopes[scope.0].valid_bits > 0 {
+      self.device.cmd_write_timestamp2(
+        cb,
+        vk::PipelineStageFlags2::ALL_COMMANDS,
+        *self.pool,
+        (scope.0 * 2 + 1) as u32,
+      );
+    }
+  }
+
+  // Timings of all scopes in the order they were begun
+  // Blocks until the queries are available, so the command buffers that contain the scopes should
+  // have been submitted. Resubmitted command buffers report their last execution.
+  pub fn results(&self) -> Result<Vec<ScopeTiming>, vk::Result> {
+    let mut timings = Vec::with_capacity(self.scopes.len());
+    for (i, scope) in self.scopes.iter().enumerate() {
+      let duration = if scope.valid_bits > 0 {
+        let mut ticks = [0u64; 2];
+        unsafe {
+          self.device.get_query_pool_results(
+            *self.pool,
+            (i * 2) as u32,
+            2,
+            &mut ticks,
+            vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT,
+          )?;
+        }
+        Some(ticks_to_duration(
+          ticks[0],
+          ticks[1],
+          scope.valid_bits,
+          self.timestamp_period,
+        ))
+      } else {
+        None
+      };
+      timings.push(ScopeTiming {
+        name: scope.name.clone(),
+        duration,
+      });
+    }
+    Ok(timings)
+  }
+}
+
+fn family_valid_bits(queue_families: &QueueFamilies) -> Vec<(u32, u32)> {
+  queue_families
+    .unique_indices
+    .iter()
+    .map(|&index| {
+      let family = queue_families.get(index).unwrap();
+      (index, family.timestamp_valid_bits)
+    })
+    .collect()
+}
+
+// Time between two timestamps of a family with valid_bits
+// Bits above valid_bits are undefined and the counter can wrap around between both values
+pub fn ticks_to_duration(start: u64, end: u64, valid_bits: u32, timestamp_period: f64) -> Duration {
+  let mask = if valid_bits >= 64 {
+    u64::MAX
+  } else {
+    (1u64 << valid_bits) - 1
+  };
+  let ticks = (end & mask).wrapping_sub(start & mask) & mask;
+  Duration::from_nanos((ticks as f64 * timestamp_period).round() as u64)
+}
+
+// sum of all durations, None if there are no timings or any of them is unsupported
+pub fn total_duration(timings: &[ScopeTiming]) -> Option<Duration> {
+  if timings.is_empty() {
+    return None;
+  }
+  timings.iter().map(|timing| timing.duration).sum()
+}
+
+// table with one scope per line, with an additional total line if there is more than one scope
+// the total is unsupported if any of the scopes is
+pub fn format_table(timings: &[ScopeTiming]) -> String {
+  let name_width = timings
+    .iter()
+    .map(|timing| timing.name.len())
+    .chain(["total".len()])
+    .max()
+    .unwrap();
+  let format_ms = |duration: Option<Duration>| match duration {
+    Some(duration) => format!("{:>10.3} ms", duration.as_secs_f64() * 1000.0),
+    None => format!("{:>13}", "unsupported"),
+  };
+
+  let mut table = String::new();
+  for timing in timings.iter() {
+    let _ = writeln!(
+      table,
+      "{:<name_width$}  {}",
+      timing.name,
+      format_ms(timing.duration)
+    );
+  }
+  if timings.len() > 1 {
+    let _ = writeln!(
+      table,
+      "{:<name_width$}  {}",
+      "total",
+      format_ms(total_duration(timings))
+    );
+  }
+  table
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ticks_are_scaled_by_period() {
+    assert_eq!(
+      ticks_to_duration(1000, 3000, 64, 1.0),
+      Duration::from_nanos(2000)
+    );
+    assert_eq!(
+      ticks_to_duration(10, 20, 64, 83.333),
+      Duration::from_nanos(833)
+    );
+  }
+
+  #[test]
+  fn invalid_bits_are_ignored() {
+    // only the lower 36 bits are valid
+    let garbage = 0xABCD << 36;
+    assert_eq!(
+      ticks_to_duration(garbage | 5, 15, 36, 1.0),
+      Duration::from_nanos(10)
+    );
+  }
+
+  #[test]
+  fn counter_wraps_around() {
+    let max = (1u64 << 32) - 1;
+    assert_eq!(
+      ticks_to_duration(max - 4, 5, 32, 1.0),
+      Duration::from_nanos(10)
+    );
+  }
+
+  #[test]
+  fn table_contains_all_scopes_and_total() {
+    let timings = [
+      ScopeTiming {
+        name: "clear".to_owned(),
+        duration: Some(Duration::from_micros(1500)),
+      },
+      ScopeTiming {
+        name: "copy to buffer".to_owned(),
+        duration: Some(Duration::from_micros(500)),
+      },
+      ScopeTiming {
+        name: "transfer".to_owned(),
+        duration: None,
+      },
+    ];
+    let table = format_table(&timings);
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("clear ") && lines[0].ends_with("1.500 ms"));
+    assert!(lines[2].ends_with("unsupported"));
+    // the total doesn't include the unsupported scope, so it isn't shown
+    assert!(lines[3].starts_with("total") && lines[3].ends_with("unsupported"));
+    assert_eq!(total_duration(&timings), None);
+
+    let table = format_table(&timings[..2]);
+    assert!(table.lines().last().unwrap().ends_with("2.000 ms"));
+    assert_eq!(
+      total_duration(&timings[..2]),
+      Some(Duration::from_millis(2))
+    );
+  }
+}
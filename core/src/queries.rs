@@ -0,0 +1,307 @@
+// Pipeline statistics and occlusion queries around draw calls
+//
+// An occlusion query counts the samples that pass the per-fragment tests, which is non zero if
+// anything was drawn (the exact number is only meaningful if occlusionQueryPrecise is enabled).
+// A pipeline statistics query counts how many vertices and primitives were assembled and how many
+// times each shader stage got invoked, which catches mistakes in vertex and index buffer setup.
+// Pipeline statistics require the pipelineStatisticsQuery feature, so they are optional.
+
+use std::{error::Error, fmt, ptr, sync::Arc};
+
+use ash::vk;
+
+use crate::{Device, Owned, PhysicalDeviceFeatures};
+
+// statistics retrieved by DrawQueries, results are written in bit order
+const GRAPHICS_STATISTICS: vk::QueryPipelineStatisticFlags =
+  vk::QueryPipelineStatisticFlags::from_raw(
+    vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES.as_raw()
+      | vk::QueryPipelineStatisticFlags::INPUT_ASSEMBLY_PRIMITIVES.as_raw()
+      | vk::QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+      | vk::QueryPipelineStatisticFlags::CLIPPING_INVOCATIONS.as_raw()
+      | vk::QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.as_raw()
+      | vk::QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw(),
+  );
+const GRAPHICS_STATISTICS_COUNT: usize = 6;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineStatistics {
+  pub input_assembly_vertices: u64,
+  pub input_assembly_primitives: u64,
+  pub vertex_shader_invocations: u64,
+  pub clipping_invocations: u64,
+  pub clipping_primitives: u64,
+  pub fragment_shader_invocations: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DrawStatistics {
+  pub samples_passed: u64,
+  // None if pipelineStatisticsQuery is not enabled
+  pub pipeline: Option<PipelineStatistics>,
+}
+
+impl fmt::Display for DrawStatistics {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "samples passed: {}", self.samples_passed)?;
+    if let Some(pipeline) = &self.pipeline {
+      write!(
+        f,
+        ", vertices: {}, primitives: {}, vertex shader invocations: {}, clipping invocations: {}, \
+         clipped primitives: {}, fragment shader invocations: {}",
+        pipeline.input_assembly_vertices,
+        pipeline.input_assembly_primitives,
+        pipeline.vertex_shader_invocations,
+        pipeline.clipping_invocations,
+        pipeline.clipping_primitives,
+        pipeline.fragment_shader_invocations
+      )?;
+    }
+    Ok(())
+  }
+}
+
+// statistics that don't match what the draw calls should have done
+#[derive(Debug)]
+pub struct StatisticsMismatch(pub Vec<String>);
+
+impl fmt::Display for StatisticsMismatch {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Unexpected draw statistics: {}", self.0.join(", "))
+  }
+}
+
+impl Error for StatisticsMismatch {}
+
+impl DrawStatistics {
+  // Checks the results of indexed triangle list draws of index_count indices in total, where
+  // something should be visible
+  // Vertex shader invocations can be less than the number of indices, as implementations may
+  // reuse the results of repeated indices
+  pub fn check_indexed_triangles(&self, index_count: u64) -> Result<(), StatisticsMismatch> {
+    let mut mismatches = Vec::new();
+    if self.samples_passed == 0 {
+      mismatches.push("no samples passed".to_owned());
+    }
+    if let Some(pipeline) = &self.pipeline {
+      let mut expect = |ok: bool, message: String| {
+        if !ok {
+          mismatches.push(message);
+        }
+      };
+      expect(
+        pipeline.input_assembly_vertices == index_count,
+        format!(
+          "{} vertices assembled instead of {}",
+          pipeline.input_assembly_vertices, index_count
+        ),
+      );
+      expect(
+        pipeline.input_assembly_primitives == index_count / 3,
+        format!(
+          "{} primitives assembled instead of {}",
+          pipeline.input_assembly_primitives,
+          index_count / 3
+        ),
+      );
+      expect(
+        (1..=index_count).contains(&pipeline.vertex_shader_invocations),
+        format!(
+          "{} vertex shader invocations for {} indices",
+          pipeline.vertex_shader_invocations, index_count
+        ),
+      );
+      expect(
+        pipeline.clipping_invocations <= pipeline.input_assembly_primitives,
+        format!(
+          "{} clipping invocations for {} primitives",
+          pipeline.clipping_invocations, pipeline.input_assembly_primitives
+        ),
+      );
+      expect(
+        pipeline.fragment_shader_invocations > 0,
+        "no fragment shader invocations".to_owned(),
+      );
+    }
+
+    if mismatches.is_empty() {
+      Ok(())
+    } else {
+      Err(StatisticsMismatch(mismatches))
+    }
+  }
+}
+
+// One occlusion query and (if supported) one pipeline statistics query that surround draw calls
+pub struct DrawQueries {
+  occlusion: Owned<vk::QueryPool>,
+  occlusion_flags: vk::QueryControlFlags,
+  statistics: Option<Owned<vk::QueryPool>>,
+  device: Arc<Device>,
+}
+
+impl DrawQueries {
+  // enabled_features are the features the device was created with
+  pub fn new(
+    device: &Arc<Device>,
+    enabled_features: &PhysicalDeviceFeatures,
+  ) -> Result<Self, vk::Result> {
+    let occlusion = create_query_pool(
+      device,
+      vk::QueryType::OCCLUSION,
+      vk::QueryPipelineStatisticFlags::empty(),
+    )?;
+    let occlusion_flags = if enabled_features.f10.occlusion_query_precise == vk::TRUE {
+      vk::QueryControlFlags::PRECISE
+    } else {
+      vk::QueryControlFlags::empty()
+    };
+    let statistics = if enabled_features.f10.pipeline_statistics_query == vk::TRUE {
+      Some(create_query_pool(
+        device,
+        vk::QueryType::PIPELINE_STATISTICS,
+        GRAPHICS_STATISTICS,
+      )?)
+    } else {
+      None
+    };
+
+    Ok(Self {
+      occlusion,
+      occlusion_flags,
+      statistics,
+      device: device.clone(),
+    })
+  }
+
+  fn pools(&self) -> impl Iterator<Item = vk::QueryPool> + '_ {
+    [Some(&self.occlusion), self.statistics.as_ref()]
+      .into_iter()
+      .flatten()
+      .map(|pool| **pool)
+  }
+
+  // has to be recorded before begin, outside a render pass instance
+  pub unsafe fn reset(&self, cb: vk::CommandBuffer) {
+    for pool in self.pools() {
+      self.device.cmd_reset_query_pool(cb, pool, 0, 1);
+    }
+  }
+
+  // queries have to begin and end in the same subpass if begun inside a render pass instance
+  pub unsafe fn begin(&self, cb: vk::CommandBuffer) {
+    self
+      .device
+      .cmd_begin_query(cb, *self.occlusion, 0, self.occlusion_flags);
+    if let Some(statistics) = &self.statistics {
+      self
+        .device
+        .cmd_begin_query(cb, **statistics, 0, vk::QueryControlFlags::empty());
+    }
+  }
+
+  pub unsafe fn end(&self, cb: vk::CommandBuffer) {
+    for pool in self.pools() {
+      self.device.cmd_end_query(cb, pool, 0);
+    }
+  }
+
+  // blocks until the results are available, so the command buffer should have been submitted
+  pub fn results(&self) -> Result<DrawStatistics, vk::Result> {
+    let flags = vk::QueryResultFlags::TYPE_64 | vk::QueryResultFlags::WAIT;
+    let mut samples_passed = [0u64];
+    unsafe {
+      self
+        .device
+        .get_query_pool_results(*self.occlusion, 0, 1, &mut samples_passed, flags)?;
+    }
+
+    let pipeline = match &self.statistics {
+      Some(statistics) => {
+        // all statistics of a query are written as a single result
+        let mut results = [[0u64; GRAPHICS_STATISTICS_COUNT]];
+        unsafe {
+          self
+            .device
+            .get_query_pool_results(**statistics, 0, 1, &mut results, flags)?;
+        }
+        let r = results[0];
+        Some(PipelineStatistics {
+          input_assembly_vertices: r[0],
+          input_assembly_primitives: r[1],
+          vertex_shader_invocations: r[2],
+          clipping_invocations: r[3],
+          clipping_primitives: r[4],
+          fragment_shader_invocations: r[5],
+        })
+      }
+      None => None,
+    };
+
+    Ok(DrawStatistics {
+      samples_passed: samples_passed[0],
+      pipeline,
+    })
+  }
+}
+
+fn create_query_pool(
+  device: &Arc<Device>,
+  query_type: vk::QueryType,
+  pipeline_statistics: vk::QueryPipelineStatisticFlags,
+) -> Result<Owned<vk::QueryPool>, vk::Result> {
+  let create_info = vk::QueryPoolCreateInfo {
+    s_type: vk::StructureType::QUERY_POOL_CREATE_INFO,
+    p_next: ptr::null(),
+    flags: vk::QueryPoolCreateFlags::empty(),
+    query_type,
+    query_count: 1,
+    pipeline_statistics,
+  };
+  unsafe {
+    let pool = device.create_query_pool(&create_info, None)?;
+    Ok(Owned::from_raw(device, pool))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn quad_statistics() -> DrawStatistics {
+    DrawStatistics {
+      samples_passed: 1024,
+      pipeline: Some(PipelineStatistics {
+        input_assembly_vertices: 6,
+        input_assembly_primitives: 2,
+        vertex_shader_invocations: 4,
+        clipping_invocations: 2,
+        clipping_primitives: 2,
+        fragment_shader_invocations: 1024,
+      }),
+    }
+  }
+
+  #[test]
+  fn matching_statistics_pass() {
+    assert!(quad_statistics().check_indexed_triangles(6).is_ok());
+    let occlusion_only = DrawStatistics {
+      samples_passed: 1,
+      pipeline: None,
+    };
+    assert!(occlusion_only.check_indexed_triangles(6).is_ok());
+  }
+
+  #[test]
+  fn wrong_index_count_is_reported() {
+    // for example an index buffer bound with the wrong index type
+    let mismatch = quad_statistics().check_indexed_triangles(3).unwrap_err();
+    assert_eq!(mismatch.0.len(), 3);
+
+    let nothing_drawn = DrawStatistics::default();
+    assert_eq!(
+      nothing_drawn.check_indexed_triangles(6).unwrap_err().0,
+      vec!["no samples passed".to_owned()]
+    );
+  }
+}
@@ -0,0 +1,245 @@
+// Small edits to SPIR-V modules after they are loaded
+//
+// Storage images declare their format in the shader (for example "rgba8" in GLSL), which has to
+// match the format of the image view bound to them. Instead of compiling one shader per format,
+// the format of already compiled storage images can be replaced before the shader module is
+// created.
+
+use std::fmt;
+
+use ash::vk;
+
+use crate::utility::error_chain_fmt;
+
+const MAGIC: u32 = 0x0723_0203;
+// words before the first instruction
+const HEADER_LEN: usize = 5;
+
+const OP_CAPABILITY: u32 = 17;
+const OP_TYPE_IMAGE: u32 = 25;
+// OpTypeImage operand that is 2 for images used without a sampler (storage images)
+const IMAGE_SAMPLED_WORD: usize = 7;
+const IMAGE_FORMAT_WORD: usize = 8;
+const CAPABILITY_STORAGE_IMAGE_EXTENDED_FORMATS: u32 = 49;
+
+// SPIR-V Image Format operand, corresponds to the format qualifier in GLSL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+  Rgba32f = 1,
+  Rgba16f = 2,
+  Rgba8 = 4,
+  Rgba16 = 10,
+  R16 = 14,
+}
+
+impl ImageFormat {
+  pub fn from_vk(format: vk::Format) -> Option<Self> {
+    match format {
+      vk::Format::R32G32B32A32_SFLOAT => Some(ImageFormat::Rgba32f),
+      vk::Format::R16G16B16A16_SFLOAT => Some(ImageFormat::Rgba16f),
+      vk::Format::R8G8B8A8_UNORM => Some(ImageFormat::Rgba8),
+      vk::Format::R16G16B16A16_UNORM => Some(ImageFormat::Rgba16),
+      vk::Format::R16_UNORM => Some(ImageFormat::R16),
+      _ => None,
+    }
+  }
+
+  // Formats that need the StorageImageExtendedFormats capability in the shader and the
+  // shaderStorageImageExtendedFormats device feature
+  pub fn is_extended(self) -> bool {
+    matches!(self, ImageFormat::Rgba16 | ImageFormat::R16)
+  }
+}
+
+#[derive(PartialEq, Eq)]
+pub enum SpirvError {
+  // size is not a multiple of 4 or the magic number is wrong
+  InvalidHeader,
+  // an instruction has a word count of 0 or goes past the end of the module
+  InvalidInstruction { offset: usize },
+  NoStorageImage,
+}
+
+impl fmt::Display for SpirvError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidHeader => write!(f, "Code is not a valid SPIR-V module"),
+      Self::InvalidInstruction { offset } => {
+        write!(f, "Invalid SPIR-V instruction at word {}", offset)
+      }
+      Self::NoStorageImage => write!(f, "SPIR-V module doesn't declare any storage image"),
+    }
+  }
+}
+
+impl fmt::Debug for SpirvError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl std::error::Error for SpirvError {}
+
+// Words of a SPIR-V module stored in either endianness
+pub fn words_from_bytes(bytes: &[u8]) -> Result<Vec<u32>, SpirvError> {
+  if !bytes.len().is_multiple_of(4) || bytes.len() < HEADER_LEN * 4 {
+    return Err(SpirvError::InvalidHeader);
+  }
+  let words = |from_bytes: fn([u8; 4]) -> u32| {
+    bytes
+      .chunks_exact(4)
+      .map(|word| from_bytes([word[0], word[1], word[2], word[3]]))
+      .collect::<Vec<u32>>()
+  };
+  let little_endian = words(u32::from_le_bytes);
+  if little_endian[0] == MAGIC {
+    return Ok(little_endian);
+  }
+  let big_endian = words(u32::from_be_bytes);
+  if big_endian[0] == MAGIC {
+    return Ok(big_endian);
+  }
+  Err(SpirvError::InvalidHeader)
+}
+
+// offset of each instruction in the module
+fn instructions(code: &[u32]) -> Result<Vec<usize>, SpirvError> {
+  let mut offsets = Vec::new();
+  let mut offset = HEADER_LEN;
+  while offset < code.len() {
+    let word_count = (code[offset] >> 16) as usize;
+    if word_count == 0 || offset + word_count > code.len() {
+      return Err(SpirvError::InvalidInstruction { offset });
+    }
+    offsets.push(offset);
+    offset += word_count;
+  }
+  Ok(offsets)
+}
+
+// Replaces the format of every storage image declared in the module
+// The StorageImageExtendedFormats capability is added if the new format needs it
+pub fn set_storage_image_format(
+  code: &mut Vec<u32>,
+  format: ImageFormat,
+) -> Result<(), SpirvError> {
+  if code.len() < HEADER_LEN || code[0] != MAGIC {
+    return Err(SpirvError::InvalidHeader);
+  }
+
+  let mut has_extended_formats = false;
+  let mut replaced = false;
+  for offset in instructions(code)? {
+    let word_count = (code[offset] >> 16) as usize;
+    match code[offset] & 0xFFFF {
+      OP_CAPABILITY if word_count == 2 => {
+        has_extended_formats |= code[offset + 1] == CAPABILITY_STORAGE_IMAGE_EXTENDED_FORMATS;
+      }
+      OP_TYPE_IMAGE if word_count > IMAGE_FORMAT_WORD && code[offset + IMAGE_SAMPLED_WORD] == 2 => {
+        code[offset + IMAGE_FORMAT_WORD] = format as u32;
+        replaced = true;
+      }
+      _ => {}
+    }
+  }
+  if !replaced {
+    return Err(SpirvError::NoStorageImage);
+  }
+
+  if format.is_extended() && !has_extended_formats {
+    // capabilities are the first instructions of a module
+    code.splice(
+      HEADER_LEN..HEADER_LEN,
+      [
+        (2 << 16) | OP_CAPABILITY,
+        CAPABILITY_STORAGE_IMAGE_EXTENDED_FORMATS,
+      ],
+    );
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // header, OpCapability Shader, OpTypeFloat %2 32 and OpTypeImage %3 %2 2D 0 0 0 2 Rgba8
+  fn storage_image_module() -> Vec<u32> {
+    vec![
+      MAGIC,
+      0x0001_0000,
+      0,
+      4,
+      0,
+      (2 << 16) | OP_CAPABILITY,
+      1,
+      (3 << 16) | 22,
+      2,
+      32,
+      (9 << 16) | OP_TYPE_IMAGE,
+      3,
+      2,
+      1,
+      0,
+      0,
+      0,
+      2,
+      ImageFormat::Rgba8 as u32,
+    ]
+  }
+
+  #[test]
+  fn storage_image_format_is_replaced() {
+    let mut code = storage_image_module();
+    set_storage_image_format(&mut code, ImageFormat::Rgba16f).unwrap();
+    let mut expected = storage_image_module();
+    expected[18] = ImageFormat::Rgba16f as u32;
+    assert_eq!(code, expected);
+  }
+
+  #[test]
+  fn extended_formats_add_a_capability() {
+    let mut code = storage_image_module();
+    set_storage_image_format(&mut code, ImageFormat::R16).unwrap();
+    assert_eq!(
+      code[HEADER_LEN..HEADER_LEN + 2],
+      [
+        (2 << 16) | OP_CAPABILITY,
+        CAPABILITY_STORAGE_IMAGE_EXTENDED_FORMATS
+      ]
+    );
+    assert_eq!(code.len(), storage_image_module().len() + 2);
+    assert_eq!(*code.last().unwrap(), ImageFormat::R16 as u32);
+
+    // the capability is only added once
+    set_storage_image_format(&mut code, ImageFormat::Rgba16).unwrap();
+    assert_eq!(code.len(), storage_image_module().len() + 2);
+  }
+
+  #[test]
+  fn sampled_images_and_invalid_modules_are_rejected() {
+    let mut sampled = storage_image_module();
+    sampled[17] = 1;
+    assert_eq!(
+      set_storage_image_format(&mut sampled, ImageFormat::Rgba32f),
+      Err(SpirvError::NoStorageImage)
+    );
+
+    let mut truncated = storage_image_module();
+    truncated.pop();
+    assert_eq!(
+      set_storage_image_format(&mut truncated, ImageFormat::Rgba32f),
+      Err(SpirvError::InvalidInstruction { offset: 10 })
+    );
+  }
+
+  #[test]
+  fn words_are_read_in_both_endiannesses() {
+    let code = storage_image_module();
+    let le: Vec<u8> = code.iter().flat_map(|w| w.to_le_bytes()).collect();
+    let be: Vec<u8> = code.iter().flat_map(|w| w.to_be_bytes()).collect();
+    assert_eq!(words_from_bytes(&le), Ok(code.clone()));
+    assert_eq!(words_from_bytes(&be), Ok(code));
+    assert_eq!(words_from_bytes(&le[1..]), Err(SpirvError::InvalidHeader));
+  }
+}
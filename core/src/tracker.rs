@@ -0,0 +1,575 @@
+// Tracking of the layout, access and owning queue family of images and buffers
+//
+// Commands declare how they are going to use a resource and the tracker returns the minimal
+// barriers that have to be recorded before them, including the release half of queue family
+// ownership transfers, which has to be recorded in a command buffer of the previous family.
+
+use std::{collections::HashMap, ptr};
+
+use ash::vk;
+
+// accesses that modify memory, every other access only reads it
+const WRITE_ACCESS: vk::AccessFlags2 = vk::AccessFlags2::from_raw(
+  vk::AccessFlags2::SHADER_WRITE.as_raw()
+    | vk::AccessFlags2::SHADER_STORAGE_WRITE.as_raw()
+    | vk::AccessFlags2::COLOR_ATTACHMENT_WRITE.as_raw()
+    | vk::AccessFlags2::DEPTH_STENCIL_ATTACHMENT_WRITE.as_raw()
+    | vk::AccessFlags2::TRANSFER_WRITE.as_raw()
+    | vk::AccessFlags2::HOST_WRITE.as_raw()
+    | vk::AccessFlags2::MEMORY_WRITE.as_raw(),
+);
+
+pub(crate) fn is_write(access: vk::AccessFlags2) -> bool {
+  access.intersects(WRITE_ACCESS)
+}
+
+// how a command is going to use a resource
+#[derive(Clone, Copy, Debug)]
+pub struct Usage {
+  // family of the command buffer the command is recorded in
+  pub queue_family: u32,
+  pub stage_mask: vk::PipelineStageFlags2,
+  pub access_mask: vk::AccessFlags2,
+  // previous contents are not needed (they can be discarded by the layout transition and no
+  // ownership transfer is performed)
+  pub discard: bool,
+}
+
+impl Usage {
+  pub fn new(
+    queue_family: u32,
+    stage_mask: vk::PipelineStageFlags2,
+    access_mask: vk::AccessFlags2,
+  ) -> Self {
+    Self {
+      queue_family,
+      stage_mask,
+      access_mask,
+      discard: false,
+    }
+  }
+
+  pub fn discarding(self) -> Self {
+    Self {
+      discard: true,
+      ..self
+    }
+  }
+}
+
+// state of a resource after the last declared usage
+#[derive(Clone, Copy, Debug)]
+struct State {
+  // None if the resource wasn't used yet
+  queue_family: Option<u32>,
+  layout: vk::ImageLayout,
+  // stages and accesses of the last write (layout transitions count as writes without an access)
+  write_stages: vk::PipelineStageFlags2,
+  write_access: vk::AccessFlags2,
+  // stages that read the resource since the last write
+  read_stages: vk::PipelineStageFlags2,
+  // stages and accesses the last write is visible to
+  visible_stages: vk::PipelineStageFlags2,
+  visible_access: vk::AccessFlags2,
+}
+
+impl State {
+  const UNUSED: Self = Self {
+    queue_family: None,
+    layout: vk::ImageLayout::UNDEFINED,
+    write_stages: vk::PipelineStageFlags2::NONE,
+    write_access: vk::AccessFlags2::NONE,
+    read_stages: vk::PipelineStageFlags2::NONE,
+    visible_stages: vk::PipelineStageFlags2::NONE,
+    visible_access: vk::AccessFlags2::NONE,
+  };
+}
+
+// barrier fields that are shared between images and buffers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Dependency {
+  src_stage_mask: vk::PipelineStageFlags2,
+  src_access_mask: vk::AccessFlags2,
+  dst_stage_mask: vk::PipelineStageFlags2,
+  dst_access_mask: vk::AccessFlags2,
+  old_layout: vk::ImageLayout,
+  new_layout: vk::ImageLayout,
+  src_queue_family_index: u32,
+  dst_queue_family_index: u32,
+}
+
+#[derive(Default)]
+struct Dependencies {
+  // recorded in the previous family
+  release: Option<Dependency>,
+  // recorded before the command
+  barrier: Option<Dependency>,
+}
+
+// updates state to the usage and returns the needed dependencies
+// layout should be UNDEFINED for buffers
+fn transition(state: &mut State, usage: &Usage, layout: vk::ImageLayout) -> Dependencies {
+  let mut deps = Dependencies::default();
+  let old_layout = if usage.discard {
+    vk::ImageLayout::UNDEFINED
+  } else {
+    state.layout
+  };
+  let layout_change = old_layout != layout;
+  let writes = is_write(usage.access_mask);
+  let previous_family = state.queue_family;
+  let other_family = previous_family.is_some_and(|family| family != usage.queue_family);
+
+  if other_family && !usage.discard {
+    // paired release and acquire, the layout transition happens once in between them
+    let families = (previous_family.unwrap(), usage.queue_family);
+    deps.release = Some(Dependency {
+      src_stage_mask: state.write_stages | state.read_stages,
+      src_access_mask: state.write_access,
+      // NONE for ownership release
+      dst_stage_mask: vk::PipelineStageFlags2::NONE,
+      dst_access_mask: vk::AccessFlags2::NONE,
+      old_layout,
+      new_layout: layout,
+      src_queue_family_index: families.0,
+      dst_queue_family_index: families.1,
+    });
+    // the semaphore that orders both halves should be waited for at the usage stages, so the
+    // acquire chains with it
+    deps.barrier = Some(Dependency {
+      src_stage_mask: usage.stage_mask,
+      src_access_mask: vk::AccessFlags2::NONE, // NONE for ownership acquire
+      dst_stage_mask: usage.stage_mask,
+      dst_access_mask: usage.access_mask,
+      old_layout,
+      new_layout: layout,
+      src_queue_family_index: families.0,
+      dst_queue_family_index: families.1,
+    });
+  } else {
+    let (src_stage_mask, src_access_mask) = if other_family {
+      // previous accesses happened in another queue, so they have to be waited for with a semaphore
+      // at the usage stages, which the layout transition has to chain with
+      (usage.stage_mask, vk::AccessFlags2::NONE)
+    } else if writes || layout_change {
+      // write after read only needs an execution dependency
+      (state.write_stages | state.read_stages, state.write_access)
+    } else {
+      (state.write_stages, state.write_access)
+    };
+    let already_visible = state.visible_stages.contains(usage.stage_mask)
+      && state.visible_access.contains(usage.access_mask);
+    let needed = if other_family {
+      layout_change
+    } else {
+      layout_change
+        || (writes && !src_stage_mask.is_empty())
+        || (!writes && !state.write_stages.is_empty() && !already_visible)
+    };
+    if needed {
+      deps.barrier = Some(Dependency {
+        src_stage_mask,
+        src_access_mask,
+        dst_stage_mask: usage.stage_mask,
+        dst_access_mask: usage.access_mask,
+        old_layout,
+        new_layout: layout,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+      });
+    }
+  }
+
+  state.queue_family = Some(usage.queue_family);
+  state.layout = layout;
+  if writes {
+    state.write_stages = usage.stage_mask;
+    state.write_access = usage.access_mask & WRITE_ACCESS;
+    state.read_stages = vk::PipelineStageFlags2::NONE;
+    state.visible_stages = vk::PipelineStageFlags2::NONE;
+    state.visible_access = vk::AccessFlags2::NONE;
+  } else {
+    if deps.barrier.is_some() {
+      if layout_change || other_family {
+        // the transition (or acquire) is a write that is only visible to this usage
+        state.write_stages = usage.stage_mask;
+        state.write_access = vk::AccessFlags2::NONE;
+        state.read_stages = vk::PipelineStageFlags2::NONE;
+        state.visible_stages = vk::PipelineStageFlags2::NONE;
+        state.visible_access = vk::AccessFlags2::NONE;
+      }
+      state.visible_stages |= usage.stage_mask;
+      state.visible_access |= usage.access_mask;
+    }
+    state.read_stages |= usage.stage_mask;
+  }
+  deps
+}
+
+// barriers to be recorded with a single vkCmdPipelineBarrier2
+#[derive(Clone, Debug, Default)]
+pub struct Barriers {
+  pub buffer: Vec<vk::BufferMemoryBarrier2>,
+  pub image: Vec<vk::ImageMemoryBarrier2>,
+}
+
+impl Barriers {
+  pub fn is_empty(&self) -> bool {
+    self.buffer.is_empty() && self.image.is_empty()
+  }
+
+  pub fn extend(&mut self, other: Barriers) {
+    self.buffer.extend(other.buffer);
+    self.image.extend(other.image);
+  }
+
+  // points to self
+  pub fn dependency_info(&self) -> vk::DependencyInfo {
+    vk::DependencyInfo {
+      s_type: vk::StructureType::DEPENDENCY_INFO,
+      p_next: ptr::null(),
+      dependency_flags: vk::DependencyFlags::empty(),
+      memory_barrier_count: 0,
+      p_memory_barriers: ptr::null(),
+      buffer_memory_barrier_count: self.buffer.len() as u32,
+      p_buffer_memory_barriers: self.buffer.as_ptr(),
+      image_memory_barrier_count: self.image.len() as u32,
+      p_image_memory_barriers: self.image.as_ptr(),
+    }
+  }
+
+  // does nothing if there are no barriers
+  pub unsafe fn record(&self, device: &ash::Device, command_buffer: vk::CommandBuffer) {
+    if !self.is_empty() {
+      device.cmd_pipeline_barrier2(command_buffer, &self.dependency_info());
+    }
+  }
+}
+
+// barriers needed by one or more usages
+#[derive(Clone, Debug, Default)]
+pub struct Transition {
+  // ownership releases, to be recorded in a command buffer of the previous queue family after its
+  // last use of the resources and ordered before this usage with a semaphore
+  pub release: Barriers,
+  // to be recorded before the command that uses the resources
+  pub barriers: Barriers,
+}
+
+impl Transition {
+  pub fn and(mut self, other: Transition) -> Self {
+    self.release.extend(other.release);
+    self.barriers.extend(other.barriers);
+    self
+  }
+}
+
+struct TrackedImage {
+  subresource_range: vk::ImageSubresourceRange,
+  state: State,
+}
+
+// Records the state of images and buffers, see the module comment
+//
+// Resources should have EXCLUSIVE sharing, and all of their usages should be declared in the
+// order in which they execute.
+// Images are tracked as a whole (with the range given when tracking them).
+#[derive(Default)]
+pub struct ResourceTracker {
+  images: HashMap<vk::Image, TrackedImage>,
+  buffers: HashMap<vk::Buffer, State>,
+}
+
+impl ResourceTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  // image starts in the UNDEFINED layout without being owned by any queue family
+  pub fn track_image(&mut self, image: vk::Image, subresource_range: vk::ImageSubresourceRange) {
+    self.images.insert(
+      image,
+      TrackedImage {
+        subresource_range,
+        state: State::UNUSED,
+      },
+    );
+  }
+
+  pub fn track_buffer(&mut self, buffer: vk::Buffer) {
+    self.buffers.insert(buffer, State::UNUSED);
+  }
+
+  pub fn untrack_image(&mut self, image: vk::Image) {
+    self.images.remove(&image);
+  }
+
+  pub fn untrack_buffer(&mut self, buffer: vk::Buffer) {
+    self.buffers.remove(&buffer);
+  }
+
+  // current layout of a tracked image
+  pub fn image_layout(&self, image: vk::Image) -> vk::ImageLayout {
+    self.images[&image].state.layout
+  }
+
+  // declares that image is going to be used in layout
+  // panics if the image is not tracked
+  pub fn use_image(
+    &mut self,
+    image: vk::Image,
+    layout: vk::ImageLayout,
+    usage: Usage,
+  ) -> Transition {
+    let tracked = self.images.get_mut(&image).expect("Image is not tracked");
+    let deps = transition(&mut tracked.state, &usage, layout);
+    let range = tracked.subresource_range;
+    let barrier = |dep: Dependency| vk::ImageMemoryBarrier2 {
+      s_type: vk::StructureType::IMAGE_MEMORY_BARRIER_2,
+      p_next: ptr::null(),
+      src_stage_mask: dep.src_stage_mask,
+      src_access_mask: dep.src_access_mask,
+      dst_stage_mask: dep.dst_stage_mask,
+      dst_access_mask: dep.dst_access_mask,
+      old_layout: dep.old_layout,
+      new_layout: dep.new_layout,
+      src_queue_family_index: dep.src_queue_family_index,
+      dst_queue_family_index: dep.dst_queue_family_index,
+      image,
+      subresource_range: range,
+    };
+    Transition {
+      release: Barriers {
+        buffer: Vec::new(),
+        image: deps.release.map(barrier).into_iter().collect(),
+      },
+      barriers: Barriers {
+        buffer: Vec::new(),
+        image: deps.barrier.map(barrier).into_iter().collect(),
+      },
+    }
+  }
+
+  // declares that the whole buffer is going to be used
+  // panics if the buffer is not tracked
+  pub fn use_buffer(&mut self, buffer: vk::Buffer, usage: Usage) -> Transition {
+    let state = self
+      .buffers
+      .get_mut(&buffer)
+      .expect("Buffer is not tracked");
+    let deps = transition(state, &usage, vk::ImageLayout::UNDEFINED);
+    let barrier = |dep: Dependency| vk::BufferMemoryBarrier2 {
+      s_type: vk::StructureType::BUFFER_MEMORY_BARRIER_2,
+      p_next: ptr::null(),
+      src_stage_mask: dep.src_stage_mask,
+      src_access_mask: dep.src_access_mask,
+      dst_stage_mask: dep.dst_stage_mask,
+      dst_access_mask: dep.dst_access_mask,
+      src_queue_family_index: dep.src_queue_family_index,
+      dst_queue_family_index: dep.dst_queue_family_index,
+      buffer,
+      offset: 0,
+      size: vk::WHOLE_SIZE,
+    };
+    Transition {
+      release: Barriers {
+        buffer: deps.release.map(barrier).into_iter().collect(),
+        image: Vec::new(),
+      },
+      barriers: Barriers {
+        buffer: deps.barrier.map(barrier).into_iter().collect(),
+        image: Vec::new(),
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const COMPUTE: u32 = 1;
+  const TRANSFER: u32 = 2;
+
+  fn clear() -> Usage {
+    Usage::new(
+      COMPUTE,
+      vk::PipelineStageFlags2::CLEAR,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    )
+    .discarding()
+  }
+
+  fn copy_src(family: u32) -> Usage {
+    Usage::new(
+      family,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_READ,
+    )
+  }
+
+  fn fragment_read(family: u32) -> Usage {
+    Usage::new(
+      family,
+      vk::PipelineStageFlags2::FRAGMENT_SHADER,
+      vk::AccessFlags2::SHADER_SAMPLED_READ,
+    )
+  }
+
+  fn run(state: &mut State, usage: Usage, layout: vk::ImageLayout) -> Dependencies {
+    transition(state, &usage, layout)
+  }
+
+  #[test]
+  fn first_use_transitions_from_undefined() {
+    let mut state = State::UNUSED;
+    let deps = run(&mut state, clear(), vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    assert!(deps.release.is_none());
+    let barrier = deps.barrier.unwrap();
+    assert_eq!(barrier.src_stage_mask, vk::PipelineStageFlags2::NONE);
+    assert_eq!(barrier.old_layout, vk::ImageLayout::UNDEFINED);
+    assert_eq!(barrier.new_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    assert_eq!(barrier.dst_stage_mask, vk::PipelineStageFlags2::CLEAR);
+    assert_eq!(barrier.src_queue_family_index, vk::QUEUE_FAMILY_IGNORED);
+  }
+
+  #[test]
+  fn different_families_get_paired_release_and_acquire() {
+    let mut state = State::UNUSED;
+    run(&mut state, clear(), vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    let deps = run(
+      &mut state,
+      copy_src(TRANSFER),
+      vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    );
+
+    let release = deps.release.unwrap();
+    let acquire = deps.barrier.unwrap();
+    assert_eq!(release.src_stage_mask, vk::PipelineStageFlags2::CLEAR);
+    assert_eq!(release.src_access_mask, vk::AccessFlags2::TRANSFER_WRITE);
+    assert_eq!(release.dst_access_mask, vk::AccessFlags2::NONE);
+    assert_eq!(acquire.src_access_mask, vk::AccessFlags2::NONE);
+    assert_eq!(acquire.dst_stage_mask, vk::PipelineStageFlags2::COPY);
+    assert_eq!(acquire.dst_access_mask, vk::AccessFlags2::TRANSFER_READ);
+    // both halves have to match
+    for dep in [release, acquire] {
+      assert_eq!(dep.old_layout, vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+      assert_eq!(dep.new_layout, vk::ImageLayout::TRANSFER_SRC_OPTIMAL);
+      assert_eq!(dep.src_queue_family_index, COMPUTE);
+      assert_eq!(dep.dst_queue_family_index, TRANSFER);
+    }
+  }
+
+  #[test]
+  fn discarding_contents_skips_the_ownership_transfer() {
+    let mut state = State::UNUSED;
+    run(&mut state, clear(), vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    run(
+      &mut state,
+      copy_src(TRANSFER),
+      vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+    );
+
+    // clearing again in the compute queue
+    let deps = run(&mut state, clear(), vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    assert!(deps.release.is_none());
+    let barrier = deps.barrier.unwrap();
+    assert_eq!(barrier.old_layout, vk::ImageLayout::UNDEFINED);
+    // chains with the semaphore that waits for the copy
+    assert_eq!(barrier.src_stage_mask, vk::PipelineStageFlags2::CLEAR);
+    assert_eq!(barrier.src_access_mask, vk::AccessFlags2::NONE);
+    assert_eq!(barrier.src_queue_family_index, vk::QUEUE_FAMILY_IGNORED);
+  }
+
+  #[test]
+  fn reads_in_the_same_layout_only_wait_for_the_write_once() {
+    let mut state = State::UNUSED;
+    run(&mut state, clear(), vk::ImageLayout::TRANSFER_DST_OPTIMAL);
+    let transition_deps = run(
+      &mut state,
+      fragment_read(COMPUTE),
+      vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    );
+    assert_eq!(
+      transition_deps.barrier.unwrap().src_access_mask,
+      vk::AccessFlags2::TRANSFER_WRITE
+    );
+
+    let deps = run(
+      &mut state,
+      fragment_read(COMPUTE),
+      vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    );
+    assert!(deps.release.is_none() && deps.barrier.is_none());
+
+    // a read in a stage the transition is not visible to still needs a dependency
+    let compute_read = Usage::new(
+      COMPUTE,
+      vk::PipelineStageFlags2::COMPUTE_SHADER,
+      vk::AccessFlags2::SHADER_SAMPLED_READ,
+    );
+    let deps = run(
+      &mut state,
+      compute_read,
+      vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    );
+    let barrier = deps.barrier.unwrap();
+    assert_eq!(
+      barrier.src_stage_mask,
+      vk::PipelineStageFlags2::FRAGMENT_SHADER
+    );
+    assert_eq!(barrier.src_access_mask, vk::AccessFlags2::NONE);
+  }
+
+  #[test]
+  fn writes_wait_for_previous_reads() {
+    let mut state = State::UNUSED;
+    let buffer_write = Usage::new(
+      TRANSFER,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    );
+    let undefined = vk::ImageLayout::UNDEFINED;
+    // contents written by the host before the first submission
+    assert!(run(&mut state, copy_src(TRANSFER), undefined)
+      .barrier
+      .is_none());
+
+    // write after read: execution dependency on the read only
+    let barrier = run(&mut state, buffer_write, undefined).barrier.unwrap();
+    assert_eq!(barrier.src_stage_mask, vk::PipelineStageFlags2::COPY);
+    assert_eq!(barrier.src_access_mask, vk::AccessFlags2::NONE);
+    assert_eq!(barrier.dst_access_mask, vk::AccessFlags2::TRANSFER_WRITE);
+  }
+
+  #[test]
+  fn writes_wait_for_previous_reads_and_writes() {
+    let mut state = State::UNUSED;
+    let buffer_write = Usage::new(
+      TRANSFER,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    );
+    let host_read = Usage::new(
+      TRANSFER,
+      vk::PipelineStageFlags2::HOST,
+      vk::AccessFlags2::HOST_READ,
+    );
+    let undefined = vk::ImageLayout::UNDEFINED;
+    assert!(run(&mut state, buffer_write, undefined).barrier.is_none());
+
+    let barrier = run(&mut state, host_read, undefined).barrier.unwrap();
+    assert_eq!(barrier.src_stage_mask, vk::PipelineStageFlags2::COPY);
+    assert_eq!(barrier.src_access_mask, vk::AccessFlags2::TRANSFER_WRITE);
+    assert_eq!(barrier.dst_access_mask, vk::AccessFlags2::HOST_READ);
+
+    // write after write: the first write was only made available to the host, so it still has
+    // to be made available again, and the read only needs an execution dependency
+    let barrier = run(&mut state, buffer_write, undefined).barrier.unwrap();
+    assert_eq!(
+      barrier.src_stage_mask,
+      vk::PipelineStageFlags2::COPY | vk::PipelineStageFlags2::HOST
+    );
+    assert_eq!(barrier.src_access_mask, vk::AccessFlags2::TRANSFER_WRITE);
+  }
+}
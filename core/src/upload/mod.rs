@@ -0,0 +1,734 @@
+// Streaming of buffer and image data into device local resources
+//
+// Data is written into a persistently mapped staging ring buffer and copied by the transfer queue
+// in batches. If the resources are used by another queue family, the manager also releases them
+// from the transfer family and acquires them in the destination family, so they are ready to be
+// used there once the batch completes.
+// Completion is signaled through a timeline semaphore, which can either be waited for by the host
+// or by other submissions, so new data can be streamed in while rendering.
+
+mod ring;
+
+pub use ring::StagingRing;
+
+use std::{collections::VecDeque, fmt, ptr, sync::Arc};
+
+use ash::vk;
+
+use crate::{
+  memory::{align_up, AllocatorError, MappedMemory, OwnedAllocation, SharedAllocator},
+  utility::error_chain_fmt,
+  Device, Owned, PhysicalDevice,
+};
+
+pub enum UploadError {
+  // the data doesn't fit in the staging ring, even if empty
+  TooBig { size: u64, capacity: u64 },
+  Allocator(AllocatorError),
+  Vulkan(vk::Result),
+}
+
+impl fmt::Display for UploadError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::TooBig { size, capacity } => write!(
+        f,
+        "Upload size ({}) is bigger than the staging buffer ({})",
+        size, capacity
+      ),
+      Self::Allocator(_) => write!(f, "Failed to allocate the staging buffer"),
+      Self::Vulkan(err) => write!(f, "Failed to record or submit an upload: {:?}", err),
+    }
+  }
+}
+
+impl fmt::Debug for UploadError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl std::error::Error for UploadError {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Self::Allocator(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+impl From<vk::Result> for UploadError {
+  fn from(value: vk::Result) -> Self {
+    UploadError::Vulkan(value)
+  }
+}
+
+impl From<AllocatorError> for UploadError {
+  fn from(value: AllocatorError) -> Self {
+    UploadError::Allocator(value)
+  }
+}
+
+// queue (and its family) that executes the copies or that uses the uploaded resources
+#[derive(Clone, Copy, Debug)]
+pub struct UploadQueue {
+  pub queue: vk::Queue,
+  pub family: u32,
+}
+
+// how a resource is going to be used after the upload
+// the upload is made visible to these stages and accesses in the destination queue
+#[derive(Clone, Copy, Debug)]
+pub struct UploadDestination {
+  pub stage_mask: vk::PipelineStageFlags2,
+  pub access_mask: vk::AccessFlags2,
+}
+
+struct BufferUpload {
+  src_offset: u64,
+  buffer: vk::Buffer,
+  offset: u64,
+  size: u64,
+  destination: UploadDestination,
+}
+
+// whole first mip level and array layer of a color image
+struct ImageUpload {
+  src_offset: u64,
+  image: vk::Image,
+  extent: vk::Extent3D,
+  final_layout: vk::ImageLayout,
+  destination: UploadDestination,
+}
+
+#[derive(Clone, Copy)]
+struct BatchCommandBuffers {
+  transfer: vk::CommandBuffer,
+  // None if the transfer and destination families are the same
+  acquire: Option<vk::CommandBuffer>,
+}
+
+const SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  base_mip_level: 0,
+  level_count: 1,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// Uploads data through a staging ring buffer in batches, see the module comment
+// Requires the timelineSemaphore and synchronization2 features
+//
+// Each batch signals two timeline values: the first when the copies are done (waited by the
+// acquire submission) and the second when the resources are ready in the destination family.
+// Upload functions return the second one.
+// Destination resources should be created with EXCLUSIVE sharing and not be in use during their
+// upload. Images are transitioned from UNDEFINED, so their previous contents are discarded.
+pub struct UploadManager {
+  // command buffers of submitted batches with the value that signals their completion
+  in_flight: VecDeque<(u64, BatchCommandBuffers)>,
+  free_command_buffers: Vec<BatchCommandBuffers>,
+  buffer_uploads: Vec<BufferUpload>,
+  image_uploads: Vec<ImageUpload>,
+  ring: StagingRing,
+  // of offsets in the ring, satisfies all buffer to image copy requirements
+  alignment: u64,
+  mapped: MappedMemory,
+  // value that gets signaled by the last submitted batch
+  last_value: u64,
+
+  timeline: Owned<vk::Semaphore>,
+  // command buffers are freed with their pools
+  transfer_pool: Owned<vk::CommandPool>,
+  acquire_pool: Option<Owned<vk::CommandPool>>,
+  // the buffer is dropped before its allocation
+  staging: Owned<vk::Buffer>,
+  _staging_allocation: OwnedAllocation,
+  transfer: UploadQueue,
+  destination: UploadQueue,
+  device: Arc<Device>,
+}
+
+impl UploadManager {
+  pub fn new(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+    allocator: &Arc<SharedAllocator>,
+    transfer: UploadQueue,
+    destination: UploadQueue,
+    capacity: u64,
+  ) -> Result<Self, UploadError> {
+    // 16 is the biggest texel block size of uncompressed formats, and is a multiple of 4
+    let alignment = physical_device
+      .properties
+      .p10
+      .limits
+      .optimal_buffer_copy_offset_alignment
+      .max(16)
+      .next_power_of_two();
+    let capacity = align_up(capacity, alignment);
+
+    log::debug!("Creating staging ring buffer with {} bytes", capacity);
+    let staging = create_staging_buffer(device, capacity)?;
+    let staging_allocation = unsafe {
+      allocator.allocate_buffer(
+        *staging,
+        vk::MemoryPropertyFlags::HOST_VISIBLE,
+        vk::MemoryPropertyFlags::empty(),
+      )?
+    };
+    let mapped = staging_allocation
+      .mapped()
+      .expect("Host visible allocations are always mapped")
+      .slice(0, capacity);
+
+    let timeline = create_timeline_semaphore(device)?;
+    let transfer_pool = create_command_pool(device, transfer.family)?;
+    let acquire_pool = if transfer.family != destination.family {
+      Some(create_command_pool(device, destination.family)?)
+    } else {
+      None
+    };
+
+    Ok(Self {
+      in_flight: VecDeque::new(),
+      free_command_buffers: Vec::new(),
+      buffer_uploads: Vec::new(),
+      image_uploads: Vec::new(),
+      ring: StagingRing::new(capacity),
+      alignment,
+      mapped,
+      last_value: 0,
+
+      timeline,
+      transfer_pool,
+      acquire_pool,
+      staging,
+      _staging_allocation: staging_allocation,
+      transfer,
+      destination,
+      device: device.clone(),
+    })
+  }
+
+  // can be waited for by other submissions, with the values returned by this manager
+  pub fn semaphore(&self) -> vk::Semaphore {
+    *self.timeline
+  }
+
+  pub fn completed_value(&self) -> Result<u64, vk::Result> {
+    unsafe { self.device.get_semaphore_counter_value(*self.timeline) }
+  }
+
+  // blocks until the batch that returned value completes
+  // value should have been submitted with flush, otherwise this never returns
+  pub fn wait(&self, value: u64) -> Result<(), vk::Result> {
+    let wait_info = vk::SemaphoreWaitInfo {
+      s_type: vk::StructureType::SEMAPHORE_WAIT_INFO,
+      p_next: ptr::null(),
+      flags: vk::SemaphoreWaitFlags::empty(),
+      semaphore_count: 1,
+      p_semaphores: &*self.timeline,
+      p_values: &value,
+    };
+    unsafe { self.device.wait_semaphores(&wait_info, u64::MAX) }
+  }
+
+  // flushes pending uploads and waits for all of them to complete
+  pub fn flush_and_wait(&mut self) -> Result<(), UploadError> {
+    let value = self.flush()?;
+    self.wait(value)?;
+    self.reclaim()?;
+    Ok(())
+  }
+
+  // queues a copy of data into buffer at offset
+  // returns the value that gets signaled when the copy is complete (after being flushed)
+  pub fn upload_buffer(
+    &mut self,
+    data: &[u8],
+    buffer: vk::Buffer,
+    offset: u64,
+    destination: UploadDestination,
+  ) -> Result<u64, UploadError> {
+    if data.is_empty() {
+      return Ok(self.last_value);
+    }
+    let src_offset = self.write_staging(data)?;
+    self.buffer_uploads.push(BufferUpload {
+      src_offset,
+      buffer,
+      offset,
+      size: data.len() as u64,
+      destination,
+    });
+    Ok(self.batch_value())
+  }
+
+  // queues a copy of tightly packed texels into the first mip level and array layer of a color
+  // image, which is left in final_layout
+  // returns the value that gets signaled when the copy is complete (after being flushed)
+  pub fn upload_image(
+    &mut self,
+    data: &[u8],
+    image: vk::Image,
+    extent: vk::Extent3D,
+    final_layout: vk::ImageLayout,
+    destination: UploadDestination,
+  ) -> Result<u64, UploadError> {
+    assert!(!data.is_empty());
+    let src_offset = self.write_staging(data)?;
+    self.image_uploads.push(ImageUpload {
+      src_offset,
+      image,
+      extent,
+      final_layout,
+      destination,
+    });
+    Ok(self.batch_value())
+  }
+
+  // value that will be signaled by the batch currently being filled
+  fn batch_value(&self) -> u64 {
+    self.last_value + 2
+  }
+
+  // frees the staging space and command buffers of completed batches
+  pub fn reclaim(&mut self) -> Result<(), vk::Result> {
+    let completed = self.completed_value()?;
+    self.ring.release(completed);
+    while let Some(&(value, command_buffers)) = self.in_flight.front() {
+      if value > completed {
+        break;
+      }
+      self.free_command_buffers.push(command_buffers);
+      self.in_flight.pop_front();
+    }
+    Ok(())
+  }
+
+  // copies data into free staging space, flushing and waiting for older batches if the ring is
+  // full
+  fn write_staging(&mut self, data: &[u8]) -> Result<u64, UploadError> {
+    let size = data.len() as u64;
+    if size > self.ring.capacity() {
+      return Err(UploadError::TooBig {
+        size,
+        capacity: self.ring.capacity(),
+      });
+    }
+
+    self.reclaim()?;
+    let offset = loop {
+      if let Some(offset) = self.ring.allocate(size, self.alignment) {
+        break offset;
+      }
+      if self.ring.has_unsubmitted() {
+        self.flush()?;
+      }
+      // the ring is not empty, otherwise the allocation would have succeeded
+      let oldest = self.ring.oldest_pending().unwrap();
+      log::debug!("Staging ring buffer is full, waiting for upload {}", oldest);
+      self.wait(oldest)?;
+      self.reclaim()?;
+    };
+
+    unsafe { self.mapped.write(&self.device, offset, data)? };
+    Ok(offset)
+  }
+
+  // records and submits all queued uploads
+  // returns the value that gets signaled when they complete
+  // after a Vulkan error the manager should not be used anymore (apart from being dropped)
+  pub fn flush(&mut self) -> Result<u64, UploadError> {
+    if self.buffer_uploads.is_empty() && self.image_uploads.is_empty() {
+      return Ok(self.last_value);
+    }
+
+    let command_buffers = self.get_command_buffers()?;
+    unsafe {
+      self.record_transfer(command_buffers.transfer)?;
+      if let Some(acquire) = command_buffers.acquire {
+        self.record_acquire(acquire)?;
+      }
+    }
+
+    let copied = self.last_value + 1;
+    let ready = self.last_value + 2;
+    log::debug!(
+      "Submitting {} buffer and {} image uploads",
+      self.buffer_uploads.len(),
+      self.image_uploads.len()
+    );
+    unsafe {
+      let transfer_signal = if command_buffers.acquire.is_some() {
+        copied
+      } else {
+        ready
+      };
+      self.submit(
+        self.transfer.queue,
+        command_buffers.transfer,
+        None,
+        transfer_signal,
+      )?;
+      // the transfer may still signal its value if the acquire submit fails, so waiting on
+      // last_value (for example when dropping) should include it
+      self.last_value = transfer_signal;
+      if let Some(acquire) = command_buffers.acquire {
+        self.submit(self.destination.queue, acquire, Some(copied), ready)?;
+      }
+    }
+
+    self.last_value = ready;
+    self.ring.submit(ready);
+    self.in_flight.push_back((ready, command_buffers));
+    self.buffer_uploads.clear();
+    self.image_uploads.clear();
+    Ok(ready)
+  }
+
+  fn get_command_buffers(&mut self) -> Result<BatchCommandBuffers, vk::Result> {
+    if let Some(command_buffers) = self.free_command_buffers.pop() {
+      return Ok(command_buffers);
+    }
+    log::debug!("Allocating upload command buffers");
+    let transfer = allocate_command_buffer(&self.device, *self.transfer_pool)?;
+    let acquire = match &self.acquire_pool {
+      Some(pool) => Some(allocate_command_buffer(&self.device, **pool)?),
+      None => None,
+    };
+    Ok(BatchCommandBuffers { transfer, acquire })
+  }
+
+  // barrier that makes an upload available to the destination, releasing it from the transfer
+  // family if the families differ
+  fn release_masks(&self, destination: UploadDestination) -> UploadDestination {
+    if self.acquire_pool.is_some() {
+      // NONE for ownership release
+      UploadDestination {
+        stage_mask: vk::PipelineStageFlags2::NONE,
+        access_mask: vk::AccessFlags2::NONE,
+      }
+    } else {
+      destination
+    }
+  }
+
+  fn families(&self) -> (u32, u32) {
+    if self.acquire_pool.is_some() {
+      (self.transfer.family, self.destination.family)
+    } else {
+      (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED)
+    }
+  }
+
+  unsafe fn record_transfer(&self, cb: vk::CommandBuffer) -> Result<(), vk::Result> {
+    begin_command_buffer(&self.device, cb)?;
+
+    let to_transfer_dst: Vec<vk::ImageMemoryBarrier2> = self
+      .image_uploads
+      .iter()
+      .map(|upload| vk::ImageMemoryBarrier2 {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER_2,
+        p_next: ptr::null(),
+        src_stage_mask: vk::PipelineStageFlags2::NONE,
+        dst_stage_mask: vk::PipelineStageFlags2::COPY,
+        src_access_mask: vk::AccessFlags2::NONE,
+        dst_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+        old_layout: vk::ImageLayout::UNDEFINED,
+        new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        src_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        dst_queue_family_index: vk::QUEUE_FAMILY_IGNORED,
+        image: upload.image,
+        subresource_range: SUBRESOURCE_RANGE,
+      })
+      .collect();
+    if !to_transfer_dst.is_empty() {
+      self
+        .device
+        .cmd_pipeline_barrier2(cb, &dependency_info(&[], &to_transfer_dst));
+    }
+
+    for upload in self.buffer_uploads.iter() {
+      let region = vk::BufferCopy {
+        src_offset: upload.src_offset,
+        dst_offset: upload.offset,
+        size: upload.size,
+      };
+      self
+        .device
+        .cmd_copy_buffer(cb, *self.staging, upload.buffer, &[region]);
+    }
+    for upload in self.image_uploads.iter() {
+      // tightly packed
+      let region = vk::BufferImageCopy {
+        buffer_offset: upload.src_offset,
+        buffer_row_length: 0,
+        buffer_image_height: 0,
+        image_subresource: vk::ImageSubresourceLayers {
+          aspect_mask: vk::ImageAspectFlags::COLOR,
+          mip_level: 0,
+          base_array_layer: 0,
+          layer_count: 1,
+        },
+        image_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        image_extent: upload.extent,
+      };
+      self.device.cmd_copy_buffer_to_image(
+        cb,
+        *self.staging,
+        upload.image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[region],
+      );
+    }
+
+    // make the copies available to the destination (or release them to its family)
+    // the image layout transition happens once, in between the release and the acquire
+    let (src_family, dst_family) = self.families();
+    let buffer_barriers: Vec<vk::BufferMemoryBarrier2> = self
+      .buffer_uploads
+      .iter()
+      .map(|upload| {
+        let dst = self.release_masks(upload.destination);
+        vk::BufferMemoryBarrier2 {
+          s_type: vk::StructureType::BUFFER_MEMORY_BARRIER_2,
+          p_next: ptr::null(),
+          src_stage_mask: vk::PipelineStageFlags2::COPY,
+          dst_stage_mask: dst.stage_mask,
+          src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+          dst_access_mask: dst.access_mask,
+          src_queue_family_index: src_family,
+          dst_queue_family_index: dst_family,
+          buffer: upload.buffer,
+          offset: upload.offset,
+          size: upload.size,
+        }
+      })
+      .collect();
+    let image_barriers: Vec<vk::ImageMemoryBarrier2> = self
+      .image_uploads
+      .iter()
+      .map(|upload| {
+        let dst = self.release_masks(upload.destination);
+        vk::ImageMemoryBarrier2 {
+          s_type: vk::StructureType::IMAGE_MEMORY_BARRIER_2,
+          p_next: ptr::null(),
+          src_stage_mask: vk::PipelineStageFlags2::COPY,
+          dst_stage_mask: dst.stage_mask,
+          src_access_mask: vk::AccessFlags2::TRANSFER_WRITE,
+          dst_access_mask: dst.access_mask,
+          old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+          new_layout: upload.final_layout,
+          src_queue_family_index: src_family,
+          dst_queue_family_index: dst_family,
+          image: upload.image,
+          subresource_range: SUBRESOURCE_RANGE,
+        }
+      })
+      .collect();
+    self
+      .device
+      .cmd_pipeline_barrier2(cb, &dependency_info(&buffer_barriers, &image_barriers));
+
+    self.device.end_command_buffer(cb)
+  }
+
+  // acquires all uploads in the destination family
+  unsafe fn record_acquire(&self, cb: vk::CommandBuffer) -> Result<(), vk::Result> {
+    begin_command_buffer(&self.device, cb)?;
+
+    let (src_family, dst_family) = self.families();
+    let buffer_barriers: Vec<vk::BufferMemoryBarrier2> = self
+      .buffer_uploads
+      .iter()
+      .map(|upload| vk::BufferMemoryBarrier2 {
+        s_type: vk::StructureType::BUFFER_MEMORY_BARRIER_2,
+        p_next: ptr::null(),
+        // the semaphore is waited for at all stages, so the acquire (and the layout transition)
+        // chains with it at the destination stages
+        src_stage_mask: upload.destination.stage_mask,
+        dst_stage_mask: upload.destination.stage_mask,
+        src_access_mask: vk::AccessFlags2::NONE,
+        dst_access_mask: upload.destination.access_mask,
+        src_queue_family_index: src_family,
+        dst_queue_family_index: dst_family,
+        buffer: upload.buffer,
+        offset: upload.offset,
+        size: upload.size,
+      })
+      .collect();
+    let image_barriers: Vec<vk::ImageMemoryBarrier2> = self
+      .image_uploads
+      .iter()
+      .map(|upload| vk::ImageMemoryBarrier2 {
+        s_type: vk::StructureType::IMAGE_MEMORY_BARRIER_2,
+        p_next: ptr::null(),
+        src_stage_mask: upload.destination.stage_mask,
+        dst_stage_mask: upload.destination.stage_mask,
+        src_access_mask: vk::AccessFlags2::NONE,
+        dst_access_mask: upload.destination.access_mask,
+        old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        new_layout: upload.final_layout,
+        src_queue_family_index: src_family,
+        dst_queue_family_index: dst_family,
+        image: upload.image,
+        subresource_range: SUBRESOURCE_RANGE,
+      })
+      .collect();
+    self
+      .device
+      .cmd_pipeline_barrier2(cb, &dependency_info(&buffer_barriers, &image_barriers));
+
+    self.device.end_command_buffer(cb)
+  }
+
+  unsafe fn submit(
+    &self,
+    queue: vk::Queue,
+    cb: vk::CommandBuffer,
+    wait_value: Option<u64>,
+    signal_value: u64,
+  ) -> Result<(), vk::Result> {
+    let semaphore_info = |value| vk::SemaphoreSubmitInfo {
+      s_type: vk::StructureType::SEMAPHORE_SUBMIT_INFO,
+      p_next: ptr::null(),
+      semaphore: *self.timeline,
+      value,
+      stage_mask: vk::PipelineStageFlags2::ALL_COMMANDS,
+      device_index: 0,
+    };
+    let wait = wait_value.map(semaphore_info);
+    let signal = semaphore_info(signal_value);
+    let cb_info = vk::CommandBufferSubmitInfo {
+      s_type: vk::StructureType::COMMAND_BUFFER_SUBMIT_INFO,
+      p_next: ptr::null(),
+      command_buffer: cb,
+      device_mask: 0,
+    };
+    let submit_info = vk::SubmitInfo2 {
+      s_type: vk::StructureType::SUBMIT_INFO_2,
+      p_next: ptr::null(),
+      flags: vk::SubmitFlags::empty(),
+      wait_semaphore_info_count: wait.is_some() as u32,
+      p_wait_semaphore_infos: wait.as_ref().map_or(ptr::null(), |info| info),
+      command_buffer_info_count: 1,
+      p_command_buffer_infos: &cb_info,
+      signal_semaphore_info_count: 1,
+      p_signal_semaphore_infos: &signal,
+    };
+    self
+      .device
+      .queue_submit2(queue, &[submit_info], vk::Fence::null())
+  }
+}
+
+impl Drop for UploadManager {
+  fn drop(&mut self) {
+    // the staging buffer and command buffers may still be in use
+    if let Err(err) = self.wait(self.last_value) {
+      log::error!(
+        "Failed to wait for uploads to complete during drop: {:?}",
+        err
+      );
+    }
+  }
+}
+
+fn create_staging_buffer(device: &Arc<Device>, size: u64) -> Result<Owned<vk::Buffer>, vk::Result> {
+  let create_info = vk::BufferCreateInfo {
+    s_type: vk::StructureType::BUFFER_CREATE_INFO,
+    p_next: ptr::null(),
+    flags: vk::BufferCreateFlags::empty(),
+    size,
+    usage: vk::BufferUsageFlags::TRANSFER_SRC,
+    sharing_mode: vk::SharingMode::EXCLUSIVE,
+    queue_family_index_count: 0,
+    p_queue_family_indices: ptr::null(), // ignored when exclusive
+  };
+  unsafe {
+    let buffer = device.create_buffer(&create_info, None)?;
+    Ok(Owned::from_raw(device, buffer))
+  }
+}
+
+fn create_timeline_semaphore(device: &Arc<Device>) -> Result<Owned<vk::Semaphore>, vk::Result> {
+  let type_info = vk::SemaphoreTypeCreateInfo {
+    s_type: vk::StructureType::SEMAPHORE_TYPE_CREATE_INFO,
+    p_next: ptr::null(),
+    semaphore_type: vk::SemaphoreType::TIMELINE,
+    initial_value: 0,
+  };
+  let create_info = vk::SemaphoreCreateInfo {
+    s_type: vk::StructureType::SEMAPHORE_CREATE_INFO,
+    p_next: &type_info as *const _ as *const std::ffi::c_void,
+    flags: vk::SemaphoreCreateFlags::empty(),
+  };
+  unsafe {
+    let semaphore = device.create_semaphore(&create_info, None)?;
+    Ok(Owned::from_raw(device, semaphore))
+  }
+}
+
+// command buffers get reused, so they can be reset individually
+fn create_command_pool(
+  device: &Arc<Device>,
+  queue_family_index: u32,
+) -> Result<Owned<vk::CommandPool>, vk::Result> {
+  let create_info = vk::CommandPoolCreateInfo {
+    s_type: vk::StructureType::COMMAND_POOL_CREATE_INFO,
+    p_next: ptr::null(),
+    flags: vk::CommandPoolCreateFlags::TRANSIENT | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+    queue_family_index,
+  };
+  unsafe {
+    let pool = device.create_command_pool(&create_info, None)?;
+    Ok(Owned::from_raw(device, pool))
+  }
+}
+
+fn allocate_command_buffer(
+  device: &ash::Device,
+  command_pool: vk::CommandPool,
+) -> Result<vk::CommandBuffer, vk::Result> {
+  let allocate_info = vk::CommandBufferAllocateInfo {
+    s_type: vk::StructureType::COMMAND_BUFFER_ALLOCATE_INFO,
+    p_next: ptr::null(),
+    command_buffer_count: 1,
+    command_pool,
+    level: vk::CommandBufferLevel::PRIMARY,
+  };
+  Ok(unsafe { device.allocate_command_buffers(&allocate_info)? }[0])
+}
+
+// implicitly resets the command buffer
+unsafe fn begin_command_buffer(
+  device: &ash::Device,
+  cb: vk::CommandBuffer,
+) -> Result<(), vk::Result> {
+  let begin_info = vk::CommandBufferBeginInfo {
+    s_type: vk::StructureType::COMMAND_BUFFER_BEGIN_INFO,
+    p_next: ptr::null(),
+    flags: vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT,
+    p_inheritance_info: ptr::null(),
+  };
+  device.begin_command_buffer(cb, &begin_info)
+}
+
+fn dependency_info(
+  buffer: &[vk::BufferMemoryBarrier2],
+  image: &[vk::ImageMemoryBarrier2],
+) -> vk::DependencyInfo {
+  vk::DependencyInfo {
+    s_type: vk::StructureType::DEPENDENCY_INFO,
+    p_next: ptr::null(),
+    dependency_flags: vk::DependencyFlags::empty(),
+    memory_barrier_count: 0,
+    p_memory_barriers: ptr::null(),
+    buffer_memory_barrier_count: buffer.len() as u32,
+    p_buffer_memory_barriers: buffer.as_ptr(),
+    image_memory_barrier_count: image.len() as u32,
+    p_image_memory_barriers: image.as_ptr(),
+  }
+}
@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use crate::memory::align_up;
+
+// Ring of staging space that is written by the host and read by submitted batches
+//
+// Ranges are handed out contiguously in submission order and are reclaimed when the timeline
+// value of the batch that used them is reached. Positions grow monotonically and wrap modulo the
+// capacity; space skipped at the end of the ring (because a range wouldn't fit) belongs to the
+// batch that skipped it.
+#[derive(Debug)]
+pub struct StagingRing {
+  capacity: u64,
+  // start of the oldest range that may still be in use
+  tail: u64,
+  // end of the last handed out range
+  head: u64,
+  // end positions of submitted batches with the timeline value that signals their completion
+  submitted: VecDeque<(u64, u64)>,
+  // end of the last submitted batch
+  submitted_head: u64,
+}
+
+impl StagingRing {
+  pub fn new(capacity: u64) -> Self {
+    assert!(capacity > 0);
+    Self {
+      capacity,
+      tail: 0,
+      head: 0,
+      submitted: VecDeque::new(),
+      submitted_head: 0,
+    }
+  }
+
+  pub fn capacity(&self) -> u64 {
+    self.capacity
+  }
+
+  // bytes that are handed out and not yet reclaimed (including skipped space)
+  pub fn used(&self) -> u64 {
+    self.head - self.tail
+  }
+
+  // true if there are ranges that were handed out after the last submit
+  pub fn has_unsubmitted(&self) -> bool {
+    self.head != self.submitted_head
+  }
+
+  // timeline value of the oldest batch that still holds space
+  pub fn oldest_pending(&self) -> Option<u64> {
+    self.submitted.front().map(|&(_, value)| value)
+  }
+
+  // returns the offset in the ring of a free range of size bytes, or None if there is no
+  // contiguous free space for it until some batch completes
+  // alignment should be a power of two that divides the capacity
+  pub fn allocate(&mut self, size: u64, alignment: u64) -> Option<u64> {
+    assert!(size > 0 && size <= self.capacity);
+    debug_assert!(self.capacity.is_multiple_of(alignment));
+
+    if self.used() == 0 && !self.has_unsubmitted() {
+      // nothing is in use, so the whole ring is free starting from its beginning
+      let start = self.head.next_multiple_of(self.capacity);
+      self.tail = start;
+      self.head = start;
+      self.submitted_head = start;
+    }
+
+    let mut start = align_up(self.head, alignment);
+    if start % self.capacity + size > self.capacity {
+      // skip to the start of the ring
+      start = (start / self.capacity + 1) * self.capacity;
+    }
+    if start + size - self.tail > self.capacity {
+      return None;
+    }
+
+    self.head = start + size;
+    Some(start % self.capacity)
+  }
+
+  // marks every range handed out since the last submit as used by the batch that completes at
+  // timeline value
+  pub fn submit(&mut self, value: u64) {
+    if self.has_unsubmitted() {
+      if let Some(&(_, last)) = self.submitted.back() {
+        debug_assert!(value > last);
+      }
+      self.submitted.push_back((self.head, value));
+      self.submitted_head = self.head;
+    }
+  }
+
+  // reclaims the space of all batches with values up to completed
+  pub fn release(&mut self, completed: u64) {
+    while let Some(&(end, value)) = self.submitted.front() {
+      if value > completed {
+        break;
+      }
+      self.tail = end;
+      self.submitted.pop_front();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ranges_are_aligned_and_wrap_around() {
+    let mut ring = StagingRing::new(256);
+    assert_eq!(ring.allocate(100, 16), Some(0));
+    ring.submit(1);
+    assert_eq!(ring.allocate(100, 16), Some(112));
+    ring.submit(2);
+    // 212..256 is too small, and 0..100 is still in use
+    assert_eq!(ring.allocate(100, 16), None);
+
+    ring.release(1);
+    assert_eq!(ring.allocate(100, 16), Some(0));
+    // the skipped space is reclaimed together with the new range
+    assert_eq!(ring.used(), 256);
+    ring.submit(3);
+    ring.release(3);
+    assert_eq!(ring.used(), 0);
+  }
+
+  #[test]
+  fn empty_ring_fits_its_whole_capacity() {
+    let mut ring = StagingRing::new(256);
+    assert_eq!(ring.allocate(100, 16), Some(0));
+    ring.submit(1);
+    ring.release(1);
+    assert_eq!(ring.used(), 0);
+    assert_eq!(ring.allocate(256, 16), Some(0));
+    assert_eq!(ring.allocate(16, 16), None);
+  }
+
+  #[test]
+  fn space_is_reclaimed_in_submission_order() {
+    let mut ring = StagingRing::new(256);
+    assert_eq!(ring.allocate(128, 4), Some(0));
+    ring.submit(1);
+    assert_eq!(ring.allocate(128, 4), Some(128));
+    ring.submit(2);
+    assert_eq!(ring.oldest_pending(), Some(1));
+    assert_eq!(ring.allocate(4, 4), None);
+
+    // a later batch completing doesn't free space before the older one does
+    ring.release(0);
+    assert_eq!(ring.allocate(4, 4), None);
+    ring.release(1);
+    assert_eq!(ring.oldest_pending(), Some(2));
+    assert_eq!(ring.allocate(128, 4), Some(0));
+    assert!(ring.has_unsubmitted());
+    ring.submit(3);
+    ring.release(3);
+    assert_eq!(ring.used(), 0);
+    assert_eq!(ring.oldest_pending(), None);
+  }
+
+  #[test]
+  fn submitting_without_new_ranges_does_nothing() {
+    let mut ring = StagingRing::new(64);
+    ring.submit(1);
+    assert_eq!(ring.oldest_pending(), None);
+    assert_eq!(ring.allocate(64, 64), Some(0));
+    ring.submit(2);
+    ring.submit(3);
+    ring.release(2);
+    assert_eq!(ring.used(), 0);
+  }
+}
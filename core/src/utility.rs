@@ -2,7 +2,7 @@ use std::ffi::{c_char, CStr, FromBytesUntilNulError};
 
 use ash::vk;
 
-// this module contains general functions used in other modules
+// this module contains general functions used in other modules and by the examples
 
 pub fn parse_vulkan_api_version(v: u32) -> String {
   format!(
@@ -21,8 +21,9 @@ pub fn c_char_array_to_string(arr: &[c_char]) -> String {
     .to_owned()
 }
 
-pub unsafe fn i8_array_as_cstr<'a>(arr: &'a [i8]) -> Result<&'a CStr, FromBytesUntilNulError> {
-  CStr::from_bytes_until_nul(std::mem::transmute(arr))
+// names returned by Vulkan are stored in nul terminated fixed size arrays
+pub fn i8_array_as_cstr(arr: &[i8]) -> Result<&CStr, FromBytesUntilNulError> {
+  CStr::from_bytes_until_nul(unsafe { &*(arr as *const [i8] as *const [u8]) })
 }
 
 pub fn error_chain_fmt(
@@ -39,7 +40,7 @@ pub fn error_chain_fmt(
 }
 
 pub trait OnErr<T, E> {
-  fn on_err<O: FnOnce(&E)>(self: Self, op: O) -> Result<T, E>
+  fn on_err<O: FnOnce(&E)>(self, op: O) -> Result<T, E>
   where
     Self: Sized;
 }
@@ -60,7 +61,7 @@ impl<T, E> OnErr<T, E> for Result<T, E> {
 #[macro_export]
 macro_rules! cstr {
   ( $s:literal ) => {{
-    unsafe { std::mem::transmute::<_, &CStr>(concat!($s, "\0")) }
+    unsafe { std::mem::transmute::<&str, &std::ffi::CStr>(concat!($s, "\0")) }
   }};
 }
 
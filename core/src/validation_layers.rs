@@ -0,0 +1,527 @@
+use ash::vk::{self, DebugUtilsMessengerCreateInfoEXT};
+
+use std::{
+  ffi::{c_char, CStr},
+  fmt,
+  os::raw::c_void,
+  ptr,
+  sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{cstr, utility};
+
+// validation layers names should be valid cstrings (not contain null bytes nor invalid characters)
+pub const VALIDATION_LAYERS: [&CStr; 1] = [cstr!("VK_LAYER_KHRONOS_validation")];
+pub const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] = [
+  vk::ValidationFeatureEnableEXT::BEST_PRACTICES,
+  vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION,
+];
+
+// returns a list of supported and unsupported instance layers
+fn filter_supported(
+  available: Vec<vk::LayerProperties>,
+) -> (Vec<&'static CStr>, Vec<&'static CStr>) {
+  VALIDATION_LAYERS.into_iter().partition(|&req| {
+    available
+      .iter()
+      .any(|av| utility::i8_array_as_cstr(&av.layer_name).unwrap() == req)
+  })
+}
+
+// returns a subset of VALIDATION_LAYERS that are available
+pub fn get_supported_validation_layers(
+  entry: &ash::Entry,
+) -> Result<Box<[&'static CStr]>, vk::Result> {
+  log::info!("Querying Vulkan instance layers");
+  let (available, unavailable) = filter_supported(entry.enumerate_instance_layer_properties()?);
+
+  if !unavailable.is_empty() {
+    log::error!(
+      "Some requested validation layers are not available: {:?}",
+      unavailable
+    );
+  }
+
+  Ok(available.into_boxed_slice())
+}
+
+// environment variable that selects the ValidationMode
+pub const VALIDATION_MODE_VAR: &str = "ABE_VALIDATION";
+
+// What happens with messages received from the validation layers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValidationMode {
+  // only forward messages to log (and count them)
+  Log,
+  // also keep every message so that it can be inspected later
+  Collect,
+  // Collect and make the Vulkan call that triggered an error fail with
+  // vk::Result::ERROR_VALIDATION_FAILED_EXT
+  FailOnError,
+  // Collect and panic on the first error
+  // The callback is called by the Vulkan implementation and cannot unwind, so this aborts the
+  // process after printing the message (useful for making a test that runs an example fail)
+  PanicOnError,
+}
+
+impl ValidationMode {
+  // reads ABE_VALIDATION ("log", "collect", "fail" or "panic"), defaults to Log
+  pub fn from_env() -> Self {
+    let Ok(value) = std::env::var(VALIDATION_MODE_VAR) else {
+      return Self::Log;
+    };
+    Self::parse(&value).unwrap_or_else(|| {
+      log::warn!(
+        "Unknown {} value \"{}\", using \"log\"",
+        VALIDATION_MODE_VAR,
+        value
+      );
+      Self::Log
+    })
+  }
+
+  // an empty value is the same as "log", None if the value is unknown
+  pub fn parse(value: &str) -> Option<Self> {
+    match value {
+      "log" | "" => Some(Self::Log),
+      "collect" => Some(Self::Collect),
+      "fail" => Some(Self::FailOnError),
+      "panic" => Some(Self::PanicOnError),
+      _ => None,
+    }
+  }
+
+  // what is done with a message of `severity` after it gets logged
+  fn action(self, severity: vk::DebugUtilsMessageSeverityFlagsEXT) -> MessageAction {
+    let is_error = severity == vk::DebugUtilsMessageSeverityFlagsEXT::ERROR;
+    match self {
+      Self::Log => MessageAction::Count,
+      Self::Collect => MessageAction::Keep,
+      Self::FailOnError if is_error => MessageAction::KeepAndFail,
+      Self::PanicOnError if is_error => MessageAction::Panic,
+      Self::FailOnError | Self::PanicOnError => MessageAction::Keep,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MessageAction {
+  // only counted in the summary
+  Count,
+  // counted and stored
+  Keep,
+  // stored and the Vulkan call that triggered it fails
+  KeepAndFail,
+  Panic,
+}
+
+fn log_level(severity: vk::DebugUtilsMessageSeverityFlagsEXT) -> log::Level {
+  match severity {
+    vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE => log::Level::Debug,
+    vk::DebugUtilsMessageSeverityFlagsEXT::INFO => log::Level::Info,
+    vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => log::Level::Error,
+    // includes unknown severities
+    _ => log::Level::Warn,
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationObject {
+  pub object_type: vk::ObjectType,
+  pub handle: u64,
+  pub name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationMessage {
+  pub severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+  pub message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+  pub message_id_name: Option<String>,
+  pub message_id_number: i32,
+  pub message: String,
+  pub objects: Vec<ValidationObject>,
+}
+
+impl fmt::Display for ValidationMessage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:?} {:?} [{}] {}",
+      self.severity,
+      self.message_type,
+      self.message_id_name.as_deref().unwrap_or("<no id>"),
+      self.message
+    )?;
+    for object in self.objects.iter() {
+      write!(
+        f,
+        "\n    {:?} {:#x} {}",
+        object.object_type,
+        object.handle,
+        object.name.as_deref().unwrap_or("")
+      )?;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationSummary {
+  pub errors: usize,
+  pub warnings: usize,
+  pub infos: usize,
+  pub verbose: usize,
+}
+
+impl fmt::Display for ValidationSummary {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} errors, {} warnings, {} info and {} verbose messages",
+      self.errors, self.warnings, self.infos, self.verbose
+    )
+  }
+}
+
+// returned by DebugUtils::check if any errors were reported
+#[derive(Debug)]
+pub struct ValidationError {
+  pub summary: ValidationSummary,
+  // only available if messages were collected
+  pub first_error: Option<ValidationMessage>,
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Validation layers reported {}", self.summary)?;
+    if let Some(message) = &self.first_error {
+      write!(f, "\nFirst error: {}", message)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for ValidationError {}
+
+struct MessagesState {
+  mode: ValidationMode,
+  summary: ValidationSummary,
+  messages: Vec<ValidationMessage>,
+}
+
+// Sink that receives all messages from the debug messenger
+// Shared with the callback through p_user_data, so it has to outlive the instance
+pub struct ValidationMessages {
+  state: Mutex<MessagesState>,
+}
+
+impl ValidationMessages {
+  pub fn new(mode: ValidationMode) -> Self {
+    Self {
+      state: Mutex::new(MessagesState {
+        mode,
+        summary: ValidationSummary::default(),
+        messages: Vec::new(),
+      }),
+    }
+  }
+
+  fn lock(&self) -> MutexGuard<'_, MessagesState> {
+    // messages are still valid even if some thread panicked while holding the lock
+    self.state.lock().unwrap_or_else(|err| err.into_inner())
+  }
+
+  pub fn mode(&self) -> ValidationMode {
+    self.lock().mode
+  }
+
+  pub fn set_mode(&self, mode: ValidationMode) {
+    self.lock().mode = mode;
+  }
+
+  pub fn summary(&self) -> ValidationSummary {
+    self.lock().summary
+  }
+
+  // all collected messages, empty if mode is ValidationMode::Log
+  pub fn messages(&self) -> Vec<ValidationMessage> {
+    self.lock().messages.clone()
+  }
+
+  // Returns and clears all collected messages (counts are also reset)
+  pub fn take(&self) -> Vec<ValidationMessage> {
+    let mut state = self.lock();
+    state.summary = ValidationSummary::default();
+    std::mem::take(&mut state.messages)
+  }
+
+  pub fn check(&self) -> Result<(), ValidationError> {
+    let state = self.lock();
+    if state.summary.errors == 0 {
+      return Ok(());
+    }
+    Err(ValidationError {
+      summary: state.summary,
+      first_error: state
+        .messages
+        .iter()
+        .find(|m| m.severity == vk::DebugUtilsMessageSeverityFlagsEXT::ERROR)
+        .cloned(),
+    })
+  }
+
+  // returns if the Vulkan call that triggered the message should fail
+  fn push(&self, message: ValidationMessage) -> bool {
+    let mut state = self.lock();
+    match message.severity {
+      vk::DebugUtilsMessageSeverityFlagsEXT::ERROR => state.summary.errors += 1,
+      vk::DebugUtilsMessageSeverityFlagsEXT::WARNING => state.summary.warnings += 1,
+      vk::DebugUtilsMessageSeverityFlagsEXT::INFO => state.summary.infos += 1,
+      _ => state.summary.verbose += 1,
+    }
+
+    match state.mode.action(message.severity) {
+      MessageAction::Count => false,
+      MessageAction::Keep => {
+        state.messages.push(message);
+        false
+      }
+      MessageAction::KeepAndFail => {
+        state.messages.push(message);
+        true
+      }
+      MessageAction::Panic => {
+        drop(state);
+        panic!(
+          "Validation error ({} is \"panic\"): {}",
+          VALIDATION_MODE_VAR, message
+        );
+      }
+    }
+  }
+}
+
+unsafe fn optional_string(ptr: *const c_char) -> Option<String> {
+  if ptr.is_null() {
+    None
+  } else {
+    Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+  }
+}
+
+// can be extensively customized
+unsafe extern "system" fn vulkan_debug_utils_callback(
+  message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+  message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+  p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+  p_user_data: *mut c_void,
+) -> vk::Bool32 {
+  let data = &*p_callback_data;
+  let types = match message_type {
+    vk::DebugUtilsMessageTypeFlagsEXT::GENERAL => "[General]",
+    vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE => "[Performance]",
+    vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION => "[Validation]",
+    _ => "[Unknown]",
+  };
+  let message = optional_string(data.p_message).unwrap_or_default();
+  let formatted = format!("{} {}", types, message);
+  log::log!(log_level(message_severity), "{formatted}");
+
+  if p_user_data.is_null() {
+    return vk::FALSE;
+  }
+  let sink = &*(p_user_data as *const ValidationMessages);
+
+  let objects = if data.object_count == 0 || data.p_objects.is_null() {
+    Vec::new()
+  } else {
+    std::slice::from_raw_parts(data.p_objects, data.object_count as usize)
+      .iter()
+      .map(|object| ValidationObject {
+        object_type: object.object_type,
+        handle: object.object_handle,
+        name: optional_string(object.p_object_name),
+      })
+      .collect()
+  };
+  let fail = sink.push(ValidationMessage {
+    severity: message_severity,
+    message_type,
+    message_id_name: optional_string(data.p_message_id_name),
+    message_id_number: data.message_id_number,
+    message,
+    objects,
+  });
+
+  if fail {
+    vk::TRUE
+  } else {
+    vk::FALSE
+  }
+}
+
+pub struct DebugUtils {
+  loader: ash::extensions::ext::DebugUtils,
+  messenger: vk::DebugUtilsMessengerEXT,
+  messages: Arc<ValidationMessages>,
+}
+
+impl DebugUtils {
+  // `create_info` should have been created with `get_debug_messenger_create_info(&messages)`
+  pub fn create(
+    entry: &ash::Entry,
+    instance: &ash::Instance,
+    create_info: DebugUtilsMessengerCreateInfoEXT,
+    messages: Arc<ValidationMessages>,
+  ) -> Result<Self, vk::Result> {
+    let loader = ash::extensions::ext::DebugUtils::new(entry, instance);
+
+    let messenger = unsafe { loader.create_debug_utils_messenger(&create_info, None)? };
+
+    Ok(Self {
+      loader,
+      messenger,
+      messages,
+    })
+  }
+
+  // messages is passed as user data to the callback and has to stay alive while the create info
+  // is in use (including the instance creation and destruction if passed in its p_next chain)
+  pub fn get_debug_messenger_create_info(
+    messages: &Arc<ValidationMessages>,
+  ) -> vk::DebugUtilsMessengerCreateInfoEXT {
+    vk::DebugUtilsMessengerCreateInfoEXT {
+      s_type: vk::StructureType::DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+      p_next: ptr::null(),
+      flags: vk::DebugUtilsMessengerCreateFlagsEXT::empty(),
+      message_severity: vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+        | vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+        | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+        | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+      message_type: vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+        | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE
+        | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+      pfn_user_callback: Some(vulkan_debug_utils_callback),
+      p_user_data: Arc::as_ptr(messages) as *mut c_void,
+    }
+  }
+
+  pub fn messages(&self) -> &Arc<ValidationMessages> {
+    &self.messages
+  }
+
+  // Returns an error if the validation layers reported any errors up to this point
+  pub fn check(&self) -> Result<(), ValidationError> {
+    self.messages.check()
+  }
+
+  // Also logs a summary of all received messages
+  // Messages emitted during instance destruction are still received, so DebugUtils itself should
+  // only be dropped after the instance is destroyed
+  pub unsafe fn destroy_self(&mut self) {
+    self
+      .loader
+      .destroy_debug_utils_messenger(self.messenger, None);
+
+    let summary = self.messages.summary();
+    if summary.errors > 0 {
+      log::error!("Validation layers reported {}", summary);
+    } else {
+      log::info!("Validation layers reported {}", summary);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  type Severity = vk::DebugUtilsMessageSeverityFlagsEXT;
+
+  fn message(severity: Severity) -> ValidationMessage {
+    ValidationMessage {
+      severity,
+      message_type: vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION,
+      message_id_name: Some("VUID-test".to_owned()),
+      message_id_number: 1,
+      message: "test message".to_owned(),
+      objects: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn modes_are_parsed() {
+    assert_eq!(ValidationMode::parse(""), Some(ValidationMode::Log));
+    assert_eq!(ValidationMode::parse("log"), Some(ValidationMode::Log));
+    assert_eq!(
+      ValidationMode::parse("collect"),
+      Some(ValidationMode::Collect)
+    );
+    assert_eq!(
+      ValidationMode::parse("fail"),
+      Some(ValidationMode::FailOnError)
+    );
+    assert_eq!(
+      ValidationMode::parse("panic"),
+      Some(ValidationMode::PanicOnError)
+    );
+    assert_eq!(ValidationMode::parse("PANIC"), None);
+    assert_eq!(ValidationMode::parse("abort"), None);
+  }
+
+  #[test]
+  fn only_errors_fail_or_panic() {
+    use MessageAction::*;
+
+    for severity in [Severity::VERBOSE, Severity::INFO, Severity::WARNING] {
+      assert_eq!(ValidationMode::Log.action(severity), Count);
+      assert_eq!(ValidationMode::Collect.action(severity), Keep);
+      assert_eq!(ValidationMode::FailOnError.action(severity), Keep);
+      assert_eq!(ValidationMode::PanicOnError.action(severity), Keep);
+    }
+    assert_eq!(ValidationMode::Log.action(Severity::ERROR), Count);
+    assert_eq!(ValidationMode::Collect.action(Severity::ERROR), Keep);
+    assert_eq!(
+      ValidationMode::FailOnError.action(Severity::ERROR),
+      KeepAndFail
+    );
+    assert_eq!(ValidationMode::PanicOnError.action(Severity::ERROR), Panic);
+  }
+
+  #[test]
+  fn severities_map_to_log_levels() {
+    assert_eq!(log_level(Severity::VERBOSE), log::Level::Debug);
+    assert_eq!(log_level(Severity::INFO), log::Level::Info);
+    assert_eq!(log_level(Severity::WARNING), log::Level::Warn);
+    assert_eq!(log_level(Severity::ERROR), log::Level::Error);
+    assert_eq!(log_level(Severity::empty()), log::Level::Warn);
+  }
+
+  #[test]
+  fn messages_are_counted_and_collected() {
+    let messages = ValidationMessages::new(ValidationMode::FailOnError);
+    assert!(!messages.push(message(Severity::WARNING)));
+    assert!(messages.check().is_ok());
+    assert!(messages.push(message(Severity::ERROR)));
+
+    let error = messages.check().unwrap_err();
+    assert_eq!(error.summary.errors, 1);
+    assert_eq!(error.summary.warnings, 1);
+    assert_eq!(error.first_error.unwrap().severity, Severity::ERROR);
+    assert_eq!(messages.take().len(), 2);
+    assert!(messages.check().is_ok());
+
+    messages.set_mode(ValidationMode::Log);
+    assert!(!messages.push(message(Severity::ERROR)));
+    assert!(messages.messages().is_empty());
+    assert_eq!(messages.summary().errors, 1);
+  }
+
+  #[test]
+  #[should_panic(expected = "Validation error")]
+  fn panic_mode_panics_on_errors() {
+    let messages = ValidationMessages::new(ValidationMode::PanicOnError);
+    assert!(!messages.push(message(Severity::WARNING)));
+    messages.push(message(Severity::ERROR));
+  }
+}
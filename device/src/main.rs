@@ -1,25 +1,14 @@
-mod device;
-mod entry;
-mod instance;
-mod utility;
-
-// validation layers module will only exist if validation layers are enabled
-#[cfg(feature = "vl")]
-mod validation_layers;
+mod report;
 
 use ash::vk;
+use ash_by_example_core::{
+  create_instance, create_logical_device, cstr, get_entry, ApplicationInfo, DeviceRequirements,
+  PhysicalDevice, QueueFamilyRequirements,
+};
 use std::ffi::CStr;
 
-use crate::device::{create_logical_device, PhysicalDevice};
-
-// validation layers names should be valid cstrings (not contain null bytes nor invalid characters)
-#[cfg(feature = "vl")]
-pub const VALIDATION_LAYERS: [&'static CStr; 1] = [cstr!("VK_LAYER_KHRONOS_validation")];
-#[cfg(feature = "vl")]
-pub const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] = [
-  vk::ValidationFeatureEnableEXT::BEST_PRACTICES,
-  vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION,
-];
+// Physical device selection and logical device creation are implemented in the shared
+// ash-by-example-core crate, in core/src/device/
 
 // Vulkan API version required to run the program
 // You may have to use an older API version if you want to support devices that do not yet support
@@ -28,31 +17,81 @@ pub const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] =
 pub const TARGET_API_VERSION: u32 = vk::API_VERSION_1_3;
 
 // somewhat arbitrary
-pub const APPLICATION_NAME: &'static CStr = cstr!("Vulkan Device Creation");
+pub const APPLICATION_NAME: &CStr = cstr!("Vulkan Device Creation");
 pub const APPLICATION_VERSION: u32 = vk::make_api_version(0, 1, 0, 0);
 
-pub const REQUIRED_DEVICE_EXTENSIONS: [&'static CStr; 0] = [];
+pub const REQUIRED_DEVICE_EXTENSIONS: [&CStr; 0] = [];
+
+fn device_requirements() -> DeviceRequirements<'static> {
+  let mut requirements = DeviceRequirements::new(TARGET_API_VERSION);
+  requirements.extensions = &REQUIRED_DEVICE_EXTENSIONS;
+  requirements.features.f12.timeline_semaphore = vk::TRUE;
+  requirements.features.f13.synchronization2 = vk::TRUE;
+  requirements.queue_families = QueueFamilyRequirements {
+    graphics: true,
+    compute: true,
+    presentation: None,
+  };
+  requirements
+}
+
+const USAGE: &str = "Usage: device [--report json]";
 
 fn main() {
   env_logger::init();
 
-  let entry: ash::Entry = unsafe { entry::get_entry() };
+  // with --report json, prints the capabilities of all physical devices instead of creating a
+  // logical device
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let report = match args.as_slice() {
+    [] => false,
+    [flag, format] if flag == "--report" && format == "json" => true,
+    _ => {
+      eprintln!("{}", USAGE);
+      std::process::exit(2);
+    }
+  };
+
+  let entry: ash::Entry = unsafe { get_entry() };
+
+  let app_info = ApplicationInfo {
+    name: APPLICATION_NAME,
+    version: APPLICATION_VERSION,
+    api_version: TARGET_API_VERSION,
+  };
 
   #[cfg(feature = "vl")]
   let (instance, mut debug_utils) =
-    instance::create_instance(&entry).expect("Failed to create an instance");
+    create_instance(&entry, &app_info, &[]).expect("Failed to create an instance");
   #[cfg(not(feature = "vl"))]
-  let instance = instance::create_instance(&entry).expect("Failed to create an instance");
-
-  let physical_device = match unsafe { PhysicalDevice::select(&instance) } {
-    Ok(device_opt) => match device_opt {
-      Some(device) => device,
-      None => panic!("No suitable device found"),
-    },
-    Err(err) => panic!("Failed to query physical devices: {:?}", err),
+  let instance = create_instance(&entry, &app_info, &[]).expect("Failed to create an instance");
+
+  if report {
+    let report = report::physical_devices_report(&instance).expect("Failed to query devices");
+    println!(
+      "{}",
+      serde_json::to_string_pretty(&report).expect("Failed to serialize report")
+    );
+
+    unsafe {
+      #[cfg(feature = "vl")]
+      {
+        debug_utils.destroy_self();
+      }
+      instance.destroy_instance(None);
+    }
+    return;
+  }
+
+  let requirements = device_requirements();
+  // set ABE_DEVICE to choose a specific device (see DeviceSelector in core/src/device/selector.rs)
+  let physical_device = match unsafe { PhysicalDevice::select(&instance, &requirements) } {
+    Ok(device) => device,
+    Err(err) => panic!("Failed to select a physical device: {}", err),
   };
 
-  let (logical_device, _queues) = create_logical_device(&instance, &physical_device).expect("Failed to create an logical device");
+  let (logical_device, _queues) = create_logical_device(&instance, &physical_device, &requirements)
+    .expect("Failed to create an logical device");
 
   println!("Successfully created the logical device!");
 
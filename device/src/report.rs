@@ -0,0 +1,784 @@
+use std::ffi::{c_char, CStr};
+
+use ash::vk;
+use ash_by_example_core::{
+  device::{format_uuid, Vendor},
+  get_extended_features, get_extended_properties,
+  image_file::TexelFormat,
+  utility::{c_char_array_to_string, parse_vulkan_api_version},
+  PhysicalDeviceFeatures, PhysicalDeviceProperties,
+};
+use serde_json::{Map, Value};
+
+// Machine readable report of everything that can be queried from each physical device
+// Meant to be diffed between machines or attached to bug reports
+
+// formats used by the other examples that are not image formats selectable with TexelFormat
+const OTHER_REPORTED_FORMATS: [vk::Format; 4] = [
+  // swapchain
+  vk::Format::B8G8R8A8_UNORM,
+  vk::Format::B8G8R8A8_SRGB,
+  // vertex attributes
+  vk::Format::R32G32_SFLOAT,
+  vk::Format::R32G32B32_SFLOAT,
+];
+
+// formats used by the other examples
+fn reported_formats() -> Vec<vk::Format> {
+  TexelFormat::ALL
+    .into_iter()
+    .map(TexelFormat::vk_format)
+    .chain(OTHER_REPORTED_FORMATS)
+    .collect()
+}
+
+trait ToJson {
+  fn to_json(&self) -> Value;
+}
+
+// Builds a JSON object out of struct fields, using the field names as keys
+// Fields listed after `bools` are vk::Bool32 (which is an alias of u32) and are converted to
+// JSON booleans
+macro_rules! json_object {
+  ($s:expr, [$($field:ident),*]) => {
+    json_object!($s, [$($field),*], bools [])
+  };
+  ($s:expr, [$($field:ident),*], bools [$($bool_field:ident),*]) => {{
+    let mut map = Map::new();
+    $(map.insert(stringify!($field).to_owned(), $s.$field.to_json());)*
+    $(map.insert(stringify!($bool_field).to_owned(), Value::Bool($s.$bool_field == vk::TRUE));)*
+    Value::Object(map)
+  }};
+}
+
+macro_rules! impl_to_json_as_number {
+  ($($t:ty),+) => {
+    $(impl ToJson for $t {
+      fn to_json(&self) -> Value {
+        Value::from(*self)
+      }
+    })+
+  };
+}
+impl_to_json_as_number!(u8, u32, i32, u64, usize, f32);
+
+// flags and enums are written as their names
+macro_rules! impl_to_json_as_debug {
+  ($($t:ty),+) => {
+    $(impl ToJson for $t {
+      fn to_json(&self) -> Value {
+        Value::String(format!("{:?}", self))
+      }
+    })+
+  };
+}
+impl_to_json_as_debug!(
+  vk::PhysicalDeviceType,
+  vk::SampleCountFlags,
+  vk::PointClippingBehavior,
+  vk::SubgroupFeatureFlags,
+  vk::ShaderStageFlags,
+  vk::DriverId,
+  vk::ShaderFloatControlsIndependence,
+  vk::ResolveModeFlags,
+  vk::QueueFlags,
+  vk::MemoryHeapFlags,
+  vk::MemoryPropertyFlags,
+  vk::FormatFeatureFlags
+);
+
+impl<T: ToJson> ToJson for [T] {
+  fn to_json(&self) -> Value {
+    Value::Array(self.iter().map(ToJson::to_json).collect())
+  }
+}
+
+impl ToJson for [u32; 2] {
+  fn to_json(&self) -> Value {
+    self[..].to_json()
+  }
+}
+
+impl ToJson for [u32; 3] {
+  fn to_json(&self) -> Value {
+    self[..].to_json()
+  }
+}
+
+impl ToJson for [f32; 2] {
+  fn to_json(&self) -> Value {
+    self[..].to_json()
+  }
+}
+
+// UUIDs
+impl ToJson for [u8; vk::UUID_SIZE] {
+  fn to_json(&self) -> Value {
+    Value::String(format_uuid(self))
+  }
+}
+
+// LUIDs
+impl ToJson for [u8; vk::LUID_SIZE] {
+  fn to_json(&self) -> Value {
+    Value::String(self.iter().map(|b| format!("{:02x}", b)).collect())
+  }
+}
+
+// device name, driver name and driver info (all have the same size)
+impl ToJson for [c_char; vk::MAX_PHYSICAL_DEVICE_NAME_SIZE] {
+  fn to_json(&self) -> Value {
+    Value::String(c_char_array_to_string(self))
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceProperties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        api_version,
+        driver_version,
+        vendor_id,
+        device_id,
+        device_type,
+        device_name,
+        pipeline_cache_uuid,
+        limits,
+        sparse_properties
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceLimits {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        max_image_dimension1_d, max_image_dimension2_d, max_image_dimension3_d,
+        max_image_dimension_cube, max_image_array_layers, max_texel_buffer_elements,
+        max_uniform_buffer_range, max_storage_buffer_range, max_push_constants_size,
+        max_memory_allocation_count, max_sampler_allocation_count, buffer_image_granularity,
+        sparse_address_space_size, max_bound_descriptor_sets, max_per_stage_descriptor_samplers,
+        max_per_stage_descriptor_uniform_buffers, max_per_stage_descriptor_storage_buffers,
+        max_per_stage_descriptor_sampled_images, max_per_stage_descriptor_storage_images,
+        max_per_stage_descriptor_input_attachments, max_per_stage_resources,
+        max_descriptor_set_samplers, max_descriptor_set_uniform_buffers,
+        max_descriptor_set_uniform_buffers_dynamic, max_descriptor_set_storage_buffers,
+        max_descriptor_set_storage_buffers_dynamic, max_descriptor_set_sampled_images,
+        max_descriptor_set_storage_images, max_descriptor_set_input_attachments,
+        max_vertex_input_attributes, max_vertex_input_bindings, max_vertex_input_attribute_offset,
+        max_vertex_input_binding_stride, max_vertex_output_components,
+        max_tessellation_generation_level, max_tessellation_patch_size,
+        max_tessellation_control_per_vertex_input_components,
+        max_tessellation_control_per_vertex_output_components,
+        max_tessellation_control_per_patch_output_components,
+        max_tessellation_control_total_output_components,
+        max_tessellation_evaluation_input_components,
+        max_tessellation_evaluation_output_components, max_geometry_shader_invocations,
+        max_geometry_input_components, max_geometry_output_components,
+        max_geometry_output_vertices, max_geometry_total_output_components,
+        max_fragment_input_components, max_fragment_output_attachments,
+        max_fragment_dual_src_attachments, max_fragment_combined_output_resources,
+        max_compute_shared_memory_size, max_compute_work_group_count,
+        max_compute_work_group_invocations, max_compute_work_group_size, sub_pixel_precision_bits,
+        sub_texel_precision_bits, mipmap_precision_bits, max_draw_indexed_index_value,
+        max_draw_indirect_count, max_sampler_lod_bias, max_sampler_anisotropy, max_viewports,
+        max_viewport_dimensions, viewport_bounds_range, viewport_sub_pixel_bits,
+        min_memory_map_alignment, min_texel_buffer_offset_alignment,
+        min_uniform_buffer_offset_alignment, min_storage_buffer_offset_alignment, min_texel_offset,
+        max_texel_offset, min_texel_gather_offset, max_texel_gather_offset,
+        min_interpolation_offset, max_interpolation_offset, sub_pixel_interpolation_offset_bits,
+        max_framebuffer_width, max_framebuffer_height, max_framebuffer_layers,
+        framebuffer_color_sample_counts, framebuffer_depth_sample_counts,
+        framebuffer_stencil_sample_counts, framebuffer_no_attachments_sample_counts,
+        max_color_attachments, sampled_image_color_sample_counts,
+        sampled_image_integer_sample_counts, sampled_image_depth_sample_counts,
+        sampled_image_stencil_sample_counts, storage_image_sample_counts, max_sample_mask_words,
+        timestamp_period, max_clip_distances, max_cull_distances,
+        max_combined_clip_and_cull_distances, discrete_queue_priorities, point_size_range,
+        line_width_range, point_size_granularity, line_width_granularity,
+        optimal_buffer_copy_offset_alignment, optimal_buffer_copy_row_pitch_alignment,
+        non_coherent_atom_size
+      ],
+      bools [timestamp_compute_and_graphics, strict_lines, standard_sample_locations]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceSparseProperties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [],
+      bools [
+        residency_standard2_d_block_shape, residency_standard2_d_multisample_block_shape,
+        residency_standard3_d_block_shape, residency_aligned_mip_size,
+        residency_non_resident_strict
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceVulkan11Properties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        device_uuid, driver_uuid, device_luid, device_node_mask, subgroup_size,
+        subgroup_supported_stages, subgroup_supported_operations, point_clipping_behavior,
+        max_multiview_view_count, max_multiview_instance_index, max_per_set_descriptors,
+        max_memory_allocation_size
+      ],
+      bools [device_luid_valid, subgroup_quad_operations_in_all_stages, protected_no_fault]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceVulkan12Properties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        driver_id, driver_name, driver_info, conformance_version, denorm_behavior_independence,
+        rounding_mode_independence, max_update_after_bind_descriptors_in_all_pools,
+        max_per_stage_descriptor_update_after_bind_samplers,
+        max_per_stage_descriptor_update_after_bind_uniform_buffers,
+        max_per_stage_descriptor_update_after_bind_storage_buffers,
+        max_per_stage_descriptor_update_after_bind_sampled_images,
+        max_per_stage_descriptor_update_after_bind_storage_images,
+        max_per_stage_descriptor_update_after_bind_input_attachments,
+        max_per_stage_update_after_bind_resources, max_descriptor_set_update_after_bind_samplers,
+        max_descriptor_set_update_after_bind_uniform_buffers,
+        max_descriptor_set_update_after_bind_uniform_buffers_dynamic,
+        max_descriptor_set_update_after_bind_storage_buffers,
+        max_descriptor_set_update_after_bind_storage_buffers_dynamic,
+        max_descriptor_set_update_after_bind_sampled_images,
+        max_descriptor_set_update_after_bind_storage_images,
+        max_descriptor_set_update_after_bind_input_attachments, supported_depth_resolve_modes,
+        supported_stencil_resolve_modes, max_timeline_semaphore_value_difference,
+        framebuffer_integer_color_sample_counts
+      ],
+      bools [
+        shader_signed_zero_inf_nan_preserve_float16, shader_signed_zero_inf_nan_preserve_float32,
+        shader_signed_zero_inf_nan_preserve_float64, shader_denorm_preserve_float16,
+        shader_denorm_preserve_float32, shader_denorm_preserve_float64,
+        shader_denorm_flush_to_zero_float16, shader_denorm_flush_to_zero_float32,
+        shader_denorm_flush_to_zero_float64, shader_rounding_mode_rte_float16,
+        shader_rounding_mode_rte_float32, shader_rounding_mode_rte_float64,
+        shader_rounding_mode_rtz_float16, shader_rounding_mode_rtz_float32,
+        shader_rounding_mode_rtz_float64, shader_uniform_buffer_array_non_uniform_indexing_native,
+        shader_sampled_image_array_non_uniform_indexing_native,
+        shader_storage_buffer_array_non_uniform_indexing_native,
+        shader_storage_image_array_non_uniform_indexing_native,
+        shader_input_attachment_array_non_uniform_indexing_native,
+        robust_buffer_access_update_after_bind, quad_divergent_implicit_lod,
+        independent_resolve_none, independent_resolve, filter_minmax_single_component_formats,
+        filter_minmax_image_component_mapping
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceVulkan13Properties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        min_subgroup_size, max_subgroup_size, max_compute_workgroup_subgroups,
+        required_subgroup_size_stages, max_inline_uniform_block_size,
+        max_per_stage_descriptor_inline_uniform_blocks,
+        max_per_stage_descriptor_update_after_bind_inline_uniform_blocks,
+        max_descriptor_set_inline_uniform_blocks,
+        max_descriptor_set_update_after_bind_inline_uniform_blocks, max_inline_uniform_total_size,
+        storage_texel_buffer_offset_alignment_bytes, uniform_texel_buffer_offset_alignment_bytes,
+        max_buffer_size
+      ],
+      bools [
+        integer_dot_product8_bit_unsigned_accelerated, integer_dot_product8_bit_signed_accelerated,
+        integer_dot_product8_bit_mixed_signedness_accelerated,
+        integer_dot_product4x8_bit_packed_unsigned_accelerated,
+        integer_dot_product4x8_bit_packed_signed_accelerated,
+        integer_dot_product4x8_bit_packed_mixed_signedness_accelerated,
+        integer_dot_product16_bit_unsigned_accelerated,
+        integer_dot_product16_bit_signed_accelerated,
+        integer_dot_product16_bit_mixed_signedness_accelerated,
+        integer_dot_product32_bit_unsigned_accelerated,
+        integer_dot_product32_bit_signed_accelerated,
+        integer_dot_product32_bit_mixed_signedness_accelerated,
+        integer_dot_product64_bit_unsigned_accelerated,
+        integer_dot_product64_bit_signed_accelerated,
+        integer_dot_product64_bit_mixed_signedness_accelerated,
+        integer_dot_product_accumulating_saturating8_bit_unsigned_accelerated,
+        integer_dot_product_accumulating_saturating8_bit_signed_accelerated,
+        integer_dot_product_accumulating_saturating8_bit_mixed_signedness_accelerated,
+        integer_dot_product_accumulating_saturating4x8_bit_packed_unsigned_accelerated,
+        integer_dot_product_accumulating_saturating4x8_bit_packed_signed_accelerated,
+        integer_dot_product_accumulating_saturating16_bit_unsigned_accelerated,
+        integer_dot_product_accumulating_saturating16_bit_signed_accelerated,
+        integer_dot_product_accumulating_saturating16_bit_mixed_signedness_accelerated,
+        integer_dot_product_accumulating_saturating32_bit_unsigned_accelerated,
+        integer_dot_product_accumulating_saturating32_bit_signed_accelerated,
+        integer_dot_product_accumulating_saturating32_bit_mixed_signedness_accelerated,
+        integer_dot_product_accumulating_saturating64_bit_unsigned_accelerated,
+        integer_dot_product_accumulating_saturating64_bit_signed_accelerated,
+        integer_dot_product_accumulating_saturating64_bit_mixed_signedness_accelerated,
+        storage_texel_buffer_offset_single_texel_alignment,
+        uniform_texel_buffer_offset_single_texel_alignment
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceFeatures {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [],
+      bools [
+        robust_buffer_access, full_draw_index_uint32, image_cube_array, independent_blend,
+        geometry_shader, tessellation_shader, sample_rate_shading, dual_src_blend, logic_op,
+        multi_draw_indirect, draw_indirect_first_instance, depth_clamp, depth_bias_clamp,
+        fill_mode_non_solid, depth_bounds, wide_lines, large_points, alpha_to_one, multi_viewport,
+        sampler_anisotropy, texture_compression_etc2, texture_compression_astc_ldr,
+        texture_compression_bc, occlusion_query_precise, pipeline_statistics_query,
+        vertex_pipeline_stores_and_atomics, fragment_stores_and_atomics,
+        shader_tessellation_and_geometry_point_size, shader_image_gather_extended,
+        shader_storage_image_extended_formats, shader_storage_image_multisample,
+        shader_storage_image_read_without_format, shader_storage_image_write_without_format,
+        shader_uniform_buffer_array_dynamic_indexing, shader_sampled_image_array_dynamic_indexing,
+        shader_storage_buffer_array_dynamic_indexing, shader_storage_image_array_dynamic_indexing,
+        shader_clip_distance, shader_cull_distance, shader_float64, shader_int64, shader_int16,
+        shader_resource_residency, shader_resource_min_lod, sparse_binding,
+        sparse_residency_buffer, sparse_residency_image2_d, sparse_residency_image3_d,
+        sparse_residency2_samples, sparse_residency4_samples, sparse_residency8_samples,
+        sparse_residency16_samples, sparse_residency_aliased, variable_multisample_rate,
+        inherited_queries
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceVulkan11Features {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [],
+      bools [
+        storage_buffer16_bit_access, uniform_and_storage_buffer16_bit_access,
+        storage_push_constant16, storage_input_output16, multiview, multiview_geometry_shader,
+        multiview_tessellation_shader, variable_pointers_storage_buffer, variable_pointers,
+        protected_memory, sampler_ycbcr_conversion, shader_draw_parameters
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceVulkan12Features {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [],
+      bools [
+        sampler_mirror_clamp_to_edge, draw_indirect_count, storage_buffer8_bit_access,
+        uniform_and_storage_buffer8_bit_access, storage_push_constant8,
+        shader_buffer_int64_atomics, shader_shared_int64_atomics, shader_float16, shader_int8,
+        descriptor_indexing, shader_input_attachment_array_dynamic_indexing,
+        shader_uniform_texel_buffer_array_dynamic_indexing,
+        shader_storage_texel_buffer_array_dynamic_indexing,
+        shader_uniform_buffer_array_non_uniform_indexing,
+        shader_sampled_image_array_non_uniform_indexing,
+        shader_storage_buffer_array_non_uniform_indexing,
+        shader_storage_image_array_non_uniform_indexing,
+        shader_input_attachment_array_non_uniform_indexing,
+        shader_uniform_texel_buffer_array_non_uniform_indexing,
+        shader_storage_texel_buffer_array_non_uniform_indexing,
+        descriptor_binding_uniform_buffer_update_after_bind,
+        descriptor_binding_sampled_image_update_after_bind,
+        descriptor_binding_storage_image_update_after_bind,
+        descriptor_binding_storage_buffer_update_after_bind,
+        descriptor_binding_uniform_texel_buffer_update_after_bind,
+        descriptor_binding_storage_texel_buffer_update_after_bind,
+        descriptor_binding_update_unused_while_pending, descriptor_binding_partially_bound,
+        descriptor_binding_variable_descriptor_count, runtime_descriptor_array,
+        sampler_filter_minmax, scalar_block_layout, imageless_framebuffer,
+        uniform_buffer_standard_layout, shader_subgroup_extended_types,
+        separate_depth_stencil_layouts, host_query_reset, timeline_semaphore,
+        buffer_device_address, buffer_device_address_capture_replay,
+        buffer_device_address_multi_device, vulkan_memory_model, vulkan_memory_model_device_scope,
+        vulkan_memory_model_availability_visibility_chains, shader_output_viewport_index,
+        shader_output_layer, subgroup_broadcast_dynamic_id
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::PhysicalDeviceVulkan13Features {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [],
+      bools [
+        robust_image_access, inline_uniform_block,
+        descriptor_binding_inline_uniform_block_update_after_bind, pipeline_creation_cache_control,
+        private_data, shader_demote_to_helper_invocation, shader_terminate_invocation,
+        subgroup_size_control, compute_full_subgroups, synchronization2,
+        texture_compression_astc_hdr, shader_zero_initialize_workgroup_memory, dynamic_rendering,
+        shader_integer_dot_product, maintenance4
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::ConformanceVersion {
+  fn to_json(&self) -> Value {
+    json_object!(self, [major, minor, subminor, patch])
+  }
+}
+
+impl ToJson for vk::QueueFamilyProperties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        queue_flags,
+        queue_count,
+        timestamp_valid_bits,
+        min_image_transfer_granularity
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::MemoryHeap {
+  fn to_json(&self) -> Value {
+    json_object!(self, [size, flags])
+  }
+}
+
+impl ToJson for vk::MemoryType {
+  fn to_json(&self) -> Value {
+    json_object!(self, [property_flags, heap_index])
+  }
+}
+
+impl ToJson for vk::FormatProperties {
+  fn to_json(&self) -> Value {
+    json_object!(
+      self,
+      [
+        linear_tiling_features,
+        optimal_tiling_features,
+        buffer_features
+      ]
+    )
+  }
+}
+
+impl ToJson for vk::Extent3D {
+  fn to_json(&self) -> Value {
+    json_object!(self, [width, height, depth])
+  }
+}
+
+// everything that is reported about a physical device, queried beforehand so that the
+// serialization doesn't depend on a Vulkan implementation
+pub struct DeviceInfo {
+  pub index: usize,
+  pub properties: PhysicalDeviceProperties,
+  pub features: PhysicalDeviceFeatures,
+  pub queue_families: Vec<vk::QueueFamilyProperties>,
+  pub memory: vk::PhysicalDeviceMemoryProperties,
+  // (name, spec version)
+  pub extensions: Vec<(String, u32)>,
+  pub formats: Vec<(vk::Format, vk::FormatProperties)>,
+}
+
+impl DeviceInfo {
+  pub fn query(
+    instance: &ash::Instance,
+    index: usize,
+    physical_device: vk::PhysicalDevice,
+  ) -> Result<Self, vk::Result> {
+    let extensions = unsafe { instance.enumerate_device_extension_properties(physical_device)? }
+      .iter()
+      .map(|extension| {
+        let name = unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) };
+        (name.to_string_lossy().into_owned(), extension.spec_version)
+      })
+      .collect();
+    let formats = reported_formats()
+      .into_iter()
+      .map(|format| {
+        let properties =
+          unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+        (format, properties)
+      })
+      .collect();
+
+    Ok(Self {
+      index,
+      properties: get_extended_properties(instance, physical_device),
+      features: get_extended_features(instance, physical_device),
+      queue_families: unsafe {
+        instance.get_physical_device_queue_family_properties(physical_device)
+      },
+      memory: unsafe { instance.get_physical_device_memory_properties(physical_device) },
+      extensions,
+      formats,
+    })
+  }
+}
+
+fn memory_json(memory: &vk::PhysicalDeviceMemoryProperties) -> Value {
+  let mut map = Map::new();
+  map.insert(
+    "heaps".to_owned(),
+    memory.memory_heaps[..memory.memory_heap_count as usize].to_json(),
+  );
+  map.insert(
+    "types".to_owned(),
+    memory.memory_types[..memory.memory_type_count as usize].to_json(),
+  );
+  Value::Object(map)
+}
+
+fn physical_device_json(info: &DeviceInfo) -> Value {
+  let properties = &info.properties;
+  let features = &info.features;
+  let vendor = Vendor::from_id(properties.p10.vendor_id);
+
+  let mut device = Map::new();
+  device.insert("index".to_owned(), info.index.to_json());
+  device.insert("name".to_owned(), properties.p10.device_name.to_json());
+  // readable versions of some values that are also included raw in "properties"
+  device.insert(
+    "api_version".to_owned(),
+    Value::String(parse_vulkan_api_version(properties.p10.api_version)),
+  );
+  device.insert("vendor".to_owned(), Value::String(vendor.to_string()));
+  device.insert(
+    "driver_version".to_owned(),
+    Value::String(vendor.parse_driver_version(properties.p10.driver_version)),
+  );
+
+  let mut all_properties = Map::new();
+  all_properties.insert("vulkan10".to_owned(), properties.p10.to_json());
+  all_properties.insert("vulkan11".to_owned(), properties.p11.to_json());
+  all_properties.insert("vulkan12".to_owned(), properties.p12.to_json());
+  all_properties.insert("vulkan13".to_owned(), properties.p13.to_json());
+  device.insert("properties".to_owned(), Value::Object(all_properties));
+
+  let mut all_features = Map::new();
+  all_features.insert("vulkan10".to_owned(), features.f10.to_json());
+  all_features.insert("vulkan11".to_owned(), features.f11.to_json());
+  all_features.insert("vulkan12".to_owned(), features.f12.to_json());
+  all_features.insert("vulkan13".to_owned(), features.f13.to_json());
+  device.insert("features".to_owned(), Value::Object(all_features));
+
+  device.insert("queue_families".to_owned(), info.queue_families.to_json());
+  device.insert("memory".to_owned(), memory_json(&info.memory));
+
+  let mut extensions = Map::new();
+  for (name, spec_version) in info.extensions.iter() {
+    extensions.insert(name.clone(), spec_version.to_json());
+  }
+  device.insert("extensions".to_owned(), Value::Object(extensions));
+
+  let mut formats = Map::new();
+  for (format, properties) in info.formats.iter() {
+    formats.insert(format!("{:?}", format), properties.to_json());
+  }
+  device.insert("formats".to_owned(), Value::Object(formats));
+
+  Value::Object(device)
+}
+
+pub fn report_json(devices: &[DeviceInfo]) -> Value {
+  let mut report = Map::new();
+  report.insert(
+    "physical_devices".to_owned(),
+    Value::Array(devices.iter().map(physical_device_json).collect()),
+  );
+  Value::Object(report)
+}
+
+// report on all physical devices, not only on the ones that would be selected
+pub fn physical_devices_report(instance: &ash::Instance) -> Result<Value, vk::Result> {
+  let physical_devices = unsafe { instance.enumerate_physical_devices()? };
+  let devices = physical_devices
+    .into_iter()
+    .enumerate()
+    .map(|(i, physical_device)| DeviceInfo::query(instance, i, physical_device))
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok(report_json(&devices))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn device_info() -> DeviceInfo {
+    let mut properties = PhysicalDeviceProperties {
+      p10: vk::PhysicalDeviceProperties::default(),
+      p11: vk::PhysicalDeviceVulkan11Properties::default(),
+      p12: vk::PhysicalDeviceVulkan12Properties::default(),
+      p13: vk::PhysicalDeviceVulkan13Properties::default(),
+    };
+    properties.p10.api_version = vk::API_VERSION_1_3;
+    properties.p10.vendor_id = 0x10005; // Mesa
+    properties.p10.device_type = vk::PhysicalDeviceType::CPU;
+    for (c, b) in properties.p10.device_name.iter_mut().zip(b"llvmpipe") {
+      *c = *b as c_char;
+    }
+    properties.p10.limits.max_compute_work_group_size = [1024, 1024, 64];
+    properties.p11.device_uuid = [0xAB; vk::UUID_SIZE];
+
+    let mut features = PhysicalDeviceFeatures::default();
+    features.f13.synchronization2 = vk::TRUE;
+
+    let mut memory = vk::PhysicalDeviceMemoryProperties {
+      memory_heap_count: 1,
+      memory_type_count: 1,
+      ..Default::default()
+    };
+    memory.memory_heaps[0] = vk::MemoryHeap {
+      size: 1 << 30,
+      flags: vk::MemoryHeapFlags::DEVICE_LOCAL,
+    };
+    memory.memory_types[0] = vk::MemoryType {
+      property_flags: vk::MemoryPropertyFlags::DEVICE_LOCAL,
+      heap_index: 0,
+    };
+
+    DeviceInfo {
+      index: 0,
+      properties,
+      features,
+      queue_families: vec![vk::QueueFamilyProperties {
+        queue_flags: vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE,
+        queue_count: 1,
+        timestamp_valid_bits: 64,
+        min_image_transfer_granularity: vk::Extent3D {
+          width: 1,
+          height: 1,
+          depth: 1,
+        },
+      }],
+      memory,
+      extensions: vec![("VK_KHR_swapchain".to_owned(), 70)],
+      formats: vec![(
+        vk::Format::R8G8B8A8_UNORM,
+        vk::FormatProperties {
+          optimal_tiling_features: vk::FormatFeatureFlags::STORAGE_IMAGE,
+          ..Default::default()
+        },
+      )],
+    }
+  }
+
+  fn keys(value: &Value) -> Vec<&str> {
+    value
+      .as_object()
+      .unwrap()
+      .keys()
+      .map(String::as_str)
+      .collect()
+  }
+
+  // consumers of the report depend on these names, so renaming any of them should be deliberate
+  #[test]
+  fn report_shape() {
+    let report = report_json(&[device_info()]);
+    assert_eq!(keys(&report), ["physical_devices"]);
+    let device = &report["physical_devices"][0];
+
+    let mut device_keys = keys(device);
+    device_keys.sort_unstable();
+    assert_eq!(
+      device_keys,
+      [
+        "api_version",
+        "driver_version",
+        "extensions",
+        "features",
+        "formats",
+        "index",
+        "memory",
+        "name",
+        "properties",
+        "queue_families",
+        "vendor"
+      ]
+    );
+    for versioned in ["properties", "features"] {
+      let mut versions = keys(&device[versioned]);
+      versions.sort_unstable();
+      assert_eq!(versions, ["vulkan10", "vulkan11", "vulkan12", "vulkan13"]);
+    }
+
+    assert_eq!(device["index"], 0);
+    assert_eq!(device["name"], "llvmpipe");
+    assert_eq!(device["api_version"], "1.3.0");
+    let p10 = &device["properties"]["vulkan10"];
+    assert_eq!(p10["device_type"], "CPU");
+    assert_eq!(
+      p10["limits"]["max_compute_work_group_size"],
+      serde_json::json!([1024, 1024, 64])
+    );
+    assert_eq!(p10["limits"]["strict_lines"], false);
+    assert_eq!(
+      device["properties"]["vulkan11"]["device_uuid"],
+      "abababab-abab-abab-abab-abababababab"
+    );
+    assert_eq!(device["features"]["vulkan13"]["synchronization2"], true);
+    assert_eq!(device["features"]["vulkan13"]["dynamic_rendering"], false);
+
+    assert_eq!(
+      device["queue_families"],
+      serde_json::json!([{
+        "queue_flags": "GRAPHICS | COMPUTE",
+        "queue_count": 1,
+        "timestamp_valid_bits": 64,
+        "min_image_transfer_granularity": {"width": 1, "height": 1, "depth": 1}
+      }])
+    );
+    assert_eq!(
+      device["memory"],
+      serde_json::json!({
+        "heaps": [{"size": 1u64 << 30, "flags": "DEVICE_LOCAL"}],
+        "types": [{"property_flags": "DEVICE_LOCAL", "heap_index": 0}]
+      })
+    );
+    assert_eq!(
+      device["extensions"],
+      serde_json::json!({"VK_KHR_swapchain": 70})
+    );
+    assert_eq!(
+      device["formats"]["R8G8B8A8_UNORM"]["optimal_tiling_features"],
+      "STORAGE_IMAGE"
+    );
+  }
+
+  #[test]
+  fn selectable_and_vertex_formats_are_reported() {
+    let formats = reported_formats();
+    for format in TexelFormat::ALL {
+      assert!(formats.contains(&format.vk_format()), "{:?}", format);
+    }
+    assert!(formats.contains(&vk::Format::R32G32_SFLOAT));
+    assert!(formats.contains(&vk::Format::R32G32B32_SFLOAT));
+    // formats are keys of the report
+    for (i, format) in formats.iter().enumerate() {
+      assert!(!formats[..i].contains(format), "{:?}", format);
+    }
+  }
+
+  #[test]
+  fn report_round_trips_through_text() {
+    let report = report_json(&[device_info(), device_info()]);
+    let text = serde_json::to_string_pretty(&report).unwrap();
+    assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), report);
+  }
+}
@@ -0,0 +1,392 @@
+// Helpers for the golden image regression tests of each example
+//
+// Every test runs the example binary, reads the image that it saved and compares it pixel-wise
+// with a checked-in reference. Tests are skipped if no Vulkan implementation is available, so
+// that running `cargo test` on a machine without a driver still succeeds.
+//
+// Environment variables:
+// - ABE_GOLDEN_REQUIRE_VULKAN: fail instead of skipping if Vulkan is not available (set this on CI
+//   so that a broken driver setup doesn't silently pass).
+// - ABE_GOLDEN_BLESS: overwrite references with the produced images instead of comparing them.
+//
+// Use a software implementation (for example Mesa lavapipe) by pointing the loader to its ICD:
+// `VK_DRIVER_FILES=/usr/share/vulkan/icd.d/lvp_icd.x86_64.json cargo test`
+
+use std::{
+  ffi::CStr,
+  fmt,
+  path::Path,
+  process::{Command, Output},
+  ptr,
+  sync::OnceLock,
+};
+
+use ash::vk;
+use image::{Rgba, RgbaImage};
+
+const REQUIRE_VULKAN_VAR: &str = "ABE_GOLDEN_REQUIRE_VULKAN";
+const BLESS_VAR: &str = "ABE_GOLDEN_BLESS";
+// see ValidationMode in core/src/validation_layers.rs
+const VALIDATION_MODE_VAR: &str = "ABE_VALIDATION";
+
+// all examples target Vulkan 1.3
+const REQUIRED_API_VERSION: u32 = vk::API_VERSION_1_3;
+
+fn env_flag(name: &str) -> bool {
+  std::env::var_os(name).is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+// Returns the name of the first physical device that supports the required API version
+// Fails if the Vulkan library cannot be loaded or if there is no such device
+fn find_vulkan_device() -> Result<String, String> {
+  let entry = unsafe { ash::Entry::load() }.map_err(|err| format!("{}", err))?;
+
+  let app_info = vk::ApplicationInfo {
+    s_type: vk::StructureType::APPLICATION_INFO,
+    p_next: ptr::null(),
+    p_application_name: ptr::null(),
+    application_version: 0,
+    p_engine_name: ptr::null(),
+    engine_version: 0,
+    api_version: REQUIRED_API_VERSION,
+  };
+  let create_info = vk::InstanceCreateInfo {
+    s_type: vk::StructureType::INSTANCE_CREATE_INFO,
+    p_next: ptr::null(),
+    flags: vk::InstanceCreateFlags::empty(),
+    p_application_info: &app_info,
+    enabled_layer_count: 0,
+    pp_enabled_layer_names: ptr::null(),
+    enabled_extension_count: 0,
+    pp_enabled_extension_names: ptr::null(),
+  };
+
+  unsafe {
+    let instance = entry
+      .create_instance(&create_info, None)
+      .map_err(|err| format!("Failed to create an instance: {:?}", err))?;
+
+    let result = match instance.enumerate_physical_devices() {
+      Ok(devices) => devices
+        .into_iter()
+        .map(|device| instance.get_physical_device_properties(device))
+        .find(|properties| properties.api_version >= REQUIRED_API_VERSION)
+        .map(|properties| {
+          CStr::from_ptr(properties.device_name.as_ptr())
+            .to_string_lossy()
+            .into_owned()
+        })
+        .ok_or_else(|| "No physical device supports Vulkan 1.3".to_owned()),
+      Err(err) => Err(format!("Failed to enumerate physical devices: {:?}", err)),
+    };
+
+    instance.destroy_instance(None);
+    result
+  }
+}
+
+// Returns true if the test should be skipped because there is no usable Vulkan implementation
+// Panics instead if ABE_GOLDEN_REQUIRE_VULKAN is set
+pub fn skip_without_vulkan() -> bool {
+  static AVAILABLE: OnceLock<Result<String, String>> = OnceLock::new();
+
+  match AVAILABLE.get_or_init(find_vulkan_device) {
+    Ok(device_name) => {
+      eprintln!("Running golden test on \"{}\"", device_name);
+      false
+    }
+    Err(reason) => {
+      if env_flag(REQUIRE_VULKAN_VAR) {
+        panic!(
+          "Vulkan is not available ({}) and {} is set",
+          reason, REQUIRE_VULKAN_VAR
+        );
+      }
+      eprintln!("Skipping golden test: Vulkan is not available ({})", reason);
+      true
+    }
+  }
+}
+
+// Runs an example binary in `dir` (examples load shaders and textures relative to the current
+// directory) and panics with its output if it fails
+// Unless ABE_VALIDATION is already set, examples built with validation layers abort on the first
+// validation error, so a test also fails if its example uses the API incorrectly
+pub fn run_example(bin: &str, dir: &Path, args: &[&str]) -> Output {
+  run_example_with_env(bin, dir, args, &[])
+}
+
+// Same as run_example, with additional environment variables set for the example
+pub fn run_example_with_env(bin: &str, dir: &Path, args: &[&str], env: &[(&str, &str)]) -> Output {
+  let mut command = Command::new(bin);
+  command
+    .args(args)
+    .current_dir(dir)
+    .envs(env.iter().copied());
+  if std::env::var_os(VALIDATION_MODE_VAR).is_none() {
+    command.env(VALIDATION_MODE_VAR, "panic");
+  }
+  let output = command
+    .output()
+    .unwrap_or_else(|err| panic!("Failed to run {}: {}", bin, err));
+
+  if !output.status.success() {
+    panic!(
+      "{} {:?} exited with {}\n--- stdout ---\n{}\n--- stderr ---\n{}",
+      bin,
+      args,
+      output.status,
+      String::from_utf8_lossy(&output.stdout),
+      String::from_utf8_lossy(&output.stderr),
+    );
+  }
+  output
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+  // maximum absolute difference in any channel for a pixel to be considered equal
+  pub channel: u8,
+  // maximum ratio of pixels that are allowed to differ
+  pub mismatched_ratio: f64,
+}
+
+impl Tolerance {
+  pub const EXACT: Self = Self {
+    channel: 0,
+    mismatched_ratio: 0.0,
+  };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+  pub mismatched: u64,
+  pub total: u64,
+  pub max_channel_difference: u8,
+}
+
+impl Comparison {
+  pub fn mismatched_ratio(&self) -> f64 {
+    if self.total == 0 {
+      0.0
+    } else {
+      self.mismatched as f64 / self.total as f64
+    }
+  }
+
+  pub fn is_within(&self, tolerance: Tolerance) -> bool {
+    self.mismatched_ratio() <= tolerance.mismatched_ratio
+  }
+}
+
+impl fmt::Display for Comparison {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} of {} pixels differ ({:.4}%), max channel difference {}",
+      self.mismatched,
+      self.total,
+      self.mismatched_ratio() * 100.0,
+      self.max_channel_difference
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimensionMismatch {
+  pub actual: (u32, u32),
+  pub expected: (u32, u32),
+}
+
+fn channel_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> u8 {
+  a.0
+    .iter()
+    .zip(b.0.iter())
+    .map(|(x, y)| x.abs_diff(*y))
+    .max()
+    .unwrap()
+}
+
+// Compares two images pixel by pixel
+// A pixel is mismatched if any of its channels differs by more than `channel_tolerance`
+pub fn compare_images(
+  actual: &RgbaImage,
+  expected: &RgbaImage,
+  channel_tolerance: u8,
+) -> Result<Comparison, DimensionMismatch> {
+  if actual.dimensions() != expected.dimensions() {
+    return Err(DimensionMismatch {
+      actual: actual.dimensions(),
+      expected: expected.dimensions(),
+    });
+  }
+
+  let mut comparison = Comparison {
+    mismatched: 0,
+    total: actual.width() as u64 * actual.height() as u64,
+    max_channel_difference: 0,
+  };
+  for (a, b) in actual.pixels().zip(expected.pixels()) {
+    let difference = channel_difference(a, b);
+    comparison.max_channel_difference = comparison.max_channel_difference.max(difference);
+    if difference > channel_tolerance {
+      comparison.mismatched += 1;
+    }
+  }
+  Ok(comparison)
+}
+
+// Image with mismatched pixels in red and the rest as a faded version of the expected image
+pub fn difference_image(
+  actual: &RgbaImage,
+  expected: &RgbaImage,
+  channel_tolerance: u8,
+) -> RgbaImage {
+  RgbaImage::from_fn(expected.width(), expected.height(), |x, y| {
+    let expected_pixel = expected.get_pixel(x, y);
+    if channel_difference(actual.get_pixel(x, y), expected_pixel) > channel_tolerance {
+      Rgba([255, 0, 0, 255])
+    } else {
+      let luma = expected_pixel.0[..3].iter().map(|&c| c as u32).sum::<u32>() / 3;
+      let faded = (luma / 4) as u8;
+      Rgba([faded, faded, faded, 255])
+    }
+  })
+}
+
+fn open_rgba(path: &Path) -> RgbaImage {
+  image::open(path)
+    .unwrap_or_else(|err| panic!("Failed to open {}: {}", path.display(), err))
+    .into_rgba8()
+}
+
+// Compares the image saved by an example with its reference
+// If the images don't match, a difference image is saved to `diff_dir` before panicking
+// If ABE_GOLDEN_BLESS is set, the reference is replaced by the output instead
+pub fn assert_matches_reference(
+  output: &Path,
+  reference: &Path,
+  diff_dir: &Path,
+  tolerance: Tolerance,
+) {
+  if env_flag(BLESS_VAR) {
+    std::fs::copy(output, reference).unwrap_or_else(|err| {
+      panic!(
+        "Failed to bless {} with {}: {}",
+        reference.display(),
+        output.display(),
+        err
+      )
+    });
+    eprintln!("Blessed {}", reference.display());
+    return;
+  }
+
+  let actual = open_rgba(output);
+  let expected = open_rgba(reference);
+
+  let comparison = match compare_images(&actual, &expected, tolerance.channel) {
+    Ok(comparison) => comparison,
+    Err(mismatch) => panic!(
+      "{} has dimensions {:?} while reference {} has {:?}",
+      output.display(),
+      mismatch.actual,
+      reference.display(),
+      mismatch.expected
+    ),
+  };
+  eprintln!("{}: {}", output.display(), comparison);
+
+  if !comparison.is_within(tolerance) {
+    let file_name = reference.file_stem().unwrap().to_string_lossy();
+    let diff_path = diff_dir.join(format!("{}.diff.png", file_name));
+    let actual_path = diff_dir.join(format!("{}.actual.png", file_name));
+    std::fs::create_dir_all(diff_dir).expect("Failed to create difference image directory");
+    difference_image(&actual, &expected, tolerance.channel)
+      .save(&diff_path)
+      .expect("Failed to save difference image");
+    actual
+      .save(&actual_path)
+      .expect("Failed to save actual image");
+
+    panic!(
+      "{} doesn't match reference {} within {:?}: {}\nSaved the difference to {}",
+      output.display(),
+      reference.display(),
+      tolerance,
+      comparison,
+      diff_path.display()
+    );
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn solid(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+    RgbaImage::from_pixel(width, height, Rgba(color))
+  }
+
+  #[test]
+  fn equal_images_have_no_mismatches() {
+    let a = solid(4, 3, [10, 20, 30, 255]);
+    let comparison = compare_images(&a, &a.clone(), 0).unwrap();
+    assert_eq!(comparison.mismatched, 0);
+    assert_eq!(comparison.total, 12);
+    assert_eq!(comparison.max_channel_difference, 0);
+    assert!(comparison.is_within(Tolerance::EXACT));
+  }
+
+  #[test]
+  fn channel_tolerance_is_inclusive() {
+    let a = solid(2, 2, [100, 100, 100, 255]);
+    let mut b = a.clone();
+    b.put_pixel(0, 0, Rgba([102, 100, 100, 255]));
+    b.put_pixel(1, 1, Rgba([100, 97, 100, 255]));
+
+    let comparison = compare_images(&b, &a, 2).unwrap();
+    assert_eq!(comparison.mismatched, 1);
+    assert_eq!(comparison.max_channel_difference, 3);
+
+    assert_eq!(compare_images(&b, &a, 3).unwrap().mismatched, 0);
+  }
+
+  #[test]
+  fn mismatched_ratio_tolerance() {
+    let a = solid(10, 10, [0, 0, 0, 255]);
+    let mut b = a.clone();
+    b.put_pixel(5, 5, Rgba([255, 255, 255, 255]));
+
+    let comparison = compare_images(&b, &a, 0).unwrap();
+    assert!(!comparison.is_within(Tolerance::EXACT));
+    assert!(comparison.is_within(Tolerance {
+      channel: 0,
+      mismatched_ratio: 0.01,
+    }));
+  }
+
+  #[test]
+  fn dimension_mismatch_is_reported() {
+    let a = solid(2, 3, [0; 4]);
+    let b = solid(3, 2, [0; 4]);
+    assert_eq!(
+      compare_images(&a, &b, 0),
+      Err(DimensionMismatch {
+        actual: (2, 3),
+        expected: (3, 2)
+      })
+    );
+  }
+
+  #[test]
+  fn difference_image_marks_mismatches() {
+    let a = solid(2, 1, [40, 40, 40, 255]);
+    let mut b = a.clone();
+    b.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+
+    let diff = difference_image(&b, &a, 0);
+    assert_eq!(diff.get_pixel(0, 0), &Rgba([10, 10, 10, 255]));
+    assert_eq!(diff.get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+  }
+}
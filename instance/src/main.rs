@@ -1,22 +1,9 @@
-mod entry;
-mod instance;
-mod utility;
-
-// validation layers module will only exist if validation layers are enabled
-#[cfg(feature = "vl")]
-mod validation_layers;
-
 use ash::vk;
+use ash_by_example_core::{create_instance, cstr, get_entry, ApplicationInfo};
 use std::ffi::CStr;
 
-// validation layers names should be valid cstrings (not contain null bytes nor invalid characters)
-#[cfg(feature = "vl")]
-pub const VALIDATION_LAYERS: [&'static CStr; 1] = [cstr!("VK_LAYER_KHRONOS_validation")];
-#[cfg(feature = "vl")]
-pub const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] = [
-  vk::ValidationFeatureEnableEXT::BEST_PRACTICES,
-  vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION,
-];
+// Instance creation (as well as validation layers) are implemented in the shared
+// ash-by-example-core crate, in core/src/instance.rs and core/src/validation_layers.rs
 
 // Vulkan API version required to run the program
 // You may have to use an older API version if you want to support devices that do not yet support
@@ -25,18 +12,25 @@ pub const ADDITIONAL_VALIDATION_FEATURES: [vk::ValidationFeatureEnableEXT; 2] =
 pub const TARGET_API_VERSION: u32 = vk::API_VERSION_1_3;
 
 // somewhat arbitrary
-pub const APPLICATION_NAME: &'static CStr = cstr!("Vulkan Instance Creation");
+pub const APPLICATION_NAME: &CStr = cstr!("Vulkan Instance Creation");
 pub const APPLICATION_VERSION: u32 = vk::make_api_version(0, 1, 0, 0);
 
 fn main() {
   env_logger::init();
 
-  let entry: ash::Entry = unsafe { entry::get_entry() };
+  let entry: ash::Entry = unsafe { get_entry() };
+
+  let app_info = ApplicationInfo {
+    name: APPLICATION_NAME,
+    version: APPLICATION_VERSION,
+    api_version: TARGET_API_VERSION,
+  };
 
   #[cfg(feature = "vl")]
-  let (instance, mut debug_utils) = instance::create_instance(&entry).expect("Failed to create an instance");
+  let (instance, mut debug_utils) =
+    create_instance(&entry, &app_info, &[]).expect("Failed to create an instance");
   #[cfg(not(feature = "vl"))]
-  let instance = instance::create_instance(&entry).expect("Failed to create an instance");
+  let instance = create_instance(&entry, &app_info, &[]).expect("Failed to create an instance");
 
   println!("Successfully created an Instance!");
 
@@ -0,0 +1,245 @@
+use std::path::PathBuf;
+
+use ash::vk;
+use ash_by_example_core::{image_file::TexelFormat, spirv};
+
+use crate::errors::ConfigError;
+
+pub const USAGE: &str = "Usage: storage-image-compute-shader [OPTIONS]
+
+Options:
+  --width <PIXELS>           image width (default: 4000)
+  --height <PIXELS>          image height (default: 4000)
+  --focal-point <X,Y>        complex plane coordinates of the image center (default: -0.765,0)
+  --zoom <ZOOM>              zoom relative to the image width (default: 0.40486)
+  --max-iterations <COUNT>   maximum number of iterations for each pixel (default: 10000)
+  --format <FORMAT>          image format, one of: R8G8B8A8_UNORM, R16_UNORM, R16G16B16A16_UNORM,
+                             R16G16B16A16_SFLOAT, R32G32B32A32_SFLOAT (default: R8G8B8A8_UNORM)
+  --output <PATH>            where to save the resulting image, .exr and .hdr files store floats
+                             (default: image.png)
+  --bench <N>                submit the recorded work N times and report timings
+  --help                     print this message";
+
+const DEFAULT_WIDTH: u32 = 4000;
+const DEFAULT_HEIGHT: u32 = 4000;
+const DEFAULT_FOCAL_POINT: [f32; 2] = [-0.765, 0.0];
+const DEFAULT_ZOOM: f32 = 0.40486;
+const DEFAULT_MAX_ITERATIONS: u32 = 10000;
+const DEFAULT_FORMAT: TexelFormat = TexelFormat::R8G8B8A8Unorm;
+const DEFAULT_OUTPUT: &str = "image.png";
+
+// Mandelbrot view and output parameters
+// Values that the shader uses are passed to it as specialization constants (see pipeline.rs)
+#[derive(Debug, Clone)]
+pub struct Config {
+  pub width: u32,
+  pub height: u32,
+  pub focal_point: [f32; 2], // complex plane coordinates of the image center
+  pub zoom: f32,
+  pub max_iterations: u32,
+  // format of the image the shader writes to
+  pub format: TexelFormat,
+  pub output: PathBuf,
+  // number of benchmark iterations
+  pub bench: Option<u32>,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      width: DEFAULT_WIDTH,
+      height: DEFAULT_HEIGHT,
+      focal_point: DEFAULT_FOCAL_POINT,
+      zoom: DEFAULT_ZOOM,
+      max_iterations: DEFAULT_MAX_ITERATIONS,
+      format: DEFAULT_FORMAT,
+      output: PathBuf::from(DEFAULT_OUTPUT),
+      bench: None,
+    }
+  }
+}
+
+impl Config {
+  // Returns Ok(None) if --help was passed
+  pub fn from_args() -> Result<Option<Self>, ConfigError> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    Self::parse(&args)
+  }
+
+  // `args` don't include the program name
+  fn parse(args: &[String]) -> Result<Option<Self>, ConfigError> {
+    // --help is recognized anywhere, even in place of a value
+    if args.iter().any(|arg| arg == "--help") {
+      return Ok(None);
+    }
+
+    let mut config = Self::default();
+    let mut args = args.iter();
+    while let Some(option) = args.next() {
+      let value = args
+        .next()
+        .ok_or_else(|| ConfigError::MissingValue(option.clone()))?;
+      config.set(option, value)?;
+    }
+
+    Ok(Some(config))
+  }
+
+  fn set(&mut self, option: &str, value: &str) -> Result<(), ConfigError> {
+    let invalid = |reason| ConfigError::InvalidValue {
+      option: option.to_owned(),
+      value: value.to_owned(),
+      reason,
+    };
+    // values that are not finite would make every pixel bail out immediately
+    let parse_finite = |s: &str| {
+      s.trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| invalid("expected a finite number"))
+    };
+    let parse_positive = |s: &str| {
+      s.parse::<u32>()
+        .ok()
+        .filter(|&v| v > 0)
+        .ok_or_else(|| invalid("expected a number greater than 0"))
+    };
+
+    match option {
+      "--width" => self.width = parse_positive(value)?,
+      "--height" => self.height = parse_positive(value)?,
+      "--focal-point" => {
+        let (x, y) = value
+          .split_once(',')
+          .ok_or_else(|| invalid("expected two comma separated numbers"))?;
+        self.focal_point = [parse_finite(x)?, parse_finite(y)?];
+      }
+      "--zoom" => {
+        self.zoom = parse_finite(value)?;
+        if self.zoom <= 0.0 {
+          return Err(invalid("zoom should be greater than 0"));
+        }
+      }
+      "--max-iterations" => self.max_iterations = parse_positive(value)?,
+      "--format" => {
+        self.format = TexelFormat::from_name(value).ok_or_else(|| invalid("unsupported format"))?;
+        // sRGB formats can't be used as storage images
+        if spirv::ImageFormat::from_vk(self.format.vk_format()).is_none() {
+          return Err(invalid("format can't be used as a storage image"));
+        }
+      }
+      "--output" => self.output = PathBuf::from(value),
+      "--bench" => self.bench = Some(parse_positive(value)?),
+      _ => return Err(ConfigError::UnknownOption(option.to_owned())),
+    }
+    Ok(())
+  }
+
+  // storage image format declared in the shader, see shaders/shader.rs
+  pub fn shader_format(&self) -> spirv::ImageFormat {
+    spirv::ImageFormat::from_vk(self.format.vk_format()).unwrap()
+  }
+
+  pub fn extent(&self) -> vk::Extent2D {
+    vk::Extent2D {
+      width: self.width,
+      height: self.height,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse(args: &[&str]) -> Result<Option<Config>, ConfigError> {
+    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+    Config::parse(&args)
+  }
+
+  #[test]
+  fn options_are_parsed() {
+    let config = parse(&[
+      "--width",
+      "1920",
+      "--height",
+      "1080",
+      "--focal-point",
+      "-0.7436, 0.1318",
+      "--zoom",
+      "200",
+      "--max-iterations",
+      "20000",
+      "--format",
+      "R32G32B32A32_SFLOAT",
+      "--output",
+      "seahorse.exr",
+      "--bench",
+      "5",
+    ])
+    .unwrap()
+    .unwrap();
+    assert_eq!((config.width, config.height), (1920, 1080));
+    assert_eq!(config.focal_point, [-0.7436, 0.1318]);
+    assert_eq!(config.zoom, 200.0);
+    assert_eq!(config.max_iterations, 20000);
+    assert_eq!(config.format, TexelFormat::R32G32B32A32Sfloat);
+    assert_eq!(config.output, PathBuf::from("seahorse.exr"));
+    assert_eq!(config.bench, Some(5));
+
+    let defaults = parse(&[]).unwrap().unwrap();
+    assert_eq!(defaults.focal_point, DEFAULT_FOCAL_POINT);
+    assert_eq!(defaults.format, DEFAULT_FORMAT);
+  }
+
+  #[test]
+  fn help_is_recognized_in_any_position() {
+    assert!(parse(&["--help"]).unwrap().is_none());
+    assert!(parse(&["--zoom", "--help"]).unwrap().is_none());
+    assert!(parse(&["--zoom", "2", "--help"]).unwrap().is_none());
+    assert!(parse(&["--bogus", "1", "--help"]).unwrap().is_none());
+  }
+
+  #[test]
+  fn missing_values_are_rejected() {
+    assert!(matches!(
+      parse(&["--zoom"]),
+      Err(ConfigError::MissingValue(option)) if option == "--zoom"
+    ));
+  }
+
+  #[test]
+  fn invalid_values_are_rejected() {
+    for args in [
+      ["--width", "0"],
+      ["--height", "-1"],
+      ["--focal-point", "0.5"],
+      ["--focal-point", "NaN,0"],
+      ["--zoom", "0"],
+      ["--zoom", "inf"],
+      ["--max-iterations", "many"],
+      ["--format", "R8G8B8A8_SRGB"],
+      ["--format", "B8G8R8A8_UNORM"],
+      ["--bench", "0"],
+    ] {
+      assert!(
+        matches!(
+          parse(&args),
+          Err(ConfigError::InvalidValue { ref option, ref value, .. })
+            if option == args[0] && value == args[1]
+        ),
+        "{:?} was accepted",
+        args
+      );
+    }
+  }
+
+  #[test]
+  fn unknown_options_are_rejected() {
+    assert!(matches!(
+      parse(&["--color", "1,2,3"]),
+      Err(ConfigError::UnknownOption(option)) if option == "--color"
+    ));
+  }
+}
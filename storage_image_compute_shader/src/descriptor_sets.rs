@@ -1,27 +1,26 @@
-use std::ptr::{self, addr_of};
+use std::{
+  ptr::{self, addr_of},
+  sync::Arc,
+};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 pub struct DescriptorSets {
-  pub layout: vk::DescriptorSetLayout,
+  // sets are freed with the pool, which is dropped before the layout
   pub pool: DescriptorSetPool,
+  pub layout: Owned<vk::DescriptorSetLayout>,
 }
 
 impl DescriptorSets {
-  pub fn new(device: &ash::Device) -> Self {
-    let layout = create_layout(device);
-
-    let pool = DescriptorSetPool::new(device, layout);
-    Self { layout, pool }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    self.pool.destroy_self(device);
-    device.destroy_descriptor_set_layout(self.layout, None);
+  pub fn new(device: &Arc<Device>) -> Result<Self, vk::Result> {
+    let layout = unsafe { Owned::from_raw(device, create_layout(device)?) };
+    let pool = DescriptorSetPool::new(device, *layout)?;
+    Ok(Self { pool, layout })
   }
 }
 
-fn create_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
+fn create_layout(device: &ash::Device) -> Result<vk::DescriptorSetLayout, vk::Result> {
   let bindings = [vk::DescriptorSetLayoutBinding {
     binding: 0,
     descriptor_type: vk::DescriptorType::STORAGE_IMAGE,
@@ -38,20 +37,16 @@ fn create_layout(device: &ash::Device) -> vk::DescriptorSetLayout {
     p_bindings: bindings.as_ptr(),
   };
 
-  unsafe {
-    device
-      .create_descriptor_set_layout(&create_info, None)
-      .expect("Failed to create a descriptor set layout")
-  }
+  unsafe { device.create_descriptor_set_layout(&create_info, None) }
 }
 
 pub struct DescriptorSetPool {
-  pool: vk::DescriptorPool,
+  _pool: Owned<vk::DescriptorPool>,
   pub mandelbrot: vk::DescriptorSet,
 }
 
 impl DescriptorSetPool {
-  pub fn new(device: &ash::Device, layout: vk::DescriptorSetLayout) -> Self {
+  pub fn new(device: &Arc<Device>, layout: vk::DescriptorSetLayout) -> Result<Self, vk::Result> {
     let sizes = [vk::DescriptorPoolSize {
       ty: vk::DescriptorType::STORAGE_IMAGE,
       descriptor_count: 1,
@@ -65,28 +60,27 @@ impl DescriptorSetPool {
       flags: vk::DescriptorPoolCreateFlags::empty(),
     };
     let pool = unsafe {
-      device
-        .create_descriptor_pool(&pool_create_info, None)
-        .expect("Failed to create descriptor pool")
+      Owned::from_raw(
+        device,
+        device.create_descriptor_pool(&pool_create_info, None)?,
+      )
     };
 
     let allocate_info = vk::DescriptorSetAllocateInfo {
       s_type: vk::StructureType::DESCRIPTOR_SET_ALLOCATE_INFO,
       p_next: ptr::null(),
-      descriptor_pool: pool,
+      descriptor_pool: *pool,
       descriptor_set_count: 1,
       p_set_layouts: addr_of!(layout),
     };
-    let descriptor_set = unsafe {
-      device
-        .allocate_descriptor_sets(&allocate_info)
-        .expect("Failed to allocate descriptor set")[0]
-    };
+    // can also fail with vk::Result::ERROR_OUT_OF_POOL_MEMORY or ERROR_FRAGMENTED_POOL if the
+    // pool is too small, which shouldn't happen here
+    let descriptor_set = unsafe { device.allocate_descriptor_sets(&allocate_info)?[0] };
 
-    Self {
-      pool,
+    Ok(Self {
+      _pool: pool,
       mandelbrot: descriptor_set,
-    }
+    })
   }
 
   pub fn write_image(&mut self, device: &ash::Device, view: vk::ImageView, sampler: vk::Sampler) {
@@ -114,8 +108,4 @@ impl DescriptorSetPool {
       device.update_descriptor_sets(&[write], &[]);
     }
   }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_descriptor_pool(self.pool, None);
-  }
 }
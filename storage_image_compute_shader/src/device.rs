@@ -0,0 +1,176 @@
+use ash::vk;
+use ash_by_example_core::{
+  const_flag_bitor, DeviceRequirements, PhysicalDeviceProperties, QueueFamilyRequirements,
+};
+
+use crate::{
+  config::Config, work_group_count, REQUIRED_DEVICE_EXTENSIONS, SHADER_GROUP_SIZE_X,
+  SHADER_GROUP_SIZE_Y, TARGET_API_VERSION,
+};
+
+// kinda overkill
+const REQUIRED_FORMAT_IMAGE_FLAGS_OPTIMAL: vk::FormatFeatureFlags = const_flag_bitor!(
+  vk::FormatFeatureFlags,
+  vk::FormatFeatureFlags::TRANSFER_SRC,
+  vk::FormatFeatureFlags::STORAGE_IMAGE
+);
+const REQUIRED_FORMAT_IMAGE_FLAGS_LINEAR: vk::FormatFeatureFlags =
+  vk::FormatFeatureFlags::TRANSFER_DST;
+
+const REQUIRED_IMAGE_USAGE_FLAGS_OPTIMAL: vk::ImageUsageFlags = const_flag_bitor!(
+  vk::ImageUsageFlags,
+  vk::ImageUsageFlags::TRANSFER_SRC,
+  vk::ImageUsageFlags::STORAGE
+);
+const REQUIRED_IMAGE_USAGE_FLAGS_LINEAR: vk::ImageUsageFlags = vk::ImageUsageFlags::TRANSFER_DST;
+
+fn check_formats_support(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  format: vk::Format,
+) -> bool {
+  let properties =
+    unsafe { instance.get_physical_device_format_properties(physical_device, format) };
+
+  properties
+    .optimal_tiling_features
+    .contains(REQUIRED_FORMAT_IMAGE_FLAGS_OPTIMAL)
+    && properties
+      .linear_tiling_features
+      .contains(REQUIRED_FORMAT_IMAGE_FLAGS_LINEAR)
+}
+
+fn check_image_size_support(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  config: &Config,
+  tiling: vk::ImageTiling,
+  usage: vk::ImageUsageFlags,
+) -> Result<bool, vk::Result> {
+  let format = config.format.vk_format();
+  let properties = unsafe {
+    instance.get_physical_device_image_format_properties(
+      physical_device,
+      format,
+      vk::ImageType::TYPE_2D,
+      tiling,
+      usage,
+      vk::ImageCreateFlags::empty(),
+    )?
+  };
+  log::debug!(
+    "{:?} image {:?} properties: {:#?}",
+    tiling,
+    format,
+    properties
+  );
+
+  Ok(
+    config.width <= properties.max_extent.width
+      && config.height <= properties.max_extent.height
+      && config.width as u64 * config.height as u64 * config.format.texel_size()
+        <= properties.max_resource_size,
+  )
+}
+
+fn check_group_size_support(limits: &vk::PhysicalDeviceLimits) -> bool {
+  if SHADER_GROUP_SIZE_X > limits.max_compute_work_group_size[0] {
+    log::warn!("Skipped physical device: Device does not support required compute shader local group size in the X dimension");
+    return false;
+  }
+  if SHADER_GROUP_SIZE_Y > limits.max_compute_work_group_size[1] {
+    log::warn!("Skipped physical device: Device does not support required compute shader local group size in the Y dimension");
+    return false;
+  }
+  if SHADER_GROUP_SIZE_X * SHADER_GROUP_SIZE_Y > limits.max_compute_work_group_invocations {
+    log::warn!("Skipped physical device: Device does not support required compute shader total local group size requirements");
+    return false;
+  }
+  true
+}
+
+// the number of dispatched work groups depends on the image size
+fn check_group_count_support(limits: &vk::PhysicalDeviceLimits, extent: vk::Extent2D) -> bool {
+  let (x, y) = work_group_count(extent);
+  if x > limits.max_compute_work_group_count[0] || y > limits.max_compute_work_group_count[1] {
+    log::warn!(
+      "Skipped physical device: Device does not support dispatching {}x{} work groups (maximum is {}x{})",
+      x,
+      y,
+      limits.max_compute_work_group_count[0],
+      limits.max_compute_work_group_count[1]
+    );
+    return false;
+  }
+  true
+}
+
+// checks specific to this example
+pub fn is_suitable(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  properties: &PhysicalDeviceProperties,
+  config: &Config,
+) -> bool {
+  if !check_formats_support(instance, physical_device, config.format.vk_format()) {
+    log::warn!(
+      "Skipped physical device: Device does not support the required image format ({})",
+      config.format.name()
+    );
+    return false;
+  }
+
+  // check if image sizes are supported
+  for (tiling, usage) in [
+    (vk::ImageTiling::LINEAR, REQUIRED_IMAGE_USAGE_FLAGS_LINEAR),
+    (vk::ImageTiling::OPTIMAL, REQUIRED_IMAGE_USAGE_FLAGS_OPTIMAL),
+  ] {
+    match check_image_size_support(instance, physical_device, config, tiling, usage) {
+      Ok(true) => {}
+      Ok(false) => {
+        log::warn!(
+          "Skipped physical device: Image size ({}x{}) is bigger than supported by the device",
+          config.width,
+          config.height
+        );
+        return false;
+      }
+      Err(err) => {
+        log::error!("Device selection error: {:?}", err);
+        return false;
+      }
+    }
+  }
+
+  check_group_size_support(&properties.p10.limits)
+    && check_group_count_support(&properties.p10.limits, config.extent())
+}
+
+pub fn device_requirements<'a>(
+  is_suitable: &'a dyn Fn(&ash::Instance, vk::PhysicalDevice, &PhysicalDeviceProperties) -> bool,
+  config: &Config,
+) -> DeviceRequirements<'a> {
+  let mut requirements = DeviceRequirements::new(TARGET_API_VERSION);
+  requirements.extensions = &REQUIRED_DEVICE_EXTENSIONS;
+  // maintenance4 enables the use of dynamic local group sizes in shaders
+  // this was a extension before Vulkan 1.3
+  requirements.features.f13.maintenance4 = vk::TRUE;
+  // synchronization2 enables pipeline barriers to wait for nothing or signal nothing
+  requirements.features.f13.synchronization2 = vk::TRUE;
+  // timestamp queries are reset from the host
+  requirements.features.f12.host_query_reset = vk::TRUE;
+  // 16 bit unorm formats can only be written by the shader with this feature
+  if config.shader_format().is_extended() {
+    requirements
+      .features
+      .f10
+      .shader_storage_image_extended_formats = vk::TRUE;
+  }
+  requirements.queue_families = QueueFamilyRequirements {
+    graphics: false,
+    compute: true,
+    presentation: None,
+  };
+  requirements.is_suitable = is_suitable;
+  requirements
+}
@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+
+use ash::vk;
+
+use ash_by_example_core::{
+  memory::PlanError, spirv::SpirvError, utility::error_chain_fmt, DeviceSelectionError,
+};
+
+#[derive(thiserror::Error)]
+pub enum OutOfMemoryError {
+  #[error("Out of Device Memory")]
+  OutOfDeviceMemory,
+  #[error("Out of host memory")]
+  OutOfHostMemory,
+}
+impl std::fmt::Debug for OutOfMemoryError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl From<vk::Result> for OutOfMemoryError {
+  fn from(value: vk::Result) -> Self {
+    match value {
+      vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => OutOfMemoryError::OutOfDeviceMemory,
+      vk::Result::ERROR_OUT_OF_HOST_MEMORY => OutOfMemoryError::OutOfHostMemory,
+      _ => {
+        panic!("Invalid vk::Result to OutOfMemoryError cast: {:?}", value);
+      }
+    }
+  }
+}
+
+impl From<OutOfMemoryError> for vk::Result {
+  fn from(value: OutOfMemoryError) -> Self {
+    match value {
+      OutOfMemoryError::OutOfDeviceMemory => vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+      OutOfMemoryError::OutOfHostMemory => vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+    }
+  }
+}
+
+#[derive(thiserror::Error)]
+pub enum InitializationError {
+  #[error("No physical device supports the application")]
+  NoCompatibleDevices(#[source] DeviceSelectionError),
+
+  #[error("Not enough memory")]
+  NotEnoughMemory(#[source] Option<AllocationError>),
+
+  #[error("Failed to load shader")]
+  ShaderLoading(#[source] ShaderError),
+
+  // undefined behavior / driver or application bug (see vl)
+  #[error("Device is lost")]
+  DeviceLost,
+  #[error("Unknown")]
+  Unknown,
+}
+impl std::fmt::Debug for InitializationError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl From<vk::Result> for InitializationError {
+  fn from(value: vk::Result) -> Self {
+    match value {
+      vk::Result::ERROR_OUT_OF_DEVICE_MEMORY | vk::Result::ERROR_OUT_OF_HOST_MEMORY => {
+        InitializationError::NotEnoughMemory(None)
+      }
+      vk::Result::ERROR_DEVICE_LOST => InitializationError::DeviceLost,
+      vk::Result::ERROR_UNKNOWN => InitializationError::Unknown,
+      // validation layers may say more on this
+      vk::Result::ERROR_INITIALIZATION_FAILED => InitializationError::Unknown,
+      _ => {
+        log::error!("Invalid vk::Result: {:?}", value);
+        InitializationError::Unknown
+      }
+    }
+  }
+}
+
+impl From<OutOfMemoryError> for InitializationError {
+  fn from(_value: OutOfMemoryError) -> Self {
+    InitializationError::NotEnoughMemory(None)
+  }
+}
+
+impl From<DeviceSelectionError> for InitializationError {
+  fn from(value: DeviceSelectionError) -> Self {
+    match value {
+      DeviceSelectionError::Vulkan(vk_err) => vk_err.into(),
+      _ => InitializationError::NoCompatibleDevices(value),
+    }
+  }
+}
+
+impl From<AllocationError> for InitializationError {
+  fn from(value: AllocationError) -> Self {
+    InitializationError::NotEnoughMemory(Some(value))
+  }
+}
+
+impl From<ShaderError> for InitializationError {
+  fn from(value: ShaderError) -> Self {
+    match value {
+      ShaderError::Vulkan(vk_err) => vk_err.into(),
+      _ => InitializationError::ShaderLoading(value),
+    }
+  }
+}
+
+#[derive(thiserror::Error)]
+pub enum AllocationError {
+  #[error("Failed to find a memory type for the allocation")]
+  Planning(#[source] PlanError),
+  #[error("Not enough memory")]
+  NotEnoughMemory(#[source] OutOfMemoryError),
+}
+impl std::fmt::Debug for AllocationError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl From<vk::Result> for AllocationError {
+  fn from(value: vk::Result) -> Self {
+    AllocationError::NotEnoughMemory(OutOfMemoryError::from(value))
+  }
+}
+
+impl From<PlanError> for AllocationError {
+  fn from(value: PlanError) -> Self {
+    AllocationError::Planning(value)
+  }
+}
+
+impl From<OutOfMemoryError> for AllocationError {
+  fn from(value: OutOfMemoryError) -> Self {
+    AllocationError::NotEnoughMemory(value)
+  }
+}
+
+#[derive(thiserror::Error)]
+pub enum ShaderError {
+  #[error("Failed to read shader file at {path:?}")]
+  Read {
+    path: PathBuf,
+    #[source]
+    source: std::io::Error,
+  },
+  #[error("Failed to patch shader code")]
+  Spirv(#[source] SpirvError),
+  #[error("Failed to create shader module")]
+  Vulkan(#[source] vk::Result),
+}
+impl std::fmt::Debug for ShaderError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+#[derive(thiserror::Error)]
+pub enum SaveError {
+  // map can fail with vk::Result::ERROR_MEMORY_MAP_FAILED
+  #[error("Failed to access image memory")]
+  Vulkan(#[source] vk::Result),
+  #[error("Failed to save image")]
+  Encoding(#[source] image::ImageError),
+}
+impl std::fmt::Debug for SaveError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl From<vk::Result> for SaveError {
+  fn from(value: vk::Result) -> Self {
+    SaveError::Vulkan(value)
+  }
+}
+
+impl From<image::ImageError> for SaveError {
+  fn from(value: image::ImageError) -> Self {
+    SaveError::Encoding(value)
+  }
+}
+
+// all errors that can stop the example, reported by main
+#[derive(thiserror::Error)]
+pub enum RunError {
+  #[error("Failed to initialize")]
+  Initialization(#[source] InitializationError),
+  // can be vk::Result::ERROR_DEVICE_LOST
+  #[error("Failed to submit work")]
+  Submission(#[source] vk::Result),
+  #[error("Failed to save the resulting image")]
+  Saving(#[source] SaveError),
+}
+impl std::fmt::Debug for RunError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
+
+impl RunError {
+  // the work can be tried again from scratch with a new device
+  pub fn is_device_lost(&self) -> bool {
+    matches!(
+      self,
+      RunError::Initialization(InitializationError::DeviceLost)
+        | RunError::Submission(vk::Result::ERROR_DEVICE_LOST)
+    )
+  }
+}
+
+#[derive(thiserror::Error)]
+pub enum ConfigError {
+  #[error("Unknown option \"{0}\"")]
+  UnknownOption(String),
+  #[error("Missing value for option \"{0}\"")]
+  MissingValue(String),
+  #[error("Invalid value \"{value}\" for option \"{option}\": {reason}")]
+  InvalidValue {
+    option: String,
+    value: String,
+    reason: &'static str,
+  },
+}
+impl std::fmt::Debug for ConfigError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    error_chain_fmt(self, f)
+  }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+
+use ash::vk;
+use ash_by_example_core::{
+  graph::{CompiledGraph, QueueType, RenderGraph},
+  Device, PhysicalDevice, Queues,
+};
+
+use crate::{descriptor_sets::DescriptorSets, pipeline::ComputePipeline, work_group_count};
+
+// images have 1 mip_level / 1 array layer
+const SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  base_mip_level: 0,
+  level_count: 1,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// 1 color layer
+const SUBRESOURCE_LAYERS: vk::ImageSubresourceLayers = vk::ImageSubresourceLayers {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  mip_level: 0,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// Dispatches the Mandelbrot shader, which writes to local_image, in the compute queue and copies
+// the result to host_image in the transfer queue
+// The graph inserts the layout transitions, the local image ownership transfer from the compute to
+// the transfer family and the semaphores between both submissions
+#[allow(clippy::too_many_arguments)]
+pub fn build_graph(
+  device: &Arc<Device>,
+  physical_device: &PhysicalDevice,
+  queues: &Queues,
+  pipeline: &ComputePipeline,
+  descriptor_sets: &DescriptorSets,
+  local_image: vk::Image,
+  host_image: vk::Image,
+  extent: vk::Extent2D,
+) -> Result<CompiledGraph, vk::Result> {
+  let mut graph = RenderGraph::new(device, &physical_device.queue_families, queues);
+  // time each pass
+  graph.enable_profiling(physical_device);
+  // log what was executing if the device gets lost
+  graph.enable_loss_diagnostics(physical_device);
+  graph.import_image(local_image, SUBRESOURCE_RANGE);
+  graph.import_image(host_image, SUBRESOURCE_RANGE);
+
+  // image layout is required to be GENERAL in order to be used as storage in a shader
+  graph
+    .add_pass("mandelbrot", QueueType::Compute)
+    .discard_image(
+      local_image,
+      vk::ImageLayout::GENERAL,
+      vk::PipelineStageFlags2::COMPUTE_SHADER,
+      vk::AccessFlags2::SHADER_STORAGE_WRITE,
+    )
+    .record(|device, cb| unsafe {
+      // descriptor set should already have the image info written to it
+      device.cmd_bind_descriptor_sets(
+        cb,
+        vk::PipelineBindPoint::COMPUTE,
+        *pipeline.layout,
+        0,
+        &[descriptor_sets.pool.mandelbrot],
+        &[],
+      );
+      device.cmd_bind_pipeline(cb, vk::PipelineBindPoint::COMPUTE, *pipeline.pipeline);
+      let (group_count_x, group_count_y) = work_group_count(extent);
+      device.cmd_dispatch(cb, group_count_x, group_count_y, 1);
+    });
+
+  graph
+    .add_pass("copy to host image", QueueType::Transfer)
+    .image(
+      local_image,
+      vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_READ,
+    )
+    .discard_image(
+      host_image,
+      vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    )
+    .record(move |device, cb| unsafe {
+      // full image
+      let copy_region = vk::ImageCopy {
+        src_subresource: SUBRESOURCE_LAYERS,
+        src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        dst_subresource: SUBRESOURCE_LAYERS,
+        dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        extent: vk::Extent3D {
+          width: extent.width,
+          height: extent.height,
+          depth: 1,
+        },
+      };
+      device.cmd_copy_image(
+        cb,
+        local_image,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        host_image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[copy_region],
+      );
+    });
+
+  // Optimal layouts can have different internal representations depending on what the driver
+  // implemented, GENERAL must be used in order to interpret the image by the CPU
+  graph
+    .add_pass("host read", QueueType::Transfer)
+    .image(
+      host_image,
+      vk::ImageLayout::GENERAL,
+      vk::PipelineStageFlags2::HOST,
+      vk::AccessFlags2::HOST_READ,
+    )
+    .barriers_only();
+
+  graph.compile()
+}
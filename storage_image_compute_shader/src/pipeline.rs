@@ -2,18 +2,21 @@ use std::{
   ffi::{c_void, CString},
   mem::{offset_of, size_of},
   ptr::{self, addr_of},
+  sync::Arc,
 };
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 use crate::{
-  descriptor_sets::DescriptorSets, shaders::Shader, FOCAL_POINT, MAX_ITERATIONS,
-  SHADER_GROUP_SIZE_X, SHADER_GROUP_SIZE_Y, ZOOM,
+  config::Config, descriptor_sets::DescriptorSets, errors::InitializationError, shaders::Shader,
+  SHADER_GROUP_SIZE_X, SHADER_GROUP_SIZE_Y,
 };
 
 pub struct ComputePipeline {
-  pub layout: vk::PipelineLayout,
-  pub pipeline: vk::Pipeline,
+  // the pipeline is dropped before its layout
+  pub pipeline: Owned<vk::Pipeline>,
+  pub layout: Owned<vk::PipelineLayout>,
 }
 
 #[repr(C)]
@@ -65,20 +68,22 @@ impl SpecializationData {
 
 impl ComputePipeline {
   pub fn create(
-    device: &ash::Device,
+    device: &Arc<Device>,
     cache: vk::PipelineCache,
     descriptor_sets: &DescriptorSets,
-  ) -> Self {
-    let mut shader = Shader::load(device);
+    config: &Config,
+  ) -> Result<Self, InitializationError> {
+    // the shader module is not needed after pipeline creation, so it only lives in this function
+    let shader = Shader::load(device, config.shader_format())?;
     let main_function_name = CString::new("main").unwrap(); // the beginning function name in shader code
 
     let specialization_data = SpecializationData {
       group_size_x: SHADER_GROUP_SIZE_X,
       group_size_y: SHADER_GROUP_SIZE_Y,
-      max_iterations: MAX_ITERATIONS,
-      focal_point_x: FOCAL_POINT[0],
-      focal_point_y: FOCAL_POINT[1],
-      zoom: ZOOM,
+      max_iterations: config.max_iterations,
+      focal_point_x: config.focal_point[0],
+      focal_point_y: config.focal_point[1],
+      zoom: config.zoom,
     };
     let entries = SpecializationData::entries();
     let specialization_info = vk::SpecializationInfo {
@@ -92,7 +97,7 @@ impl ComputePipeline {
       s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
       p_next: ptr::null(),
       flags: vk::PipelineShaderStageCreateFlags::empty(),
-      module: shader.module,
+      module: *shader.module,
       p_name: main_function_name.as_ptr(),
       p_specialization_info: addr_of!(specialization_info),
       stage: vk::ShaderStageFlags::COMPUTE,
@@ -103,14 +108,15 @@ impl ComputePipeline {
       p_next: ptr::null(),
       flags: vk::PipelineLayoutCreateFlags::empty(),
       set_layout_count: 1,
-      p_set_layouts: addr_of!(descriptor_sets.layout),
+      p_set_layouts: &*descriptor_sets.layout,
       push_constant_range_count: 0,
       p_push_constant_ranges: ptr::null(),
     };
     let layout = unsafe {
-      device
-        .create_pipeline_layout(&layout_create_info, None)
-        .expect("Failed to create pipeline layout")
+      Owned::from_raw(
+        device,
+        device.create_pipeline_layout(&layout_create_info, None)?,
+      )
     };
 
     let create_info = vk::ComputePipelineCreateInfo {
@@ -118,26 +124,18 @@ impl ComputePipeline {
       p_next: ptr::null(),
       stage,
       flags: vk::PipelineCreateFlags::empty(),
-      layout,
+      layout: *layout,
       base_pipeline_handle: vk::Pipeline::null(),
       base_pipeline_index: -1, // -1 for invalid
     };
 
     let pipeline = unsafe {
-      device
+      let pipelines = device
         .create_compute_pipelines(cache, &[create_info], None)
-        .expect("Failed to create compute pipelines")[0]
+        .map_err(|(_, err)| err)?;
+      Owned::from_raw(device, pipelines[0])
     };
 
-    unsafe {
-      shader.destroy_self(device);
-    }
-
-    Self { layout, pipeline }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_pipeline(self.pipeline, None);
-    device.destroy_pipeline_layout(self.layout, None);
+    Ok(Self { pipeline, layout })
   }
 }
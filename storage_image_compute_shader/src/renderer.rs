@@ -0,0 +1,222 @@
+use ash::vk;
+use std::{ops::BitOr, path::Path, ptr, sync::Arc};
+
+use ash_by_example_core::{
+  create_pipeline_cache, get_entry, graph::CompiledGraph, profiler::ScopeTiming,
+  save_pipeline_cache, ApplicationInfo, Device, Instance, Owned, PhysicalDevice,
+};
+
+use crate::{
+  config::Config,
+  descriptor_sets::DescriptorSets,
+  device::{device_requirements, is_suitable},
+  errors::{InitializationError, OutOfMemoryError, SaveError},
+  image::Image,
+  passes::build_graph,
+  pipeline::ComputePipeline,
+  APPLICATION_NAME, APPLICATION_VERSION, PIPELINE_CACHE_PATH, TARGET_API_VERSION,
+};
+
+fn create_sampler(device: &Arc<Device>) -> Result<Owned<vk::Sampler>, OutOfMemoryError> {
+  let sampler_create_info = vk::SamplerCreateInfo {
+    s_type: vk::StructureType::SAMPLER_CREATE_INFO,
+    p_next: ptr::null(),
+    flags: vk::SamplerCreateFlags::empty(),
+    mag_filter: vk::Filter::NEAREST,
+    min_filter: vk::Filter::NEAREST,
+    address_mode_u: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+    address_mode_v: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+    address_mode_w: vk::SamplerAddressMode::CLAMP_TO_BORDER,
+    anisotropy_enable: vk::FALSE,
+    max_anisotropy: 0.0,
+    border_color: vk::BorderColor::INT_OPAQUE_BLACK,
+    unnormalized_coordinates: vk::TRUE,
+    compare_enable: vk::FALSE,
+    compare_op: vk::CompareOp::NEVER,
+    mipmap_mode: vk::SamplerMipmapMode::NEAREST,
+    mip_lod_bias: 0.0,
+    max_lod: 0.0,
+    min_lod: 0.0,
+  };
+  unsafe {
+    let sampler = device.create_sampler(&sampler_create_info, None)?;
+    Ok(Owned::from_raw(device, sampler))
+  }
+}
+
+// Fields are dropped in declaration order, however all objects keep the device alive (and the
+// device keeps the instance alive), so they are always destroyed before it
+pub struct Renderer {
+  // recorded dispatch and copy
+  graph: CompiledGraph,
+  _pipeline: ComputePipeline,
+  _descriptor_sets: DescriptorSets,
+  gpu_data: GPUData,
+  device: Arc<Device>,
+}
+
+struct GPUData {
+  // the sampler technically is useless as the image is never used as a sampled image, however
+  // it still needs to be passed to the write descriptor set
+  sampler: Owned<vk::Sampler>,
+  local_image_view: Owned<vk::ImageView>,
+  // GPU image with DEVICE_LOCAL flags
+  local_image: Image,
+  // CPU accessible image with HOST_VISIBLE flags
+  host_image: Image,
+}
+
+impl Renderer {
+  pub fn initialize(config: &Config) -> Result<Self, InitializationError> {
+    let entry: ash::Entry = unsafe { get_entry() };
+
+    let app_info = ApplicationInfo {
+      name: APPLICATION_NAME,
+      version: APPLICATION_VERSION,
+      api_version: TARGET_API_VERSION,
+    };
+    // everything created up to an error gets destroyed when returning early
+    let instance = Instance::create(entry, &app_info, &[])?;
+
+    let is_suitable = |instance: &ash::Instance, physical_device, properties: &_| {
+      is_suitable(instance, physical_device, properties, config)
+    };
+    let requirements = device_requirements(&is_suitable, config);
+    let physical_device = unsafe { PhysicalDevice::select(&instance, &requirements) }?;
+
+    let (device, queues) = Device::create(&instance, &physical_device, &requirements)?;
+
+    println!("Allocating images...");
+    let gpu_data = GPUData::new(&device, &physical_device, config)?;
+
+    let mut descriptor_sets = DescriptorSets::new(&device)?;
+    descriptor_sets
+      .pool
+      .write_image(&device, *gpu_data.local_image_view, *gpu_data.sampler);
+
+    let pipeline = create_pipeline(&device, &physical_device, &descriptor_sets, config)?;
+
+    let graph = build_graph(
+      &device,
+      &physical_device,
+      &queues,
+      &pipeline,
+      &descriptor_sets,
+      *gpu_data.local_image,
+      *gpu_data.host_image,
+      gpu_data.local_image.extent,
+    )?;
+
+    Ok(Self {
+      graph,
+      _pipeline: pipeline,
+      _descriptor_sets: descriptor_sets,
+      gpu_data,
+      device,
+    })
+  }
+
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn submit_and_wait(&mut self) -> Result<(), vk::Result> {
+    self.graph.submit_and_wait()
+  }
+
+  // GPU time of each pass of the last completed submission
+  pub fn timings(&self) -> Result<Vec<ScopeTiming>, vk::Result> {
+    Ok(self.graph.timings()?.unwrap_or_default())
+  }
+
+  // host image memory should not be in use by the device
+  pub fn save_image(&self, path: &Path) -> Result<(), SaveError> {
+    self.gpu_data.host_image.save_to_file(&self.device, path)
+  }
+}
+
+impl Drop for Renderer {
+  fn drop(&mut self) {
+    log::debug!("Destroying renderer objects...");
+    // wait until all operations have finished and objects are safe to destroy
+    // this only fails if the device is lost, in which case objects can be destroyed anyway
+    if let Err(err) = unsafe { self.device.device_wait_idle() } {
+      log::error!(
+        "Failed to wait for the device to become idle during drop: {:?}",
+        err
+      );
+    }
+    // fields get dropped after this
+  }
+}
+
+// Creates the pipeline through a pipeline cache that is saved and loaded across program
+// invocations, so that the driver doesn't have to recompile the shader
+fn create_pipeline(
+  device: &Arc<Device>,
+  physical_device: &PhysicalDevice,
+  descriptor_sets: &DescriptorSets,
+  config: &Config,
+) -> Result<ComputePipeline, InitializationError> {
+  log::info!("Creating pipeline cache");
+  let (pipeline_cache, created_from_file) =
+    create_pipeline_cache(device, physical_device, Path::new(PIPELINE_CACHE_PATH))?;
+  // no more pipelines will be created, so the cache is destroyed at the end of this function
+  let pipeline_cache = unsafe { Owned::from_raw(device, pipeline_cache) };
+  if created_from_file {
+    log::info!("Cache successfully created from an existing cache file");
+  } else {
+    log::info!("Cache initialized as empty");
+  }
+
+  log::debug!("Creating pipeline");
+  let pipeline = ComputePipeline::create(device, *pipeline_cache, descriptor_sets, config)?;
+
+  log::info!("Saving pipeline cache");
+  if let Err(err) = save_pipeline_cache(
+    device,
+    physical_device,
+    *pipeline_cache,
+    Path::new(PIPELINE_CACHE_PATH),
+  ) {
+    log::error!("Failed to save pipeline cache: {:?}", err);
+  }
+
+  Ok(pipeline)
+}
+
+impl GPUData {
+  pub fn new(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+    config: &Config,
+  ) -> Result<Self, InitializationError> {
+    let local_image = Image::new(
+      device,
+      physical_device,
+      config.extent(),
+      config.format,
+      vk::ImageTiling::OPTIMAL,
+      vk::ImageUsageFlags::TRANSFER_SRC.bitor(vk::ImageUsageFlags::STORAGE),
+      vk::MemoryPropertyFlags::DEVICE_LOCAL,
+      vk::MemoryPropertyFlags::empty(),
+    )?;
+    let host_image = Image::new(
+      device,
+      physical_device,
+      config.extent(),
+      config.format,
+      vk::ImageTiling::LINEAR,
+      vk::ImageUsageFlags::TRANSFER_DST,
+      vk::MemoryPropertyFlags::HOST_VISIBLE,
+      vk::MemoryPropertyFlags::HOST_CACHED,
+    )?;
+
+    let local_image_view = local_image.create_view(device)?;
+    let sampler = create_sampler(device)?;
+
+    Ok(Self {
+      sampler,
+      local_image_view,
+      local_image,
+      host_image,
+    })
+  }
+}
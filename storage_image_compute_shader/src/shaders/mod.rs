@@ -1,39 +1,48 @@
 use std::{fs::File, io::Read, path::Path, ptr};
 
 use ash::vk;
+use ash_by_example_core::spirv::{self, set_storage_image_format, words_from_bytes};
+
+use crate::errors::ShaderError;
 
 pub mod shader;
 
 pub use shader::Shader;
 
-pub fn load_shader(device: &ash::Device, shader_path: &Path) -> vk::ShaderModule {
-  let code = read_shader_code(shader_path);
-  create_shader_module(device, code)
+// loads a shader whose storage images get replaced to have the given format
+pub fn load_shader_with_storage_format(
+  device: &ash::Device,
+  shader_path: &Path,
+  format: spirv::ImageFormat,
+) -> Result<vk::ShaderModule, ShaderError> {
+  let bytes = read_shader_code(shader_path).map_err(|source| ShaderError::Read {
+    path: shader_path.to_owned(),
+    source,
+  })?;
+  let mut code = words_from_bytes(&bytes).map_err(ShaderError::Spirv)?;
+  set_storage_image_format(&mut code, format).map_err(ShaderError::Spirv)?;
+  create_shader_module(device, &code).map_err(ShaderError::Vulkan)
 }
 
-fn read_shader_code(shader_path: &Path) -> Vec<u8> {
-  let mut file =
-    File::open(shader_path).expect(&format!("Failed to find spv file at {:?}", shader_path));
+fn read_shader_code(shader_path: &Path) -> std::io::Result<Vec<u8>> {
+  let mut file = File::open(shader_path)?;
 
   let mut bytes = Vec::new();
-  file
-    .read_to_end(&mut bytes)
-    .expect("Failed to read shader file");
-  bytes
+  file.read_to_end(&mut bytes)?;
+  Ok(bytes)
 }
 
-fn create_shader_module(device: &ash::Device, code: Vec<u8>) -> vk::ShaderModule {
+fn create_shader_module(
+  device: &ash::Device,
+  code: &[u32],
+) -> Result<vk::ShaderModule, vk::Result> {
   let shader_module_create_info = vk::ShaderModuleCreateInfo {
     s_type: vk::StructureType::SHADER_MODULE_CREATE_INFO,
     p_next: ptr::null(),
     flags: vk::ShaderModuleCreateFlags::empty(),
-    code_size: code.len(),
-    p_code: code.as_ptr() as *const u32,
+    code_size: std::mem::size_of_val(code), // in bytes
+    p_code: code.as_ptr(),
   };
 
-  unsafe {
-    device
-      .create_shader_module(&shader_module_create_info, None)
-      .expect("Failed to create shader module")
-  }
+  unsafe { device.create_shader_module(&shader_module_create_info, None) }
 }
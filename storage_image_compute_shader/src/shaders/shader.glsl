@@ -10,6 +10,7 @@ layout (constant_id = 5) const float ZOOM = 1.0;
 // uses index 0 specialization constant as the local group size for x and y
 layout(local_size_x_id = 0, local_size_y_id = 1, local_size_z = 1) in;
 
+// the format gets replaced when the shader is loaded, see shader.rs
 layout(set = 0, binding = 0, rgba8) uniform writeonly image2D output_image;
 
 
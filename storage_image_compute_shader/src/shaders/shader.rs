@@ -1,21 +1,23 @@
-use std::path::Path;
+use std::{path::Path, sync::Arc};
 
 use ash::vk;
+use ash_by_example_core::{spirv, Device, Owned};
 
-const SHADER_PATH: &'static str = "./shaders/shader.spv";
+use crate::errors::ShaderError;
+
+const SHADER_PATH: &str = "./shaders/shader.spv";
 
 pub struct Shader {
-  pub module: vk::ShaderModule,
+  pub module: Owned<vk::ShaderModule>,
 }
 
 impl Shader {
-  pub fn load(device: &ash::Device) -> Self {
-    Self {
-      module: super::load_shader(device, Path::new(SHADER_PATH)),
-    }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_shader_module(self.module, None);
+  // The shader is compiled with a rgba8 output image, which gets replaced by the format of the
+  // image that is bound to it
+  pub fn load(device: &Arc<Device>, format: spirv::ImageFormat) -> Result<Self, ShaderError> {
+    let module = super::load_shader_with_storage_format(device, Path::new(SHADER_PATH), format)?;
+    Ok(Self {
+      module: unsafe { Owned::from_raw(device, module) },
+    })
   }
 }
@@ -0,0 +1,63 @@
+// Runs the example and compares the saved image with tests/reference/mandelbrot.png
+// See golden/src/lib.rs on how to run this against a software Vulkan implementation
+
+use std::path::Path;
+
+use ash_by_example_core::device_lost::FAKE_DEVICE_LOST_VAR;
+use ash_by_example_golden::{
+  assert_matches_reference, run_example, run_example_with_env, skip_without_vulkan, Tolerance,
+};
+
+// see mandelbrot_matches_reference
+const TOLERANCE: Tolerance = Tolerance {
+  channel: 1,
+  mismatched_ratio: 0.01,
+};
+
+#[test]
+fn mandelbrot_matches_reference() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("mandelbrot.png");
+
+  run_example(
+    env!("CARGO_BIN_EXE_storage-image-compute-shader"),
+    dir,
+    &["--output", output.to_str().unwrap()],
+  );
+
+  // Iteration counts near the border of the set are sensitive to floating point precision, which
+  // is not required to be the same across implementations
+  assert_matches_reference(
+    &output,
+    &dir.join("tests/reference/mandelbrot.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    TOLERANCE,
+  );
+}
+
+// the first submission reports a lost device, after which everything is created again
+#[test]
+fn mandelbrot_recovers_from_device_loss() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+  let output = Path::new(env!("CARGO_TARGET_TMPDIR")).join("mandelbrot_device_lost.png");
+
+  run_example_with_env(
+    env!("CARGO_BIN_EXE_storage-image-compute-shader"),
+    dir,
+    &["--output", output.to_str().unwrap()],
+    &[(FAKE_DEVICE_LOST_VAR, "1")],
+  );
+
+  assert_matches_reference(
+    &output,
+    &dir.join("tests/reference/mandelbrot.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    TOLERANCE,
+  );
+}
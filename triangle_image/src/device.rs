@@ -0,0 +1,114 @@
+use ash::vk;
+use ash_by_example_core::{
+  const_flag_bitor, DeviceRequirements, PhysicalDeviceProperties, QueueFamilyRequirements,
+};
+
+use crate::{
+  IMAGE_FORMAT, IMAGE_HEIGHT, IMAGE_WIDTH, REQUIRED_DEVICE_EXTENSIONS, TARGET_API_VERSION,
+};
+
+// kinda overkill
+const REQUIRED_FORMAT_IMAGE_FLAGS_OPTIMAL: vk::FormatFeatureFlags = const_flag_bitor!(
+  vk::FormatFeatureFlags,
+  vk::FormatFeatureFlags::TRANSFER_SRC,
+  vk::FormatFeatureFlags::COLOR_ATTACHMENT
+);
+const REQUIRED_FORMAT_IMAGE_FLAGS_LINEAR: vk::FormatFeatureFlags =
+  vk::FormatFeatureFlags::TRANSFER_DST;
+
+const REQUIRED_IMAGE_USAGE_FLAGS_OPTIMAL: vk::ImageUsageFlags = const_flag_bitor!(
+  vk::ImageUsageFlags,
+  vk::ImageUsageFlags::TRANSFER_SRC,
+  vk::ImageUsageFlags::COLOR_ATTACHMENT
+);
+const REQUIRED_IMAGE_USAGE_FLAGS_LINEAR: vk::ImageUsageFlags = vk::ImageUsageFlags::TRANSFER_DST;
+
+fn check_formats_support(instance: &ash::Instance, physical_device: vk::PhysicalDevice) -> bool {
+  let properties =
+    unsafe { instance.get_physical_device_format_properties(physical_device, IMAGE_FORMAT) };
+
+  properties
+    .optimal_tiling_features
+    .contains(REQUIRED_FORMAT_IMAGE_FLAGS_OPTIMAL)
+    && properties
+      .linear_tiling_features
+      .contains(REQUIRED_FORMAT_IMAGE_FLAGS_LINEAR)
+}
+
+fn check_image_size_support(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  tiling: vk::ImageTiling,
+  usage: vk::ImageUsageFlags,
+) -> Result<bool, vk::Result> {
+  let properties = unsafe {
+    instance.get_physical_device_image_format_properties(
+      physical_device,
+      IMAGE_FORMAT,
+      vk::ImageType::TYPE_2D,
+      tiling,
+      usage,
+      vk::ImageCreateFlags::empty(),
+    )?
+  };
+  log::debug!(
+    "{:?} image {:?} properties: {:#?}",
+    tiling,
+    IMAGE_FORMAT,
+    properties
+  );
+
+  Ok(IMAGE_WIDTH <= properties.max_extent.width && IMAGE_HEIGHT <= properties.max_extent.height)
+}
+
+// checks specific to this example
+fn is_suitable(
+  instance: &ash::Instance,
+  physical_device: vk::PhysicalDevice,
+  _properties: &PhysicalDeviceProperties,
+) -> bool {
+  if !check_formats_support(instance, physical_device) {
+    log::warn!("Skipped physical device: Device does not support required formats");
+    return false;
+  }
+
+  // check if image sizes are supported
+  for (tiling, usage) in [
+    (vk::ImageTiling::LINEAR, REQUIRED_IMAGE_USAGE_FLAGS_LINEAR),
+    (vk::ImageTiling::OPTIMAL, REQUIRED_IMAGE_USAGE_FLAGS_OPTIMAL),
+  ] {
+    match check_image_size_support(instance, physical_device, tiling, usage) {
+      Ok(true) => {}
+      Ok(false) => {
+        log::warn!("Skipped physical device: Application image size requirements are bigger than supported by the device");
+        return false;
+      }
+      Err(err) => {
+        log::error!("Device selection error: {:?}", err);
+        return false;
+      }
+    }
+  }
+
+  true
+}
+
+pub fn device_requirements() -> DeviceRequirements<'static> {
+  let mut requirements = DeviceRequirements::new(TARGET_API_VERSION);
+  requirements.extensions = &REQUIRED_DEVICE_EXTENSIONS;
+  // used by the upload manager
+  requirements.features.f12.timeline_semaphore = vk::TRUE;
+  requirements.features.f13.synchronization2 = vk::TRUE;
+  // timestamp queries are reset from the host
+  requirements.features.f12.host_query_reset = vk::TRUE;
+  // draw statistics, only collected if supported
+  requirements.optional_features.f10.pipeline_statistics_query = vk::TRUE;
+  requirements.optional_features.f10.occlusion_query_precise = vk::TRUE;
+  requirements.queue_families = QueueFamilyRequirements {
+    graphics: true,
+    compute: false,
+    presentation: None,
+  };
+  requirements.is_suitable = &is_suitable;
+  requirements
+}
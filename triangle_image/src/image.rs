@@ -1,13 +1,30 @@
-use std::ptr;
+use std::{
+  ffi::c_void,
+  ptr::{self, addr_of},
+  sync::Arc,
+};
 
 use ash::vk;
 
-use crate::{device::PhysicalDevice, IMAGE_FORMAT, IMAGE_HEIGHT, IMAGE_SAVE_TYPE, IMAGE_WIDTH};
+use ash_by_example_core::{
+  memory::{
+    image_memory_requirements, plan_allocation, prioritize_by_budget, MappedMemory, MemoryLimits,
+    ResourceKind, ResourceRequirements,
+  },
+  Device, Owned, PhysicalDevice,
+};
+
+use crate::{
+  errors::{AllocationError, SaveError},
+  IMAGE_FORMAT, IMAGE_HEIGHT, IMAGE_SAVE_TYPE, IMAGE_WIDTH,
+};
 
 pub struct Image {
-  vk_img: vk::Image,
-  pub memory: vk::DeviceMemory,
-  pub memory_type_i: u32,
+  // the image is dropped before its memory
+  vk_img: Owned<vk::Image>,
+  // host visible memory stays mapped while the image exists
+  mapped: Option<MappedMemory>,
+  _memory: Owned<vk::DeviceMemory>,
   pub memory_size: u64,
 }
 
@@ -21,47 +38,65 @@ impl std::ops::Deref for Image {
 
 impl Image {
   pub fn new(
-    device: &ash::Device,
+    device: &Arc<Device>,
     physical_device: &PhysicalDevice,
     tiling: vk::ImageTiling,
     usage: vk::ImageUsageFlags,
     required_memory_properties: vk::MemoryPropertyFlags,
     optional_memory_properties: vk::MemoryPropertyFlags,
-  ) -> Self {
+  ) -> Result<Self, AllocationError> {
     log::debug!("Creating image");
-    let vk_img = create_image(device, tiling, usage);
+    let vk_img = create_image(device, tiling, usage)?;
+    let vk_img = unsafe { Owned::from_raw(device, vk_img) };
 
     log::debug!("Allocating memory for image");
     let (memory, memory_type_i, memory_size) = allocate_image_memory(
       device,
       physical_device,
-      vk_img,
+      *vk_img,
+      tiling,
       required_memory_properties,
       optional_memory_properties,
-    );
+    )?;
 
     log::debug!("Binding memory to image");
-    unsafe {
-      device
-        .bind_image_memory(vk_img, memory, 0)
-        .expect("Failed to bind memory to image")
+    unsafe { device.bind_image_memory(*vk_img, *memory, 0) }?;
+
+    let memory_flags = physical_device
+      .memory_type(memory_type_i as usize)
+      .property_flags;
+    let mapped = if memory_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+      log::debug!("Mapping image memory");
+      // memory gets implicitly unmapped when freed
+      let mapped = unsafe {
+        MappedMemory::map(
+          device,
+          *memory,
+          memory_size,
+          memory_flags,
+          physical_device.properties.p10.limits.non_coherent_atom_size,
+        )
+      }?;
+      Some(mapped)
+    } else {
+      None
     };
 
-    Self {
+    Ok(Self {
       vk_img,
-      memory,
-      memory_type_i,
+      mapped,
+      _memory: memory,
       memory_size,
-    }
+    })
   }
 
   // creates a image view with all the default channels
-  pub fn create_view(&self, device: &ash::Device) -> vk::ImageView {
+  pub fn create_view(&self, device: &Arc<Device>) -> Result<Owned<vk::ImageView>, vk::Result> {
     let create_info = vk::ImageViewCreateInfo {
       s_type: vk::StructureType::IMAGE_VIEW_CREATE_INFO,
       p_next: ptr::null(),
       flags: vk::ImageViewCreateFlags::empty(),
-      image: self.vk_img,
+      image: *self.vk_img,
       view_type: vk::ImageViewType::TYPE_2D,
       format: IMAGE_FORMAT,
       components: vk::ComponentMapping {
@@ -80,76 +115,37 @@ impl Image {
     };
 
     unsafe {
-      device
-        .create_image_view(&create_info, None)
-        .expect("Failed to create an image view")
+      let view = device.create_image_view(&create_info, None)?;
+      Ok(Owned::from_raw(device, view))
     }
   }
 
-  pub fn save_to_file<P>(&self, device: &ash::Device, physical_device: &PhysicalDevice, path: P)
+  pub fn save_to_file<P>(&self, device: &ash::Device, path: P) -> Result<(), SaveError>
   where
     P: AsRef<std::path::Path>,
   {
     // image memory needs to not be busy (getting used by device)
+    let mapped = self
+      .mapped
+      .as_ref()
+      .expect("Image memory is not host visible");
 
-    let mem_type_flags = physical_device
-      .get_memory_type(self.memory_type_i)
-      .property_flags;
-
-    assert!(mem_type_flags.contains(vk::MemoryPropertyFlags::HOST_VISIBLE));
-
-    // map entire memory
-    let image_bytes = unsafe {
-      log::debug!("Mapping image memory");
-      let ptr = device
-        .map_memory(
-          self.memory,
-          0,
-          self.memory_size,
-          vk::MemoryMapFlags::empty(),
-        )
-        .expect("Failed to map image memory") as *const u8;
-      std::slice::from_raw_parts(ptr, self.memory_size as usize)
-    };
-
-    if !mem_type_flags.contains(vk::MemoryPropertyFlags::HOST_COHERENT) {
-      // If the memory is not coherent, reading from it may give old results even if the GPU has
-      // finished
-      // Invalidate memory in order to make all previous change available to the host
-      let host_img_memory_range = vk::MappedMemoryRange {
-        s_type: vk::StructureType::MAPPED_MEMORY_RANGE,
-        p_next: ptr::null(),
-        memory: self.memory,
-        offset: 0,
-        size: self.memory_size,
-      };
-      log::debug!("Invalidating image memory");
-      unsafe {
-        device
-          .invalidate_mapped_memory_ranges(&[host_img_memory_range])
-          .expect("Failed to invalidate host image memory_ranges");
-      }
-    }
-
-    // read bytes and save to file
+    // If the memory is not coherent, reading from it may give old results even if the GPU has
+    // finished, so it gets invalidated before reading
     log::debug!("Saving image");
-    image::save_buffer(
-      path,
-      image_bytes,
-      IMAGE_WIDTH,
-      IMAGE_HEIGHT,
-      IMAGE_SAVE_TYPE,
-    )
-    .expect("Failed to save image");
-
-    unsafe {
-      device.unmap_memory(self.memory);
-    }
-  }
+    let result = unsafe {
+      mapped.read(device, 0, self.memory_size, |image_bytes| {
+        image::save_buffer(
+          path,
+          image_bytes,
+          IMAGE_WIDTH,
+          IMAGE_HEIGHT,
+          IMAGE_SAVE_TYPE,
+        )
+      })
+    }?;
 
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_image(self.vk_img, None);
-    device.free_memory(self.memory, None);
+    result.map_err(|err| err.into())
   }
 }
 
@@ -157,7 +153,7 @@ fn create_image(
   device: &ash::Device,
   tiling: vk::ImageTiling,
   usage: vk::ImageUsageFlags,
-) -> vk::Image {
+) -> Result<vk::Image, vk::Result> {
   // 1 color layer 2d image
   let create_info = vk::ImageCreateInfo {
     s_type: vk::StructureType::IMAGE_CREATE_INFO,
@@ -181,55 +177,59 @@ fn create_image(
     initial_layout: vk::ImageLayout::UNDEFINED,
   };
 
-  unsafe {
-    device
-      .create_image(&create_info, None)
-      .expect("Failed to create image")
-  }
+  unsafe { device.create_image(&create_info, None) }
 }
 
 // usually all images of similar type will use only one memory allocation
 fn allocate_image_memory(
-  device: &ash::Device,
+  device: &Arc<Device>,
   physical_device: &PhysicalDevice,
   image: vk::Image,
+  tiling: vk::ImageTiling,
   required_memory_properties: vk::MemoryPropertyFlags,
   optional_memory_properties: vk::MemoryPropertyFlags,
-) -> (vk::DeviceMemory, u32, u64) {
-  let memory_requirements = unsafe { device.get_image_memory_requirements(image) };
+) -> Result<(Owned<vk::DeviceMemory>, u32, u64), AllocationError> {
+  let (memory_requirements, dedicated) = unsafe { image_memory_requirements(device, image) };
+  let kind = match tiling {
+    vk::ImageTiling::OPTIMAL => ResourceKind::Optimal,
+    _ => ResourceKind::Linear,
+  };
 
+  // fails if the image is bigger than allowed for all memory types that support it
   // in this case you can sub allocate multiple times for the image and individually manage each
   // allocation
-  if memory_requirements.size >= physical_device.get_max_memory_allocation_size() {
-    panic!("Memory required to allocate an image ({}mb) is higher than the maximum allowed on the device ({}mb)", 
-      memory_requirements.size / 1_000_000,
-      physical_device.get_max_memory_allocation_size() / 1_000_000
-    );
-  }
-
-  // Try to find optimal memory type
-  // If it doesn't exist, try to find required memory type
-  let memory_type = physical_device
-    .find_optimal_memory_type(
-      memory_requirements.memory_type_bits,
-      required_memory_properties,
-      optional_memory_properties,
-    )
-    .expect("Failed to find appropriate memory type for allocating an image");
-
-  let heap_size = physical_device.get_memory_type_heap(memory_type).size;
-  if memory_requirements.size >= heap_size {
-    panic!("Memory required to allocate an image ({}mb) is higher than the size of the requested heap ({}mb)", 
-      memory_requirements.size / 1_000_000,
-      heap_size / 1_000_000
-    );
-  }
-
+  let limits = MemoryLimits::new(physical_device);
+  let mut plan = plan_allocation(
+    &[ResourceRequirements::new(memory_requirements, kind)],
+    required_memory_properties,
+    optional_memory_properties,
+    &limits,
+  )?;
+  // optimal memory type if it exists, otherwise one with only the required properties
+  // memory types whose heap doesn't have enough remaining budget are only used as a last resort
+  let budget = physical_device.memory_budget();
+  prioritize_by_budget(&mut plan.memory_types, plan.size, &limits, &budget);
+  let memory_type = plan.memory_types[0];
+  budget.check(&limits, memory_type, plan.size);
+
+  // the image already gets its own memory, but telling the implementation which resource it is for
+  // allows it to do some optimizations (for example, with big render or storage targets)
+  log::debug!("Dedicated allocation for image: {}", dedicated);
+  let dedicated_info = vk::MemoryDedicatedAllocateInfo {
+    s_type: vk::StructureType::MEMORY_DEDICATED_ALLOCATE_INFO,
+    p_next: ptr::null(),
+    image,
+    buffer: vk::Buffer::null(),
+  };
   let allocate_info = vk::MemoryAllocateInfo {
     s_type: vk::StructureType::MEMORY_ALLOCATE_INFO,
-    p_next: ptr::null(),
-    allocation_size: memory_requirements.size,
-    memory_type_index: memory_type,
+    p_next: if dedicated.is_wanted() {
+      addr_of!(dedicated_info) as *const c_void
+    } else {
+      ptr::null()
+    },
+    allocation_size: plan.size,
+    memory_type_index: memory_type as u32,
   };
 
   // Even if memory requirements are less than heap size and max memory allocation size, this
@@ -237,11 +237,7 @@ fn allocate_image_memory(
   // There is no reliable a way to know beforehand if a allocate operation is going to succeed or
   // not, so handle errors accordingly
   // see https://registry.khronos.org/vulkan/specs/1.3-extensions/man/html/vkAllocateMemory.html
-  let memory = unsafe {
-    device
-      .allocate_memory(&allocate_info, None)
-      .expect("Failed to allocate memory for an image")
-  };
+  let memory = unsafe { Owned::from_raw(device, device.allocate_memory(&allocate_info, None)?) };
 
-  (memory, memory_type, memory_requirements.size)
+  Ok((memory, memory_type as u32, plan.size))
 }
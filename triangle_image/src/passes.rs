@@ -0,0 +1,153 @@
+use std::{ptr, sync::Arc};
+
+use ash::vk;
+use ash_by_example_core::{
+  graph::{CompiledGraph, QueueType, RenderGraph},
+  queries::DrawQueries,
+  Device, PhysicalDevice, Queues,
+};
+
+use crate::{
+  constant_buffers::ConstantBuffers, pipeline::GraphicsPipeline, BACKGROUND_COLOR, IMAGE_HEIGHT,
+  IMAGE_WIDTH, INDEX_COUNT,
+};
+
+// images have 1 mip_level / 1 array layer
+const SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  base_mip_level: 0,
+  level_count: 1,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// 1 color layer
+const SUBRESOURCE_LAYERS: vk::ImageSubresourceLayers = vk::ImageSubresourceLayers {
+  aspect_mask: vk::ImageAspectFlags::COLOR,
+  mip_level: 0,
+  base_array_layer: 0,
+  layer_count: 1,
+};
+
+// Draws the triangle to local_image in the graphics queue and copies it to host_image in the
+// transfer queue
+// The render pass keeps the image in COLOR_ATTACHMENT_OPTIMAL, the graph inserts the layout
+// transitions around it, the ownership transfer to the transfer family and the semaphores between
+// both submissions
+// Vertex and index buffers are already owned by the graphics family after being uploaded
+#[allow(clippy::too_many_arguments)]
+pub fn build_graph(
+  device: &Arc<Device>,
+  physical_device: &PhysicalDevice,
+  queues: &Queues,
+  render_pass: vk::RenderPass,
+  framebuffer: vk::Framebuffer,
+  pipeline: &GraphicsPipeline,
+  buffers: &ConstantBuffers,
+  draw_queries: &DrawQueries,
+  local_image: vk::Image,
+  host_image: vk::Image,
+) -> Result<CompiledGraph, vk::Result> {
+  let mut graph = RenderGraph::new(device, &physical_device.queue_families, queues);
+  // time each pass
+  graph.enable_profiling(physical_device);
+  // log what was executing if the device gets lost
+  graph.enable_loss_diagnostics(physical_device);
+  graph.import_image(local_image, SUBRESOURCE_RANGE);
+  graph.import_image(host_image, SUBRESOURCE_RANGE);
+
+  // the render pass clears the image when it begins
+  graph
+    .add_pass("triangle", QueueType::Graphics)
+    .discard_image(
+      local_image,
+      vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+      vk::PipelineStageFlags2::COLOR_ATTACHMENT_OUTPUT,
+      vk::AccessFlags2::COLOR_ATTACHMENT_WRITE,
+    )
+    .record(|device, cb| unsafe {
+      let clear_value = vk::ClearValue {
+        color: BACKGROUND_COLOR,
+      };
+      let render_pass_begin_info = vk::RenderPassBeginInfo {
+        s_type: vk::StructureType::RENDER_PASS_BEGIN_INFO,
+        p_next: ptr::null(),
+        render_pass,
+        framebuffer,
+        // whole image
+        render_area: vk::Rect2D {
+          offset: vk::Offset2D { x: 0, y: 0 },
+          extent: vk::Extent2D {
+            width: IMAGE_WIDTH,
+            height: IMAGE_HEIGHT,
+          },
+        },
+        clear_value_count: 1,
+        p_clear_values: &clear_value,
+      };
+
+      // queries can't be reset inside a render pass instance
+      draw_queries.reset(cb);
+      device.cmd_begin_render_pass(cb, &render_pass_begin_info, vk::SubpassContents::INLINE);
+      {
+        device.cmd_bind_pipeline(cb, vk::PipelineBindPoint::GRAPHICS, *pipeline.pipeline);
+        device.cmd_bind_vertex_buffers(cb, 0, &[*buffers.vertex], &[0]);
+        device.cmd_bind_index_buffer(cb, *buffers.index, 0, vk::IndexType::UINT16);
+        draw_queries.begin(cb);
+        device.cmd_draw_indexed(cb, INDEX_COUNT as u32, 1, 0, 0, 0);
+        draw_queries.end(cb);
+      }
+      device.cmd_end_render_pass(cb);
+    });
+
+  graph
+    .add_pass("copy to host image", QueueType::Transfer)
+    .image(
+      local_image,
+      vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_READ,
+    )
+    .discard_image(
+      host_image,
+      vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+      vk::PipelineStageFlags2::COPY,
+      vk::AccessFlags2::TRANSFER_WRITE,
+    )
+    .record(move |device, cb| unsafe {
+      // full image
+      let copy_region = vk::ImageCopy {
+        src_subresource: SUBRESOURCE_LAYERS,
+        src_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        dst_subresource: SUBRESOURCE_LAYERS,
+        dst_offset: vk::Offset3D { x: 0, y: 0, z: 0 },
+        extent: vk::Extent3D {
+          width: IMAGE_WIDTH,
+          height: IMAGE_HEIGHT,
+          depth: 1,
+        },
+      };
+      device.cmd_copy_image(
+        cb,
+        local_image,
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        host_image,
+        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+        &[copy_region],
+      );
+    });
+
+  // Optimal layouts can have different internal representations depending on what the driver
+  // implemented, GENERAL must be used in order to interpret the image by the CPU
+  graph
+    .add_pass("host read", QueueType::Transfer)
+    .image(
+      host_image,
+      vk::ImageLayout::GENERAL,
+      vk::PipelineStageFlags2::HOST,
+      vk::AccessFlags2::HOST_READ,
+    )
+    .barriers_only();
+
+  graph.compile()
+}
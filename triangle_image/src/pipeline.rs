@@ -1,25 +1,33 @@
-use std::{pin::pin, ptr::{self, addr_of}};
+use std::{
+  pin::pin,
+  ptr::{self, addr_of},
+  sync::Arc,
+};
 
 use ash::vk;
+use ash_by_example_core::{Device, Owned};
 
 use crate::{
+  errors::InitializationError,
   shaders,
   vertex::{PipelineVertexInputStateCreateInfoGen, Vertex},
   IMAGE_HEIGHT, IMAGE_WIDTH,
 };
 
 pub struct GraphicsPipeline {
-  pub layout: vk::PipelineLayout,
-  pub pipeline: vk::Pipeline,
+  // the pipeline is dropped before its layout
+  pub pipeline: Owned<vk::Pipeline>,
+  _layout: Owned<vk::PipelineLayout>,
 }
 
 impl GraphicsPipeline {
   pub fn create(
-    device: &ash::Device,
+    device: &Arc<Device>,
     cache: vk::PipelineCache,
     render_pass: vk::RenderPass,
-  ) -> Self {
-    let mut shader = shaders::Shader::load(device);
+  ) -> Result<Self, InitializationError> {
+    // the shader modules are not needed after pipeline creation, so they only live in this function
+    let shader = shaders::Shader::load(device)?;
     let shader_stages = shader.get_pipeline_shader_creation_info();
 
     let vertex_input_state_gen = pin!(Vertex::get_input_state_create_info_gen(0, 0));
@@ -61,7 +69,7 @@ impl GraphicsPipeline {
       // no blend state
       blend_enable: vk::FALSE,
       color_write_mask: vk::ColorComponentFlags::RGBA,
-    
+
       // everything else doesn't matter
       ..Default::default()
     };
@@ -87,9 +95,10 @@ impl GraphicsPipeline {
       p_push_constant_ranges: ptr::null(),
     };
     let layout = unsafe {
-      device
-        .create_pipeline_layout(&layout_create_info, None)
-        .expect("Failed to create pipeline layout")
+      Owned::from_raw(
+        device,
+        device.create_pipeline_layout(&layout_create_info, None)?,
+      )
     };
 
     let create_info = vk::GraphicsPipelineCreateInfo {
@@ -107,28 +116,23 @@ impl GraphicsPipeline {
       p_depth_stencil_state: ptr::null(),
       p_color_blend_state: &color_blend_state,
       p_dynamic_state: ptr::null(),
-      layout,
+      layout: *layout,
       render_pass,
       subpass: 0,
       base_pipeline_handle: vk::Pipeline::null(),
       base_pipeline_index: -1, // -1 for null
     };
     let pipeline = unsafe {
-      device
+      let pipelines = device
         .create_graphics_pipelines(cache, &[create_info], None)
-        .expect("Failed to create graphics pipelines")[0]
+        .map_err(|(_, err)| err)?;
+      Owned::from_raw(device, pipelines[0])
     };
 
-    unsafe {
-      shader.destroy_self(device);
-    }
-
-    Self { layout, pipeline }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_pipeline(self.pipeline, None);
-    device.destroy_pipeline_layout(self.layout, None);
+    Ok(Self {
+      pipeline,
+      _layout: layout,
+    })
   }
 }
 
@@ -4,7 +4,7 @@ use ash::vk;
 
 use crate::IMAGE_FORMAT;
 
-pub fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
+pub fn create_render_pass(device: &ash::Device) -> Result<vk::RenderPass, vk::Result> {
   let image_attachment = vk::AttachmentDescription {
     flags: vk::AttachmentDescriptionFlags::empty(),
     format: IMAGE_FORMAT,
@@ -13,8 +13,10 @@ pub fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
     store_op: vk::AttachmentStoreOp::STORE,
     stencil_load_op: vk::AttachmentLoadOp::DONT_CARE,
     stencil_store_op: vk::AttachmentStoreOp::DONT_CARE,
-    initial_layout: vk::ImageLayout::UNDEFINED,
-    final_layout: vk::ImageLayout::TRANSFER_SRC_OPTIMAL, // layout after render pass finishes
+    // layout transitions and synchronization with other commands are done by the render graph
+    // with pipeline barriers
+    initial_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+    final_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
   };
 
   let attachment_ref = vk::AttachmentReference {
@@ -36,29 +38,6 @@ pub fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
     p_preserve_attachments: ptr::null(),
   };
 
-  let dependencies = [
-    // change access flags to attachment before subpass begins
-    vk::SubpassDependency {
-      src_subpass: vk::SUBPASS_EXTERNAL,
-      dst_subpass: 0, // image_subpass
-      src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-      dst_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-      src_access_mask: vk::AccessFlags::NONE,
-      dst_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-      dependency_flags: vk::DependencyFlags::empty(),
-    },
-    // wait for subpass to finish before doing any transfer
-    vk::SubpassDependency {
-      src_subpass: 0,
-      dst_subpass: vk::SUBPASS_EXTERNAL,
-      src_stage_mask: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
-      dst_stage_mask: vk::PipelineStageFlags::TRANSFER,
-      src_access_mask: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
-      dst_access_mask: vk::AccessFlags::NONE,
-      dependency_flags: vk::DependencyFlags::empty(),
-    },
-  ];
-
   let create_info = vk::RenderPassCreateInfo {
     s_type: vk::StructureType::RENDER_PASS_CREATE_INFO,
     flags: vk::RenderPassCreateFlags::empty(),
@@ -67,14 +46,10 @@ pub fn create_render_pass(device: &ash::Device) -> vk::RenderPass {
     p_attachments: &image_attachment,
     subpass_count: 1,
     p_subpasses: &image_subpass,
-    dependency_count: dependencies.len() as u32,
-    p_dependencies: dependencies.as_ptr(),
+    dependency_count: 0,
+    p_dependencies: ptr::null(),
   };
-  unsafe {
-    device
-      .create_render_pass(&create_info, None)
-      .expect("Failed to create render pass!")
-  }
+  unsafe { device.create_render_pass(&create_info, None) }
 }
 
 pub fn create_framebuffer(
@@ -82,7 +57,7 @@ pub fn create_framebuffer(
   render_pass: vk::RenderPass,
   image_view: vk::ImageView,
   extent: vk::Extent2D,
-) -> vk::Framebuffer {
+) -> Result<vk::Framebuffer, vk::Result> {
   let create_info = vk::FramebufferCreateInfo {
     s_type: vk::StructureType::FRAMEBUFFER_CREATE_INFO,
     p_next: ptr::null(),
@@ -94,9 +69,5 @@ pub fn create_framebuffer(
     height: extent.height,
     layers: 1,
   };
-  unsafe {
-    device
-      .create_framebuffer(&create_info, None)
-      .expect("Failed to create framebuffer")
-  }
+  unsafe { device.create_framebuffer(&create_info, None) }
 }
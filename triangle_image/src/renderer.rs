@@ -0,0 +1,246 @@
+use ash::vk;
+use std::{ops::BitOr, path::Path, sync::Arc};
+
+use ash_by_example_core::{
+  create_pipeline_cache, get_entry,
+  graph::CompiledGraph,
+  profiler::ScopeTiming,
+  queries::{DrawQueries, DrawStatistics},
+  save_pipeline_cache,
+  upload::{UploadManager, UploadQueue},
+  ApplicationInfo, Device, Instance, MemoryAllocator, Owned, PhysicalDevice, SharedAllocator,
+};
+
+use crate::{
+  constant_buffers::ConstantBuffers,
+  device::device_requirements,
+  errors::{InitializationError, SaveError},
+  image::Image,
+  passes::build_graph,
+  pipeline::GraphicsPipeline,
+  render_pass::{create_framebuffer, create_render_pass},
+  APPLICATION_NAME, APPLICATION_VERSION, IMAGE_HEIGHT, IMAGE_WIDTH, PIPELINE_CACHE_PATH,
+  TARGET_API_VERSION,
+};
+
+// size of the staging ring buffer used for uploads
+const STAGING_SIZE: u64 = 64 * 1024;
+
+// Fields are dropped in declaration order, however all objects keep the device alive (and the
+// device keeps the instance alive), so they are always destroyed before it
+pub struct Renderer {
+  // recorded draw and copy
+  graph: CompiledGraph,
+  // surround the triangle draw
+  draw_queries: DrawQueries,
+  _render_targets: RenderTargets,
+  _pipeline: GraphicsPipeline,
+  _buffers: ConstantBuffers,
+  // can stream more data into device local memory
+  _uploads: UploadManager,
+  gpu_data: GPUData,
+  device: Arc<Device>,
+}
+
+struct GPUData {
+  // GPU image with DEVICE_LOCAL flags
+  local_image: Image,
+  // CPU accessible image with HOST_VISIBLE flags
+  host_image: Image,
+}
+
+// objects that describe how the local image is rendered to
+struct RenderTargets {
+  framebuffer: Owned<vk::Framebuffer>,
+  _image_view: Owned<vk::ImageView>,
+  render_pass: Owned<vk::RenderPass>,
+}
+
+impl Renderer {
+  pub fn initialize() -> Result<Self, InitializationError> {
+    let entry: ash::Entry = unsafe { get_entry() };
+
+    let app_info = ApplicationInfo {
+      name: APPLICATION_NAME,
+      version: APPLICATION_VERSION,
+      api_version: TARGET_API_VERSION,
+    };
+    // everything created up to an error gets destroyed when returning early
+    let instance = Instance::create(entry, &app_info, &[])?;
+
+    let requirements = device_requirements();
+    let physical_device = unsafe { PhysicalDevice::select(&instance, &requirements) }?;
+
+    let (device, queues) = Device::create(&instance, &physical_device, &requirements)?;
+
+    println!("Allocating images...");
+    let gpu_data = GPUData::new(&device, &physical_device)?;
+
+    let render_targets = RenderTargets::new(&device, &gpu_data.local_image)?;
+
+    let pipeline = create_pipeline(&device, &physical_device, *render_targets.render_pass)?;
+
+    // copies vertices and indices into device local memory with the transfer queue
+    let allocator = SharedAllocator::new(&device, MemoryAllocator::new(&physical_device));
+    let mut uploads = UploadManager::new(
+      &device,
+      &physical_device,
+      &allocator,
+      UploadQueue {
+        queue: queues.transfer,
+        family: physical_device.queue_families.get_transfer_index(),
+      },
+      UploadQueue {
+        queue: queues.graphics,
+        family: physical_device.queue_families.get_graphics_index(),
+      },
+      STAGING_SIZE,
+    )?;
+    let buffers = ConstantBuffers::new(&device, &physical_device, &mut uploads)?;
+
+    // pipeline statistics are only collected if supported
+    let enabled_features = requirements.enabled_features(&physical_device.features);
+    let draw_queries = DrawQueries::new(&device, &enabled_features)?;
+
+    let graph = build_graph(
+      &device,
+      &physical_device,
+      &queues,
+      *render_targets.render_pass,
+      *render_targets.framebuffer,
+      &pipeline,
+      &buffers,
+      &draw_queries,
+      *gpu_data.local_image,
+      *gpu_data.host_image,
+    )?;
+
+    Ok(Self {
+      graph,
+      draw_queries,
+      _render_targets: render_targets,
+      _pipeline: pipeline,
+      _buffers: buffers,
+      _uploads: uploads,
+      gpu_data,
+      device,
+    })
+  }
+
+  // can return vk::Result::ERROR_DEVICE_LOST
+  pub fn submit_and_wait(&mut self) -> Result<(), vk::Result> {
+    self.graph.submit_and_wait()
+  }
+
+  // GPU time of each pass of the last completed submission
+  pub fn timings(&self) -> Result<Vec<ScopeTiming>, vk::Result> {
+    Ok(self.graph.timings()?.unwrap_or_default())
+  }
+
+  // occlusion and pipeline statistics of the last completed triangle draw
+  pub fn draw_statistics(&self) -> Result<DrawStatistics, vk::Result> {
+    self.draw_queries.results()
+  }
+
+  // host image memory should not be in use by the device
+  pub fn save_image(&self, path: &Path) -> Result<(), SaveError> {
+    self.gpu_data.host_image.save_to_file(&self.device, path)
+  }
+}
+
+impl Drop for Renderer {
+  fn drop(&mut self) {
+    log::debug!("Destroying renderer objects...");
+    // wait until all operations have finished and objects are safe to destroy
+    // this only fails if the device is lost, in which case objects can be destroyed anyway
+    if let Err(err) = unsafe { self.device.device_wait_idle() } {
+      log::error!(
+        "Failed to wait for the device to become idle during drop: {:?}",
+        err
+      );
+    }
+    // fields get dropped after this
+  }
+}
+
+// Creates the pipeline through a pipeline cache that is saved and loaded across program
+// invocations, so that the driver doesn't have to recompile the shaders
+fn create_pipeline(
+  device: &Arc<Device>,
+  physical_device: &PhysicalDevice,
+  render_pass: vk::RenderPass,
+) -> Result<GraphicsPipeline, InitializationError> {
+  log::info!("Creating pipeline cache");
+  let (pipeline_cache, created_from_file) =
+    create_pipeline_cache(device, physical_device, Path::new(PIPELINE_CACHE_PATH))?;
+  // no more pipelines will be created, so the cache is destroyed at the end of this function
+  let pipeline_cache = unsafe { Owned::from_raw(device, pipeline_cache) };
+  if created_from_file {
+    log::info!("Cache successfully created from an existing cache file");
+  } else {
+    log::info!("Cache initialized as empty");
+  }
+
+  log::debug!("Creating pipeline");
+  let pipeline = GraphicsPipeline::create(device, *pipeline_cache, render_pass)?;
+
+  log::info!("Saving pipeline cache");
+  if let Err(err) = save_pipeline_cache(
+    device,
+    physical_device,
+    *pipeline_cache,
+    Path::new(PIPELINE_CACHE_PATH),
+  ) {
+    log::error!("Failed to save pipeline cache: {:?}", err);
+  }
+
+  Ok(pipeline)
+}
+
+impl GPUData {
+  pub fn new(
+    device: &Arc<Device>,
+    physical_device: &PhysicalDevice,
+  ) -> Result<Self, InitializationError> {
+    let local_image = Image::new(
+      device,
+      physical_device,
+      vk::ImageTiling::OPTIMAL,
+      vk::ImageUsageFlags::TRANSFER_SRC.bitor(vk::ImageUsageFlags::COLOR_ATTACHMENT),
+      vk::MemoryPropertyFlags::DEVICE_LOCAL,
+      vk::MemoryPropertyFlags::empty(),
+    )?;
+    let host_image = Image::new(
+      device,
+      physical_device,
+      vk::ImageTiling::LINEAR,
+      vk::ImageUsageFlags::TRANSFER_DST,
+      vk::MemoryPropertyFlags::HOST_VISIBLE,
+      vk::MemoryPropertyFlags::HOST_CACHED,
+    )?;
+
+    Ok(Self {
+      local_image,
+      host_image,
+    })
+  }
+}
+
+impl RenderTargets {
+  pub fn new(device: &Arc<Device>, local_image: &Image) -> Result<Self, vk::Result> {
+    let render_pass = unsafe { Owned::from_raw(device, create_render_pass(device)?) };
+    let image_view = local_image.create_view(device)?;
+    let extent = vk::Extent2D {
+      width: IMAGE_WIDTH,
+      height: IMAGE_HEIGHT,
+    };
+    let framebuffer = create_framebuffer(device, *render_pass, *image_view, extent)?;
+    let framebuffer = unsafe { Owned::from_raw(device, framebuffer) };
+
+    Ok(Self {
+      framebuffer,
+      _image_view: image_view,
+      render_pass,
+    })
+  }
+}
@@ -1,32 +1,30 @@
-use std::{ffi::CStr, path::Path, ptr};
+use std::{ffi::CStr, path::Path, ptr, sync::Arc};
 
 use ash::vk;
 
-use crate::utility::cstr;
+use ash_by_example_core::{cstr, Device, Owned};
+
+use crate::errors::ShaderError;
 
 use super::load_shader;
 
-const VERT_SHADER_PATH: &'static str = "./shaders/vert.spv";
-const FRAG_SHADER_PATH: &'static str = "./shaders/frag.spv";
+const VERT_SHADER_PATH: &str = "./shaders/vert.spv";
+const FRAG_SHADER_PATH: &str = "./shaders/frag.spv";
 
-const MAIN_FN_NAME: &'static CStr = cstr!("main");
+const MAIN_FN_NAME: &CStr = cstr!("main");
 
 pub struct Shader {
-  pub vert: vk::ShaderModule,
-  pub frag: vk::ShaderModule,
+  pub vert: Owned<vk::ShaderModule>,
+  pub frag: Owned<vk::ShaderModule>,
 }
 
 impl Shader {
-  pub fn load(device: &ash::Device) -> Self {
-    Self {
-      vert: load_shader(device, Path::new(VERT_SHADER_PATH)),
-      frag: load_shader(device, Path::new(FRAG_SHADER_PATH)),
-    }
-  }
-
-  pub unsafe fn destroy_self(&mut self, device: &ash::Device) {
-    device.destroy_shader_module(self.vert, None);
-    device.destroy_shader_module(self.frag, None);
+  pub fn load(device: &Arc<Device>) -> Result<Self, ShaderError> {
+    let vert = load_shader(device, Path::new(VERT_SHADER_PATH))?;
+    let vert = unsafe { Owned::from_raw(device, vert) };
+    let frag = load_shader(device, Path::new(FRAG_SHADER_PATH))?;
+    let frag = unsafe { Owned::from_raw(device, frag) };
+    Ok(Self { vert, frag })
   }
 }
 
@@ -38,7 +36,7 @@ impl Shader {
         s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
         p_next: ptr::null(),
         flags: vk::PipelineShaderStageCreateFlags::empty(),
-        module: self.vert,
+        module: *self.vert,
         p_name: MAIN_FN_NAME.as_ptr(),
         p_specialization_info: ptr::null(),
         stage: vk::ShaderStageFlags::VERTEX,
@@ -48,7 +46,7 @@ impl Shader {
         s_type: vk::StructureType::PIPELINE_SHADER_STAGE_CREATE_INFO,
         p_next: ptr::null(),
         flags: vk::PipelineShaderStageCreateFlags::empty(),
-        module: self.frag,
+        module: *self.frag,
         p_name: MAIN_FN_NAME.as_ptr(),
         p_specialization_info: ptr::null(),
         stage: vk::ShaderStageFlags::FRAGMENT,
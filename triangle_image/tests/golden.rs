@@ -0,0 +1,30 @@
+// Runs the example and compares the saved image with tests/reference/triangle.png
+// See golden/src/lib.rs on how to run this against a software Vulkan implementation
+
+use std::path::Path;
+
+use ash_by_example_golden::{
+  assert_matches_reference, run_example, skip_without_vulkan, Tolerance,
+};
+
+#[test]
+fn triangle_matches_reference() {
+  if skip_without_vulkan() {
+    return;
+  }
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+  run_example(env!("CARGO_BIN_EXE_triangle-image"), dir, &[]);
+
+  // color interpolation and sRGB conversion precision vary between implementations, as well as
+  // coverage of pixels that lie exactly on the triangle edges
+  assert_matches_reference(
+    &dir.join("triangle.png"),
+    &dir.join("tests/reference/triangle.png"),
+    Path::new(env!("CARGO_TARGET_TMPDIR")),
+    Tolerance {
+      channel: 2,
+      mismatched_ratio: 0.005,
+    },
+  );
+}